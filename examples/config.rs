@@ -0,0 +1,88 @@
+//! Evaluate a Javascript configuration file, which may compute its values,
+//! in a context with only the base objects, and read it back as a Rust struct.
+#[macro_use]
+extern crate failure;
+
+use failure::{err_msg, Error};
+
+use qjs::{Context, Eval, Local, Runtime, Value};
+
+const CONFIG: &str = r#"
+({
+    name: 'demo',
+    port: 8000 + 80,
+    debug: env === 'development',
+    hosts: ['api', 'www'].map(host => host + '.example.com'),
+})
+"#;
+
+#[derive(Debug, PartialEq)]
+struct Config {
+    name: String,
+    port: i32,
+    debug: bool,
+    hosts: Vec<String>,
+}
+
+fn field<'a>(obj: &'a Local<Value>, name: &str) -> Result<Local<'a, Value>, Error> {
+    obj.get_property(name)
+        .ok_or_else(|| format_err!("missing `{}` field", name))
+}
+
+fn load_config(source: &str, env: &str) -> Result<Config, Error> {
+    let rt = Runtime::new();
+    let ctxt = Context::builder(&rt)
+        .with_base_objects()
+        .with_eval()
+        .build();
+
+    ctxt.global_object().set_property("env", env)?;
+
+    let obj = ctxt.eval_script(source, "config.js", Eval::GLOBAL | Eval::STRICT)?;
+
+    let config = Config {
+        name: field(&obj, "name")?.to_string(),
+        port: field(&obj, "port")?
+            .to_int32()
+            .ok_or_else(|| err_msg("`port` should be a number"))?,
+        debug: field(&obj, "debug")?.to_bool().unwrap_or_default(),
+        hosts: field(&obj, "hosts")?
+            .iter_elements::<String>()
+            .flatten()
+            .collect(),
+    };
+
+    Ok(config)
+}
+
+fn main() -> Result<(), Error> {
+    pretty_env_logger::init();
+
+    let config = load_config(CONFIG, "development")?;
+
+    println!("{:#?}", config);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config() {
+        let _ = pretty_env_logger::try_init();
+
+        assert_eq!(
+            load_config(CONFIG, "production").unwrap(),
+            Config {
+                name: "demo".into(),
+                port: 8080,
+                debug: false,
+                hosts: vec!["api.example.com".into(), "www.example.com".into()],
+            }
+        );
+
+        assert!(load_config("({ name: 'demo' })", "production").is_err());
+    }
+}
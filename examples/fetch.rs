@@ -0,0 +1,128 @@
+//! An async script which `await`s a host provided `fetch` function and transforms the result.
+//!
+//! The `fetch` requests are queued by the script and served by the host,
+//! which resolves the promises and drives the pending jobs until the script completes.
+#[macro_use]
+extern crate log;
+
+use failure::{err_msg, Error};
+
+use qjs::{Context, ContextRef, Eval, Local, Runtime, Value};
+
+const PRELUDE: &str = r#"
+var requests = [];
+
+function fetch(url) {
+    return new Promise((resolve, reject) => requests.push({ url, resolve, reject }));
+}
+"#;
+
+const SCRIPT: &str = r#"
+async function main() {
+    const users = await fetch('mem://users');
+    const teams = await fetch('mem://teams');
+
+    return users
+        .filter(user => user.active)
+        .map(user => user.name + '@' + teams[user.team])
+        .join(', ');
+}
+
+main().then(res => result = res, err => error = err.message);
+"#;
+
+fn serve(url: &str) -> Option<&'static str> {
+    match url {
+        "mem://users" => Some(
+            r#"[
+                { "name": "alice", "team": 0, "active": true },
+                { "name": "bob", "team": 1, "active": false },
+                { "name": "carol", "team": 1, "active": true }
+            ]"#,
+        ),
+        "mem://teams" => Some(r#"["dev", "ops"]"#),
+        _ => None,
+    }
+}
+
+/// Serve the queued `fetch` requests, returns `false` if no more request.
+fn serve_requests(ctxt: &ContextRef, requests: &Local<Value>) -> Result<bool, Error> {
+    let mut served = false;
+
+    while let Some(req) = requests.invoke("shift", ())?.check_undefined() {
+        let url = req
+            .get_property("url")
+            .ok_or_else(|| err_msg("missing `url`"))?
+            .to_string();
+
+        debug!("fetch {}", url);
+
+        if let Some(body) = serve(&url) {
+            let data = ctxt.parse_json(body, &url)?;
+
+            req.invoke("resolve", data)?;
+        } else {
+            let err = ctxt.new_error();
+
+            err.set_property("message", format!("{} not found", url))?;
+
+            req.invoke("reject", err)?;
+        }
+
+        served = true;
+    }
+
+    Ok(served)
+}
+
+fn fetch_and_transform() -> Result<String, Error> {
+    let rt = Runtime::new();
+    let ctxt = Context::new(&rt);
+
+    ctxt.eval_script(PRELUDE, "<prelude>", Eval::GLOBAL)?;
+    ctxt.eval_script(SCRIPT, "main.js", Eval::GLOBAL)?;
+
+    let global = ctxt.global_object();
+    let requests = global
+        .get_property("requests")
+        .ok_or_else(|| err_msg("missing `requests`"))?;
+
+    loop {
+        while rt.is_job_pending() {
+            rt.execute_pending_job()?;
+        }
+
+        if !serve_requests(&ctxt, &requests)? {
+            break;
+        }
+    }
+
+    let error = global.get_property("error").map(|err| err.to_string());
+    let result = global.get_property("result").map(|res| res.to_string());
+
+    match (error, result) {
+        (Some(err), _) => Err(err_msg(err)),
+        (None, Some(res)) => Ok(res),
+        (None, None) => Err(err_msg("script not completed")),
+    }
+}
+
+fn main() -> Result<(), Error> {
+    pretty_env_logger::init();
+
+    println!("{}", fetch_and_transform()?);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fetch() {
+        let _ = pretty_env_logger::try_init();
+
+        assert_eq!(fetch_and_transform().unwrap(), "alice@dev, carol@ops");
+    }
+}
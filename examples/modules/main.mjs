@@ -0,0 +1,3 @@
+import { greet } from './lib/greet.mjs';
+
+globalThis.message = greet('world');
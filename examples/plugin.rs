@@ -0,0 +1,98 @@
+//! A plugin system where the host loads Javascript plugins which register themselves,
+//! and then runs the input through each plugin's `transform` hook.
+#[macro_use]
+extern crate log;
+
+use failure::{err_msg, Error};
+
+use qjs::{Context, Eval, Prop, Runtime};
+
+const PRELUDE: &str = r#"
+var plugins = [];
+
+function registerPlugin(plugin) {
+    plugins.push(plugin);
+}
+"#;
+
+const PLUGINS: &[(&str, &str)] = &[
+    (
+        "trim.js",
+        r#"registerPlugin({ name: 'trim', transform: s => s.trim() })"#,
+    ),
+    (
+        "upper.js",
+        r#"registerPlugin({ name: 'upper', transform: s => s.toUpperCase() })"#,
+    ),
+    (
+        "signature.js",
+        r#"registerPlugin({ name: 'signature', transform: s => s + ' (qjs ' + hostVersion() + ')' })"#,
+    ),
+];
+
+fn transform(input: &str) -> Result<(Vec<String>, String), Error> {
+    let rt = Runtime::new();
+    let ctxt = Context::new(&rt);
+
+    let host_version = ctxt.new_c_function(|_, _, _| qjs::VERSION, Some("hostVersion"), 0)?;
+
+    let global = ctxt.global_object();
+
+    global.define_property_value("hostVersion", host_version, Prop::CONFIGURABLE)?;
+    ctxt.eval_script(PRELUDE, "<prelude>", Eval::GLOBAL)?;
+
+    for (filename, source) in PLUGINS {
+        debug!("load plugin `{}`", filename);
+
+        ctxt.eval_script(*source, filename, Eval::GLOBAL)?;
+    }
+
+    let plugins = global
+        .get_property("plugins")
+        .ok_or_else(|| err_msg("missing `plugins`"))?;
+    let names = plugins
+        .invoke("map", ctxt.eval_script("p => p.name", "<host>", Eval::GLOBAL)?)?
+        .iter_elements::<String>()
+        .flatten()
+        .collect::<Vec<_>>();
+
+    let mut output = input.to_owned();
+
+    for (idx, name) in names.iter().enumerate() {
+        let plugin = plugins
+            .get_property(idx as u32)
+            .ok_or_else(|| err_msg("missing plugin"))?;
+
+        output = plugin.invoke("transform", output.as_str())?.to_string();
+
+        debug!("plugin `{}` -> {}", name, output);
+    }
+
+    Ok((names, output))
+}
+
+fn main() -> Result<(), Error> {
+    pretty_env_logger::init();
+
+    let (names, output) = transform("  hello world  ")?;
+
+    println!("plugins: {}", names.join(", "));
+    println!("output: {}", output);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plugins() {
+        let _ = pretty_env_logger::try_init();
+
+        let (names, output) = transform("  hello world  ").unwrap();
+
+        assert_eq!(names, vec!["trim", "upper", "signature"]);
+        assert_eq!(output, format!("HELLO WORLD (qjs {})", qjs::VERSION));
+    }
+}
@@ -15,7 +15,7 @@ use failure::Error;
 use foreign_types::ForeignTypeRef;
 use structopt::StructOpt;
 
-use qjs::{ffi, Context, ContextRef, ErrorKind, Eval, Local, MallocFunctions, Runtime, Value};
+use qjs::{ffi, Context, ContextRef, Eval, Local, MallocFunctions, ModuleStatus, Runtime, Value};
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "qjs", about = "QuickJS stand alone interpreter")]
@@ -183,13 +183,14 @@ fn eval_buf<'a>(
     buf: &str,
     filename: &str,
     flags: Eval,
-) -> Result<Local<'a, Value>, Error> {
+) -> Result<Local<'a, Value>, qjs::Error> {
     if flags.contains(Eval::MODULE) {
-        let val = ctxt.eval_script(buf, filename, flags | Eval::COMPILE_ONLY)?;
+        let module = ctxt.eval_module(buf, filename)?;
 
-        let _ = ctxt.set_import_meta(&val, true, true);
-
-        ctxt.eval_function(val)
+        match module.status() {
+            ModuleStatus::Evaluated => Ok(ctxt.undefined()),
+            ModuleStatus::Failed(err) => Err(err.to_string().into()),
+        }
     } else {
         ctxt.eval_script(buf, filename, flags)
     }
@@ -266,12 +267,11 @@ globalThis.os = os;
             debug!("eval file: {}", filename);
 
             let buf = qjs::load_file(filename)?;
-            let eval_flags =
-                if opt.module || filename.ends_with(".mjs") || qjs::detect_module(buf.as_str()) {
-                    Eval::MODULE
-                } else {
-                    Eval::GLOBAL
-                };
+            let eval_flags = if opt.module || ctxt.is_module_source(&buf, filename) {
+                Eval::MODULE
+            } else {
+                Eval::GLOBAL
+            };
 
             eval_buf(&ctxt, &buf, filename, eval_flags)
         } else {
@@ -289,7 +289,7 @@ globalThis.os = os;
             Err(err) => {
                 eprintln!("{}", err);
 
-                if let Some(stack) = err.downcast_ref::<ErrorKind>().and_then(|err| err.stack()) {
+                if let Some(stack) = err.kind().and_then(|err| err.stack()) {
                     eprintln!("{}", stack)
                 }
             }
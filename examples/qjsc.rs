@@ -65,6 +65,10 @@ pub struct Opt {
     #[structopt(short = "f")]
     features: Vec<String>,
 
+    /// Print the disassembled bytecode of the scripts
+    #[structopt(long = "disasm")]
+    disasm: bool,
+
     /// Use link time optimization
     #[structopt(long = "lto")]
     use_lto: bool,
@@ -306,6 +310,10 @@ impl Loader {
 
         self.cnames.insert(cname.to_string(), false);
 
+        if self.disasm && !is_module {
+            eprint!("{}", ctxt.disassemble(&func)?);
+        }
+
         self.output_object_code(ctxt, &func, &cname)?;
 
         Ok(())
@@ -336,6 +344,7 @@ impl Loader {
         } else {
             // compile the module
             ctxt.eval_file(module_name, Eval::MODULE | Eval::COMPILE_ONLY)
+                .map_err(Error::from)
                 .and_then(|func| {
                     let cname = get_c_name(module_name).expect("cname");
 
@@ -464,7 +473,7 @@ impl Generator {
             flags |= WriteObj::BSWAP;
         }
 
-        let buf = ctxt.write_object(obj, flags)?;
+        let buf = ctxt.write_raw_object(obj, flags)?;
 
         writeln!(
             &mut self.w,
@@ -0,0 +1,125 @@
+//! A sandboxed expression service, which evaluates untrusted expressions
+//! in a minimal context with memory limit and execution timeout.
+#[macro_use]
+extern crate log;
+
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+use qjs::{Context, Error, Eval, Interrupt, Runtime, RuntimeRef};
+
+const MEMORY_LIMIT: usize = 4 * 1024 * 1024;
+const TIMEOUT: Duration = Duration::from_millis(100);
+
+thread_local! {
+    static DEADLINE: Cell<Option<Instant>> = Cell::new(None);
+}
+
+fn check_deadline(_rt: &RuntimeRef) -> Interrupt {
+    match DEADLINE.with(Cell::get) {
+        Some(deadline) if Instant::now() > deadline => Interrupt::Break,
+        _ => Interrupt::Continue,
+    }
+}
+
+pub struct Sandbox {
+    // the context must be dropped before the runtime
+    ctxt: Context,
+    rt: Runtime,
+}
+
+impl Sandbox {
+    pub fn new() -> Self {
+        let rt = Runtime::new();
+
+        rt.set_memory_limit(Some(MEMORY_LIMIT));
+        rt.set_interrupt_handler(Some(check_deadline));
+
+        let ctxt = Context::builder(&rt)
+            .with_base_objects()
+            .with_eval()
+            .build();
+
+        Sandbox { ctxt, rt }
+    }
+
+    pub fn eval(&self, expr: &str) -> Result<f64, Error> {
+        debug!("eval `{}` with {:?} timeout", expr, TIMEOUT);
+
+        DEADLINE.with(|deadline| deadline.set(Some(Instant::now() + TIMEOUT)));
+
+        let res = self
+            .ctxt
+            .eval_script(expr, "<expr>", Eval::GLOBAL | Eval::STRICT)
+            .and_then(|v| {
+                v.to_float64()
+                    .ok_or_else(|| Error::from("expression should return a number"))
+            });
+
+        DEADLINE.with(|deadline| deadline.set(None));
+
+        debug!(
+            "{} bytes memory used",
+            self.rt.memory_usage().memory_used_size
+        );
+
+        res
+    }
+}
+
+fn main() -> Result<(), Error> {
+    pretty_env_logger::init();
+
+    let sandbox = Sandbox::new();
+
+    for expr in &[
+        "1 + 2 * 3",
+        "Math.sqrt(16) + Math.max(1, 2, 3)",
+        "while (true) {}",
+        "new Array(1e7).fill(0).length",
+        "require('fs')",
+    ] {
+        match sandbox.eval(expr) {
+            Ok(v) => println!("{} = {}", expr, v),
+            Err(err) => println!("{} failed, {}", expr, err),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sandbox() {
+        let _ = pretty_env_logger::try_init();
+
+        let sandbox = Sandbox::new();
+
+        assert_eq!(sandbox.eval("1 + 2 * 3").unwrap(), 7.0);
+        assert_eq!(sandbox.eval("Math.sqrt(16)").unwrap(), 4.0);
+
+        assert_eq!(
+            sandbox
+                .eval("while (true) {}")
+                .unwrap_err()
+                .into_kind()
+                .unwrap()
+                .message(),
+            "interrupted"
+        );
+        assert_eq!(
+            sandbox
+                .eval("new Array(1e7).fill(0).length")
+                .unwrap_err()
+                .into_kind()
+                .unwrap()
+                .message(),
+            "out of memory"
+        );
+
+        assert_eq!(sandbox.eval("40 + 2").unwrap(), 42.0);
+    }
+}
@@ -0,0 +1,114 @@
+//! Worker threads, each one owns a separated `Runtime` and handles
+//! the JSON messages posted from the main thread with its `onmessage` handler.
+#[macro_use]
+extern crate log;
+
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+use failure::{err_msg, Error};
+
+use qjs::{Context, Eval, Runtime};
+
+const WORKER: &str = r#"
+function onmessage(msg) {
+    return { id: msg.id, worker: workerId, sum: msg.values.reduce((a, b) => a + b, 0) };
+}
+"#;
+
+fn worker(id: usize, requests: Receiver<String>, replies: Sender<String>) -> Result<(), Error> {
+    let rt = Runtime::new();
+    let ctxt = Context::new(&rt);
+    let global = ctxt.global_object();
+
+    global.set_property("workerId", id as i32)?;
+    ctxt.eval_script(WORKER, "worker.js", Eval::GLOBAL)?;
+
+    let onmessage = global
+        .get_property("onmessage")
+        .ok_or_else(|| err_msg("missing `onmessage` handler"))?;
+    let json = global
+        .get_property("JSON")
+        .ok_or_else(|| err_msg("missing `JSON`"))?;
+
+    for request in requests {
+        trace!("worker #{} received: {}", id, request);
+
+        let msg = ctxt.parse_json(request, "<message>")?;
+        let reply = onmessage.call(None, msg)?;
+        let reply = json.invoke("stringify", reply)?.to_string();
+
+        trace!("worker #{} reply: {}", id, reply);
+
+        replies.send(reply)?;
+    }
+
+    Ok(())
+}
+
+fn dispatch(workers: usize, messages: usize) -> Result<Vec<String>, Error> {
+    let (reply_tx, reply_rx) = channel();
+
+    let (senders, handles): (Vec<_>, Vec<_>) = (0..workers)
+        .map(|id| {
+            let (request_tx, request_rx) = channel();
+            let reply_tx = reply_tx.clone();
+            let handle = thread::spawn(move || worker(id, request_rx, reply_tx));
+
+            (request_tx, handle)
+        })
+        .unzip();
+
+    for id in 0..messages {
+        let msg = format!(r#"{{ "id": {}, "values": [{}, {}, {}] }}"#, id, id, id + 1, id + 2);
+
+        senders[id % workers].send(msg)?;
+    }
+
+    drop(senders);
+    drop(reply_tx);
+
+    let mut replies = reply_rx.iter().collect::<Vec<_>>();
+
+    for handle in handles {
+        handle
+            .join()
+            .map_err(|_| err_msg("worker panicked"))??;
+    }
+
+    replies.sort();
+
+    Ok(replies)
+}
+
+fn main() -> Result<(), Error> {
+    pretty_env_logger::init();
+
+    for reply in dispatch(4, 8)? {
+        println!("{}", reply);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn workers() {
+        let _ = pretty_env_logger::try_init();
+
+        let replies = dispatch(2, 4).unwrap();
+
+        assert_eq!(
+            replies,
+            vec![
+                r#"{"id":0,"worker":0,"sum":3}"#,
+                r#"{"id":1,"worker":1,"sum":6}"#,
+                r#"{"id":2,"worker":0,"sum":9}"#,
+                r#"{"id":3,"worker":1,"sum":12}"#,
+            ]
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use qjs::{ffi, Context, ContextRef, Eval, Runtime, Value};
+use qjs_bench::*;
+
+fn context_new(c: &mut Criterion) {
+    c.bench_function("qjs/context_new", |b| {
+        b.iter(|| {
+            let rt = Runtime::new();
+            let ctxt = Context::new(&rt);
+
+            black_box(&ctxt);
+        })
+    });
+}
+
+fn marshal_to_js(c: &mut Criterion) {
+    let rt = Runtime::new();
+    let ctxt = Context::new(&rt);
+    let items = items();
+
+    c.bench_function("qjs/marshal_to_js", |b| {
+        b.iter(|| {
+            let arr = ctxt.bind(ctxt.new_array());
+
+            for (idx, item) in items.iter().enumerate() {
+                let obj = ctxt.bind(ctxt.new_object());
+
+                obj.set_property("id", item.id).unwrap();
+                obj.set_property("name", item.name.as_str()).unwrap();
+                obj.set_property("score", item.score).unwrap();
+                arr.set_property(idx as u32, obj).unwrap();
+            }
+
+            ctxt.global_object().set_property("items", arr).unwrap();
+
+            assert_eq!(
+                ctxt.eval::<_, f64>(SUM_SCORES, Eval::GLOBAL).unwrap(),
+                Some(sum_scores())
+            );
+        })
+    });
+}
+
+fn marshal_from_js(c: &mut Criterion) {
+    let rt = Runtime::new();
+    let ctxt = Context::new(&rt);
+
+    c.bench_function("qjs/marshal_from_js", |b| {
+        b.iter(|| {
+            let arr = ctxt
+                .eval_script(MAKE_ITEMS, "<bench>", Eval::GLOBAL)
+                .unwrap();
+            let len = arr.get_property("length").and_then(|len| len.as_int());
+            let items = (0..len.unwrap_or_default() as u32)
+                .map(|idx| {
+                    let obj = arr.get_property(idx).unwrap();
+                    let name = obj.get_property("name").unwrap().to_string();
+
+                    Item {
+                        id: obj.get_property("id").and_then(|id| id.as_int()).unwrap(),
+                        name,
+                        score: obj
+                            .get_property("score")
+                            .and_then(|score| score.to_float64())
+                            .unwrap(),
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            assert_eq!(items.len(), ITEMS);
+        })
+    });
+}
+
+fn add(ctxt: &ContextRef, _this: Option<&Value>, args: &[Value]) -> ffi::JSValue {
+    let sum = ctxt.to_int32(&args[0]).unwrap() + ctxt.to_int32(&args[1]).unwrap();
+
+    ctxt.bind(sum).into_inner().raw()
+}
+
+fn callback_loop(c: &mut Criterion) {
+    let rt = Runtime::new();
+    let ctxt = Context::new(&rt);
+
+    ctxt.global_object()
+        .set_property("add", ctxt.new_c_function(add, Some("add"), 2).unwrap())
+        .unwrap();
+
+    c.bench_function("qjs/callback_loop", |b| {
+        b.iter(|| {
+            assert_eq!(
+                ctxt.eval::<_, i32>(CALLBACK_LOOP, Eval::GLOBAL).unwrap(),
+                Some(callback_sum())
+            );
+        })
+    });
+}
+
+fn module_load(c: &mut Criterion) {
+    let rt = Runtime::new();
+    let modules = modules();
+    let main = main_module();
+
+    c.bench_function("qjs/module_load", |b| {
+        b.iter(|| {
+            let ctxt = Context::new(&rt);
+
+            for (name, source) in &modules {
+                ctxt.eval_script(source.as_str(), name, Eval::MODULE | Eval::COMPILE_ONLY)
+                    .unwrap();
+            }
+
+            ctxt.eval_script(main.as_str(), "main.js", Eval::MODULE)
+                .unwrap();
+
+            assert_eq!(
+                ctxt.eval::<_, i32>("total", Eval::GLOBAL).unwrap(),
+                Some(module_total())
+            );
+        })
+    });
+}
+
+fn invoke_hook(c: &mut Criterion) {
+    let rt = Runtime::new();
+    let ctxt = Context::new(&rt);
+    let hooks = ctxt
+        .eval_script(
+            "new (class { constructor() { this.n = 0 } hook(step) { return this.n += step } })",
+            "<bench>",
+            Eval::GLOBAL,
+        )
+        .unwrap();
+
+    c.bench_function("qjs/invoke_hook", |b| {
+        b.iter(|| {
+            for _ in 0..CALLS {
+                black_box(hooks.invoke("hook", 1).unwrap());
+            }
+        })
+    });
+
+    c.bench_function("qjs/invoke_hook_cached", |b| {
+        let hook = ctxt.cached_method("hook");
+
+        b.iter(|| {
+            for _ in 0..CALLS {
+                black_box(hook.invoke(&hooks, 1).unwrap());
+            }
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    context_new,
+    marshal_to_js,
+    marshal_from_js,
+    callback_loop,
+    module_load,
+    invoke_hook
+);
+criterion_main!(benches);
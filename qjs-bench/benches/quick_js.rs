@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use qjs_bench::*;
+use quick_js::{Context, JsValue};
+
+fn context_new(c: &mut Criterion) {
+    c.bench_function("quick-js/context_new", |b| {
+        b.iter(|| black_box(Context::new().unwrap()))
+    });
+}
+
+fn marshal_to_js(c: &mut Criterion) {
+    let ctx = Context::new().unwrap();
+    let items = items();
+
+    c.bench_function("quick-js/marshal_to_js", |b| {
+        b.iter(|| {
+            let arr = items
+                .iter()
+                .map(|item| {
+                    let mut obj = HashMap::new();
+
+                    obj.insert("id".to_owned(), JsValue::Int(item.id));
+                    obj.insert("name".to_owned(), JsValue::String(item.name.clone()));
+                    obj.insert("score".to_owned(), JsValue::Float(item.score));
+
+                    JsValue::Object(obj)
+                })
+                .collect::<Vec<_>>();
+
+            ctx.set_global("items", JsValue::Array(arr)).unwrap();
+
+            let sum = match ctx.eval(SUM_SCORES).unwrap() {
+                JsValue::Int(n) => f64::from(n),
+                JsValue::Float(n) => n,
+                v => panic!("unexpected result {:?}", v),
+            };
+
+            assert_eq!(sum, sum_scores());
+        })
+    });
+}
+
+fn marshal_from_js(c: &mut Criterion) {
+    let ctx = Context::new().unwrap();
+
+    c.bench_function("quick-js/marshal_from_js", |b| {
+        b.iter(|| {
+            let arr = match ctx.eval(MAKE_ITEMS).unwrap() {
+                JsValue::Array(arr) => arr,
+                v => panic!("unexpected result {:?}", v),
+            };
+            let items = arr
+                .into_iter()
+                .map(|obj| match obj {
+                    JsValue::Object(mut obj) => Item {
+                        id: match obj.remove("id") {
+                            Some(JsValue::Int(id)) => id,
+                            v => panic!("unexpected id {:?}", v),
+                        },
+                        name: match obj.remove("name") {
+                            Some(JsValue::String(name)) => name,
+                            v => panic!("unexpected name {:?}", v),
+                        },
+                        score: match obj.remove("score") {
+                            Some(JsValue::Int(n)) => f64::from(n),
+                            Some(JsValue::Float(n)) => n,
+                            v => panic!("unexpected score {:?}", v),
+                        },
+                    },
+                    v => panic!("unexpected item {:?}", v),
+                })
+                .collect::<Vec<_>>();
+
+            assert_eq!(items.len(), ITEMS);
+        })
+    });
+}
+
+fn callback_loop(c: &mut Criterion) {
+    let ctx = Context::new().unwrap();
+
+    ctx.add_callback("add", |a: i32, b: i32| a + b).unwrap();
+
+    c.bench_function("quick-js/callback_loop", |b| {
+        b.iter(|| {
+            assert_eq!(ctx.eval_as::<i32>(CALLBACK_LOOP).unwrap(), callback_sum());
+        })
+    });
+}
+
+// `quick-js` doesn't support declaring the modules, so the `module_load` scenario is skipped.
+criterion_group!(
+    benches,
+    context_new,
+    marshal_to_js,
+    marshal_from_js,
+    callback_loop
+);
+criterion_main!(benches);
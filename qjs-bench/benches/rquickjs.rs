@@ -0,0 +1,123 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use qjs_bench::*;
+use rquickjs::{Array, Context, Function, Module, Object, Runtime};
+
+fn context_new(c: &mut Criterion) {
+    c.bench_function("rquickjs/context_new", |b| {
+        b.iter(|| {
+            let rt = Runtime::new().unwrap();
+            let ctx = Context::full(&rt).unwrap();
+
+            black_box(&ctx);
+        })
+    });
+}
+
+fn marshal_to_js(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let ctx = Context::full(&rt).unwrap();
+    let items = items();
+
+    c.bench_function("rquickjs/marshal_to_js", |b| {
+        b.iter(|| {
+            ctx.with(|ctx| {
+                let arr = Array::new(ctx.clone()).unwrap();
+
+                for (idx, item) in items.iter().enumerate() {
+                    let obj = Object::new(ctx.clone()).unwrap();
+
+                    obj.set("id", item.id).unwrap();
+                    obj.set("name", item.name.as_str()).unwrap();
+                    obj.set("score", item.score).unwrap();
+                    arr.set(idx, obj).unwrap();
+                }
+
+                ctx.globals().set("items", arr).unwrap();
+
+                assert_eq!(ctx.eval::<f64, _>(SUM_SCORES).unwrap(), sum_scores());
+            })
+        })
+    });
+}
+
+fn marshal_from_js(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let ctx = Context::full(&rt).unwrap();
+
+    c.bench_function("rquickjs/marshal_from_js", |b| {
+        b.iter(|| {
+            ctx.with(|ctx| {
+                let arr = ctx.eval::<Array, _>(MAKE_ITEMS).unwrap();
+                let items = arr
+                    .iter::<Object>()
+                    .map(|obj| {
+                        let obj = obj.unwrap();
+
+                        Item {
+                            id: obj.get("id").unwrap(),
+                            name: obj.get("name").unwrap(),
+                            score: obj.get("score").unwrap(),
+                        }
+                    })
+                    .collect::<Vec<_>>();
+
+                assert_eq!(items.len(), ITEMS);
+            })
+        })
+    });
+}
+
+fn callback_loop(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let ctx = Context::full(&rt).unwrap();
+
+    ctx.with(|ctx| {
+        let add = Function::new(ctx.clone(), |a: i32, b: i32| a + b).unwrap();
+
+        ctx.globals().set("add", add).unwrap();
+    });
+
+    c.bench_function("rquickjs/callback_loop", |b| {
+        b.iter(|| {
+            ctx.with(|ctx| {
+                assert_eq!(ctx.eval::<i32, _>(CALLBACK_LOOP).unwrap(), callback_sum());
+            })
+        })
+    });
+}
+
+fn module_load(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let modules = modules();
+    let main = main_module();
+
+    c.bench_function("rquickjs/module_load", |b| {
+        b.iter(|| {
+            let ctx = Context::full(&rt).unwrap();
+
+            ctx.with(|ctx| {
+                for (name, source) in &modules {
+                    Module::declare(ctx.clone(), name.as_str(), source.as_str()).unwrap();
+                }
+
+                Module::evaluate(ctx.clone(), "main.js", main.as_str())
+                    .unwrap()
+                    .finish::<()>()
+                    .unwrap();
+
+                assert_eq!(ctx.eval::<i32, _>("total").unwrap(), module_total());
+            })
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    context_new,
+    marshal_to_js,
+    marshal_from_js,
+    callback_loop,
+    module_load
+);
+criterion_main!(benches);
@@ -0,0 +1,7 @@
+var sum = 0;
+
+for (var i = 0; i < 10000; i++) {
+    sum = add(sum, i);
+}
+
+sum
@@ -0,0 +1 @@
+Array.from({ length: 100 }, (_, id) => ({ id, name: `item-${id}`, score: id / 2 }))
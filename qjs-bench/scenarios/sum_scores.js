@@ -0,0 +1 @@
+items.reduce((sum, item) => sum + item.score, 0)
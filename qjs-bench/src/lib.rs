@@ -0,0 +1,93 @@
+//! The scenarios shared by the benchmarks of `qjs` and the other QuickJS bindings.
+//!
+//! Each engine runs the same scripts with the same sizes, so the results are comparable:
+//!
+//! - `context_new`: create a runtime and a context.
+//! - `marshal_to_js`: build the `items` objects in Rust, then sum their scores in Javascript.
+//! - `marshal_from_js`: build the items in Javascript, then read their properties in Rust.
+//! - `callback_loop`: call a native `add` function in a Javascript loop.
+//! - `module_load`: declare the modules, then evaluate a module which imports all of them.
+//! - `invoke_hook` (`qjs` only): call a script method from Rust `CALLS` times, with and without `cached_method`.
+//!
+//! ```sh
+//! cargo bench -p qjs-bench --bench qjs
+//! cargo bench -p qjs-bench --features qjs-bench/rquickjs --bench rquickjs
+//! cargo bench -p qjs-bench --features qjs-bench/quick-js --bench quick_js
+//! ```
+
+/// The number of items marshaled between Rust and Javascript.
+pub const ITEMS: usize = 100;
+
+/// The number of the native calls in the `callback_loop` script.
+pub const CALLS: usize = 10_000;
+
+/// The number of modules imported by the main module.
+pub const MODULES: usize = 10;
+
+/// Build `ITEMS` objects with the `id`, `name` and `score` properties.
+pub const MAKE_ITEMS: &str = include_str!("../scenarios/make_items.js");
+
+/// Sum the scores of the `items` global.
+pub const SUM_SCORES: &str = include_str!("../scenarios/sum_scores.js");
+
+/// Call the native `add` function `CALLS` times.
+pub const CALLBACK_LOOP: &str = include_str!("../scenarios/callback_loop.js");
+
+/// An item marshaled between Rust and Javascript.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Item {
+    pub id: i32,
+    pub name: String,
+    pub score: f64,
+}
+
+/// The items built by the `MAKE_ITEMS` script, or read by the `SUM_SCORES` script.
+pub fn items() -> Vec<Item> {
+    (0..ITEMS as i32)
+        .map(|id| Item {
+            id,
+            name: format!("item-{}", id),
+            score: f64::from(id) / 2.0,
+        })
+        .collect()
+}
+
+/// The expected result of the `SUM_SCORES` script.
+pub fn sum_scores() -> f64 {
+    items().iter().map(|item| item.score).sum()
+}
+
+/// The expected result of the `CALLBACK_LOOP` script.
+pub fn callback_sum() -> i32 {
+    (0..CALLS as i32).sum()
+}
+
+/// The names and sources of the modules imported by the main module.
+pub fn modules() -> Vec<(String, String)> {
+    (0..MODULES)
+        .map(|idx| {
+            (
+                format!("m{}.js", idx),
+                format!("export const value = {};", idx),
+            )
+        })
+        .collect()
+}
+
+/// The main module which imports all `modules` and stores the sum of their values as the `total` global.
+pub fn main_module() -> String {
+    let imports = (0..MODULES)
+        .map(|idx| format!("import {{ value as v{0} }} from 'm{0}.js';\n", idx))
+        .collect::<String>();
+    let sum = (0..MODULES)
+        .map(|idx| format!("v{}", idx))
+        .collect::<Vec<_>>()
+        .join(" + ");
+
+    format!("{}globalThis.total = {};\n", imports, sum)
+}
+
+/// The expected `total` global after evaluating the main module.
+pub fn module_total() -> i32 {
+    (0..MODULES as i32).sum()
+}
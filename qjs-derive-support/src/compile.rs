@@ -0,0 +1,419 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::env;
+use std::ffi::{CStr, CString};
+use std::fs;
+use std::os::raw::{c_char, c_int, c_void};
+use std::path::{Component, Path, PathBuf};
+use std::ptr;
+use std::slice;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use proc_macro2::{Span, TokenStream};
+use qjs_sys as ffi;
+use quote::quote;
+use syn::{Error, LitByteStr, LitStr, Result};
+
+/// Compile the Javascript file to bytecode, and embed it as a `&'static [u8]`.
+///
+/// The path is relative to the `CARGO_MANIFEST_DIR` of the crate which using the macro,
+/// the file will be compiled as a module if it has the `.mjs` extension or was detected as a module.
+pub fn qjs_compile(input: TokenStream) -> Result<TokenStream> {
+    let lit: LitStr = syn::parse2(input)?;
+    let path = env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_default()
+        .join(lit.value());
+
+    trace!("compile script: {:?}", path);
+
+    let source = fs::read(&path)
+        .map_err(|err| Error::new(lit.span(), format!("fail to read {:?}, {}", path, err)))?;
+    let filename = path
+        .file_name()
+        .map_or_else(|| lit.value(), |s| s.to_string_lossy().into_owned());
+    let bytecode = compile(&source, &filename, false).map_err(|err| Error::new(lit.span(), err))?;
+
+    trace!("compiled {} bytes bytecode", bytecode.len());
+
+    let bytecode = LitByteStr::new(&bytecode, Span::call_site());
+    let path = path.to_string_lossy();
+
+    Ok(quote! {{
+        // recompile when the script was changed
+        const _: &[u8] = include_bytes!(#path);
+
+        #bytecode as &'static [u8]
+    }})
+}
+
+/// Compile the entry module and its relative imports to bytecode,
+/// and embed them as a `&'static [(&'static str, &'static [u8])]`.
+///
+/// The modules are named by their paths relative to the directory of the entry module,
+/// the entry module comes first, followed by its dependencies sorted by name.
+/// The modules are compiled in parallel, since compiling a module doesn't link its imports.
+pub fn qjs_bundle(input: TokenStream) -> Result<TokenStream> {
+    let lit: LitStr = syn::parse2(input)?;
+    let path = env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_default()
+        .join(lit.value());
+
+    trace!("bundle module: {:?}", path);
+
+    let modules = bundle(&path).map_err(|err| Error::new(lit.span(), err))?;
+    let modules = modules.iter().map(|module| {
+        let name = &module.name;
+        let path = module.path.to_string_lossy();
+        let bytecode = LitByteStr::new(&module.bytecode, Span::call_site());
+
+        quote! {{
+            // recompile when the module was changed
+            const _: &[u8] = include_bytes!(#path);
+
+            (#name, #bytecode as &'static [u8])
+        }}
+    });
+
+    Ok(quote! {
+        &[#(#modules),*] as &'static [(&'static str, &'static [u8])]
+    })
+}
+
+/// A compiled module of the bundle.
+#[derive(Debug)]
+struct Module {
+    name: String,
+    path: PathBuf,
+    bytecode: Vec<u8>,
+}
+
+/// Collect the entry module and its relative imports, then compile them on a pool of threads.
+fn bundle(entry: &Path) -> std::result::Result<Vec<Module>, String> {
+    let root = entry.parent().unwrap_or_else(|| Path::new(""));
+    let entry_name = module_name(root, entry)?;
+    let mut sources = BTreeMap::new();
+    let mut queue = VecDeque::from(vec![(entry_name.clone(), entry.to_owned())]);
+
+    while let Some((name, path)) = queue.pop_front() {
+        if sources.contains_key(&name) {
+            continue;
+        }
+
+        let source = fs::read(&path).map_err(|err| format!("fail to read {:?}, {}", path, err))?;
+        let dir = path.parent().unwrap_or(root);
+
+        for specifier in scan_imports(&String::from_utf8_lossy(&source)) {
+            if specifier.starts_with("./") || specifier.starts_with("../") {
+                let path = normalize(&dir.join(&specifier));
+
+                queue.push_back((module_name(root, &path)?, path));
+            }
+        }
+
+        sources.insert(name, (path, source));
+    }
+
+    let sources = sources.into_iter().collect::<Vec<_>>();
+    let next = AtomicUsize::new(0);
+    let compiled = Mutex::new(BTreeMap::new());
+    let workers = thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(sources.len());
+
+    trace!("compile {} modules with {} threads", sources.len(), workers);
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                while let Some((name, (_, source))) =
+                    sources.get(next.fetch_add(1, Ordering::Relaxed))
+                {
+                    let res = compile(source, name, *name != entry_name);
+
+                    compiled.lock().unwrap().insert(name.clone(), res);
+                }
+            });
+        }
+    });
+
+    let mut compiled = compiled.into_inner().unwrap();
+    let mut modules = Vec::with_capacity(sources.len());
+
+    for (name, (path, _)) in sources {
+        let bytecode = compiled
+            .remove(&name)
+            .expect("compiled")
+            .map_err(|err| format!("{}: {}", name, err))?;
+        let module = Module {
+            name,
+            path,
+            bytecode,
+        };
+
+        if module.name == entry_name {
+            modules.insert(0, module);
+        } else {
+            modules.push(module);
+        }
+    }
+
+    Ok(modules)
+}
+
+/// Name the module by its path relative to the root directory, with the `/` separators.
+fn module_name(root: &Path, path: &Path) -> std::result::Result<String, String> {
+    let path = path
+        .strip_prefix(root)
+        .map_err(|_| format!("{:?} is outside of the bundle directory {:?}", path, root))?;
+
+    Ok(path
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/"))
+}
+
+/// Remove the `.` and `..` components of the path without accessing the file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+/// Scan the specifiers of the imports and re-exports of a module source.
+///
+/// It's a lightweight lexer instead of a parser, which skips the comments and string literals,
+/// and takes the string literals following the `import` or `from` keywords.
+fn scan_imports(source: &str) -> Vec<String> {
+    let mut specifiers = vec![];
+    let mut chars = source.chars().peekable();
+    let mut word = String::new();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'/') => {
+                chars.find(|&c| c == '\n');
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+
+                let mut prev = ' ';
+
+                chars.find(|&c| {
+                    let end = prev == '*' && c == '/';
+                    prev = c;
+                    end
+                });
+            }
+            '\'' | '"' | '`' => {
+                let mut s = String::new();
+
+                while let Some(next) = chars.next() {
+                    match next {
+                        '\\' => s.extend(chars.next()),
+                        _ if next == c => break,
+                        _ => s.push(next),
+                    }
+                }
+
+                if c != '`' && (word == "import" || word == "from") {
+                    specifiers.push(s);
+                }
+
+                word.clear();
+            }
+            '(' if word == "import" => {}
+            _ if c.is_alphanumeric() || c == '_' || c == '$' => {
+                word.clear();
+                word.push(c);
+
+                while let Some(&next) = chars.peek() {
+                    if next.is_alphanumeric() || next == '_' || next == '$' {
+                        word.push(next);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+            }
+            _ if c.is_whitespace() => {}
+            _ => word.clear(),
+        }
+    }
+
+    specifiers
+}
+
+fn compile(source: &[u8], filename: &str, module: bool) -> std::result::Result<Vec<u8>, String> {
+    let source = CString::new(source).map_err(|err| err.to_string())?;
+    let filename = CString::new(filename).map_err(|err| err.to_string())?;
+
+    unsafe {
+        let rt = ffi::JS_NewRuntime();
+        let ctx = ffi::JS_NewContext(rt);
+
+        ffi::JS_SetModuleLoaderFunc(rt, None, Some(stub_module_loader), ptr::null_mut());
+
+        let eval_type = if module
+            || filename.as_bytes().ends_with(b".mjs")
+            || ffi::JS_DetectModule(source.as_ptr(), source.as_bytes().len()) != 0
+        {
+            ffi::JS_EVAL_TYPE_MODULE
+        } else {
+            ffi::JS_EVAL_TYPE_GLOBAL
+        };
+        let func = ffi::JS_Eval(
+            ctx,
+            source.as_ptr(),
+            source.as_bytes().len(),
+            filename.as_ptr(),
+            (eval_type | ffi::JS_EVAL_FLAG_COMPILE_ONLY) as i32,
+        );
+
+        let res = if func.tag == i64::from(ffi::JS_TAG_EXCEPTION) {
+            let err = ffi::JS_GetException(ctx);
+            let msg = to_string(ctx, err);
+
+            free_value(ctx, err);
+
+            Err(msg)
+        } else {
+            let mut len = 0;
+            let buf = ffi::JS_WriteObject(ctx, &mut len, func, ffi::JS_WRITE_OBJ_BYTECODE as i32);
+
+            free_value(ctx, func);
+
+            if buf.is_null() {
+                Err("fail to write bytecode".to_owned())
+            } else {
+                let bytecode = slice::from_raw_parts(buf, len).to_vec();
+
+                ffi::js_free(ctx, buf as *mut _);
+
+                Ok(bytecode)
+            }
+        };
+
+        ffi::JS_FreeContext(ctx);
+        ffi::JS_FreeRuntime(rt);
+
+        res
+    }
+}
+
+/// The imported modules are only linked when evaluating, so compiling a module could resolve them to empty stubs.
+unsafe extern "C" fn stub_module_loader(
+    ctx: *mut ffi::JSContext,
+    module_name: *const c_char,
+    _opaque: *mut c_void,
+) -> *mut ffi::JSModuleDef {
+    unsafe extern "C" fn init(_ctx: *mut ffi::JSContext, _m: *mut ffi::JSModuleDef) -> c_int {
+        0
+    }
+
+    ffi::JS_NewCModule(ctx, module_name, Some(init))
+}
+
+unsafe fn to_string(ctx: *mut ffi::JSContext, v: ffi::JSValue) -> String {
+    let mut len = 0;
+    let p = ffi::JS_ToCStringLen2(ctx, &mut len, v, ffi::FALSE_VALUE);
+
+    if p.is_null() {
+        "unknown error".to_owned()
+    } else {
+        let s = CStr::from_ptr(p).to_string_lossy().into_owned();
+
+        ffi::JS_FreeCString(ctx, p);
+
+        s
+    }
+}
+
+unsafe fn free_value(ctx: *mut ffi::JSContext, v: ffi::JSValue) {
+    if (v.tag as u32) >= (ffi::JS_TAG_FIRST as u32) {
+        let ref_cnt = v.u.ptr as *mut ffi::JSRefCountHeader;
+
+        (*ref_cnt).ref_count -= 1;
+
+        if (*ref_cnt).ref_count <= 0 {
+            ffi::__JS_FreeValue(ctx, v)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compile_script() {
+        assert!(!compile(b"1 + 2", "test.js", false).unwrap().is_empty());
+        assert!(!compile(b"export default 42;", "test.mjs", false)
+            .unwrap()
+            .is_empty());
+        assert!(compile(b"1 +", "test.js", false)
+            .unwrap_err()
+            .starts_with("SyntaxError"));
+    }
+
+    #[test]
+    fn bundle_modules() {
+        assert_eq!(
+            scan_imports(
+                r#"
+import { a } from './a.js'; // import './comment.js'
+import "./b.js";
+export * from "../c.js";
+const s = 'import "./string.js"';
+/* from './block.js' */
+import('./d.js').then(d => d.from);
+"#
+            ),
+            vec!["./a.js", "./b.js", "../c.js", "./d.js"]
+        );
+
+        let root = env::temp_dir().join(format!("qjs-bundle-{}", std::process::id()));
+        let files: &[(&str, &str)] = &[
+            (
+                "main.js",
+                "import { b } from './lib/b.js'; import a from './lib/a.js';",
+            ),
+            (
+                "lib/a.js",
+                "import { b } from '../lib/b.js'; export default b;",
+            ),
+            ("lib/b.js", "export const b = 42;"),
+            ("unused.js", "export const c = 1;"),
+        ];
+
+        for (name, source) in files {
+            let path = root.join(name);
+
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, source).unwrap();
+        }
+
+        let modules = bundle(&root.join("main.js")).unwrap();
+
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            modules.iter().map(|m| m.name.as_str()).collect::<Vec<_>>(),
+            vec!["main.js", "lib/a.js", "lib/b.js"]
+        );
+        assert!(modules.iter().all(|m| !m.bytecode.is_empty()));
+    }
+}
@@ -0,0 +1,405 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{
+    ext::IdentExt, parse_quote, Data, DataEnum, DeriveInput, Error, Field, Fields, GenericParam,
+    Generics, Ident, LitStr, Result,
+};
+
+/// The property name of the variant in the tagged objects of the data enums.
+const TAG: &str = "type";
+
+/// The property name of the tuple variant data in the tagged objects.
+const VALUE: &str = "value";
+
+/// Derive `NewValue` for the structs and enums.
+///
+/// - the named structs are created as objects, the newtype structs as the inner value,
+///   the tuple structs as arrays and the unit structs as `null`.
+/// - the unit variants of enums are created as strings of the variant name,
+///   the others are created as the objects tagged with `type`,
+///   the tuple variants put their data at `value`, so their named fields can't be called `type`.
+/// - the raw identifiers are named without the `r#` prefix.
+pub fn derive_new_value(input: TokenStream) -> Result<TokenStream> {
+    let input: DeriveInput = syn::parse2(input)?;
+    let name = &input.ident;
+    let generics = add_bounds(input.generics.clone(), parse_quote!(qjs::NewValue));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    trace!("derive NewValue for {}", name);
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let bindings = bindings(&data.fields);
+            let pattern = pattern(quote! { #name }, &data.fields, &bindings);
+            let value = new_fields(&data.fields, &bindings, None);
+
+            quote! {
+                let #pattern = self;
+                #value
+            }
+        }
+        Data::Enum(data) => {
+            check_tag(data)?;
+
+            let arms = data.variants.iter().map(|variant| {
+                let ident = &variant.ident;
+                let tag = LitStr::new(&ident.unraw().to_string(), ident.span());
+                let bindings = bindings(&variant.fields);
+                let pattern = pattern(quote! { #name::#ident }, &variant.fields, &bindings);
+                let value = match &variant.fields {
+                    Fields::Unit => quote! { qjs::NewValue::new_value(#tag, ctxt) },
+                    Fields::Named(_) => new_fields(&variant.fields, &bindings, Some(&tag)),
+                    Fields::Unnamed(_) => {
+                        let value = new_fields(&variant.fields, &bindings, None);
+
+                        new_object(
+                            Some(&tag),
+                            quote! {
+                                obj.set_property(#VALUE, { #value })?;
+                            },
+                        )
+                    }
+                };
+
+                quote! { #pattern => { #value } }
+            });
+
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Union(_) => {
+            return Err(Error::new(
+                Span::call_site(),
+                "NewValue can't be derived for union",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics qjs::NewValue for #name #ty_generics #where_clause {
+            fn new_value(self, ctxt: &qjs::ContextRef) -> qjs::ffi::JSValue {
+                #body
+            }
+        }
+    })
+}
+
+/// Derive `ExtractValue` for the structs and enums, which accepts the shapes created by `NewValue`.
+pub fn derive_extract_value(input: TokenStream) -> Result<TokenStream> {
+    let input: DeriveInput = syn::parse2(input)?;
+    let name = &input.ident;
+    let generics = add_bounds(input.generics.clone(), parse_quote!(qjs::ExtractValue));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    trace!("derive ExtractValue for {}", name);
+
+    let body = match &input.data {
+        Data::Struct(data) => extract_fields(quote! { #name }, &data.fields, quote! { v }),
+        Data::Enum(data) => {
+            check_tag(data)?;
+
+            let units = data
+                .variants
+                .iter()
+                .filter(|variant| matches!(variant.fields, Fields::Unit))
+                .map(|variant| {
+                    let ident = &variant.ident;
+                    let tag = LitStr::new(&ident.unraw().to_string(), ident.span());
+
+                    quote! { #tag => Some(#name::#ident), }
+                });
+            let tagged = data
+                .variants
+                .iter()
+                .filter(|variant| !matches!(variant.fields, Fields::Unit))
+                .map(|variant| {
+                    let ident = &variant.ident;
+                    let tag = LitStr::new(&ident.unraw().to_string(), ident.span());
+                    let value = match &variant.fields {
+                        Fields::Named(_) => {
+                            extract_fields(quote! { #name::#ident }, &variant.fields, quote! { v })
+                        }
+                        _ => {
+                            let value = extract_fields(
+                                quote! { #name::#ident },
+                                &variant.fields,
+                                quote! { &value },
+                            );
+
+                            quote! {
+                                let value = v.get_property(#VALUE)?;
+                                #value
+                            }
+                        }
+                    };
+
+                    quote! { #tag => { #value } }
+                });
+
+            quote! {
+                if v.is_string() {
+                    match v.to_string().as_str() {
+                        #(#units)*
+                        _ => None,
+                    }
+                } else if v.is_object() {
+                    match v.extract_property::<_, String>(#TAG)?.as_str() {
+                        #(#tagged)*
+                        _ => None,
+                    }
+                } else {
+                    None
+                }
+            }
+        }
+        Data::Union(_) => {
+            return Err(Error::new(
+                Span::call_site(),
+                "ExtractValue can't be derived for union",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics qjs::ExtractValue for #name #ty_generics #where_clause {
+            fn extract_value(v: &qjs::Local<qjs::Value>) -> Option<Self> {
+                #body
+            }
+        }
+    })
+}
+
+fn add_bounds(mut generics: Generics, bound: syn::TypeParamBound) -> Generics {
+    for param in &mut generics.params {
+        if let GenericParam::Type(ty) = param {
+            ty.bounds.push(bound.clone());
+        }
+    }
+
+    generics
+}
+
+/// The property name of the named field, without the `r#` prefix of the raw identifier.
+fn property_name(field: &Field) -> String {
+    field.ident.as_ref().unwrap().unraw().to_string()
+}
+
+/// The named fields of the data enum variants can't be called `type`, which is the tag of the variant.
+fn check_tag(data: &DataEnum) -> Result<()> {
+    for variant in &data.variants {
+        if let Fields::Named(named) = &variant.fields {
+            for ident in named.named.iter().flat_map(|field| field.ident.as_ref()) {
+                if ident.unraw() == TAG {
+                    return Err(Error::new(
+                        ident.span(),
+                        format!(
+                            "the field `{}` conflicts with the tag of variant `{}`",
+                            TAG, variant.ident
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The local names of the fields when destructuring.
+fn bindings(fields: &Fields) -> Vec<Ident> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(idx, field)| match &field.ident {
+            Some(ident) => format_ident!("field_{}", ident),
+            None => format_ident!("field_{}", idx),
+        })
+        .collect()
+}
+
+fn pattern(path: TokenStream, fields: &Fields, bindings: &[Ident]) -> TokenStream {
+    match fields {
+        Fields::Named(named) => {
+            let names = named.named.iter().map(|field| &field.ident);
+
+            quote! { #path { #(#names: #bindings),* } }
+        }
+        Fields::Unnamed(_) => quote! { #path ( #(#bindings),* ) },
+        Fields::Unit => quote! { #path },
+    }
+}
+
+/// Create an object, set the properties with `obj`, then returns it or the error.
+fn new_object(tag: Option<&LitStr>, props: TokenStream) -> TokenStream {
+    let tag = tag.map(|tag| quote! { obj.set_property(#TAG, #tag)?; });
+
+    quote! {
+        let obj = ctxt.bind(ctxt.new_object());
+        let res = (|| -> ::std::result::Result<_, qjs::Error> {
+            #tag
+            #props
+            Ok(obj)
+        })();
+
+        qjs::NewValue::new_value(res, ctxt)
+    }
+}
+
+fn new_fields(fields: &Fields, bindings: &[Ident], tag: Option<&LitStr>) -> TokenStream {
+    match fields {
+        Fields::Named(named) => {
+            let names = named.named.iter().map(property_name);
+
+            new_object(
+                tag,
+                quote! {
+                    #(obj.set_property(#names, #bindings)?;)*
+                },
+            )
+        }
+        Fields::Unnamed(_) if bindings.len() == 1 => {
+            let binding = &bindings[0];
+
+            quote! { qjs::NewValue::new_value(#binding, ctxt) }
+        }
+        Fields::Unnamed(_) => {
+            let indexes = (0..bindings.len() as u32).collect::<Vec<_>>();
+
+            quote! {
+                let arr = ctxt.bind(ctxt.new_array());
+                let res = (|| -> ::std::result::Result<_, qjs::Error> {
+                    #(arr.set_property(#indexes, #bindings)?;)*
+                    Ok(arr)
+                })();
+
+                qjs::NewValue::new_value(res, ctxt)
+            }
+        }
+        Fields::Unit => quote! { qjs::ffi::NULL },
+    }
+}
+
+fn extract_fields(path: TokenStream, fields: &Fields, value: TokenStream) -> TokenStream {
+    match fields {
+        Fields::Named(named) => {
+            let idents = named.named.iter().map(|field| &field.ident);
+            let names = named.named.iter().map(property_name);
+
+            quote! {
+                let v: &qjs::Local<qjs::Value> = #value;
+
+                if !v.is_object() {
+                    return None;
+                }
+
+                Some(#path {
+                    #(#idents: v.extract_property(#names)?,)*
+                })
+            }
+        }
+        Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => {
+            quote! {
+                Some(#path(qjs::ExtractValue::extract_value(#value)?))
+            }
+        }
+        Fields::Unnamed(unnamed) => {
+            let indexes = (0..unnamed.unnamed.len() as u32).collect::<Vec<_>>();
+
+            quote! {
+                let v: &qjs::Local<qjs::Value> = #value;
+
+                if !v.is_array().unwrap_or_default() {
+                    return None;
+                }
+
+                Some(#path(#(v.extract_property(#indexes)?),*))
+            }
+        }
+        Fields::Unit => quote! { Some(#path) },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derive_unit_enum() {
+        let output = derive_new_value(quote! {
+            enum Color { Red, Green }
+        })
+        .unwrap()
+        .to_string();
+
+        assert!(
+            output.contains("impl qjs :: NewValue for Color"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("Color :: Red => { qjs :: NewValue :: new_value (\"Red\" , ctxt) }"),
+            "{}",
+            output
+        );
+
+        let output = derive_extract_value(quote! {
+            struct Point<T> { x: T, y: T }
+        })
+        .unwrap()
+        .to_string();
+
+        assert!(
+            output.contains("impl < T : qjs :: ExtractValue > qjs :: ExtractValue for Point < T >"),
+            "{}",
+            output
+        );
+
+        assert!(derive_new_value(quote! { union Foo { a: i32 } }).is_err());
+    }
+
+    #[test]
+    fn derive_raw_ident() {
+        let output = derive_new_value(quote! {
+            struct Token { r#type: String, r#loop: bool }
+        })
+        .unwrap()
+        .to_string();
+
+        assert!(
+            output.contains("obj . set_property (\"type\" , field_type)"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("obj . set_property (\"loop\" , field_loop)"),
+            "{}",
+            output
+        );
+
+        let output = derive_extract_value(quote! {
+            struct Token { r#type: String }
+        })
+        .unwrap()
+        .to_string();
+
+        assert!(
+            output.contains("v . extract_property (\"type\")"),
+            "{}",
+            output
+        );
+
+        for input in [
+            quote! { enum Event { Click { r#type: String } } },
+            quote! { enum Event { Click { x: i32, r#type: i32 } } },
+        ] {
+            assert!(derive_new_value(input.clone())
+                .unwrap_err()
+                .to_string()
+                .contains("conflicts with the tag"));
+            assert!(derive_extract_value(input).is_err());
+        }
+    }
+}
@@ -9,6 +9,9 @@ extern crate if_chain;
 #[macro_use]
 extern crate matches;
 
+mod compile;
+mod derive;
+
 use std::fmt;
 
 use proc_macro2::{Delimiter, Group, Ident, Spacing, Span, TokenStream, TokenTree};
@@ -22,6 +25,9 @@ use syn::{
     Expr, FnArg, Result, ReturnType, Type,
 };
 
+pub use compile::{qjs_bundle, qjs_compile};
+pub use derive::{derive_extract_value, derive_new_value};
+
 pub fn qjs(input: TokenStream) -> Result<TokenStream> {
     match syn::parse2(input)? {
         Item::Eval(Eval { context, script }) => {
@@ -34,21 +40,14 @@ pub fn qjs(input: TokenStream) -> Result<TokenStream> {
                 script.to_string(),
             );
 
-            let context = context.map_or_else(
-                || {
-                    quote! {
-                        let rt = qjs::Runtime::new();
-                        let ctxt = qjs::Context::new(&rt);
-                    }
-                },
-                |WithContext { ident, .. }| {
-                    quote! {
-                        let ctxt = #ident;
-                    }
-                },
-            );
+            let awaiting = has_await(&script);
             let mut vars = vec![];
-            let interpolated_script = interpolate(script, &mut vars)?.to_string();
+            let interpolated_script = interpolate(script, &mut vars)?;
+            let interpolated_script = if awaiting {
+                async_script(interpolated_script)
+            } else {
+                interpolated_script.to_string()
+            };
 
             trace!("found {} variables: {:?}", vars.len(), vars);
             trace!("interpolated script: {}", interpolated_script.to_string());
@@ -77,13 +76,27 @@ pub fn qjs(input: TokenStream) -> Result<TokenStream> {
                 }
             });
 
-            let expanded = quote! {{
-                #context
+            let body = quote! {
                 #global
                 #(#captures)*
 
-                ctxt.eval(#interpolated_script, qjs::Eval::GLOBAL)
-            }};
+                ctxt.eval_script(#interpolated_script, "<evalScript>", qjs::Eval::GLOBAL)
+                    .and_then(|v| v.resolve_promise())
+                    .map(|v| if v.is_undefined() {
+                        None
+                    } else {
+                        qjs::ExtractValue::extract_value(&v)
+                    })
+            };
+            let expanded = match context {
+                Some(WithContext { ident, .. }) => quote! {{
+                    let ctxt = #ident;
+                    #body
+                }},
+                None => quote! {
+                    qjs::with_context(|ctxt| { #body })
+                },
+            };
 
             trace!("generated:\n{}", expanded.to_string());
 
@@ -121,10 +134,12 @@ pub fn qjs(input: TokenStream) -> Result<TokenStream> {
                 })
                 .collect::<Vec<_>>();
 
+            let awaiting = has_await(&script);
             let mut vars = vec![];
             let interpolated_script = interpolate(script, &mut vars)?;
             let script = format!(
-                "({}) => {{ {} }}",
+                "{}({}) => {{ {} }}",
+                if awaiting { "async " } else { "" },
                 param_names.join(", "),
                 interpolated_script.to_string()
             );
@@ -143,7 +158,7 @@ pub fn qjs(input: TokenStream) -> Result<TokenStream> {
                         ReturnType::Type(
                             rarrow,
                             Box::new(Type::Path(parse_quote! {
-                                Result<Option<#output_ty>, failure::Error>
+                                Result<Option<#output_ty>, qjs::Error>
                             })),
                         ),
                         output_ty,
@@ -172,19 +187,20 @@ pub fn qjs(input: TokenStream) -> Result<TokenStream> {
 
             let expanded = quote! {
                 move | #(#args),* | #output {
-                    let rt = qjs::Runtime::new();
-                    let ctxt = qjs::Context::new(&rt);
-                    #global
-                    #(#captures)*
-
-                    let func = ctxt.eval_script(#script, "<evalScript>", qjs::Eval::GLOBAL)?;
-
-                    func.call(None, (#(#args),*))
-                        .map(|v| if v.is_undefined() {
-                            None
-                        } else {
-                            <#output_ty as qjs::ExtractValue>::extract_value(&v)
-                        })
+                    qjs::with_context(|ctxt| -> Result<Option<#output_ty>, qjs::Error> {
+                        #global
+                        #(#captures)*
+
+                        let func = ctxt.eval_script(#script, "<evalScript>", qjs::Eval::GLOBAL)?;
+
+                        func.call(None, (#(#args),*))
+                            .and_then(|v| v.resolve_promise())
+                            .map(|v| if v.is_undefined() {
+                                None
+                            } else {
+                                <#output_ty as qjs::ExtractValue>::extract_value(&v)
+                            })
+                    })
                 }
             };
 
@@ -351,7 +367,13 @@ fn interpolate(input: TokenStream, vars: &mut Vec<Variable>) -> Result<TokenStre
 
                 output.extend(quote! { #name });
 
-                vars.push(Variable::Ident(name.clone()));
+                // the variable interpolated twice will only be set once
+                if !vars.iter().any(|var| match var {
+                    Variable::Ident(ident) => ident == name,
+                    _ => false,
+                }) {
+                    vars.push(Variable::Ident(name.clone()));
+                }
             }
             TokenTree::Group(ref group)
                 if interpolating.is_some() && group.delimiter() == Delimiter::Parenthesis =>
@@ -380,6 +402,38 @@ fn interpolate(input: TokenStream, vars: &mut Vec<Variable>) -> Result<TokenStre
     Ok(output)
 }
 
+/// Returns `true` if the script contains an `await` expression.
+fn has_await(input: &TokenStream) -> bool {
+    input.clone().into_iter().any(|token| match token {
+        TokenTree::Ident(ref ident) => ident == "await",
+        TokenTree::Group(ref group) => has_await(&group.stream()),
+        _ => false,
+    })
+}
+
+/// Wrap the script in an async function, which returns the value of the last statement.
+fn async_script(input: TokenStream) -> String {
+    let tokens = input.into_iter().collect::<Vec<_>>();
+    let pos = tokens
+        .iter()
+        .rposition(|token| match token {
+            TokenTree::Punct(ref punct) => punct.as_char() == ';',
+            _ => false,
+        })
+        .map_or(0, |pos| pos + 1);
+    let (stmts, expr) = tokens.split_at(pos);
+    let stmts = stmts.iter().cloned().collect::<TokenStream>();
+    let expr = expr.iter().cloned().collect::<TokenStream>();
+
+    if expr.is_empty() {
+        format!("(async () => {{ {} }})()", stmts)
+    } else if stmts.is_empty() {
+        format!("(async () => {{ return {}; }})()", expr)
+    } else {
+        format!("(async () => {{ {} return {}; }})()", stmts, expr)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
@@ -390,11 +444,17 @@ mod tests {
     fn javascript() {
         assert_eq!(
             qjs(quote! { 1+2 }).unwrap().to_string(),
-            quote! {{
-                let rt = qjs::Runtime::new();
-                let ctxt = qjs::Context::new(&rt);
-                ctxt.eval("1 + 2", qjs::Eval::GLOBAL)
-            }}
+            quote! {
+                qjs::with_context(|ctxt| {
+                    ctxt.eval_script("1 + 2", "<evalScript>", qjs::Eval::GLOBAL)
+                        .and_then(|v| v.resolve_promise())
+                        .map(|v| if v.is_undefined() {
+                            None
+                        } else {
+                            qjs::ExtractValue::extract_value(&v)
+                        })
+                })
+            }
             .to_string(),
         );
 
@@ -402,7 +462,13 @@ mod tests {
             qjs(quote! { ctxt => 1+2 }).unwrap().to_string(),
             quote! {{
                 let ctxt = ctxt;
-                ctxt.eval("1 + 2", qjs::Eval::GLOBAL)
+                ctxt.eval_script("1 + 2", "<evalScript>", qjs::Eval::GLOBAL)
+                    .and_then(|v| v.resolve_promise())
+                    .map(|v| if v.is_undefined() {
+                        None
+                    } else {
+                        qjs::ExtractValue::extract_value(&v)
+                    })
             }}
             .to_string(),
         );
@@ -410,16 +476,18 @@ mod tests {
         assert_eq!(
             qjs(quote! { () => 1+2 }).unwrap().to_string(),
             quote! {move | | {
-                let rt = qjs::Runtime::new();
-                let ctxt = qjs::Context::new(&rt);
-                let func = ctxt.eval_script("() => { 1 + 2 }", "<evalScript>", qjs::Eval::GLOBAL)?;
-                func.call(None, ()).map(|v|
-                    if v.is_undefined() {
-                        None
-                    } else {
-                        <() as qjs::ExtractValue>::extract_value(&v)
-                    }
-                )
+                qjs::with_context(|ctxt| -> Result<Option<()>, qjs::Error> {
+                    let func = ctxt.eval_script("() => { 1 + 2 }", "<evalScript>", qjs::Eval::GLOBAL)?;
+                    func.call(None, ())
+                        .and_then(|v| v.resolve_promise())
+                        .map(|v|
+                        if v.is_undefined() {
+                            None
+                        } else {
+                            <() as qjs::ExtractValue>::extract_value(&v)
+                        }
+                    )
+                })
             }}
             .to_string()
         );
@@ -429,23 +497,48 @@ mod tests {
                 .unwrap()
                 .to_string(),
             quote! {
-                move |n| -> Result<Option<usize>, failure::Error> {
-                    let rt = qjs::Runtime::new();
-                    let ctxt = qjs::Context::new(&rt);
-                    let func = ctxt.eval_script("(n) => { n + 1 }", "<evalScript>", qjs::Eval::GLOBAL)?;
-                    func.call(None, (n)).map(|v|
-                        if v.is_undefined() {
-                            None
-                        } else {
-                            <usize as qjs::ExtractValue>::extract_value(&v)
-                        }
-                    )
+                move |n| -> Result<Option<usize>, qjs::Error> {
+                    qjs::with_context(|ctxt| -> Result<Option<usize>, qjs::Error> {
+                        let func = ctxt.eval_script("(n) => { n + 1 }", "<evalScript>", qjs::Eval::GLOBAL)?;
+                        func.call(None, (n))
+                            .and_then(|v| v.resolve_promise())
+                            .map(|v|
+                            if v.is_undefined() {
+                                None
+                            } else {
+                                <usize as qjs::ExtractValue>::extract_value(&v)
+                            }
+                        )
+                    })
                 }
             }
             .to_string()
         );
     }
 
+    #[test]
+    fn awaiting() {
+        assert!(has_await(&quote! { foo(await bar()) }));
+        assert!(!has_await(&quote! { foo(bar()) }));
+
+        assert_eq!(
+            async_script(quote! { await foo() }),
+            format!("(async () => {{ return {}; }})()", quote! { await foo() })
+        );
+        assert_eq!(
+            async_script(quote! { let x = await foo(); x + 1 }),
+            format!(
+                "(async () => {{ {} return {}; }})()",
+                quote! { let x = await foo(); },
+                quote! { x + 1 }
+            )
+        );
+        assert_eq!(
+            async_script(quote! { await foo(); }),
+            format!("(async () => {{ {} }})()", quote! { await foo(); })
+        );
+    }
+
     #[test]
     fn eval() {
         let e: Eval = parse_quote! { 1+2 };
@@ -543,5 +636,19 @@ mod tests {
             .to_string(),
             quote! { print(var1) }.to_string()
         );
+
+        assert_eq!(
+            interpolate(
+                TokenStream::from_str("print(#person.name, #person.age, #name)").unwrap(),
+                &mut vars
+            )
+            .unwrap()
+            .to_string(),
+            quote! { print(person.name, person.age, name) }.to_string()
+        );
+        assert_eq!(
+            format!("{:?}", vars),
+            r#"[Variable::Ident("name"), Variable::Expr("person . name"), Variable::Ident("person")]"#
+        );
     }
 }
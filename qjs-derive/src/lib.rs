@@ -14,6 +14,42 @@ pub fn qjs(input: TokenStream) -> TokenStream {
         .into()
 }
 
+#[proc_macro_hack]
+pub fn qjs_compile(input: TokenStream) -> TokenStream {
+    LOG_INIT.call_once(log_init);
+
+    qjs_derive_support::qjs_compile(proc_macro2::TokenStream::from(input))
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+#[proc_macro_hack]
+pub fn qjs_bundle(input: TokenStream) -> TokenStream {
+    LOG_INIT.call_once(log_init);
+
+    qjs_derive_support::qjs_bundle(proc_macro2::TokenStream::from(input))
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+#[proc_macro_derive(NewValue)]
+pub fn derive_new_value(input: TokenStream) -> TokenStream {
+    LOG_INIT.call_once(log_init);
+
+    qjs_derive_support::derive_new_value(proc_macro2::TokenStream::from(input))
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+#[proc_macro_derive(ExtractValue)]
+pub fn derive_extract_value(input: TokenStream) -> TokenStream {
+    LOG_INIT.call_once(log_init);
+
+    qjs_derive_support::derive_extract_value(proc_macro2::TokenStream::from(input))
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
 const ERROR: usize = 0;
 const WARN: usize = 1;
 const INFO: usize = 2;
@@ -9,6 +9,7 @@ use lazy_static::lazy_static;
 use regex::Regex;
 
 const QUICKJS_SRC: &str = "quickjs-2019-09-18.tar.xz";
+const DISASM_SRC: &str = "src/disasm.c";
 
 lazy_static! {
     static ref OUT_DIR: PathBuf = env::var_os("OUT_DIR").expect("OUT_DIR").into();
@@ -97,6 +98,92 @@ fn patch_quickjs(quickjs: &Path) -> Result<(), Error> {
         content = content.replace("//#define DUMP_READ_OBJECT\n", "#define DUMP_READ_OBJECT\n");
     }
 
+    if !content.contains("JS_GetModuleNamespace") {
+        // the namespace of a module is not exposed by the public API
+        content.push_str(
+            "\nJSValue JS_GetModuleNamespace(JSContext *ctx, JSModuleDef *m)\n{\n    if (!m->instantiated)\n        return JS_ThrowTypeError(ctx, \"module is not instantiated\");\n    return js_get_module_ns(ctx, m);\n}\n",
+        );
+    }
+
+    if !content.contains("JS_SetContextStackTop") {
+        // the stack top is recorded when the context is created, it must be updated on another thread
+        content.push_str(
+            "\nvoid JS_SetContextStackTop(JSContext *ctx, const uint8_t *stack_top)\n{\n    ctx->stack_top = stack_top;\n}\n",
+        );
+    }
+
+    if !content.contains("JS_GetObjectClassID") {
+        // the class ID of the object is not exposed by the public API
+        content.push_str(
+            "\nJSClassID JS_GetObjectClassID(JSValueConst obj)\n{\n    if (JS_VALUE_GET_TAG(obj) != JS_TAG_OBJECT)\n        return 0;\n    return JS_VALUE_GET_OBJ(obj)->class_id;\n}\n",
+        );
+    }
+
+    if !content.contains("JS_GetTypedArrayType") {
+        // the class ID of the builtin objects is not exposed by the public API
+        content.push_str(
+            "\nint JS_GetArrayBufferType(JSValueConst obj)\n{\n    JSObject *p;\n\n    if (JS_VALUE_GET_TAG(obj) != JS_TAG_OBJECT)\n        return 0;\n    p = JS_VALUE_GET_OBJ(obj);\n    switch (p->class_id) {\n    case JS_CLASS_ARRAY_BUFFER:\n        return 1;\n    case JS_CLASS_SHARED_ARRAY_BUFFER:\n        return 2;\n    default:\n        return 0;\n    }\n}\n\nint JS_GetTypedArrayType(JSValueConst obj)\n{\n    JSObject *p;\n\n    if (JS_VALUE_GET_TAG(obj) != JS_TAG_OBJECT)\n        return -1;\n    p = JS_VALUE_GET_OBJ(obj);\n    switch (p->class_id) {\n    case JS_CLASS_UINT8C_ARRAY:\n        return 0;\n    case JS_CLASS_INT8_ARRAY:\n        return 1;\n    case JS_CLASS_UINT8_ARRAY:\n        return 2;\n    case JS_CLASS_INT16_ARRAY:\n        return 3;\n    case JS_CLASS_UINT16_ARRAY:\n        return 4;\n    case JS_CLASS_INT32_ARRAY:\n        return 5;\n    case JS_CLASS_UINT32_ARRAY:\n        return 6;\n#ifdef CONFIG_BIGNUM\n    case JS_CLASS_BIG_INT64_ARRAY:\n        return 7;\n    case JS_CLASS_BIG_UINT64_ARRAY:\n        return 8;\n#endif\n    case JS_CLASS_FLOAT32_ARRAY:\n        return 9;\n    case JS_CLASS_FLOAT64_ARRAY:\n        return 10;\n    case JS_CLASS_DATAVIEW:\n        return 11;\n    default:\n        return -1;\n    }\n}\n",
+        );
+    }
+
+    if !content.contains("JS_GetTypedArrayBuffer") {
+        // the `buffer`, `byteOffset` and `byteLength` getters could be overridden by the scripts
+        content.push_str(
+            "\nJSValue JS_GetTypedArrayBuffer(JSContext *ctx, JSValueConst obj, size_t *pbyte_offset, size_t *pbyte_length, size_t *pbytes_per_element)\n{\n    JSObject *p;\n    JSTypedArray *ta;\n\n    if (JS_GetTypedArrayType(obj) < 0)\n        return JS_ThrowTypeError(ctx, \"not a TypedArray or DataView\");\n    p = JS_VALUE_GET_OBJ(obj);\n    ta = p->u.typed_array;\n    if (pbyte_offset)\n        *pbyte_offset = ta->offset;\n    if (pbyte_length)\n        *pbyte_length = typed_array_is_detached(ctx, p) ? 0 : ta->length;\n    if (pbytes_per_element)\n        *pbytes_per_element = p->class_id == JS_CLASS_DATAVIEW ? 1 : 1 << typed_array_size_log2(p->class_id);\n    return JS_DupValue(ctx, JS_MKPTR(JS_TAG_OBJECT, ta->buffer));\n}\n",
+        );
+    }
+
+    if !content.contains("JS_SetModuleInitFunc") {
+        // the init function of a C module is replaced to filter the exports of the builtin modules
+        content.push_str(
+            "\nJSModuleInitFunc *JS_SetModuleInitFunc(JSModuleDef *m, JSModuleInitFunc *func)\n{\n    JSModuleInitFunc *prev = m->init_func;\n    m->init_func = func;\n    return prev;\n}\n",
+        );
+    }
+
+    if !content.contains("JS_NewSymbolWithDescription") {
+        // the symbols are created by the intrinsics, which the scripts can't override
+        content.push_str(
+            "\nJSValue JS_NewSymbolWithDescription(JSContext *ctx, const char *descr, size_t len)\n{\n    JSValue str;\n\n    if (!descr)\n        return JS_NewSymbol(ctx, NULL, JS_ATOM_TYPE_SYMBOL);\n    str = JS_NewStringLen(ctx, descr, len);\n    if (JS_IsException(str))\n        return JS_EXCEPTION;\n    return JS_NewSymbol(ctx, JS_VALUE_GET_STRING(str), JS_ATOM_TYPE_SYMBOL);\n}\n\nJSValue JS_GetWellKnownSymbol(JSContext *ctx, int idx)\n{\n    if (idx < 0 || idx > JS_ATOM_Symbol_asyncIterator - JS_ATOM_Symbol_toPrimitive)\n        return JS_ThrowRangeError(ctx, \"invalid well-known symbol\");\n    return JS_AtomToValue(ctx, JS_ATOM_Symbol_toPrimitive + idx);\n}\n",
+        );
+    }
+
+    if !content.contains("JS_NewPrivateSymbol") {
+        // the private symbols are hidden from the scripts, like the brands of the classes
+        content.push_str(
+            "\nJSValue JS_NewPrivateSymbol(JSContext *ctx, const char *descr)\n{\n    JSValue str;\n\n    str = JS_NewString(ctx, descr);\n    if (JS_IsException(str))\n        return JS_EXCEPTION;\n    return JS_NewSymbol(ctx, JS_VALUE_GET_STRING(str), JS_ATOM_TYPE_PRIVATE);\n}\n",
+        );
+    }
+
+    if !content.contains("JS_GetPendingJobContext") {
+        // the job list is private, the context of the next job is charged for the job
+        content.push_str(
+            "\nJSContext *JS_GetPendingJobContext(JSRuntime *rt)\n{\n    if (list_empty(&rt->job_list))\n        return NULL;\n    return list_entry(rt->job_list.next, JSJobEntry, link)->ctx;\n}\n",
+        );
+    }
+
+    if !content.contains("JS_SameValue") {
+        // the `SameValue` algorithms compare the strings and numbers without any conversion
+        content.push_str(
+            "\nint JS_SameValue(JSContext *ctx, JSValueConst op1, JSValueConst op2, int zero)\n{\n    return zero ? js_same_value_zero(ctx, op1, op2) : js_same_value(ctx, op1, op2);\n}\n",
+        );
+    }
+
+    if !content.contains("JS_SetContextInt64Policy") {
+        // the policy is checked for each 64-bit integer, so it is cached on the context
+        content = content.replace(
+            "struct JSContext {\n    JSRuntime *rt;\n",
+            "struct JSContext {\n    JSRuntime *rt;\n    int int64_policy;\n",
+        );
+        content.push_str(
+            "\nint JS_GetContextInt64Policy(JSContext *ctx)\n{\n    return ctx->int64_policy;\n}\n\nvoid JS_SetContextInt64Policy(JSContext *ctx, int policy)\n{\n    ctx->int64_policy = policy;\n}\n",
+        );
+    }
+
+    if !content.contains("JS_DisasmInstr") {
+        // the bytecode is private, so the disassembler must be compiled with the engine
+        content.push_str(&fs::read_to_string(CARGO_MANIFEST_DIR.join(DISASM_SRC))?);
+    }
+
     fs::rename(quickjs, quickjs.with_extension("bak"))?;
     fs::write(quickjs, content.as_bytes())?;
 
@@ -113,6 +200,13 @@ fn patch_quickjs_libc(quickjs_libc: &Path) -> Result<(), Error> {
             .replace("(&st.st_ctim)", "(&st.st_ctimespec)");
     }
 
+    if !content.contains("js_std_poll") {
+        // the timers and handlers are private, the host polls them without blocking
+        content.push_str(
+            "\nint64_t js_os_next_timer_deadline(void)\n{\n    struct list_head *el;\n    int64_t cur_time, delay, min_delay = -1;\n\n    if (os_pending_signals != 0)\n        return 0;\n    if (list_empty(&os_timers))\n        return -1;\n    cur_time = get_time_ms();\n    list_for_each(el, &os_timers) {\n        JSOSTimer *th = list_entry(el, JSOSTimer, link);\n        delay = th->timeout - cur_time;\n        if (delay < 0)\n            delay = 0;\n        if (min_delay < 0 || delay < min_delay)\n            min_delay = delay;\n    }\n    return min_delay;\n}\n\nint js_std_poll(JSContext *ctx)\n{\n    struct list_head *el;\n    int64_t cur_time;\n\n    if (unlikely(os_pending_signals != 0)) {\n        JSOSSignalHandler *sh;\n        uint64_t mask;\n\n        list_for_each(el, &os_signal_handlers) {\n            sh = list_entry(el, JSOSSignalHandler, link);\n            mask = (uint64_t)1 << sh->sig_num;\n            if (os_pending_signals & mask) {\n                os_pending_signals &= ~mask;\n                call_handler(ctx, sh->func);\n                return 1;\n            }\n        }\n    }\n\n    if (list_empty(&os_rw_handlers) && list_empty(&os_timers))\n        return -1;\n\n    cur_time = get_time_ms();\n    list_for_each(el, &os_timers) {\n        JSOSTimer *th = list_entry(el, JSOSTimer, link);\n        if (th->timeout <= cur_time) {\n            JSValue func = th->func;\n            th->func = JS_UNDEFINED;\n            unlink_timer(JS_GetRuntime(ctx), th);\n            if (!th->has_object)\n                free_timer(JS_GetRuntime(ctx), th);\n            call_handler(ctx, func);\n            JS_FreeValue(ctx, func);\n            return 1;\n        }\n    }\n\n#ifndef _WIN32\n    if (!list_empty(&os_rw_handlers)) {\n        JSOSRWHandler *rh;\n        fd_set rfds, wfds;\n        struct timeval tv = { 0, 0 };\n        int fd_max = -1;\n\n        FD_ZERO(&rfds);\n        FD_ZERO(&wfds);\n        list_for_each(el, &os_rw_handlers) {\n            rh = list_entry(el, JSOSRWHandler, link);\n            fd_max = max_int(fd_max, rh->fd);\n            if (!JS_IsNull(rh->rw_func[0]))\n                FD_SET(rh->fd, &rfds);\n            if (!JS_IsNull(rh->rw_func[1]))\n                FD_SET(rh->fd, &wfds);\n        }\n        if (select(fd_max + 1, &rfds, &wfds, NULL, &tv) > 0) {\n            list_for_each(el, &os_rw_handlers) {\n                rh = list_entry(el, JSOSRWHandler, link);\n                if (!JS_IsNull(rh->rw_func[0]) && FD_ISSET(rh->fd, &rfds)) {\n                    call_handler(ctx, rh->rw_func[0]);\n                    return 1;\n                }\n                if (!JS_IsNull(rh->rw_func[1]) && FD_ISSET(rh->fd, &wfds)) {\n                    call_handler(ctx, rh->rw_func[1]);\n                    return 1;\n                }\n            }\n        }\n    }\n#endif\n\n    return 0;\n}\n",
+        );
+    }
+
     fs::rename(quickjs_libc, quickjs_libc.with_extension("bak"))?;
     fs::write(quickjs_libc, content.as_bytes())?;
 
@@ -179,6 +273,7 @@ fn build_libquickjs() -> Result<(), Error> {
     );
     println!("cargo:rustc-link-lib=static={}", quickjs);
     println!("cargo:rerun-if-changed={}", QUICKJS_SRC);
+    println!("cargo:rerun-if-changed={}", DISASM_SRC);
 
     if cfg!(feature = "repl") {
         cc::Build::new()
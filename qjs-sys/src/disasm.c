@@ -0,0 +1,232 @@
+
+/* The bytecode disassembler, appended to `quickjs.c` by the build script of `qjs-sys`. */
+
+static const char * const js_disasm_opcode_names[OP_COUNT + (OP_TEMP_END - OP_TEMP_START)] = {
+#define DEF(id, size, n_pop, n_push, f) #id,
+#include "quickjs-opcode.h"
+#undef DEF
+};
+
+#if SHORT_OPCODES
+#define js_disasm_opcode_index(op) \
+    ((op) >= OP_TEMP_START ? (op) + (OP_TEMP_END - OP_TEMP_START) : (op))
+#else
+#define js_disasm_opcode_index(op) (op)
+#endif
+
+enum {
+    JS_DISASM_OPERAND_INT,
+    JS_DISASM_OPERAND_LABEL,
+    JS_DISASM_OPERAND_ATOM,
+    JS_DISASM_OPERAND_LOC,
+    JS_DISASM_OPERAND_ARG,
+    JS_DISASM_OPERAND_VAR_REF,
+    JS_DISASM_OPERAND_CONST,
+};
+
+typedef struct JSDisasmOperand {
+    int kind;
+    int32_t value;
+} JSDisasmOperand;
+
+typedef struct JSDisasmInstr {
+    uint32_t pos;
+    const char *name;
+    int operand_count;
+    JSDisasmOperand operands[3];
+} JSDisasmInstr;
+
+typedef struct JSDisasmFunction {
+    JSAtom name;
+    JSAtom filename;
+    int line_num;
+    int arg_count;
+    int var_count;
+    int closure_var_count;
+    int cpool_count;
+    int byte_code_len;
+} JSDisasmFunction;
+
+static JSFunctionBytecode *js_disasm_get_bytecode(JSValueConst val)
+{
+    JSObject *p;
+
+    switch (JS_VALUE_GET_TAG(val)) {
+    case JS_TAG_FUNCTION_BYTECODE:
+        return JS_VALUE_GET_PTR(val);
+    case JS_TAG_OBJECT:
+        p = JS_VALUE_GET_OBJ(val);
+        if (p->class_id == JS_CLASS_BYTECODE_FUNCTION)
+            return p->u.func.function_bytecode;
+        return NULL;
+    default:
+        return NULL;
+    }
+}
+
+int JS_DisasmFunction(JSValueConst val, JSDisasmFunction *f)
+{
+    JSFunctionBytecode *b = js_disasm_get_bytecode(val);
+
+    if (!b)
+        return -1;
+    f->name = b->func_name;
+    f->filename = b->has_debug ? b->debug.filename : JS_ATOM_NULL;
+    f->line_num = b->has_debug ? b->debug.line_num : 0;
+    f->arg_count = b->arg_count;
+    f->var_count = b->var_count;
+    f->closure_var_count = b->closure_var_count;
+    f->cpool_count = b->cpool_count;
+    f->byte_code_len = b->byte_code_len;
+    return 0;
+}
+
+/* kind: 0 for the arguments, 1 for the local variables and 2 for the closure variables */
+JSAtom JS_DisasmVarName(JSValueConst val, int kind, int idx)
+{
+    JSFunctionBytecode *b = js_disasm_get_bytecode(val);
+
+    if (!b)
+        return JS_ATOM_NULL;
+    switch (kind) {
+    case 0:
+        return b->vardefs && idx < b->arg_count ? b->vardefs[idx].var_name : JS_ATOM_NULL;
+    case 1:
+        return b->vardefs && idx < b->var_count ? b->vardefs[b->arg_count + idx].var_name : JS_ATOM_NULL;
+    case 2:
+        return idx < b->closure_var_count ? b->closure_var[idx].var_name : JS_ATOM_NULL;
+    default:
+        return JS_ATOM_NULL;
+    }
+}
+
+JSValue JS_DisasmConst(JSContext *ctx, JSValueConst val, int idx)
+{
+    JSFunctionBytecode *b = js_disasm_get_bytecode(val);
+
+    if (!b || idx < 0 || idx >= b->cpool_count)
+        return JS_UNDEFINED;
+    return JS_DupValue(ctx, b->cpool[idx]);
+}
+
+static void js_disasm_operand(JSDisasmInstr *instr, int kind, int32_t value)
+{
+    instr->operands[instr->operand_count].kind = kind;
+    instr->operands[instr->operand_count].value = value;
+    instr->operand_count++;
+}
+
+/* decode the instruction at `pos`, returns the position of the next one, or -1 on the invalid opcode */
+int JS_DisasmInstr(JSValueConst val, uint32_t pos, JSDisasmInstr *instr)
+{
+    JSFunctionBytecode *b = js_disasm_get_bytecode(val);
+    const uint8_t *tab;
+    const JSOpCode *oi;
+    int op;
+
+    if (!b || pos >= b->byte_code_len)
+        return -1;
+    tab = b->byte_code_buf;
+    op = tab[pos];
+    if (op >= OP_COUNT)
+        return -1;
+    oi = &short_opcode_info(op);
+    if (pos + oi->size > b->byte_code_len)
+        return -1;
+    instr->pos = pos;
+    instr->name = js_disasm_opcode_names[js_disasm_opcode_index(op)];
+    instr->operand_count = 0;
+    pos++;
+    switch (oi->fmt) {
+    case OP_FMT_none_int:
+        js_disasm_operand(instr, JS_DISASM_OPERAND_INT, op - OP_push_0);
+        break;
+    case OP_FMT_npopx:
+        js_disasm_operand(instr, JS_DISASM_OPERAND_INT, op - OP_call0);
+        break;
+    case OP_FMT_u8:
+        js_disasm_operand(instr, JS_DISASM_OPERAND_INT, get_u8(tab + pos));
+        break;
+    case OP_FMT_i8:
+        js_disasm_operand(instr, JS_DISASM_OPERAND_INT, get_i8(tab + pos));
+        break;
+    case OP_FMT_u16:
+    case OP_FMT_npop:
+        js_disasm_operand(instr, JS_DISASM_OPERAND_INT, get_u16(tab + pos));
+        break;
+    case OP_FMT_npop_u16:
+        js_disasm_operand(instr, JS_DISASM_OPERAND_INT, get_u16(tab + pos));
+        js_disasm_operand(instr, JS_DISASM_OPERAND_INT, get_u16(tab + pos + 2));
+        break;
+    case OP_FMT_i16:
+        js_disasm_operand(instr, JS_DISASM_OPERAND_INT, get_i16(tab + pos));
+        break;
+    case OP_FMT_i32:
+    case OP_FMT_u32:
+        js_disasm_operand(instr, JS_DISASM_OPERAND_INT, get_i32(tab + pos));
+        break;
+#if SHORT_OPCODES
+    case OP_FMT_label8:
+        js_disasm_operand(instr, JS_DISASM_OPERAND_LABEL, pos + get_i8(tab + pos));
+        break;
+    case OP_FMT_label16:
+        js_disasm_operand(instr, JS_DISASM_OPERAND_LABEL, pos + get_i16(tab + pos));
+        break;
+    case OP_FMT_const8:
+        js_disasm_operand(instr, JS_DISASM_OPERAND_CONST, get_u8(tab + pos));
+        break;
+#endif
+    case OP_FMT_label:
+        js_disasm_operand(instr, JS_DISASM_OPERAND_LABEL, pos + get_u32(tab + pos));
+        break;
+    case OP_FMT_label_u16:
+        js_disasm_operand(instr, JS_DISASM_OPERAND_LABEL, pos + get_u32(tab + pos));
+        js_disasm_operand(instr, JS_DISASM_OPERAND_INT, get_u16(tab + pos + 4));
+        break;
+    case OP_FMT_const:
+        js_disasm_operand(instr, JS_DISASM_OPERAND_CONST, get_u32(tab + pos));
+        break;
+    case OP_FMT_atom:
+        js_disasm_operand(instr, JS_DISASM_OPERAND_ATOM, get_u32(tab + pos));
+        break;
+    case OP_FMT_atom_u8:
+        js_disasm_operand(instr, JS_DISASM_OPERAND_ATOM, get_u32(tab + pos));
+        js_disasm_operand(instr, JS_DISASM_OPERAND_INT, get_u8(tab + pos + 4));
+        break;
+    case OP_FMT_atom_u16:
+        js_disasm_operand(instr, JS_DISASM_OPERAND_ATOM, get_u32(tab + pos));
+        js_disasm_operand(instr, JS_DISASM_OPERAND_INT, get_u16(tab + pos + 4));
+        break;
+    case OP_FMT_atom_label_u8:
+    case OP_FMT_atom_label_u16:
+        js_disasm_operand(instr, JS_DISASM_OPERAND_ATOM, get_u32(tab + pos));
+        js_disasm_operand(instr, JS_DISASM_OPERAND_LABEL, pos + 4 + get_u32(tab + pos + 4));
+        js_disasm_operand(instr, JS_DISASM_OPERAND_INT,
+                          oi->fmt == OP_FMT_atom_label_u8 ? get_u8(tab + pos + 8) : get_u16(tab + pos + 8));
+        break;
+    case OP_FMT_none_loc:
+        js_disasm_operand(instr, JS_DISASM_OPERAND_LOC, (op - OP_get_loc0) % 4);
+        break;
+    case OP_FMT_loc8:
+        js_disasm_operand(instr, JS_DISASM_OPERAND_LOC, get_u8(tab + pos));
+        break;
+    case OP_FMT_loc:
+        js_disasm_operand(instr, JS_DISASM_OPERAND_LOC, get_u16(tab + pos));
+        break;
+    case OP_FMT_none_arg:
+        js_disasm_operand(instr, JS_DISASM_OPERAND_ARG, (op - OP_get_arg0) % 4);
+        break;
+    case OP_FMT_arg:
+        js_disasm_operand(instr, JS_DISASM_OPERAND_ARG, get_u16(tab + pos));
+        break;
+    case OP_FMT_none_var_ref:
+        js_disasm_operand(instr, JS_DISASM_OPERAND_VAR_REF, (op - OP_get_var_ref0) % 4);
+        break;
+    case OP_FMT_var_ref:
+        js_disasm_operand(instr, JS_DISASM_OPERAND_VAR_REF, get_u16(tab + pos));
+        break;
+    default:
+        break;
+    }
+    return instr->pos + oi->size;
+}
@@ -8,6 +8,8 @@ extern crate cfg_if;
 #[macro_use]
 extern crate lazy_static;
 
+use std::os::raw::{c_char, c_int};
+
 cfg_if! {
     if #[cfg(feature = "gen")] {
         include!(concat!(env!("OUT_DIR"), "/raw.rs"));
@@ -16,6 +18,125 @@ cfg_if! {
     }
 }
 
+extern "C" {
+    /// Returns the namespace object of the module, patched into `quickjs.c` by the build script.
+    pub fn JS_GetModuleNamespace(ctx: *mut JSContext, m: *mut JSModuleDef) -> JSValue;
+
+    /// Set the stack top of the context to detect the stack overflow, patched into `quickjs.c` by the build script.
+    pub fn JS_SetContextStackTop(ctx: *mut JSContext, stack_top: *const u8);
+
+    /// Returns the class ID of the object, or 0 for the other values.
+    pub fn JS_GetObjectClassID(obj: JSValue) -> JSClassID;
+
+    /// Replace the init function of a C module, returns the previous one.
+    pub fn JS_SetModuleInitFunc(m: *mut JSModuleDef, func: JSModuleInitFunc) -> JSModuleInitFunc;
+
+    /// Create a new symbol with the optional description, like `Symbol(descr)`.
+    pub fn JS_NewSymbolWithDescription(
+        ctx: *mut JSContext,
+        descr: *const c_char,
+        len: usize,
+    ) -> JSValue;
+
+    /// Returns the well-known symbol in the order of `Symbol.toPrimitive`..`Symbol.asyncIterator`.
+    pub fn JS_GetWellKnownSymbol(ctx: *mut JSContext, idx: c_int) -> JSValue;
+
+    /// Create a new private symbol, which properties are invisible to the scripts.
+    pub fn JS_NewPrivateSymbol(ctx: *mut JSContext, descr: *const c_char) -> JSValue;
+
+    /// Returns the context of the next pending job, or null if no job is pending.
+    pub fn JS_GetPendingJobContext(rt: *mut JSRuntime) -> *mut JSContext;
+
+    /// Returns the milliseconds until the next timer of the `os` module fires, or -1 without timers.
+    pub fn js_os_next_timer_deadline() -> i64;
+
+    /// Call one ready signal, timer or I/O handler of the `os` module without blocking.
+    ///
+    /// Returns 1 if a handler was called, 0 if none is ready and -1 if no handler is registered.
+    pub fn js_std_poll(ctx: *mut JSContext) -> c_int;
+
+    /// Returns 1 for `ArrayBuffer`, 2 for `SharedArrayBuffer` and 0 for the other values.
+    pub fn JS_GetArrayBufferType(obj: JSValue) -> c_int;
+
+    /// Returns the type of the typed array or `DataView` in the order of `JS_CLASS_UINT8C_ARRAY`..,
+    /// 11 for `DataView`, or -1 for the other values.
+    pub fn JS_GetTypedArrayType(obj: JSValue) -> c_int;
+
+    /// Returns the underlying buffer of the typed array or `DataView` with the offset and length in bytes,
+    /// the length is 0 if the buffer was detached.
+    pub fn JS_GetTypedArrayBuffer(
+        ctx: *mut JSContext,
+        obj: JSValue,
+        pbyte_offset: *mut usize,
+        pbyte_length: *mut usize,
+        pbytes_per_element: *mut usize,
+    ) -> JSValue;
+
+    /// Compare two values with the `SameValue` algorithm, or `SameValueZero` if `zero` is not 0.
+    pub fn JS_SameValue(ctx: *mut JSContext, op1: JSValue, op2: JSValue, zero: c_int) -> c_int;
+
+    /// Returns the policy of the 64-bit integers cached on the context, or 0 if it was not set.
+    pub fn JS_GetContextInt64Policy(ctx: *mut JSContext) -> c_int;
+
+    /// Cache the policy of the 64-bit integers on the context.
+    pub fn JS_SetContextInt64Policy(ctx: *mut JSContext, policy: c_int);
+
+    /// Describe the bytecode function, returns -1 if the value is not a bytecode function.
+    pub fn JS_DisasmFunction(val: JSValue, f: *mut JSDisasmFunction) -> c_int;
+
+    /// Returns the name of the argument (kind 0), local variable (kind 1) or closure variable (kind 2).
+    pub fn JS_DisasmVarName(val: JSValue, kind: c_int, idx: c_int) -> JSAtom;
+
+    /// Returns the item of the constant pool, which should be freed by the caller.
+    pub fn JS_DisasmConst(ctx: *mut JSContext, val: JSValue, idx: c_int) -> JSValue;
+
+    /// Decode the instruction at the position, returns the position of the next one or -1 if invalid.
+    pub fn JS_DisasmInstr(val: JSValue, pos: u32, instr: *mut JSDisasmInstr) -> c_int;
+}
+
+/// The null atom, e.g. the name of an anonymous function.
+pub const JS_ATOM_NULL: JSAtom = 0;
+
+pub const JS_DISASM_OPERAND_INT: c_int = 0;
+pub const JS_DISASM_OPERAND_LABEL: c_int = 1;
+pub const JS_DISASM_OPERAND_ATOM: c_int = 2;
+pub const JS_DISASM_OPERAND_LOC: c_int = 3;
+pub const JS_DISASM_OPERAND_ARG: c_int = 4;
+pub const JS_DISASM_OPERAND_VAR_REF: c_int = 5;
+pub const JS_DISASM_OPERAND_CONST: c_int = 6;
+
+/// An operand of the disassembled instruction, patched into `quickjs.c` by the build script.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JSDisasmOperand {
+    pub kind: c_int,
+    pub value: i32,
+}
+
+/// The disassembled instruction, patched into `quickjs.c` by the build script.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct JSDisasmInstr {
+    pub pos: u32,
+    pub name: *const c_char,
+    pub operand_count: c_int,
+    pub operands: [JSDisasmOperand; 3],
+}
+
+/// The description of a bytecode function, patched into `quickjs.c` by the build script.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JSDisasmFunction {
+    pub name: JSAtom,
+    pub filename: JSAtom,
+    pub line_num: c_int,
+    pub arg_count: c_int,
+    pub var_count: c_int,
+    pub closure_var_count: c_int,
+    pub cpool_count: c_int,
+    pub byte_code_len: c_int,
+}
+
 lazy_static! {
     pub static ref VERSION: &'static str =
         include_str!(concat!(env!("OUT_DIR"), "/VERSION")).trim();
@@ -0,0 +1,191 @@
+use std::os::raw::c_int;
+use std::panic;
+use std::slice;
+
+use foreign_types::ForeignTypeRef;
+
+use crate::{
+    cfunc::c_args, ffi, ContextRef, DefinePropertyGetSet, Error, ExtractValue, Local, NewValue,
+    Prop, Value,
+};
+
+unsafe extern "C" fn getter<T, G>(
+    ctx: *mut ffi::JSContext,
+    _this_val: ffi::JSValue,
+    _argc: c_int,
+    _argv: *mut ffi::JSValue,
+    _magic: c_int,
+    data: *mut ffi::JSValue,
+) -> ffi::JSValue
+where
+    T: NewValue,
+    G: Fn(&ContextRef) -> T + Send + 'static,
+{
+    panic::catch_unwind(|| {
+        let ctxt = ContextRef::from_ptr(ctx);
+        let data = slice::from_raw_parts(data as *const Value, 1);
+        let get = ctxt.downcast_native::<G>(&data[0]).expect("getter");
+
+        get(ctxt).new_value(ctxt)
+    })
+    .unwrap_or_default()
+}
+
+unsafe extern "C" fn setter<T, S>(
+    ctx: *mut ffi::JSContext,
+    _this_val: ffi::JSValue,
+    argc: c_int,
+    argv: *mut ffi::JSValue,
+    _magic: c_int,
+    data: *mut ffi::JSValue,
+) -> ffi::JSValue
+where
+    T: ExtractValue,
+    S: Fn(&ContextRef, T) + Send + 'static,
+{
+    panic::catch_unwind(|| {
+        let ctxt = ContextRef::from_ptr(ctx);
+        let data = slice::from_raw_parts(data as *const Value, 1);
+        let set = ctxt.downcast_native::<S>(&data[0]).expect("setter");
+        let arg = c_args(argc, argv)
+            .first()
+            .and_then(|arg| T::extract_value(&ctxt.clone_value(arg)));
+
+        match arg {
+            Some(v) => {
+                set(ctxt, v);
+
+                ffi::UNDEFINED
+            }
+            None => ctxt.throw_type_error("invalid property value").into(),
+        }
+    })
+    .unwrap_or_default()
+}
+
+impl<'a> Local<'a, Value> {
+    /// Defines an accessor property backed by the Rust closures, see `ContextRef::define_accessor`.
+    pub fn define_accessor<N, T, G, S>(&self, prop: N, get: G, set: S) -> Result<bool, Error>
+    where
+        N: DefinePropertyGetSet,
+        T: NewValue + ExtractValue,
+        G: Fn(&ContextRef) -> T + Send + 'static,
+        S: Fn(&ContextRef, T) + Send + 'static,
+    {
+        self.ctxt.define_accessor(self, prop, get, set)
+    }
+
+    /// Defines a read-only accessor property backed by the Rust closure, see `ContextRef::define_getter`.
+    pub fn define_getter<N, T, G>(&self, prop: N, get: G) -> Result<bool, Error>
+    where
+        N: DefinePropertyGetSet,
+        T: NewValue,
+        G: Fn(&ContextRef) -> T + Send + 'static,
+    {
+        self.ctxt.define_getter(self, prop, get)
+    }
+}
+
+impl ContextRef {
+    /// Defines an accessor property backed by the Rust closures, e.g. a computed property of the Rust state.
+    ///
+    /// The closures are owned by the getter and setter functions,
+    /// the setter throws a `TypeError` if the value could not be extracted as `T`.
+    pub fn define_accessor<N, T, G, S>(
+        &self,
+        obj: &Value,
+        prop: N,
+        get: G,
+        set: S,
+    ) -> Result<bool, Error>
+    where
+        N: DefinePropertyGetSet,
+        T: NewValue + ExtractValue,
+        G: Fn(&ContextRef) -> T + Send + 'static,
+        S: Fn(&ContextRef, T) + Send + 'static,
+    {
+        let getter: Value = self
+            .new_c_function_data(getter::<T, G>, 0, 0, self.wrap_native(get)?)?
+            .into();
+        let setter: Value = self
+            .new_c_function_data(setter::<T, S>, 1, 0, self.wrap_native(set)?)?
+            .into();
+
+        // the getter and setter are freed by the engine
+        prop.define_property(
+            self,
+            obj,
+            Some(&getter),
+            Some(&setter),
+            Prop::CONFIGURABLE | Prop::ENUMERABLE,
+        )
+    }
+
+    /// Defines a read-only accessor property backed by the Rust closure.
+    pub fn define_getter<N, T, G>(&self, obj: &Value, prop: N, get: G) -> Result<bool, Error>
+    where
+        N: DefinePropertyGetSet,
+        T: NewValue,
+        G: Fn(&ContextRef) -> T + Send + 'static,
+    {
+        let getter: Value = self
+            .new_c_function_data(getter::<T, G>, 0, 0, self.wrap_native(get)?)?
+            .into();
+
+        prop.define_property(
+            self,
+            obj,
+            Some(&getter),
+            None,
+            Prop::CONFIGURABLE | Prop::ENUMERABLE,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicI32, Ordering},
+        Arc,
+    };
+
+    use crate::{Context, Eval, Runtime};
+
+    #[test]
+    fn define_accessor() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let counter = Arc::new(AtomicI32::new(1));
+        let obj = ctxt.global_object();
+
+        let (get, set) = (counter.clone(), counter.clone());
+
+        assert!(obj
+            .define_accessor(
+                "count",
+                move |_| get.load(Ordering::SeqCst),
+                move |_, n| set.store(n, Ordering::SeqCst)
+            )
+            .unwrap());
+        assert!(obj.define_getter("version", |_| "1.0").unwrap());
+
+        assert_eq!(ctxt.eval("count += 41", Eval::GLOBAL).unwrap(), Some(42));
+        assert_eq!(counter.load(Ordering::SeqCst), 42);
+        assert_eq!(
+            ctxt.eval("Object.keys(globalThis).includes('count')", Eval::GLOBAL)
+                .unwrap(),
+            Some(true)
+        );
+        assert_eq!(
+            ctxt.eval::<_, String>("version", Eval::GLOBAL).unwrap(),
+            Some("1.0".to_owned())
+        );
+        assert!(ctxt
+            .eval::<_, ()>("count = Symbol()", Eval::GLOBAL)
+            .is_err());
+        assert_eq!(counter.load(Ordering::SeqCst), 42);
+    }
+}
@@ -0,0 +1,305 @@
+use std::cmp;
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+use foreign_types::ForeignTypeRef;
+
+use crate::{ffi, ContextRef, Error, ExtractValue, Local, NewValue, Prop, Value};
+
+/// A lazy iterator over the elements of a Javascript array (or array-like object).
+///
+/// The `length` property is read once upfront, then each element is fetched on demand,
+/// so huge arrays can be processed without materializing a `Vec`.
+pub struct Elements<'a, T> {
+    array: Local<'a, Value>,
+    index: u32,
+    len: u32,
+    phantom: PhantomData<T>,
+}
+
+impl<'a, T> Elements<'a, T> {
+    /// The number of elements of the array.
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Returns `true` if the array contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<'a, T: ExtractValue> Iterator for Elements<'a, T> {
+    type Item = Option<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index < self.len {
+            let idx = self.index;
+
+            self.index += 1;
+
+            Some(
+                self.array
+                    .get_property(idx)
+                    .and_then(|v| T::extract_value(&v)),
+            )
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.len - self.index) as usize;
+
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a, T: ExtractValue> ExactSizeIterator for Elements<'a, T> {}
+
+impl<'a> Local<'a, Value> {
+    /// Returns a lazy iterator that extracts the elements of an array.
+    ///
+    /// The `undefined` elements or the elements that can't be extracted will be returned as `None`.
+    pub fn iter_elements<T: ExtractValue>(&self) -> Elements<'a, T> {
+        let len = self
+            .get_property("length")
+            .and_then(|len| len.to_index())
+            .map_or(0, |len| cmp::min(len, u64::from(u32::max_value())) as u32);
+
+        trace!("iterate {} elements of {:?}", len, self.inner);
+
+        Elements {
+            array: self.clone(),
+            index: 0,
+            len,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a> Local<'a, Value> {
+    /// Returns `true` if the value is an array, or a proxy of an array.
+    pub fn is_array(&self) -> Result<bool, Error> {
+        self.ctxt.is_array(self)
+    }
+
+    /// Converts to the `JsArray` wrapper if the value is an array.
+    pub fn into_array(self) -> Result<JsArray<'a>, Self> {
+        if self.is_array().unwrap_or_default() {
+            Ok(JsArray(self))
+        } else {
+            Err(self)
+        }
+    }
+}
+
+/// A Javascript array.
+#[repr(transparent)]
+#[derive(Clone, Debug)]
+pub struct JsArray<'a>(Local<'a, Value>);
+
+impl<'a> NewValue for JsArray<'a> {
+    fn new_value(self, ctxt: &ContextRef) -> ffi::JSValue {
+        self.0.new_value(ctxt)
+    }
+}
+
+impl<'a> Deref for JsArray<'a> {
+    type Target = Local<'a, Value>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'a> JsArray<'a> {
+    /// Create a new empty array.
+    pub fn new(ctxt: &'a ContextRef) -> Self {
+        JsArray(ctxt.bind(ctxt.new_array()))
+    }
+
+    /// Create a new array with the values.
+    pub fn from_iter<I>(ctxt: &'a ContextRef, iter: I) -> Result<Self, Error>
+    where
+        I: IntoIterator,
+        I::Item: NewValue,
+    {
+        let arr = JsArray::new(ctxt);
+
+        for (idx, value) in iter.into_iter().enumerate() {
+            arr.set(idx as u32, value)?;
+        }
+
+        Ok(arr)
+    }
+
+    /// Returns the `length` of the array.
+    pub fn len(&self) -> usize {
+        self.0
+            .get_property("length")
+            .and_then(|len| len.to_index())
+            .unwrap_or_default() as usize
+    }
+
+    /// Returns `true` if the array contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the element at the index, or `None` if it is out of bounds or `undefined`.
+    pub fn get(&self, idx: u32) -> Option<Local<'a, Value>> {
+        self.0.ctxt.get_property(&self.0, idx)
+    }
+
+    /// Set the element at the index, the array grows if the index is out of bounds.
+    pub fn set<V: NewValue>(&self, idx: u32, value: V) -> Result<(), Error> {
+        self.0.set_property(idx, value).map(|_| ())
+    }
+
+    /// Append the value to the end of the array.
+    pub fn push<V: NewValue>(&self, value: V) -> Result<(), Error> {
+        self.set(self.len() as u32, value)
+    }
+
+    /// Remove the last element and returns it, or `None` if the array is empty.
+    pub fn pop(&self) -> Result<Option<Local<'a, Value>>, Error> {
+        match self.len() {
+            0 => Ok(None),
+            len => {
+                let last = self
+                    .get(len as u32 - 1)
+                    .unwrap_or_else(|| self.0.ctxt.undefined());
+
+                self.0.set_property("length", len as u32 - 1)?;
+
+                Ok(Some(last))
+            }
+        }
+    }
+
+    /// Returns a lazy iterator that extracts the elements, see `Local::iter_elements`.
+    pub fn iter<T: ExtractValue>(&self) -> Elements<'a, T> {
+        self.0.iter_elements()
+    }
+
+    /// Converts to the bound value.
+    pub fn into_value(self) -> Local<'a, Value> {
+        self.0
+    }
+}
+
+impl ContextRef {
+    /// Returns `true` if the value is an array, or a proxy of an array.
+    pub fn is_array(&self, val: &Value) -> Result<bool, Error> {
+        self.check_bool(unsafe { ffi::JS_IsArray(self.as_ptr(), val.raw()) })
+    }
+
+    /// Create a new array with the bound values, each value is referenced by the array instead of extracted.
+    pub fn array_from_locals(&self, values: &[Local<Value>]) -> Result<Local<Value>, Error> {
+        let arr = self.bind(self.new_array());
+
+        for (idx, value) in values.iter().enumerate() {
+            arr.define_property_value(idx as u32, value, Prop::C_W_E)?;
+        }
+
+        Ok(arr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, Eval, Runtime};
+
+    use super::*;
+
+    #[test]
+    fn iter_elements() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let arr = ctxt
+            .eval_script("[1, 2, , undefined]", "<evalScript>", Eval::GLOBAL)
+            .unwrap();
+
+        let mut iter = arr.iter_elements::<i32>();
+
+        assert_eq!(iter.len(), 4);
+        assert_eq!(iter.next(), Some(Some(1)));
+        assert_eq!(iter.next(), Some(Some(2)));
+        assert_eq!(iter.next(), Some(None));
+        assert_eq!(iter.next(), Some(None));
+        assert_eq!(iter.next(), None);
+
+        let obj = ctxt
+            .eval_script("new Object()", "<evalScript>", Eval::GLOBAL)
+            .unwrap();
+
+        assert!(obj.iter_elements::<i32>().is_empty());
+    }
+
+    #[test]
+    fn js_array() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let arr = JsArray::from_iter(&ctxt, vec![1, 2, 3]).unwrap();
+
+        assert_eq!(arr.len(), 3);
+
+        arr.push("four").unwrap();
+        arr.set(5, true).unwrap();
+
+        assert_eq!(arr.to_string(), "1,2,3,four,,true");
+        assert_eq!(arr.get(3).unwrap().to_string(), "four");
+        assert!(arr.get(4).is_none());
+        assert_eq!(arr.pop().unwrap().unwrap().as_bool(), Some(true));
+        assert_eq!(arr.len(), 5);
+        assert_eq!(
+            arr.iter::<String>().collect::<Vec<_>>(),
+            vec![
+                Some("1".to_owned()),
+                Some("2".to_owned()),
+                Some("3".to_owned()),
+                Some("four".to_owned()),
+                None
+            ]
+        );
+
+        ctxt.global_object().set_property("arr", arr).unwrap();
+
+        let arr = ctxt
+            .eval_script("arr.splice(0, 4)", "<evalScript>", Eval::GLOBAL)
+            .unwrap()
+            .into_array()
+            .unwrap();
+
+        assert_eq!(arr.len(), 4);
+        assert!(JsArray::new(&ctxt).pop().unwrap().is_none());
+        assert!(ctxt.bind(ctxt.new_object()).into_array().is_err());
+    }
+
+    #[test]
+    fn array_from_locals() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let obj = ctxt.bind(ctxt.new_object());
+        let values = vec![ctxt.bind(1), ctxt.bind("foo"), obj.clone()];
+        let arr = ctxt.array_from_locals(&values).unwrap();
+
+        assert_eq!(arr.to_string(), "1,foo,[object Object]");
+        assert!(ctxt.same_value(&arr.get_property(2).unwrap(), &obj));
+        assert!(ctxt
+            .array_from_locals(&[])
+            .unwrap()
+            .iter_elements::<i32>()
+            .is_empty());
+    }
+}
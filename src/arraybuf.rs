@@ -1,10 +1,13 @@
 use std::ops::Deref;
+use std::os::raw::c_void;
 use std::ptr;
 use std::slice::{self, SliceIndex};
+use std::sync::atomic::AtomicU8;
+use std::sync::Arc;
 
 use foreign_types::ForeignTypeRef;
 
-use crate::{ffi, value::NewValue, ContextRef, Local, Value};
+use crate::{ffi, value::NewValue, ContextRef, Error, Local, Value};
 
 /// `ArrayBuffer` represent a generic, fixed-length raw binary data buffer.
 #[repr(transparent)]
@@ -17,6 +20,87 @@ pub struct ArrayBuffer<'a>(Local<'a, Value>);
 #[derive(Debug)]
 pub struct SharedArrayBuffer<'a>(Local<'a, Value>);
 
+/// The memory shared between the runtimes, e.g. the runtimes of the worker threads created by the host.
+///
+/// QuickJS allocates the `SharedArrayBuffer` created by the script from the runtime,
+/// so the memory should be allocated by the host and exposed to each runtime
+/// with `ContextRef::new_shared_memory_buffer`, the memory is freed after the last reference is dropped.
+#[derive(Clone, Debug)]
+pub struct SharedMemory(Arc<[AtomicU8]>);
+
+impl SharedMemory {
+    /// Allocate the zeroed memory of the given length.
+    pub fn new(len: usize) -> Self {
+        SharedMemory((0..len).map(|_| AtomicU8::new(0)).collect())
+    }
+}
+
+impl Deref for SharedMemory {
+    type Target = [AtomicU8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// The type of the `TypedArray` elements.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TypedArrayType {
+    Uint8Clamped,
+    Int8,
+    Uint8,
+    Int16,
+    Uint16,
+    Int32,
+    Uint32,
+    BigInt64,
+    BigUint64,
+    Float32,
+    Float64,
+    /// The `DataView` of the bytes.
+    DataView,
+}
+
+impl TypedArrayType {
+    fn from_raw(ty: i32) -> Option<Self> {
+        use TypedArrayType::*;
+
+        [
+            Uint8Clamped,
+            Int8,
+            Uint8,
+            Int16,
+            Uint16,
+            Int32,
+            Uint32,
+            BigInt64,
+            BigUint64,
+            Float32,
+            Float64,
+            DataView,
+        ]
+        .get(ty as usize)
+        .cloned()
+    }
+
+    /// Returns the size of the element in bytes.
+    pub fn element_size(self) -> usize {
+        use TypedArrayType::*;
+
+        match self {
+            Uint8Clamped | Int8 | Uint8 | DataView => 1,
+            Int16 | Uint16 => 2,
+            Int32 | Uint32 | Float32 => 4,
+            BigInt64 | BigUint64 | Float64 => 8,
+        }
+    }
+}
+
+/// `TypedArray` or `DataView` represent a view of the underlying `ArrayBuffer`.
+#[repr(transparent)]
+#[derive(Debug)]
+pub struct TypedArray<'a>(Local<'a, Value>);
+
 impl<'a> NewValue for ArrayBuffer<'a> {
     fn new_value(self, ctxt: &ContextRef) -> ffi::JSValue {
         self.0.new_value(ctxt)
@@ -77,8 +161,8 @@ impl<'a> ArrayBuffer<'a> {
 }
 
 impl<'a> NewValue for SharedArrayBuffer<'a> {
-    fn new_value(self, _ctxt: &ContextRef) -> ffi::JSValue {
-        self.raw()
+    fn new_value(self, ctxt: &ContextRef) -> ffi::JSValue {
+        self.0.new_value(ctxt)
     }
 }
 
@@ -128,6 +212,136 @@ impl<'a> SharedArrayBuffer<'a> {
     }
 }
 
+impl<'a> NewValue for TypedArray<'a> {
+    fn new_value(self, ctxt: &ContextRef) -> ffi::JSValue {
+        self.0.new_value(ctxt)
+    }
+}
+
+impl<'a> Deref for TypedArray<'a> {
+    type Target = Local<'a, Value>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'a> AsRef<[u8]> for TypedArray<'a> {
+    fn as_ref(&self) -> &[u8] {
+        let (data, len) = self.data();
+
+        unsafe { slice::from_raw_parts(data, len) }
+    }
+}
+
+impl<'a> AsMut<[u8]> for TypedArray<'a> {
+    fn as_mut(&mut self) -> &mut [u8] {
+        let (data, len) = self.data();
+
+        unsafe { slice::from_raw_parts_mut(data, len) }
+    }
+}
+
+impl<'a> TypedArray<'a> {
+    /// Returns the type of the elements.
+    pub fn array_type(&self) -> TypedArrayType {
+        TypedArrayType::from_raw(unsafe { ffi::JS_GetTypedArrayType(self.raw()) })
+            .expect("typed array")
+    }
+
+    /// Returns the underlying `ArrayBuffer` or `SharedArrayBuffer` of the view.
+    pub fn buffer(&self) -> Result<ArrayBuffer<'a>, Error> {
+        self.view().map(|(buf, _, _)| buf)
+    }
+
+    /// Returns the offset of the view in bytes from the start of its `ArrayBuffer`.
+    pub fn byte_offset(&self) -> usize {
+        self.view().map_or(0, |(_, offset, _)| offset)
+    }
+
+    /// Returns the length of the view in bytes, or 0 if the buffer was detached.
+    pub fn byte_length(&self) -> usize {
+        self.view().map_or(0, |(_, _, len)| len)
+    }
+
+    /// Returns the number of the elements.
+    pub fn len(&self) -> usize {
+        self.byte_length() / self.array_type().element_size()
+    }
+
+    /// Returns `true` if the view has no element.
+    pub fn is_empty(&self) -> bool {
+        self.byte_length() == 0
+    }
+
+    /// Returns the buffer, offset and length of the view from the engine,
+    /// because the getters of the prototype could be overridden by the script.
+    fn view(&self) -> Result<(ArrayBuffer<'a>, usize, usize), Error> {
+        let mut offset = 0;
+        let mut len = 0;
+
+        self.ctxt
+            .bind(unsafe {
+                ffi::JS_GetTypedArrayBuffer(
+                    self.ctxt.as_ptr(),
+                    self.raw(),
+                    &mut offset,
+                    &mut len,
+                    ptr::null_mut(),
+                )
+            })
+            .ok()
+            .map(|buf| (ArrayBuffer(buf), offset, len))
+    }
+
+    fn data(&self) -> (*mut u8, usize) {
+        let empty = (ptr::NonNull::dangling().as_ptr(), 0);
+
+        let (buf, offset, len) = match self.view() {
+            // the detached buffer throws a `TypeError`
+            Ok((_, _, 0)) | Err(_) => return empty,
+            Ok(view) => view,
+        };
+
+        let mut size = 0;
+        let data = unsafe { ffi::JS_GetArrayBuffer(self.ctxt.as_ptr(), &mut size, buf.raw()) };
+
+        match offset.checked_add(len) {
+            Some(end) if !data.is_null() && end <= size => unsafe { (data.add(offset), len) },
+            _ => empty,
+        }
+    }
+}
+
+impl<'a> Local<'a, Value> {
+    /// Downcast the value to an `ArrayBuffer`, or `None` if it is not an `ArrayBuffer`.
+    pub fn as_array_buffer(&self) -> Option<ArrayBuffer<'a>> {
+        if unsafe { ffi::JS_GetArrayBufferType(self.raw()) } == 1 {
+            Some(ArrayBuffer(self.ctxt.clone_value(self)))
+        } else {
+            None
+        }
+    }
+
+    /// Downcast the value to a `SharedArrayBuffer`, or `None` if it is not a `SharedArrayBuffer`.
+    pub fn as_shared_array_buffer(&self) -> Option<SharedArrayBuffer<'a>> {
+        if unsafe { ffi::JS_GetArrayBufferType(self.raw()) } == 2 {
+            Some(SharedArrayBuffer(self.ctxt.clone_value(self)))
+        } else {
+            None
+        }
+    }
+
+    /// Downcast the value to a `TypedArray`, or `None` if it is not a `TypedArray` or `DataView`.
+    pub fn as_typed_array(&self) -> Option<TypedArray<'a>> {
+        if unsafe { ffi::JS_GetTypedArrayType(self.raw()) } >= 0 {
+            Some(TypedArray(self.ctxt.clone_value(self)))
+        } else {
+            None
+        }
+    }
+}
+
 impl ContextRef {
     /// Creates a new `ArrayBuffer` of the given bytes.
     pub fn new_array_buffer<T: AsMut<[u8]>>(&self, buf: &mut T) -> ArrayBuffer {
@@ -145,6 +359,66 @@ impl ContextRef {
         }))
     }
 
+    /// Creates a new `ArrayBuffer` which takes the ownership of the bytes without copying.
+    ///
+    /// The bytes are dropped when the `ArrayBuffer` is garbage collected.
+    pub fn new_array_buffer_with_free(&self, buf: Vec<u8>) -> ArrayBuffer {
+        let mut buf = Box::new(buf);
+        let data = buf.as_mut_ptr();
+        let len = buf.len();
+
+        ArrayBuffer(self.bind(unsafe {
+            ffi::JS_NewArrayBuffer(
+                self.as_ptr(),
+                data,
+                len,
+                Some(free_boxed::<Vec<u8>>),
+                Box::into_raw(buf) as *mut _,
+                ffi::FALSE_VALUE,
+            )
+        }))
+    }
+
+    /// Creates a new `ArrayBuffer` which shares the bytes of the `Arc` without copying.
+    ///
+    /// A reference of the `Arc` is held until the `ArrayBuffer` is garbage collected.
+    /// The script could modify the bytes through the views, so the bytes are atomic.
+    pub fn new_array_buffer_from_arc(&self, buf: Arc<[AtomicU8]>) -> ArrayBuffer {
+        let data = buf.as_ptr() as *mut u8;
+        let len = buf.len();
+
+        ArrayBuffer(self.bind(unsafe {
+            ffi::JS_NewArrayBuffer(
+                self.as_ptr(),
+                data,
+                len,
+                Some(free_boxed::<Arc<[AtomicU8]>>),
+                Box::into_raw(Box::new(buf)) as *mut _,
+                ffi::FALSE_VALUE,
+            )
+        }))
+    }
+
+    /// Creates a new `SharedArrayBuffer` backed by the memory shared with the other runtimes.
+    ///
+    /// The memory should be accessed through `Atomics` in the script, and the atomic operations in Rust.
+    pub fn new_shared_memory_buffer(&self, mem: &SharedMemory) -> SharedArrayBuffer {
+        let mem = mem.0.clone();
+        let data = mem.as_ptr() as *mut u8;
+        let len = mem.len();
+
+        SharedArrayBuffer(self.bind(unsafe {
+            ffi::JS_NewArrayBuffer(
+                self.as_ptr(),
+                data,
+                len,
+                Some(free_boxed::<Arc<[AtomicU8]>>),
+                Box::into_raw(Box::new(mem)) as *mut _,
+                ffi::TRUE_VALUE,
+            )
+        }))
+    }
+
     /// Creates a new `SharedArrayBuffer` of the given bytes.
     pub fn new_shared_array_buffer<T: Into<Vec<u8>>>(&self, buf: T) -> SharedArrayBuffer {
         let mut buf = Box::new(buf.into());
@@ -169,12 +443,92 @@ impl ContextRef {
             ffi::JS_NewArrayBufferCopy(self.as_ptr(), buf.as_mut_ptr(), buf.len())
         }))
     }
+
+    /// Creates a new `Uint8Array` which copy the given bytes.
+    #[cfg_attr(
+        not(any(feature = "protobuf", feature = "web-compat", feature = "http")),
+        allow(dead_code)
+    )]
+    pub(crate) fn new_uint8_array(&self, buf: &[u8]) -> Result<Local<Value>, Error> {
+        let buf = self
+            .bind(unsafe { ffi::JS_NewArrayBufferCopy(self.as_ptr(), buf.as_ptr(), buf.len()) });
+        let ctor = self
+            .get_property(&self.global_object(), "Uint8Array")
+            .ok_or_else(|| Error::from("`Uint8Array` not found"))?;
+
+        self.call_constructor(&ctor, &buf)
+    }
+
+    /// Access the bytes of an `ArrayBuffer` or a view of it, like `Uint8Array` or `DataView`.
+    #[cfg_attr(
+        not(any(
+            feature = "protobuf",
+            feature = "web-compat",
+            feature = "http",
+            feature = "wasm"
+        )),
+        allow(dead_code)
+    )]
+    pub(crate) fn with_buffer_source<T, F: FnOnce(&[u8]) -> T>(
+        &self,
+        value: &Value,
+        f: F,
+    ) -> Option<T> {
+        self.with_buffer_source_mut(value, |buf| f(buf))
+    }
+
+    /// Modify the bytes of an `ArrayBuffer` or a view of it in place.
+    ///
+    /// The view is resolved by the engine instead of the getters, which could be overridden by the script.
+    pub(crate) fn with_buffer_source_mut<T, F: FnOnce(&mut [u8]) -> T>(
+        &self,
+        value: &Value,
+        f: F,
+    ) -> Option<T> {
+        let value = self.clone_value(value);
+        let (buf, offset, len) = if let Some(view) = value.as_typed_array() {
+            match view.view().ok()? {
+                // the view of a detached buffer is empty
+                (_, _, 0) => return Some(f(&mut [])),
+                (buf, offset, len) => (buf.0, offset, Some(len)),
+            }
+        } else if unsafe { ffi::JS_GetArrayBufferType(value.raw()) } > 0 {
+            (value, 0, None)
+        } else {
+            return None;
+        };
+        let mut size = 0;
+        let data = unsafe { ffi::JS_GetArrayBuffer(self.as_ptr(), &mut size, buf.raw()) };
+        let len = len.unwrap_or(size);
+
+        match offset.checked_add(len) {
+            Some(end) if !data.is_null() && end <= size => Some(f(unsafe {
+                slice::from_raw_parts_mut(data.add(offset), len)
+            })),
+            _ => None,
+        }
+    }
+}
+
+/// Drop the owner of the bytes when the `ArrayBuffer` is freed.
+unsafe extern "C" fn free_boxed<T>(
+    _rt: *mut ffi::JSRuntime,
+    opaque: *mut c_void,
+    _ptr: *mut c_void,
+) {
+    trace!("free the bytes of the ArrayBuffer");
+
+    drop(Box::from_raw(opaque as *mut T))
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use crate::{Context, Eval, Runtime};
 
+    use super::*;
+
     #[test]
     fn array_buffer() {
         let _ = pretty_env_logger::try_init();
@@ -203,4 +557,192 @@ mod tests {
 
         assert_eq!(buf, [123, 0, 200, 1, 55, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
     }
+
+    #[test]
+    fn shared_memory() {
+        let _ = pretty_env_logger::try_init();
+
+        let mem = SharedMemory::new(8);
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+        let worker = {
+            let mem = mem.clone();
+
+            std::thread::spawn(move || {
+                let rt = Runtime::new();
+                let ctxt = Context::new(&rt);
+
+                rt.set_can_block(true);
+
+                let buf = ctxt.new_shared_memory_buffer(&mem);
+
+                ctxt.global_object().set_property("buf", buf).unwrap();
+                ctxt.eval::<_, String>(
+                    "const arr = new Int32Array(buf); Atomics.wait(arr, 0, 0, 5000)",
+                    Eval::GLOBAL,
+                )
+                .unwrap()
+            })
+        };
+
+        assert!(ctxt
+            .eval::<_, String>(
+                "Atomics.wait(new Int32Array(new SharedArrayBuffer(4)), 0, 0)",
+                Eval::GLOBAL
+            )
+            .is_err());
+
+        let buf = ctxt.new_shared_memory_buffer(&mem);
+
+        ctxt.global_object().set_property("buf", buf).unwrap();
+        ctxt.eval::<_, ()>(
+            "const arr = new Int32Array(buf); Atomics.store(arr, 0, 42); Atomics.notify(arr, 0)",
+            Eval::GLOBAL,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            worker.join().unwrap().unwrap().as_str(),
+            "ok" | "not-equal"
+        ));
+        assert_eq!(mem[0].load(std::sync::atomic::Ordering::SeqCst), 42);
+    }
+
+    #[test]
+    fn array_buffer_with_free() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let arr_buf = ctxt.new_array_buffer_with_free(vec![1, 2, 3, 4]);
+
+        assert!(ctxt.global_object().set_property("owned", arr_buf).unwrap());
+        assert_eq!(
+            ctxt.eval(
+                "new Uint8Array(owned).reduce((a, b) => a + b)",
+                Eval::GLOBAL
+            )
+            .unwrap(),
+            Some(10)
+        );
+
+        let bytes: Arc<[AtomicU8]> = b"hello".iter().map(|&b| AtomicU8::new(b)).collect();
+
+        {
+            let arr_buf = ctxt.new_array_buffer_from_arc(bytes.clone());
+
+            assert_eq!(Arc::strong_count(&bytes), 2);
+            assert_eq!(arr_buf.as_ref(), b"hello");
+            assert!(ctxt
+                .global_object()
+                .set_property("shared", arr_buf)
+                .unwrap());
+
+            ctxt.eval_script(
+                "new Uint8Array(shared)[0] = 72",
+                "<evalScript>",
+                Eval::GLOBAL,
+            )
+            .unwrap();
+
+            assert_eq!(bytes[0].load(std::sync::atomic::Ordering::SeqCst), b'H');
+            assert!(ctxt.global_object().delete_property("shared").unwrap());
+        }
+
+        rt.run_gc();
+
+        assert_eq!(Arc::strong_count(&bytes), 1);
+    }
+
+    #[test]
+    fn typed_array() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let value = ctxt
+            .eval_script(
+                "new Uint16Array(new Uint8Array([0, 1, 2, 3, 4, 5, 6, 7]).buffer, 2, 2)",
+                "<evalScript>",
+                Eval::GLOBAL,
+            )
+            .unwrap();
+        let mut arr = value.as_typed_array().unwrap();
+
+        assert_eq!(arr.array_type(), TypedArrayType::Uint16);
+        assert_eq!(arr.byte_offset(), 2);
+        assert_eq!(arr.len(), 2);
+        assert_eq!(arr.as_ref(), &[2, 3, 4, 5]);
+        assert_eq!(arr.buffer().unwrap().as_ref().len(), 8);
+
+        arr.as_mut()[0] = 0xff;
+
+        assert_eq!(arr.buffer().unwrap().get(2), Some(&0xff));
+
+        assert!(value.as_array_buffer().is_none());
+        assert!(arr.buffer().unwrap().as_array_buffer().is_some());
+
+        ctxt.global_object()
+            .set_property("arr", ctxt.clone_value(&arr))
+            .unwrap();
+        ctxt.eval_script(
+            r#"Object.defineProperties(arr, {
+                buffer: { get: () => new ArrayBuffer(1) },
+                byteOffset: { value: 1e9 },
+                byteLength: { value: 1e9 },
+            })"#,
+            "<evalScript>",
+            Eval::GLOBAL,
+        )
+        .unwrap();
+
+        assert_eq!(arr.byte_offset(), 2);
+        assert_eq!(arr.byte_length(), 4);
+        assert_eq!(arr.as_ref(), &[0xff, 3, 4, 5]);
+        assert_eq!(
+            ctxt.with_buffer_source(&arr, |buf| buf.to_vec()),
+            Some(vec![0xff, 3, 4, 5])
+        );
+        assert_eq!(
+            ctxt.with_buffer_source(&arr.buffer().unwrap(), |buf| buf.len()),
+            Some(8)
+        );
+        assert_eq!(
+            ctxt.with_buffer_source(&ctxt.bind(1), |buf| buf.len()),
+            None
+        );
+
+        let view = ctxt
+            .eval_script(
+                "new DataView(new ArrayBuffer(4))",
+                "<evalScript>",
+                Eval::GLOBAL,
+            )
+            .unwrap();
+
+        assert_eq!(
+            view.as_typed_array().map(|view| view.array_type()),
+            Some(TypedArrayType::DataView)
+        );
+
+        let detached = ctxt.new_array_buffer_copy(&mut [1, 2, 3]);
+        let arr = ctxt
+            .call_constructor(
+                &ctxt
+                    .get_property(&ctxt.global_object(), "Uint8Array")
+                    .unwrap(),
+                &*detached,
+            )
+            .unwrap()
+            .as_typed_array()
+            .unwrap();
+
+        detached.detach();
+
+        assert!(arr.as_ref().is_empty());
+        assert_eq!(ctxt.with_buffer_source(&arr, |buf| buf.len()), Some(0));
+        assert!(ctxt.undefined().as_typed_array().is_none());
+    }
 }
@@ -0,0 +1,170 @@
+use crate::{ContextRef, Error, Local, Value, WellKnownSymbol};
+
+/// The poll-based consumer of a JS async iterator, created by `Local::async_iter`.
+///
+/// Each `next` call drives the job queue until the item is resolved,
+/// the iteration stops after the iterator is done or an error is returned.
+pub struct AsyncIter<'a> {
+    ctxt: &'a ContextRef,
+    iter: Local<'a, Value>,
+    done: bool,
+}
+
+impl<'a> Local<'a, Value> {
+    /// Returns an iterator consuming the async iterable object, like `for await`.
+    ///
+    /// The sync iterable objects are accepted too, and their items are awaited.
+    pub fn async_iter(&self) -> Result<AsyncIter<'a>, Error> {
+        self.ctxt.async_iter(self)
+    }
+}
+
+impl ContextRef {
+    /// Returns an iterator consuming the async iterable object, see `Local::async_iter`.
+    pub fn async_iter<'a>(&'a self, iterable: &Value) -> Result<AsyncIter<'a>, Error> {
+        let method = [WellKnownSymbol::AsyncIterator, WellKnownSymbol::Iterator]
+            .iter()
+            .map(|&symbol| self.well_known_symbol(symbol))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .flat_map(|symbol| self.get_property(iterable, &*symbol))
+            .find(|method| method.is_function())
+            .ok_or("value is not async iterable")?;
+
+        let iter = self.call(&method, Some(iterable), ())?;
+
+        if !iter.is_object() {
+            return Err("iterator is not an object".into());
+        }
+
+        Ok(AsyncIter {
+            ctxt: self,
+            iter,
+            done: false,
+        })
+    }
+}
+
+impl<'a> AsyncIter<'a> {
+    /// Close the iterator before it is done, which calls its `return` method if any.
+    pub fn close(mut self) -> Result<(), Error> {
+        self.done = true;
+
+        match self.iter.get_property("return") {
+            Some(ret) if ret.is_function() => self
+                .ctxt
+                .call(&ret, Some(&self.iter), ())?
+                .resolve_promise()
+                .map(|_| ()),
+            _ => Ok(()),
+        }
+    }
+
+    fn next_item(&mut self) -> Result<Option<Local<'a, Value>>, Error> {
+        let ctxt = self.ctxt;
+        let res = ctxt.invoke(&self.iter, "next", ())?.resolve_promise()?;
+
+        if !res.is_object() {
+            return Err("iterator result is not an object".into());
+        }
+
+        if ctxt
+            .get_property(&res, "done")
+            .and_then(|done| done.to_bool())
+            .unwrap_or_default()
+        {
+            Ok(None)
+        } else {
+            ctxt.get_property(&res, "value")
+                .unwrap_or_else(|| ctxt.undefined())
+                .resolve_promise()
+                .map(Some)
+        }
+    }
+}
+
+impl<'a> Iterator for AsyncIter<'a> {
+    type Item = Result<Local<'a, Value>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let item = self.next_item();
+
+        self.done = !matches!(item, Ok(Some(_)));
+
+        item.transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, Eval, Runtime};
+
+    #[test]
+    fn async_iter() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let gen = ctxt
+            .eval_script(
+                "(async function* () { for (let i = 1; i <= 3; i++) yield await Promise.resolve(i) })()",
+                "<evalScript>",
+                Eval::GLOBAL,
+            )
+            .unwrap();
+
+        let items = gen
+            .async_iter()
+            .unwrap()
+            .map(|item| item.unwrap().as_int().unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(items, vec![1, 2, 3]);
+
+        let arr = ctxt
+            .eval_script("[Promise.resolve('a'), 'b']", "<evalScript>", Eval::GLOBAL)
+            .unwrap();
+
+        assert_eq!(
+            arr.async_iter()
+                .unwrap()
+                .map(|item| item.unwrap().to_string())
+                .collect::<Vec<_>>(),
+            vec!["a", "b"]
+        );
+
+        let gen = ctxt
+            .eval_script(
+                "var closed = false; (async function* () { try { yield 1; yield 2 } finally { closed = true } })()",
+                "<evalScript>",
+                Eval::GLOBAL,
+            )
+            .unwrap();
+        let mut iter = gen.async_iter().unwrap();
+
+        assert_eq!(iter.next().unwrap().unwrap().as_int(), Some(1));
+
+        iter.close().unwrap();
+
+        assert_eq!(ctxt.eval("closed", Eval::GLOBAL).unwrap(), Some(true));
+
+        let err = ctxt
+            .eval_script(
+                "(async function* () { yield 1; throw new Error('Whoops!') })()",
+                "<evalScript>",
+                Eval::GLOBAL,
+            )
+            .unwrap()
+            .async_iter()
+            .unwrap()
+            .collect::<Vec<_>>();
+
+        assert_eq!(err.len(), 2);
+        assert!(err[1].is_err());
+    }
+}
@@ -4,9 +4,9 @@ use std::os::raw::c_char;
 
 use foreign_types::ForeignTypeRef;
 
-use crate::{ffi, handle::Unbindable, ContextRef, Local, RuntimeRef, Value};
+use crate::{ffi, handle::Unbindable, ContextRef, Error, Local, RuntimeRef, Value};
 
-/// Create or find an `Atom` base on `&str`, `*const c_char` or `u32`.
+/// Create or find an `Atom` base on `&str`, `*const c_char`, `u32` or a property key `Value` like `Symbol`.
 pub trait NewAtom {
     /// Create or find an `Atom` in the context.
     fn new_atom(self, context: &ContextRef) -> ffi::JSAtom;
@@ -30,6 +30,19 @@ impl NewAtom for u32 {
     }
 }
 
+/// The conversion may fail and return `JS_ATOM_NULL` with a pending exception, see `ContextRef::try_new_atom`.
+impl NewAtom for &Value {
+    fn new_atom(self, context: &ContextRef) -> ffi::JSAtom {
+        unsafe { ffi::JS_ValueToAtom(context.as_ptr(), self.raw()) }
+    }
+}
+
+impl NewAtom for &Atom<'_> {
+    fn new_atom(self, context: &ContextRef) -> ffi::JSAtom {
+        context.clone_atom(**self).into_inner()
+    }
+}
+
 impl Unbindable for ffi::JSAtom {
     fn unbind(ctxt: &ContextRef, atom: ffi::JSAtom) {
         ctxt.free_atom(atom)
@@ -99,7 +112,18 @@ impl ContextRef {
         self.bind_atom(v.new_atom(self))
     }
 
+    /// Create or find an `Atom` in the context, returns the exception if the conversion of a `Value` failed.
+    pub fn try_new_atom<T: NewAtom>(&self, v: T) -> Result<Local<ffi::JSAtom>, Error> {
+        match v.new_atom(self) {
+            ffi::JS_ATOM_NULL => Err(self.take_exception()?.into()),
+            atom => Ok(self.bind_atom(atom)),
+        }
+    }
+
     pub fn bind_atom(&self, atom: ffi::JSAtom) -> Local<ffi::JSAtom> {
+        #[cfg(feature = "leak-check")]
+        crate::scope::track_local(true);
+
         Local {
             ctxt: self,
             inner: Some(atom),
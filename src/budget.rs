@@ -0,0 +1,145 @@
+use foreign_types::ForeignTypeRef;
+
+use crate::{runtime::Interrupts, ContextRef};
+
+/// The op budget of a context, counted in the interrupt checks.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct OpBudget {
+    limit: u64,
+    used: u64,
+}
+
+impl ContextRef {
+    /// Limit the number of the interrupt checks charged to the context, or `None` to remove the limit.
+    ///
+    /// QuickJS checks the interrupt every 10000 operations or so, the checks are charged to
+    /// the innermost running context, and the pending jobs are charged to the context of the job.
+    /// The scripts and jobs beyond the budget fail with the uncatchable `ErrorKind::BudgetExceeded`,
+    /// until the budget is reset.
+    ///
+    /// The interrupt closure or handler of the runtime is still called for the checks within the budget.
+    pub fn set_op_budget(&self, budget: Option<u64>) {
+        trace!("{:?} set op budget to {:?}", self, budget);
+
+        let ctx = self.as_ptr() as usize;
+
+        self.runtime().with_interrupts(|interrupts| match budget {
+            Some(limit) => interrupts
+                .op_budgets
+                .insert(ctx, OpBudget { limit, used: 0 }),
+            None => interrupts.op_budgets.remove(&ctx),
+        });
+
+        if budget.is_some() {
+            self.runtime().install_interrupt_trampoline();
+        }
+    }
+
+    /// Returns the remaining op budget of the context, or `None` if unlimited.
+    pub fn remaining_op_budget(&self) -> Option<u64> {
+        self.op_budget()
+            .map(|budget| budget.limit.saturating_sub(budget.used))
+    }
+
+    /// Returns the budget if the context ran out of it.
+    pub(crate) fn exceeded_op_budget(&self) -> Option<u64> {
+        self.op_budget()
+            .filter(|budget| budget.used > budget.limit)
+            .map(|budget| budget.limit)
+    }
+
+    fn op_budget(&self) -> Option<OpBudget> {
+        let ctx = self.as_ptr() as usize;
+
+        self.runtime()
+            .with_interrupts(|interrupts| interrupts.op_budgets.get(&ctx).cloned())
+    }
+}
+
+impl Interrupts {
+    /// Charge an interrupt check to the running context, returns `true` if it ran out of the budget.
+    pub(crate) fn charge_op_budget(&mut self, running: Option<usize>) -> bool {
+        match running.and_then(|ctx| self.op_budgets.get_mut(&ctx)) {
+            Some(budget) => {
+                budget.used += 1;
+                budget.used > budget.limit
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::{Context, ErrorKind, Eval, Interrupt, Runtime, RuntimeRef};
+
+    #[test]
+    fn op_budget() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::builder(&rt).with_all().with_op_budget(10).build();
+
+        assert_eq!(ctxt.remaining_op_budget(), Some(10));
+        assert_eq!(
+            ctxt.eval::<_, i32>(
+                "let n = 0; for (let i = 0; i < 1000; i++) n++; n",
+                Eval::GLOBAL
+            )
+            .unwrap(),
+            Some(1000)
+        );
+
+        let remaining = ctxt.remaining_op_budget().unwrap();
+
+        assert!(remaining > 0 && remaining <= 10);
+        assert_eq!(
+            ctxt.eval::<_, ()>("try { for (;;) {} } catch (e) {}", Eval::GLOBAL)
+                .unwrap_err()
+                .into_kind()
+                .unwrap(),
+            ErrorKind::BudgetExceeded(10)
+        );
+        assert_eq!(ctxt.remaining_op_budget(), Some(0));
+
+        ctxt.set_op_budget(None);
+
+        assert_eq!(ctxt.remaining_op_budget(), None);
+        assert_eq!(ctxt.eval::<_, i32>("1 + 2", Eval::GLOBAL).unwrap(), Some(3));
+    }
+
+    static INTERRUPTS: AtomicUsize = AtomicUsize::new(0);
+
+    fn count_interrupts(_rt: &RuntimeRef) -> Interrupt {
+        INTERRUPTS.fetch_add(1, Ordering::SeqCst);
+
+        Interrupt::Continue
+    }
+
+    #[test]
+    fn charge_pending_jobs() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        rt.set_interrupt_handler(Some(count_interrupts));
+        ctxt.set_op_budget(Some(10));
+
+        ctxt.eval_script(
+            "Promise.resolve().then(() => { for (;;) {} })",
+            "<evalScript>",
+            Eval::GLOBAL,
+        )
+        .unwrap();
+
+        // the reaction job rejects the promise instead of failing
+        assert!(rt.execute_pending_job().unwrap().is_some());
+        assert_eq!(ctxt.remaining_op_budget(), Some(0));
+
+        // the interrupt handler is still called within the budget
+        assert_eq!(INTERRUPTS.load(Ordering::SeqCst), 10);
+    }
+}
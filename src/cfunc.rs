@@ -1,15 +1,15 @@
+use std::collections::HashMap;
 use std::ffi::CString;
 use std::os::raw::c_int;
-use std::panic;
+use std::panic::{self, Location};
 use std::ptr;
 use std::slice;
 
-use failure::Error;
 use foreign_types::ForeignTypeRef;
 
 use crate::{
     ffi::{self, JSCFunctionEnum::*},
-    Args, ContextRef, ExtractValue, Local, NewValue, Prop, Value,
+    Args, ContextRef, Error, ExtractValue, Local, NewValue, Prop, RuntimeRef, Value,
 };
 
 /// `CFunction` is a shortcut to easily add functions, setters and getters properties to a given object.
@@ -61,8 +61,73 @@ pub enum CFunc {
     IteratorNext = JS_CFUNC_iterator_next,
 }
 
+impl RuntimeRef {
+    /// Record the Rust locations where the named host functions are registered,
+    /// which are appended to the native frames of the stack traces.
+    ///
+    /// The locations are not recorded by default, because they reveal the Rust source paths.
+    pub fn record_host_locations(&self, enable: bool) {
+        self.with_state(|state| match (enable, state.host_locations.is_some()) {
+            (true, false) => state.host_locations = Some(HashMap::new()),
+            (false, true) => state.host_locations = None,
+            _ => {}
+        })
+    }
+
+    /// Returns `true` if the runtime records the host locations.
+    pub fn is_recording_host_locations(&self) -> bool {
+        self.with_state(|state| state.host_locations.is_some())
+    }
+
+    /// Returns the Rust location where the named host function was registered.
+    ///
+    /// Returns `None` if the locations are not recorded,
+    /// or the host functions with the same name were registered at different locations.
+    pub fn host_location(&self, name: &str) -> Option<&'static Location<'static>> {
+        self.with_state(|state| {
+            state
+                .host_locations
+                .as_ref()
+                .and_then(|locations| locations.get(name).cloned().flatten())
+        })
+    }
+}
+
+/// Convert the arguments of C function to a slice, the `argv` may be null if there is no argument.
+pub(crate) unsafe fn c_args<'a>(argc: c_int, argv: *mut ffi::JSValue) -> &'a [Value] {
+    if argc <= 0 || argv.is_null() {
+        &[]
+    } else {
+        slice::from_raw_parts(argv as *const Value, argc as usize)
+    }
+}
+
 impl ContextRef {
+    /// Record the Rust location of the named host function, if the runtime records them.
+    fn set_host_location(&self, name: Option<&str>, location: &'static Location<'static>) {
+        if let Some(name) = name {
+            self.runtime().with_state(|state| {
+                if let Some(locations) = state.host_locations.as_mut() {
+                    trace!("host function {} @ {}", name, location);
+
+                    locations
+                        .entry(name.to_owned())
+                        .and_modify(|found| {
+                            // the functions with the same name are ambiguous
+                            if *found != Some(location) {
+                                *found = None
+                            }
+                        })
+                        .or_insert(Some(location));
+                }
+            })
+        }
+    }
+
     /// Create a new C function.
+    ///
+    /// The Rust location of the caller is recorded if the runtime records the host locations.
+    #[track_caller]
     pub fn new_c_function<T: NewValue>(
         &self,
         func: CFunction<T>,
@@ -81,7 +146,7 @@ impl ContextRef {
                 let ctxt = ContextRef::from_ptr(ctx);
                 let this = Value::from(this_val);
                 let this = this.check_undefined();
-                let args = slice::from_raw_parts(argv, argc as usize);
+                let args = c_args(argc, argv);
                 let data = ptr::NonNull::new_unchecked(data);
                 let func = ctxt.get_userdata_unchecked::<CFunction<T>>(data.cast().as_ref());
                 let func = *func.as_ref();
@@ -94,11 +159,13 @@ impl ContextRef {
                     magic
                 );
 
-                func(ctxt, this, &*(args as *const _ as *const _)).new_value(ctxt)
+                func(ctxt, this, args).new_value(ctxt)
             })
             .unwrap_or_default()
         }
 
+        let location = Location::caller();
+
         trace!("new C function @ {:p}", &func);
 
         let func = self.new_c_function_data(stub::<T>, length, 0, self.new_userdata(func))?;
@@ -107,10 +174,13 @@ impl ContextRef {
             func.define_property_value("name", name, Prop::CONFIGURABLE)?;
         }
 
+        self.set_host_location(name, location);
+
         Ok(func)
     }
 
     /// Create a new C function with magic.
+    #[track_caller]
     pub fn new_c_function_magic(
         &self,
         func: UnsafeCFunctionMagic,
@@ -119,21 +189,30 @@ impl ContextRef {
         cproto: CFunc,
         magic: i32,
     ) -> Result<Local<Value>, Error> {
-        let name = name.map(CString::new).transpose()?;
-        self.bind(unsafe {
-            ffi::JS_NewCFunction2(
-                self.as_ptr(),
-                Some(*(&func as *const _ as *const _)),
-                name.map_or_else(ptr::null_mut, |s| s.as_ptr() as *mut _),
-                length as i32,
-                cproto as u32,
-                magic,
-            )
-        })
-        .ok()
+        let location = Location::caller();
+        let cname = name.map(CString::new).transpose()?;
+        let func = self
+            .bind(unsafe {
+                ffi::JS_NewCFunction2(
+                    self.as_ptr(),
+                    Some(*(&func as *const _ as *const _)),
+                    cname
+                        .as_ref()
+                        .map_or_else(ptr::null_mut, |s| s.as_ptr() as *mut _),
+                    length as i32,
+                    cproto as u32,
+                    magic,
+                )
+            })
+            .ok()?;
+
+        self.set_host_location(name, location);
+
+        Ok(func)
     }
 
     /// Create a new C function with prototype and magic.
+    #[track_caller]
     pub fn new_c_function2(
         &self,
         func: UnsafeCFunction,
@@ -142,18 +221,26 @@ impl ContextRef {
         cproto: CFunc,
         magic: i32,
     ) -> Result<Local<Value>, Error> {
-        let name = name.map(CString::new).transpose()?;
-        self.bind(unsafe {
-            ffi::JS_NewCFunction2(
-                self.as_ptr(),
-                Some(func),
-                name.map_or_else(ptr::null_mut, |s| s.as_ptr() as *mut _),
-                length as i32,
-                cproto as u32,
-                magic,
-            )
-        })
-        .ok()
+        let location = Location::caller();
+        let cname = name.map(CString::new).transpose()?;
+        let func = self
+            .bind(unsafe {
+                ffi::JS_NewCFunction2(
+                    self.as_ptr(),
+                    Some(func),
+                    cname
+                        .as_ref()
+                        .map_or_else(ptr::null_mut, |s| s.as_ptr() as *mut _),
+                    length as i32,
+                    cproto as u32,
+                    magic,
+                )
+            })
+            .ok()?;
+
+        self.set_host_location(name, location);
+
+        Ok(func)
     }
 
     /// Create a new C function with magic and data.
@@ -231,11 +318,11 @@ macro_rules! new_func_value {
                         let data = ptr::NonNull::new_unchecked(data);
                         let func = ctxt.get_userdata_unchecked::<fn($( $Arg ),*) -> Ret>(data.cast().as_ref());
                         let func = *func.as_ref();
-                        let args = slice::from_raw_parts(argv, argc as usize);
+                        let args = c_args(argc, argv);
                         let mut iter = args.iter();
 
                         func($({
-                            let value = ctxt.bind(*iter.next().unwrap());
+                            let value = ctxt.bind(iter.next().unwrap().raw());
                             <$Arg as ExtractValue>::extract_value(&value).unwrap()
                         }),*)
                             .new_value(&ctxt)
@@ -270,7 +357,9 @@ new_func_value! { T0 T1 T2 T3 T4 T5 T6 T7 T8 T9 T10 T11 T12 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{Context, Eval, ExtractValue, Runtime};
+    use crate::{Context, Eval, ExtractValue, Runtime, StackFormat};
+
+    use super::*;
 
     #[test]
     fn cfunc() {
@@ -299,6 +388,102 @@ mod tests {
         );
     }
 
+    #[test]
+    fn host_location() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        rt.record_host_locations(true);
+
+        unsafe extern "C" fn call_back(
+            ctx: *mut ffi::JSContext,
+            _this_val: ffi::JSValue,
+            argc: c_int,
+            argv: *mut ffi::JSValue,
+        ) -> ffi::JSValue {
+            let ctxt = ContextRef::from_ptr(ctx);
+
+            c_args(argc, argv)
+                .first()
+                .map_or(ffi::UNDEFINED, |cb| {
+                    ffi::JS_Call(ctx, cb.raw(), ffi::UNDEFINED, 0, ptr::null_mut())
+                })
+                .new_value(ctxt)
+        }
+
+        let line = line!() + 2;
+        let func = ctxt
+            .new_c_function2(call_back, Some("hostCall"), 1, CFunc::Generic, 0)
+            .unwrap();
+
+        ctxt.global_object().set_property("hostCall", func).unwrap();
+
+        let location = format!("{}:{}:", file!(), line);
+
+        assert_eq!(
+            ctxt.eval::<_, String>("typeof hostCall.hostLocation", Eval::GLOBAL)
+                .unwrap(),
+            Some("undefined".to_owned())
+        );
+        assert_eq!(rt.host_location("hostCall").unwrap().line(), line);
+
+        let err = ctxt
+            .eval::<_, ()>(
+                "hostCall(function fail() { throw new Error('boom') })",
+                Eval::GLOBAL,
+            )
+            .unwrap_err()
+            .into_kind()
+            .unwrap();
+        let short = err.format_stack(StackFormat::Short).unwrap();
+
+        assert!(short.contains(&format!("at hostCall (native {}", location)));
+        assert!(err
+            .format_stack(StackFormat::Json)
+            .unwrap()
+            .contains(r#""host":"#));
+
+        // the functions with the same name are ambiguous
+        let twin = ctxt
+            .new_c_function2(call_back, Some("hostCall"), 1, CFunc::Generic, 0)
+            .unwrap();
+
+        assert!(rt.host_location("hostCall").is_none());
+
+        drop(twin);
+
+        // the locations are recorded per runtime and only if enabled
+        let other = Runtime::new();
+        let other_ctxt = Context::new(&other);
+        let func = other_ctxt
+            .new_c_function2(call_back, Some("hostCall"), 1, CFunc::Generic, 0)
+            .unwrap();
+
+        assert!(!other.is_recording_host_locations());
+        assert!(other.host_location("hostCall").is_none());
+
+        other_ctxt
+            .global_object()
+            .set_property("hostCall", func)
+            .unwrap();
+
+        let err = other_ctxt
+            .eval::<_, ()>(
+                "hostCall(function fail() { throw new Error('boom') })",
+                Eval::GLOBAL,
+            )
+            .unwrap_err()
+            .into_kind()
+            .unwrap();
+
+        assert!(err
+            .format_stack(StackFormat::Short)
+            .unwrap()
+            .contains("at hostCall (native)\n"));
+    }
+
     #[test]
     fn new_value() {
         let _ = pretty_env_logger::try_init();
@@ -1,6 +1,23 @@
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::marker::PhantomData;
+use std::mem;
+use std::os::raw::c_int;
+use std::panic;
+use std::ptr::null_mut;
+use std::sync::Mutex;
+
 use foreign_types::ForeignTypeRef;
 
-use crate::{ffi, value::ToBool, ContextRef, Local, Runtime, RuntimeRef, Value};
+use crate::{
+    cfunc::c_args, ffi, value::ToBool, Atom, CFunc, CFunction, ContextRef, Error, Local, NewAtom,
+    NewValue, Prop, Runtime, RuntimeRef, Value,
+};
+
+lazy_static! {
+    static ref CLASS_IDS: Mutex<HashMap<TypeId, ClassId>> = Mutex::new(HashMap::new());
+}
 
 /// A globally allocated class ID.
 pub type ClassId = ffi::JSClassID;
@@ -8,6 +25,75 @@ pub type ClassId = ffi::JSClassID;
 /// The Javascript class definition.
 pub type ClassDef = ffi::JSClassDef;
 
+/// A Rust type which could be exposed as a Javascript class.
+pub trait Class: Sized + 'static {
+    /// The class name.
+    const NAME: &'static str;
+
+    /// The number of arguments of the constructor.
+    const LENGTH: usize = 0;
+
+    /// The instances could be invoked as functions with `Class::call`.
+    const CALLABLE: bool = false;
+
+    /// Construct a new instance with the arguments of the Javascript constructor.
+    fn constructor(ctxt: &ContextRef, args: &[Value]) -> Result<Self, Error>;
+
+    /// Define the methods and accessors on the prototype.
+    fn prototype(_ctxt: &ContextRef, _proto: &Value) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Invoke the instance as a function if the class is `CALLABLE`.
+    ///
+    /// The hook only handles the function calls, the engine rejects the constructor calls (`new instance()`)
+    /// with a `TypeError` because the callable instances are not constructors.
+    fn call<'a>(
+        &self,
+        _ctxt: &'a ContextRef,
+        _this: &Value,
+        _args: &[Value],
+    ) -> Result<Local<'a, Value>, Error> {
+        Err(format!("{} instance is not a function", Self::NAME).into())
+    }
+
+    /// The instances are exotic objects, which own properties are virtualized by the following hooks.
+    ///
+    /// The ordinary properties are still looked up before the hooks.
+    const EXOTIC: bool = false;
+
+    /// Returns the value of the own property if it exists on the exotic instance.
+    fn get_own_property<'a>(
+        &self,
+        _ctxt: &'a ContextRef,
+        _prop: &Atom,
+    ) -> Result<Option<Local<'a, Value>>, Error> {
+        Ok(None)
+    }
+
+    /// Returns the names of the own properties of the exotic instance.
+    fn get_own_property_names(&self, _ctxt: &ContextRef) -> Result<Vec<String>, Error> {
+        Ok(vec![])
+    }
+
+    /// Define or assign the own property of the exotic instance, returns `false` if it was rejected.
+    ///
+    /// Returns `None` to define an ordinary property.
+    fn define_own_property(
+        &self,
+        _ctxt: &ContextRef,
+        _prop: &Atom,
+        _value: &Value,
+    ) -> Result<Option<bool>, Error> {
+        Ok(None)
+    }
+
+    /// Delete the own property of the exotic instance, returns `false` if it can't be deleted.
+    fn delete_property(&self, ctxt: &ContextRef, prop: &Atom) -> Result<bool, Error> {
+        Ok(self.get_own_property(ctxt, prop)?.is_none())
+    }
+}
+
 impl Runtime {
     /// New Class ID which are globally allocated (i.e. for all runtimes).
     pub fn new_class_id() -> ClassId {
@@ -15,6 +101,20 @@ impl Runtime {
 
         unsafe { ffi::JS_NewClassID(&mut class_id) }
     }
+
+    /// The Class ID of the Rust type, which is allocated on the first use.
+    pub fn class_id<T: Class>() -> ClassId {
+        Runtime::type_class_id::<T>()
+    }
+
+    /// The Class ID of any Rust type, which is allocated on the first use.
+    pub(crate) fn type_class_id<T: 'static>() -> ClassId {
+        *CLASS_IDS
+            .lock()
+            .expect("class ids")
+            .entry(TypeId::of::<T>())
+            .or_insert_with(Runtime::new_class_id)
+    }
 }
 
 impl RuntimeRef {
@@ -29,6 +129,24 @@ impl RuntimeRef {
     }
 }
 
+impl Value {
+    /// Returns the class ID of the object, or `None` if the value is not an object.
+    pub fn class_id(&self) -> Option<ClassId> {
+        match unsafe { ffi::JS_GetObjectClassID(self.raw()) } {
+            0 => None,
+            class_id => Some(class_id),
+        }
+    }
+
+    /// Check if the object is an instance of the class, e.g. created by the `ClassBuilder` of a Rust type.
+    ///
+    /// Unlike `instanceof`, the prototype chain is not checked,
+    /// so the instance could be safely downcasted with `get_opaque`.
+    pub fn is_instance_of_class(&self, class_id: ClassId) -> bool {
+        self.class_id() == Some(class_id)
+    }
+}
+
 impl ContextRef {
     /// Define a prototype for a given class in a given JSContext.
     pub fn set_class_proto<T: Into<ffi::JSValue>>(&self, class_id: ClassId, obj: T) {
@@ -40,3 +158,688 @@ impl ContextRef {
         self.bind(unsafe { ffi::JS_GetClassProto(self.as_ptr(), class_id) })
     }
 }
+
+unsafe extern "C" fn class_finalizer<T: Class>(_rt: *mut ffi::JSRuntime, obj: ffi::JSValue) {
+    let ptr = ffi::JS_GetOpaque(obj, Runtime::class_id::<T>()) as *mut T;
+
+    trace!("free {} instance @ {:p}", T::NAME, ptr);
+
+    if !ptr.is_null() {
+        drop(Box::from_raw(ptr));
+    }
+}
+
+unsafe extern "C" fn class_constructor<T: Class>(
+    ctx: *mut ffi::JSContext,
+    new_target: ffi::JSValue,
+    argc: c_int,
+    argv: *mut ffi::JSValue,
+) -> ffi::JSValue {
+    panic::catch_unwind(|| {
+        let ctxt = ContextRef::from_ptr(ctx);
+        let new_target = Value::from(new_target);
+        let args = c_args(argc, argv);
+
+        T::constructor(ctxt, args)
+            .map(|instance| {
+                let class_id = Runtime::class_id::<T>();
+                // respect the prototype of the derived class
+                let obj = match ctxt.get_property(&new_target, "prototype") {
+                    Some(proto) if proto.is_object() => {
+                        ctxt.new_object_proto_class(&proto, class_id)
+                    }
+                    _ => ctxt.new_object_class(class_id),
+                };
+                let ptr = Box::into_raw(Box::new(instance));
+
+                trace!("new {} instance @ {:p}", T::NAME, ptr);
+
+                obj.set_opaque(ptr);
+
+                ctxt.bind(obj)
+            })
+            .new_value(ctxt)
+    })
+    .unwrap_or_default()
+}
+
+unsafe extern "C" fn class_call<T: Class>(
+    ctx: *mut ffi::JSContext,
+    func_obj: ffi::JSValue,
+    this_val: ffi::JSValue,
+    argc: c_int,
+    argv: *mut ffi::JSValue,
+) -> ffi::JSValue {
+    panic::catch_unwind(|| {
+        let ctxt = ContextRef::from_ptr(ctx);
+        let func_obj = Value::from(func_obj);
+        let this = Value::from(this_val);
+        let args = c_args(argc, argv);
+
+        match func_obj.get_opaque::<T>(Runtime::class_id::<T>()).as_ref() {
+            Some(instance) => instance.call(ctxt, &this, args),
+            None => Err(format!("expected {} instance", T::NAME).into()),
+        }
+        .new_value(ctxt)
+    })
+    .unwrap_or_default()
+}
+
+unsafe fn exotic_instance<'a, T: Class>(obj: ffi::JSValue) -> Option<&'a T> {
+    (ffi::JS_GetOpaque(obj, Runtime::class_id::<T>()) as *const T).as_ref()
+}
+
+/// Throw the error and returns `-1`, the exotic methods return a negative number for exception.
+fn throw_exotic(ctxt: &ContextRef, err: Error) -> c_int {
+    Err::<Local<Value>, _>(err).new_value(ctxt);
+
+    -1
+}
+
+unsafe extern "C" fn exotic_get_own_property<T: Class>(
+    ctx: *mut ffi::JSContext,
+    desc: *mut ffi::JSPropertyDescriptor,
+    obj: ffi::JSValue,
+    prop: ffi::JSAtom,
+) -> c_int {
+    panic::catch_unwind(|| {
+        let ctxt = ContextRef::from_ptr(ctx);
+        let prop = ctxt.clone_atom(prop);
+
+        match exotic_instance::<T>(obj).map(|instance| instance.get_own_property(ctxt, &prop)) {
+            Some(Ok(Some(value))) => {
+                // the descriptor may be null when checking the existence
+                if let Some(desc) = desc.as_mut() {
+                    desc.flags = Prop::C_W_E.bits() as c_int;
+                    desc.value = value.into_inner().raw();
+                    desc.getter = ffi::UNDEFINED;
+                    desc.setter = ffi::UNDEFINED;
+                }
+
+                ffi::TRUE_VALUE
+            }
+            Some(Err(err)) => throw_exotic(ctxt, err),
+            _ => ffi::FALSE_VALUE,
+        }
+    })
+    .unwrap_or(-1)
+}
+
+unsafe extern "C" fn exotic_get_own_property_names<T: Class>(
+    ctx: *mut ffi::JSContext,
+    ptab: *mut *mut ffi::JSPropertyEnum,
+    plen: *mut u32,
+    obj: ffi::JSValue,
+) -> c_int {
+    panic::catch_unwind(|| {
+        let ctxt = ContextRef::from_ptr(ctx);
+        let names = match exotic_instance::<T>(obj) {
+            Some(instance) => match instance.get_own_property_names(ctxt) {
+                Ok(names) => names,
+                Err(err) => return throw_exotic(ctxt, err),
+            },
+            None => vec![],
+        };
+        // the table is freed by the engine
+        let tab = ffi::js_malloc(
+            ctx,
+            mem::size_of::<ffi::JSPropertyEnum>() * names.len().max(1),
+        ) as *mut ffi::JSPropertyEnum;
+
+        if tab.is_null() {
+            return -1;
+        }
+
+        for (idx, name) in names.iter().enumerate() {
+            tab.add(idx).write(ffi::JSPropertyEnum {
+                is_enumerable: ffi::TRUE_VALUE,
+                atom: name.as_str().new_atom(ctxt),
+            });
+        }
+
+        *ptab = tab;
+        *plen = names.len() as u32;
+
+        0
+    })
+    .unwrap_or(-1)
+}
+
+unsafe extern "C" fn exotic_define_own_property<T: Class>(
+    ctx: *mut ffi::JSContext,
+    this_obj: ffi::JSValue,
+    prop: ffi::JSAtom,
+    val: ffi::JSValue,
+    getter: ffi::JSValue,
+    setter: ffi::JSValue,
+    flags: c_int,
+) -> c_int {
+    panic::catch_unwind(|| {
+        let ctxt = ContextRef::from_ptr(ctx);
+        let atom = ctxt.clone_atom(prop);
+        let res = match exotic_instance::<T>(this_obj) {
+            Some(instance) if (flags as u32 & ffi::JS_PROP_HAS_VALUE) != 0 => {
+                instance.define_own_property(ctxt, &atom, &Value::from(val))
+            }
+            _ => Ok(None),
+        };
+
+        match res {
+            Ok(Some(true)) => ffi::TRUE_VALUE,
+            Ok(Some(false)) if (flags as u32 & ffi::JS_PROP_THROW) != 0 => {
+                ctxt.throw_type_error(format!("cannot define property `{}`", atom));
+
+                -1
+            }
+            Ok(Some(false)) => ffi::FALSE_VALUE,
+            Ok(None) => ffi::JS_DefineProperty(
+                ctx,
+                this_obj,
+                prop,
+                val,
+                getter,
+                setter,
+                flags | ffi::JS_PROP_NO_EXOTIC as c_int,
+            ),
+            Err(err) => throw_exotic(ctxt, err),
+        }
+    })
+    .unwrap_or(-1)
+}
+
+unsafe extern "C" fn exotic_delete_property<T: Class>(
+    ctx: *mut ffi::JSContext,
+    obj: ffi::JSValue,
+    prop: ffi::JSAtom,
+) -> c_int {
+    panic::catch_unwind(|| {
+        let ctxt = ContextRef::from_ptr(ctx);
+        let prop = ctxt.clone_atom(prop);
+
+        match exotic_instance::<T>(obj).map(|instance| instance.delete_property(ctxt, &prop)) {
+            Some(Ok(deleted)) => deleted.to_bool(),
+            Some(Err(err)) => throw_exotic(ctxt, err),
+            None => ffi::TRUE_VALUE,
+        }
+    })
+    .unwrap_or(-1)
+}
+
+/// The exotic methods of the class, which must outlive the runtime.
+fn exotic_methods<T: Class>() -> *mut ffi::JSClassExoticMethods {
+    Box::into_raw(Box::new(ffi::JSClassExoticMethods {
+        get_own_property: Some(exotic_get_own_property::<T>),
+        get_own_property_names: Some(exotic_get_own_property_names::<T>),
+        delete_property: Some(exotic_delete_property::<T>),
+        define_own_property: Some(exotic_define_own_property::<T>),
+        has_property: None,
+        get_property: None,
+        set_property: None,
+    }))
+}
+
+/// The builder to define a Javascript class for the Rust type, created by `ContextRef::class_builder`.
+///
+/// The class is registered to the runtime, the prototype and constructor are created and linked,
+/// the instances created by the constructor own the Rust value, which is dropped by the finalizer.
+pub struct ClassBuilder<'a, T> {
+    ctxt: &'a ContextRef,
+    parent: Option<Local<'a, Value>>,
+    methods: Vec<(String, Local<'a, Value>)>,
+    global: bool,
+    err: Option<Error>,
+    phantom: PhantomData<T>,
+}
+
+impl ContextRef {
+    /// Create a builder to define a Javascript class for the Rust type.
+    pub fn class_builder<T: Class>(&self) -> ClassBuilder<T> {
+        ClassBuilder {
+            ctxt: self,
+            parent: None,
+            methods: vec![],
+            global: false,
+            err: None,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Define a Javascript class for the Rust type, and add its constructor to the global object.
+    pub fn define_global_class<T: Class>(&self) -> Result<Local<Value>, Error> {
+        self.class_builder::<T>().global(true).build()
+    }
+
+    /// Set the prototype of an object.
+    pub fn set_prototype(&self, obj: &Value, proto: &Value) -> Result<bool, Error> {
+        self.check_bool(unsafe { ffi::JS_SetPrototype(self.as_ptr(), obj.raw(), proto.raw()) })
+    }
+}
+
+impl<'a, T: Class> ClassBuilder<'a, T> {
+    /// Inherit from the parent constructor, both the prototype and the static properties.
+    pub fn extends(mut self, parent: &Value) -> Self {
+        self.parent = Some(self.ctxt.clone_value(parent));
+        self
+    }
+
+    /// Add a method to the prototype, besides the ones defined by `Class::prototype`.
+    #[track_caller]
+    pub fn method<V: NewValue>(mut self, name: &str, func: CFunction<V>) -> Self {
+        match self.ctxt.new_c_function(func, Some(name), 0) {
+            Ok(func) => self.methods.push((name.to_owned(), func)),
+            Err(err) => {
+                self.err.get_or_insert(err);
+            }
+        }
+        self
+    }
+
+    /// Add the constructor to the global object or not.
+    pub fn global(mut self, global: bool) -> Self {
+        self.global = global;
+        self
+    }
+
+    /// Define the class and returns its constructor, which instantiates the class with `call_constructor`.
+    pub fn build(self) -> Result<Local<'a, Value>, Error> {
+        if let Some(err) = self.err {
+            return Err(err);
+        }
+
+        let ctxt = self.ctxt;
+        let rt = ctxt.runtime();
+        let class_id = Runtime::class_id::<T>();
+
+        if !rt.is_registered_class(class_id) {
+            let class_name = CString::new(T::NAME)?;
+
+            rt.new_class(
+                class_id,
+                &ClassDef {
+                    class_name: class_name.as_ptr(),
+                    finalizer: Some(class_finalizer::<T>),
+                    gc_mark: None,
+                    call: if T::CALLABLE {
+                        Some(class_call::<T>)
+                    } else {
+                        None
+                    },
+                    // leaked once per runtime, the engine keeps the pointer
+                    exotic: if T::EXOTIC {
+                        exotic_methods::<T>()
+                    } else {
+                        null_mut()
+                    },
+                },
+            );
+
+            if !rt.is_registered_class(class_id) {
+                return Err(format!("fail to register class `{}`", T::NAME).into());
+            }
+        }
+
+        trace!("define class `{}` with ID {}", T::NAME, class_id);
+
+        let proto = match self.parent {
+            Some(ref parent) => {
+                let parent_proto = ctxt
+                    .get_property(parent, "prototype")
+                    .filter(|proto| proto.is_object())
+                    .ok_or_else(|| format!("`{}` extends a non-constructor", T::NAME))?;
+
+                ctxt.bind(ctxt.new_object_proto(&parent_proto))
+            }
+            None => ctxt.bind(ctxt.new_object()),
+        };
+
+        T::prototype(ctxt, &proto)?;
+
+        for (name, func) in self.methods {
+            proto.define_property_value(
+                name.as_str(),
+                func,
+                Prop::WRITABLE | Prop::CONFIGURABLE,
+            )?;
+        }
+
+        let ctor = ctxt.new_c_function2(
+            class_constructor::<T>,
+            Some(T::NAME),
+            T::LENGTH,
+            CFunc::Constructor,
+            0,
+        )?;
+
+        if let Some(ref parent) = self.parent {
+            ctxt.set_prototype(&ctor, parent)?;
+        }
+
+        ctor.define_property_value("prototype", &proto, Prop::empty())?;
+        proto.define_property_value("constructor", &ctor, Prop::WRITABLE | Prop::CONFIGURABLE)?;
+
+        ctxt.set_class_proto(class_id, proto.into_inner());
+
+        if self.global {
+            ctxt.global_object().define_property_value(
+                T::NAME,
+                &ctor,
+                Prop::WRITABLE | Prop::CONFIGURABLE,
+            )?;
+        }
+
+        Ok(ctor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+
+    use crate::{Context, ContextRef, Error, Eval, ExtractValue, Runtime, Value};
+
+    use super::*;
+
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    impl Class for Point {
+        const NAME: &'static str = "Point";
+        const LENGTH: usize = 2;
+
+        fn constructor(ctxt: &ContextRef, args: &[Value]) -> Result<Self, Error> {
+            let arg = |i: usize| {
+                args.get(i)
+                    .and_then(|v| i32::extract_value(&ctxt.bind(v)))
+                    .unwrap_or_default()
+            };
+
+            Ok(Point {
+                x: arg(0),
+                y: arg(1),
+            })
+        }
+
+        fn prototype(ctxt: &ContextRef, proto: &Value) -> Result<(), Error> {
+            ctxt.set_property(
+                proto,
+                "norm",
+                ctxt.new_c_function(
+                    |_ctxt, this, _args| {
+                        let p = this.map_or(null_mut(), |this| {
+                            this.get_opaque::<Point>(Runtime::class_id::<Point>())
+                        });
+
+                        unsafe { p.as_ref() }.map_or(0, |p| p.x.abs() + p.y.abs())
+                    },
+                    Some("norm"),
+                    0,
+                )?,
+            )?;
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn global_class() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let ctor = ctxt.define_global_class::<Point>().unwrap();
+
+        assert!(ctor.is_constructor());
+        assert_eq!(
+            ctxt.eval::<_, i32>("new Point(3, -4).norm()", Eval::GLOBAL)
+                .unwrap(),
+            Some(7)
+        );
+        assert_eq!(
+            ctxt.eval::<_, bool>(
+                "var p = new Point(1, 2); p instanceof Point && p.constructor === Point",
+                Eval::GLOBAL
+            )
+            .unwrap(),
+            Some(true)
+        );
+        assert_eq!(
+            ctxt.eval::<_, i32>(
+                "class Point3D extends Point {}; new Point3D(1, 2).norm()",
+                Eval::GLOBAL
+            )
+            .unwrap(),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn class_id() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        ctxt.define_global_class::<Point>().unwrap();
+
+        let p = ctxt
+            .eval_script("new Point(1, 2)", "<evalScript>", Eval::GLOBAL)
+            .unwrap();
+
+        assert_eq!(p.class_id(), Some(Runtime::class_id::<Point>()));
+        assert!(p.is_instance_of_class(Runtime::class_id::<Point>()));
+        assert!(!ctxt
+            .bind(ctxt.new_object())
+            .is_instance_of_class(Runtime::class_id::<Point>()));
+        assert_eq!(ctxt.bind(1).class_id(), None);
+    }
+
+    struct Greeter {
+        greeting: String,
+    }
+
+    impl Class for Greeter {
+        const NAME: &'static str = "Greeter";
+        const LENGTH: usize = 1;
+        const CALLABLE: bool = true;
+
+        fn constructor(ctxt: &ContextRef, args: &[Value]) -> Result<Self, Error> {
+            Ok(Greeter {
+                greeting: args
+                    .first()
+                    .and_then(|v| String::extract_value(&ctxt.bind(v)))
+                    .unwrap_or_default(),
+            })
+        }
+
+        fn call<'a>(
+            &self,
+            ctxt: &'a ContextRef,
+            _this: &Value,
+            args: &[Value],
+        ) -> Result<Local<'a, Value>, Error> {
+            let name = args
+                .first()
+                .and_then(|v| String::extract_value(&ctxt.bind(v)))
+                .unwrap_or_default();
+
+            Ok(ctxt.bind(format!("{}, {}!", self.greeting, name)))
+        }
+    }
+
+    #[test]
+    fn callable_class() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        ctxt.define_global_class::<Greeter>().unwrap();
+        ctxt.define_global_class::<Point>().unwrap();
+
+        assert_eq!(
+            ctxt.eval::<_, String>(
+                "var hello = new Greeter('Hello'); typeof hello + ':' + hello('world')",
+                Eval::GLOBAL
+            )
+            .unwrap(),
+            Some("function:Hello, world!".to_owned())
+        );
+        assert_eq!(
+            ctxt.eval::<_, String>("typeof new Point(1, 2)", Eval::GLOBAL)
+                .unwrap(),
+            Some("object".to_owned())
+        );
+        assert_eq!(
+            ctxt.eval::<_, bool>(
+                "try { new hello('world'); false } catch (err) { err instanceof TypeError }",
+                Eval::GLOBAL
+            )
+            .unwrap(),
+            Some(true)
+        );
+    }
+
+    struct Counters(RefCell<BTreeMap<String, i32>>);
+
+    impl Class for Counters {
+        const NAME: &'static str = "Counters";
+        const EXOTIC: bool = true;
+
+        fn constructor(_ctxt: &ContextRef, _args: &[Value]) -> Result<Self, Error> {
+            Ok(Counters(RefCell::new(BTreeMap::new())))
+        }
+
+        fn get_own_property<'a>(
+            &self,
+            ctxt: &'a ContextRef,
+            prop: &Atom,
+        ) -> Result<Option<Local<'a, Value>>, Error> {
+            Ok(self
+                .0
+                .borrow()
+                .get(&prop.to_string())
+                .map(|&n| ctxt.bind(ctxt.new_value(n))))
+        }
+
+        fn get_own_property_names(&self, _ctxt: &ContextRef) -> Result<Vec<String>, Error> {
+            Ok(self.0.borrow().keys().cloned().collect())
+        }
+
+        fn define_own_property(
+            &self,
+            _ctxt: &ContextRef,
+            prop: &Atom,
+            value: &Value,
+        ) -> Result<Option<bool>, Error> {
+            Ok(Some(value.as_int().map_or(false, |n| {
+                self.0.borrow_mut().insert(prop.to_string(), n);
+
+                true
+            })))
+        }
+
+        fn delete_property(&self, _ctxt: &ContextRef, prop: &Atom) -> Result<bool, Error> {
+            self.0.borrow_mut().remove(&prop.to_string());
+
+            Ok(true)
+        }
+    }
+
+    #[test]
+    fn exotic_class() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        ctxt.define_global_class::<Counters>().unwrap();
+
+        assert_eq!(
+            ctxt.eval::<_, String>(
+                r#"
+var c = new Counters();
+c.a = 1;
+c.b = 2;
+c.b += 40;
+[c.a, c.b, 'a' in c, 'z' in c, Object.keys(c), delete c.a, Object.keys(c), c.constructor === Counters].join()
+"#,
+                Eval::GLOBAL
+            )
+            .unwrap(),
+            Some("1,42,true,false,a,b,true,b,true".to_owned())
+        );
+        assert_eq!(
+            ctxt.eval::<_, bool>(
+                "try { Object.defineProperty(c, 's', { value: 'x' }); false } catch (err) { err instanceof TypeError }",
+                Eval::GLOBAL
+            )
+            .unwrap(),
+            Some(true)
+        );
+    }
+
+    struct Square(i32);
+
+    impl Class for Square {
+        const NAME: &'static str = "Square";
+        const LENGTH: usize = 1;
+
+        fn constructor(ctxt: &ContextRef, args: &[Value]) -> Result<Self, Error> {
+            Ok(Square(
+                args.first()
+                    .and_then(|v| i32::extract_value(&ctxt.bind(v)))
+                    .unwrap_or_default(),
+            ))
+        }
+    }
+
+    fn area(_ctxt: &ContextRef, this: Option<&Value>, _args: &[Value]) -> i32 {
+        let p = this.map_or(null_mut(), |this| {
+            this.get_opaque::<Square>(Runtime::class_id::<Square>())
+        });
+
+        unsafe { p.as_ref() }.map_or(0, |p| p.0 * p.0)
+    }
+
+    #[test]
+    fn class_builder() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let shape = ctxt
+            .eval_script(
+                "class Shape { static kind() { return 'shape' } describe() { return this.constructor.name + ' ' + this.area() } }; Shape",
+                "<evalScript>",
+                Eval::GLOBAL,
+            )
+            .unwrap();
+        let square = ctxt
+            .class_builder::<Square>()
+            .extends(&shape)
+            .method("area", area)
+            .build()
+            .unwrap();
+
+        assert!(ctxt.global_object().get_property("Square").is_none());
+
+        let obj = square.call_constructor(3).unwrap();
+
+        ctxt.global_object().set_property("sq", obj).unwrap();
+        ctxt.global_object().set_property("Square", square).unwrap();
+
+        assert_eq!(
+            ctxt.eval::<_, String>(
+                "[sq.describe(), sq instanceof Shape, sq instanceof Square, Square.kind()].join()",
+                Eval::GLOBAL
+            )
+            .unwrap(),
+            Some("Square 9,true,true,shape".to_owned())
+        );
+    }
+}
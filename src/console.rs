@@ -0,0 +1,250 @@
+use std::os::raw::c_int;
+use std::panic::{self, AssertUnwindSafe};
+
+use foreign_types::ForeignTypeRef;
+use log::Level;
+
+use crate::{cfunc::c_args, ffi, ContextRef, Error, Prop, Value};
+
+/// The methods of the `console` object with their log levels.
+const METHODS: &[(&str, Level)] = &[
+    ("log", Level::Info),
+    ("info", Level::Info),
+    ("warn", Level::Warn),
+    ("error", Level::Error),
+    ("debug", Level::Debug),
+    ("trace", Level::Trace),
+];
+
+/// The sink of the messages written by the `console` object.
+pub trait Console: Send + 'static {
+    /// Write a formatted message in the level.
+    fn log(&self, level: Level, message: &str);
+}
+
+impl<F> Console for F
+where
+    F: Fn(Level, &str) + Send + 'static,
+{
+    fn log(&self, level: Level, message: &str) {
+        self(level, message)
+    }
+}
+
+/// The `Console` which forwards the messages to the `log` crate with the `console` target.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LogConsole;
+
+impl Console for LogConsole {
+    fn log(&self, level: Level, message: &str) {
+        log!(target: "console", level, "{}", message)
+    }
+}
+
+unsafe extern "C" fn console_log(
+    ctx: *mut ffi::JSContext,
+    _this_val: ffi::JSValue,
+    argc: c_int,
+    argv: *mut ffi::JSValue,
+    magic: c_int,
+    data: *mut ffi::JSValue,
+) -> ffi::JSValue {
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        let ctxt = ContextRef::from_ptr(ctx);
+        let level = METHODS[magic as usize].1;
+
+        if let Some(console) = ctxt.downcast_native::<Box<dyn Console>>(&*(data as *const Value)) {
+            console.log(level, &format_values(ctxt, c_args(argc, argv)));
+        }
+
+        ffi::UNDEFINED
+    }))
+    .unwrap_or_default()
+}
+
+/// Format the arguments like `util.format` of Node.js.
+///
+/// The first string argument may contains the `%s`, `%d`, `%i`, `%f`, `%j`, `%o` and `%O` placeholders,
+/// the remaining arguments are appended with spaces, and the objects are formatted as JSON.
+fn format_values(ctxt: &ContextRef, args: &[Value]) -> String {
+    let mut rest = args.iter();
+    let mut parts = vec![];
+
+    if let Some(fmt) = args.first().filter(|arg| arg.is_string()) {
+        let fmt = display(ctxt, fmt);
+        let mut chars = fmt.chars().peekable();
+        let mut s = String::new();
+
+        rest.next();
+
+        while let Some(c) = chars.next() {
+            match (c, chars.peek().cloned()) {
+                ('%', Some('%')) => {
+                    chars.next();
+                    s.push('%');
+                }
+                ('%', Some(spec)) if "sdifjoO".contains(spec) => match rest.next() {
+                    Some(arg) => {
+                        chars.next();
+                        s.push_str(&format_spec(ctxt, spec, arg));
+                    }
+                    None => s.push(c),
+                },
+                _ => s.push(c),
+            }
+        }
+
+        parts.push(s);
+    }
+
+    parts.extend(rest.map(|arg| inspect(ctxt, arg)));
+    parts.join(" ")
+}
+
+fn format_spec(ctxt: &ContextRef, spec: char, arg: &Value) -> String {
+    match spec {
+        's' => display(ctxt, arg),
+        'd' | 'i' | 'f' => {
+            let n = ctxt.to_float64(arg).unwrap_or(f64::NAN);
+            let n = if spec == 'i' { n.trunc() } else { n };
+
+            display(ctxt, &Value::from(n))
+        }
+        _ => inspect(ctxt, arg),
+    }
+}
+
+/// Convert the value to string like `String(value)`, which also works for symbols.
+fn display(ctxt: &ContextRef, value: &Value) -> String {
+    ctxt.global_object()
+        .get_property("String")
+        .ok_or_else(|| Error::from("`String` not found"))
+        .and_then(|string| ctxt.call(&string, None, value).map(|s| s.to_string()))
+        .unwrap_or_default()
+}
+
+/// Convert the value to a readable string, the objects are formatted as JSON if possible.
+fn inspect(ctxt: &ContextRef, value: &Value) -> String {
+    if ctxt.is_error(value) {
+        let msg = display(ctxt, value);
+
+        return match ctxt.get_property(value, "stack") {
+            Some(stack) if stack.is_string() => {
+                format!("{}\n{}", msg, stack.to_string().trim_end())
+            }
+            _ => msg,
+        };
+    }
+
+    if value.is_object() && !ctxt.is_function(value) {
+        let json = ctxt
+            .global_object()
+            .get_property("JSON")
+            .and_then(|json| ctxt.get_property(&json, "stringify"))
+            .ok_or_else(|| Error::from("`JSON.stringify` not found"))
+            .and_then(|stringify| ctxt.call(&stringify, None, value).map(|s| s.into_inner()));
+
+        match json {
+            Ok(json) if json.is_string() => return ctxt.bind(json).to_string(),
+            Ok(json) => ctxt.free_value(json),
+            Err(_) => {}
+        }
+    }
+
+    display(ctxt, value)
+}
+
+impl ContextRef {
+    /// Install the `console` object which forwards the messages to the `log` crate.
+    pub fn init_console(&self) -> Result<(), Error> {
+        self.init_console_with(LogConsole)
+    }
+
+    /// Install the `console` object which writes the messages to the `Console` sink.
+    ///
+    /// The `console.log`, `info`, `warn`, `error`, `debug` and `trace` methods
+    /// format their arguments like `util.format` of Node.js.
+    pub fn init_console_with<C: Console>(&self, console: C) -> Result<(), Error> {
+        let sink = self.wrap_native(Box::new(console) as Box<dyn Console>)?;
+        let obj = self.bind(self.new_object());
+
+        for (magic, &(name, _)) in METHODS.iter().enumerate() {
+            let func = self.new_c_function_data(console_log, 0, magic as i32, &sink)?;
+
+            func.define_property_value("name", name, Prop::CONFIGURABLE)?;
+            obj.set_property(name, func)?;
+        }
+
+        self.global_object().define_property_value(
+            "console",
+            obj,
+            Prop::WRITABLE | Prop::CONFIGURABLE,
+        )?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::{Context, Eval, Runtime};
+
+    use super::*;
+
+    #[test]
+    fn console() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+        let messages = Arc::new(Mutex::new(vec![]));
+        let sink = messages.clone();
+
+        ctxt.init_console_with(move |level, msg: &str| {
+            sink.lock().unwrap().push((level, msg.to_owned()))
+        })
+        .unwrap();
+
+        ctxt.eval_script(
+            r#"
+console.log("%s is %d years, %i%%", "alice", 42.5, 42.5, { a: [1, "b"] }, null, undefined);
+console.warn("missing %s %o", "arg");
+console.error(Symbol("sym"), function foo() {});
+"#,
+            "<evalScript>",
+            Eval::GLOBAL,
+        )
+        .unwrap();
+
+        assert_eq!(
+            *messages.lock().unwrap(),
+            vec![
+                (
+                    Level::Info,
+                    "alice is 42.5 years, 42% {\"a\":[1,\"b\"]} null undefined".to_owned()
+                ),
+                (Level::Warn, "missing arg %o".to_owned()),
+                (Level::Error, "Symbol(sym) function foo() {}".to_owned()),
+            ]
+        );
+
+        ctxt.eval_script(
+            "console.info(new Error('boom'))",
+            "<evalScript>",
+            Eval::GLOBAL,
+        )
+        .unwrap();
+
+        assert!(messages.lock().unwrap()[3].1.starts_with("Error: boom"));
+
+        ctxt.init_console().unwrap();
+        ctxt.eval_script(
+            "console.debug('to log crate')",
+            "<evalScript>",
+            Eval::GLOBAL,
+        )
+        .unwrap();
+    }
+}
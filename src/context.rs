@@ -1,8 +1,15 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
 use std::ptr::{null_mut, NonNull};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
 use foreign_types::{ForeignType, ForeignTypeRef};
 
-use crate::{ffi, Local, RuntimeRef, Value};
+use crate::{
+    deterministic::Deterministic, ffi, pin::Pins, timers::Timers, EnvFilter, Int64Policy, Limits,
+    Local, Pipeline, Prop, RuntimeRef, SourceMap, Value,
+};
 
 foreign_type! {
     /// `Context` represents a Javascript context (or Realm).
@@ -13,86 +20,281 @@ foreign_type! {
     pub type Context : Send {
         type CType = ffi::JSContext;
 
-        fn drop = ffi::JS_FreeContext;
+        fn drop = free_context;
     }
 }
 
+lazy_static! {
+    static ref CONTEXT_STATES: Mutex<HashMap<usize, State>> = Mutex::new(HashMap::new());
+}
+
+/// The Rust side states of a `Context`, which will be released with the context.
+#[derive(Default)]
+pub(crate) struct State {
+    pub storage: HashMap<TypeId, Box<dyn Any + Send>>,
+    pub host: Option<Value>,
+    pub finalizers_key: Option<Value>,
+    pub timers: Timers,
+    pub limits: Limits,
+    pub pipeline: Pipeline,
+    pub pins: Pins,
+    pub label: Option<String>,
+    pub last_eval: Option<(SystemTime, Duration)>,
+    pub deterministic: Option<Deterministic>,
+    pub env: Option<EnvFilter>,
+    pub source_maps: HashMap<String, SourceMap>,
+    #[cfg(feature = "http")]
+    pub fetches: crate::fetch::Fetches,
+    #[cfg(feature = "stdlib")]
+    pub module_hooks: HashMap<usize, (ffi::JSModuleInitFunc, Vec<crate::stdlib::ModuleHook>)>,
+    #[cfg(feature = "stdlib")]
+    pub vfs: Option<std::sync::Arc<dyn crate::VfsHandler>>,
+    #[cfg(feature = "stdlib")]
+    pub std_helpers: bool,
+    #[cfg(feature = "stdlib")]
+    pub stdout: Option<Box<dyn std::io::Write + Send>>,
+    #[cfg(feature = "stdlib")]
+    pub stderr: Option<Box<dyn std::io::Write + Send>>,
+}
+
+// the Javascript values are only accessed with the context
+unsafe impl Send for State {}
+
+unsafe fn free_context(ctx: *mut ffi::JSContext) {
+    let state = CONTEXT_STATES
+        .lock()
+        .expect("context states")
+        .remove(&(ctx as usize));
+
+    // release the states out of the lock and before the context
+    if let Some(mut state) = state {
+        let ctxt = ContextRef::from_ptr(ctx);
+
+        for callback in state.timers.drain() {
+            ctxt.free_value(callback);
+        }
+
+        #[cfg(feature = "http")]
+        for func in state.fetches.drain() {
+            ctxt.free_value(func);
+        }
+
+        for value in state.pins.drain() {
+            ctxt.free_value(value);
+        }
+
+        if let Some(host) = state.host {
+            ctxt.free_value(host);
+        }
+
+        if let Some(key) = state.finalizers_key {
+            ctxt.free_value(key);
+        }
+    }
+
+    let rt = RuntimeRef::from_ptr(ffi::JS_GetRuntime(ctx));
+
+    rt.with_state(|state| state.contexts.retain(|&p| p != ctx as usize));
+    rt.with_interrupts(|interrupts| interrupts.op_budgets.remove(&(ctx as usize)));
+
+    ffi::JS_FreeContext(ctx)
+}
+
 impl_foreign_type!(Context, ContextRef);
 
+/// The builder of a `Context` with the selected intrinsic objects.
 pub struct Builder(Context);
 
 impl Context {
     pub fn new(runtime: &RuntimeRef) -> Context {
-        unsafe { Context::from_ptr(ffi::JS_NewContext(runtime.as_ptr())) }
+        unsafe { Context::track(runtime, ffi::JS_NewContext(runtime.as_ptr())) }
     }
 
+    /// Create a raw context without any intrinsic object, which could be added with the `Builder`.
     pub fn builder(runtime: &RuntimeRef) -> Builder {
-        Builder(unsafe { Context::from_ptr(ffi::JS_NewContextRaw(runtime.as_ptr())) })
+        Builder(unsafe { Context::track(runtime, ffi::JS_NewContextRaw(runtime.as_ptr())) })
+    }
+
+    /// Track the alive contexts, so the runtime could detect them when dropped.
+    unsafe fn track(runtime: &RuntimeRef, ctx: *mut ffi::JSContext) -> Context {
+        runtime.with_state(|state| state.contexts.push(ctx as usize));
+
+        CONTEXT_STATES
+            .lock()
+            .expect("context states")
+            .insert(ctx as usize, State::default());
+
+        Context::from_ptr(ctx)
+    }
+
+    /// Create a locked-down context with only the base objects and the evaluator.
+    ///
+    /// The other intrinsic objects should be enabled explicitly, e.g. for the untrusted code.
+    pub fn minimal(runtime: &RuntimeRef) -> Builder {
+        Context::builder(runtime).with_base_objects().with_eval()
     }
 }
 
 impl Builder {
+    /// Add the base objects, e.g. `Object`, `Function`, `Array`, `Error`, `Math`, `Symbol`,
+    /// and `BigInt` when the `bignum` feature is enabled.
     pub fn with_base_objects(self) -> Self {
         unsafe { ffi::JS_AddIntrinsicBaseObjects(self.0.as_ptr()) };
         self
     }
 
+    /// Add the `Date` object.
     pub fn with_date(self) -> Self {
         unsafe { ffi::JS_AddIntrinsicDate(self.0.as_ptr()) };
         self
     }
 
+    /// Add the evaluator which is required to evaluate the scripts.
     pub fn with_eval(self) -> Self {
         unsafe { ffi::JS_AddIntrinsicEval(self.0.as_ptr()) };
         self
     }
 
+    /// Add the `String.prototype.normalize` method.
     pub fn with_string_normalize(self) -> Self {
         unsafe { ffi::JS_AddIntrinsicStringNormalize(self.0.as_ptr()) };
         self
     }
 
+    /// Add the regular expression compiler which is required by the `RegExp` literals.
     pub fn with_regexp_compiler(self) -> Self {
         unsafe { ffi::JS_AddIntrinsicRegExpCompiler(self.0.as_ptr()) };
         self
     }
 
+    /// Add the `RegExp` object and its compiler.
     pub fn with_regexp(self) -> Self {
         unsafe { ffi::JS_AddIntrinsicRegExp(self.0.as_ptr()) };
         self
     }
 
+    /// Add the `JSON` object.
     pub fn with_json(self) -> Self {
         unsafe { ffi::JS_AddIntrinsicJSON(self.0.as_ptr()) };
         self
     }
 
+    /// Add the `Proxy` object.
     pub fn with_proxy(self) -> Self {
         unsafe { ffi::JS_AddIntrinsicProxy(self.0.as_ptr()) };
         self
     }
 
+    /// Add the `Map`, `Set`, `WeakMap` and `WeakSet` objects.
     pub fn with_map(self) -> Self {
         unsafe { ffi::JS_AddIntrinsicMapSet(self.0.as_ptr()) };
         self
     }
 
+    /// Add the `ArrayBuffer`, `SharedArrayBuffer`, `DataView` and typed arrays objects.
     pub fn with_typedarray(self) -> Self {
         unsafe { ffi::JS_AddIntrinsicTypedArrays(self.0.as_ptr()) };
         self
     }
 
+    /// Add the `Promise` object and the async functions.
     pub fn with_promise(self) -> Self {
         unsafe { ffi::JS_AddIntrinsicPromise(self.0.as_ptr()) };
         self
     }
 
+    /// Add all the intrinsic objects, like the `Context::new`.
+    pub fn with_all(self) -> Self {
+        self.with_base_objects()
+            .with_date()
+            .with_eval()
+            .with_string_normalize()
+            .with_regexp()
+            .with_json()
+            .with_proxy()
+            .with_map()
+            .with_typedarray()
+            .with_promise()
+    }
+
+    /// Define the placeholder global properties upfront.
+    ///
+    /// The shape of the global object is stable when the host fills those globals later,
+    /// which avoid the shape transitions and keep the property access fast.
+    pub fn preshape_globals(self, names: &[&str]) -> Self {
+        let global = self.0.global_object();
+
+        for name in names {
+            trace!("preshape global property `{}`", name);
+
+            if let Err(err) = global.define_property_value(*name, ffi::UNDEFINED, Prop::C_W_E) {
+                warn!("fail to preshape global property `{}`, {}", name, err);
+            }
+        }
+
+        drop(global);
+
+        self
+    }
+
+    /// Apply the post-processors to every result of `ContextRef::eval` before extracting the value.
+    pub fn result_pipeline(self, pipeline: Pipeline) -> Self {
+        self.0.set_result_pipeline(pipeline);
+        self
+    }
+
+    /// Name the context, e.g. the tenant of a sandbox, which is reported by the diagnostics.
+    pub fn label<S: Into<String>>(self, label: S) -> Self {
+        self.0.set_label(label);
+        self
+    }
+
+    /// Limit the number of the interrupt checks charged to the context,
+    /// the scripts beyond the budget fail with `ErrorKind::BudgetExceeded`.
+    pub fn with_op_budget(self, budget: u64) -> Self {
+        self.0.set_op_budget(Some(budget));
+        self
+    }
+
+    /// Seed `Math.random` and read `Date` from the host clock, so the scripts are reproducible.
+    ///
+    /// It should be called after the `Math` and `Date` objects were added.
+    pub fn deterministic(self, seed: u64) -> Self {
+        if let Err(err) = self.0.set_deterministic(seed) {
+            warn!("fail to make the context deterministic, {}", err);
+        }
+
+        self
+    }
+
+    /// Set the policy to create the 64-bit integers beyond the safe range of `Number`.
+    pub fn int64_policy(self, policy: Int64Policy) -> Self {
+        self.0.set_int64_policy(policy);
+        self
+    }
+
+    /// Build the context.
     pub fn build(self) -> Context {
         self.0
     }
 }
 
 impl ContextRef {
+    /// Access the states of the context, which panics if the context has been freed.
+    pub(crate) fn with_state<T, F: FnOnce(&mut State) -> T>(&self, f: F) -> T {
+        self.try_with_state(f)
+            .unwrap_or_else(|| panic!("{:?} has been freed", self))
+    }
+
+    /// Access the states of the context, returns `None` if the context has been freed.
+    pub(crate) fn try_with_state<T, F: FnOnce(&mut State) -> T>(&self, f: F) -> Option<T> {
+        CONTEXT_STATES
+            .lock()
+            .expect("context states")
+            .get_mut(&(self.as_ptr() as usize))
+            .map(f)
+    }
+
     pub fn runtime(&self) -> &RuntimeRef {
         unsafe { RuntimeRef::from_ptr(ffi::JS_GetRuntime(self.as_ptr())) }
     }
@@ -113,6 +315,22 @@ impl ContextRef {
         self
     }
 
+    /// Attach the host state to the context, which can be retrieved by `opaque` in the native callbacks.
+    ///
+    /// Unlike the raw `userdata` pointer, the data is owned and type-checked by the context,
+    /// it is kept in the `storage` as `Arc<T>` and replaces the previous data of the same type.
+    pub fn set_opaque<T: Any + Send + Sync>(&self, data: T) -> &Self {
+        trace!("{:?} set {} opaque", self, std::any::type_name::<T>());
+
+        self.storage().insert(Arc::new(data));
+        self
+    }
+
+    /// Returns the host state of type `T` attached to the context.
+    pub fn opaque<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        self.storage().get::<Arc<T>>()
+    }
+
     /// Set the maximum system stack size.
     pub fn set_max_stack_size(&self, stack_size: usize) -> &Self {
         trace!("{:?} set stack size to {:?}", self, stack_size);
@@ -127,3 +345,109 @@ impl ContextRef {
         self.bind(unsafe { ffi::JS_GetGlobalObject(self.as_ptr()) })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{Eval, Runtime};
+
+    use super::*;
+
+    #[test]
+    fn preshape_globals() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::builder(&rt)
+            .with_base_objects()
+            .with_eval()
+            .preshape_globals(&["cfg", "api"])
+            .build();
+
+        assert_eq!(
+            ctxt.eval::<_, String>("'cfg' in globalThis && typeof api", Eval::GLOBAL)
+                .unwrap(),
+            Some("undefined".to_owned())
+        );
+
+        ctxt.global_object().set_property("cfg", 123).unwrap();
+
+        assert_eq!(ctxt.eval::<_, i32>("cfg", Eval::GLOBAL).unwrap(), Some(123));
+        assert_eq!(
+            ctxt.eval::<_, bool>("Object.keys(globalThis).includes('api')", Eval::GLOBAL)
+                .unwrap(),
+            Some(true)
+        );
+        assert!(ctxt.try_with_state(|_| ()).is_some());
+
+        let ptr = ctxt.as_ptr() as usize;
+
+        drop(ctxt);
+
+        assert!(!CONTEXT_STATES.lock().unwrap().contains_key(&ptr));
+    }
+
+    #[test]
+    fn minimal() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::minimal(&rt).with_json().build();
+
+        assert_eq!(
+            ctxt.eval::<_, String>(
+                "[typeof Object, typeof JSON, typeof Date, typeof Promise, typeof Proxy].join()",
+                Eval::GLOBAL
+            )
+            .unwrap(),
+            Some("function,object,undefined,undefined,undefined".to_owned())
+        );
+
+        let ctxt = Context::builder(&rt).with_all().build();
+
+        assert_eq!(
+            ctxt.eval::<_, String>(
+                "[typeof Date, typeof Promise, typeof Proxy, typeof Map, typeof Uint8Array].join()",
+                Eval::GLOBAL
+            )
+            .unwrap(),
+            Some("function,function,function,function,function".to_owned())
+        );
+    }
+
+    struct Tenant(&'static str);
+
+    fn tenant(ctxt: &ContextRef, _this: Option<&Value>, _args: &[Value]) -> String {
+        ctxt.opaque::<Tenant>()
+            .map(|tenant| tenant.0.to_owned())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn opaque() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        assert!(ctxt.opaque::<Tenant>().is_none());
+
+        ctxt.set_opaque(Tenant("acme"));
+        ctxt.global_object()
+            .set_property(
+                "tenant",
+                ctxt.new_c_function(tenant, Some("tenant"), 0).unwrap(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            ctxt.eval::<_, String>("tenant()", Eval::GLOBAL).unwrap(),
+            Some("acme".to_owned())
+        );
+        assert!(ctxt.opaque::<String>().is_none());
+        assert!(Context::new(&rt).opaque::<Tenant>().is_none());
+
+        ctxt.set_opaque(Tenant("other"));
+
+        assert_eq!(ctxt.opaque::<Tenant>().unwrap().0, "other");
+    }
+}
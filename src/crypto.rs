@@ -0,0 +1,143 @@
+use crate::{ffi, ContextRef, Error, Eval, Value};
+
+/// The maximum byte length of the array filled by `crypto.getRandomValues`.
+const MAX_RANDOM_BYTES: usize = 65536;
+
+const GLUE: &str = r#"
+(function (fill, randomUUID, maxBytes) {
+    const INTEGER_ARRAYS = [
+        'Int8Array', 'Uint8Array', 'Uint8ClampedArray', 'Int16Array', 'Uint16Array',
+        'Int32Array', 'Uint32Array', 'BigInt64Array', 'BigUint64Array',
+    ];
+
+    function domException(name, message) {
+        const err = new Error(message);
+
+        err.name = name;
+
+        return err;
+    }
+
+    function getRandomValues(array) {
+        const tag = ArrayBuffer.isView(array) ? array[Symbol.toStringTag] : undefined;
+
+        if (!INTEGER_ARRAYS.includes(tag)) {
+            throw domException('TypeMismatchError', 'The data is not an integer typed array');
+        }
+        if (array.byteLength > maxBytes) {
+            throw domException('QuotaExceededError',
+                `The byte length (${array.byteLength}) exceeds the entropy available (${maxBytes})`);
+        }
+
+        fill(array);
+
+        return array;
+    }
+
+    globalThis.crypto = { getRandomValues, randomUUID };
+})
+"#;
+
+fn fill(ctxt: &ContextRef, _this: Option<&Value>, args: &[Value]) -> ffi::JSValue {
+    let res = args
+        .first()
+        .and_then(|array| ctxt.with_buffer_source_mut(array, getrandom::getrandom));
+
+    match res {
+        Some(Ok(())) => ffi::UNDEFINED,
+        Some(Err(err)) => ctxt
+            .throw_internal_error(err.to_string())
+            .into_inner()
+            .raw(),
+        None => ctxt
+            .throw_type_error("expected ArrayBufferView")
+            .into_inner()
+            .raw(),
+    }
+}
+
+/// Format the random bytes as a version 4 UUID.
+fn format_uuid(mut bytes: [u8; 16]) -> String {
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    bytes
+        .iter()
+        .enumerate()
+        .map(|(idx, b)| {
+            if [4, 6, 8, 10].contains(&idx) {
+                format!("-{:02x}", b)
+            } else {
+                format!("{:02x}", b)
+            }
+        })
+        .collect()
+}
+
+fn random_uuid(ctxt: &ContextRef, _this: Option<&Value>, _args: &[Value]) -> ffi::JSValue {
+    let mut bytes = [0; 16];
+
+    match getrandom::getrandom(&mut bytes) {
+        Ok(()) => ctxt.bind(format_uuid(bytes)).into_inner().raw(),
+        Err(err) => ctxt
+            .throw_internal_error(err.to_string())
+            .into_inner()
+            .raw(),
+    }
+}
+
+impl ContextRef {
+    /// Install the `crypto` object with the `getRandomValues` and `randomUUID` methods of the Web Crypto API,
+    /// which are backed by the random number generator of the operating system.
+    pub fn init_crypto(&self) -> Result<(), Error> {
+        self.eval_script(GLUE, "<crypto>", Eval::GLOBAL)?.call(
+            None,
+            (
+                self.new_c_function(fill, Some("fill"), 1)?,
+                self.new_c_function(random_uuid, Some("randomUUID"), 0)?,
+                MAX_RANDOM_BYTES as i32,
+            ),
+        )?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, Runtime};
+
+    use super::*;
+
+    #[test]
+    fn crypto() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        assert_eq!(
+            format_uuid([0xff; 16]),
+            "ffffffff-ffff-4fff-bfff-ffffffffffff"
+        );
+
+        ctxt.init_crypto().unwrap();
+
+        assert_eq!(
+            ctxt.eval::<_, String>(
+                r#"
+var arr = new Uint32Array(64);
+var same = crypto.getRandomValues(arr) === arr;
+var uuid = crypto.randomUUID();
+var errors = [new Float64Array(1), new Uint8Array(65537), [1, 2]].map(arr => {
+    try { crypto.getRandomValues(arr) } catch (err) { return err.name }
+});
+[same, arr.some(n => n != 0), /^[0-9a-f]{8}-[0-9a-f]{4}-4[0-9a-f]{3}-[89ab][0-9a-f]{3}-[0-9a-f]{12}$/.test(uuid), errors].join()
+"#,
+                Eval::GLOBAL
+            )
+            .unwrap(),
+            Some("true,true,true,TypeMismatchError,QuotaExceededError,TypeMismatchError".to_owned())
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{ContextRef, Error, Eval, Value};
+
+/// Replace `Date` with a wrapper reading the host clock, the explicit dates are untouched.
+const DATE: &str = r#"
+(now) => {
+    const NativeDate = globalThis.Date;
+    const Date = function Date(...args) {
+        if (new.target === undefined) {
+            return new NativeDate(now()).toString();
+        }
+
+        return Reflect.construct(NativeDate, args.length ? args : [now()], new.target);
+    };
+
+    // copy the statics instead of inheriting them, so the native `Date.now` is not reachable
+    for (const key of Reflect.ownKeys(NativeDate)) {
+        const desc = Object.getOwnPropertyDescriptor(NativeDate, key);
+
+        Object.defineProperty(Date, key, key === "now" ? Object.assign(desc, { value: now }) : desc);
+    }
+
+    NativeDate.prototype.constructor = Date;
+    globalThis.Date = Date;
+}
+"#;
+
+/// The seeded random generator and the host clock of a deterministic context.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Deterministic {
+    seed: u64,
+    now: Duration,
+}
+
+impl Deterministic {
+    /// The `splitmix64` generator, which accepts any seed including zero.
+    fn next_u64(&mut self) -> u64 {
+        self.seed = self.seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+fn random(ctxt: &ContextRef, _this: Option<&Value>, _args: &[Value]) -> f64 {
+    ctxt.with_state(|state| {
+        state.deterministic.as_mut().map_or(0.0, |det| {
+            (det.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+        })
+    })
+}
+
+fn now(ctxt: &ContextRef, _this: Option<&Value>, _args: &[Value]) -> f64 {
+    ctxt.with_state(|state| {
+        state
+            .deterministic
+            .map_or(0.0, |det| det.now.as_millis() as f64)
+    })
+}
+
+impl ContextRef {
+    /// Make the scripts reproducible, `Math.random` is seeded and `Date` reads the host clock.
+    ///
+    /// The clock starts at the Unix epoch and never ticks by itself,
+    /// it should be moved with `ContextRef::set_clock` or `ContextRef::advance_clock`.
+    /// The `Math` and `Date` intrinsic objects are replaced only if they were added.
+    pub fn set_deterministic(&self, seed: u64) -> Result<(), Error> {
+        trace!("{:?} set deterministic with seed {}", self, seed);
+
+        self.with_state(|state| {
+            state.deterministic = Some(Deterministic {
+                seed,
+                now: Duration::default(),
+            })
+        });
+
+        let global = self.global_object();
+
+        if let Some(math) = global.get_property("Math").filter(|math| math.is_object()) {
+            math.set_property("random", self.new_c_function(random, Some("random"), 0)?)?;
+        }
+
+        if global.get_property("Date").is_some() {
+            self.eval_script(DATE, "<deterministic>", Eval::GLOBAL)?
+                .call(None, self.new_c_function(now, Some("now"), 0)?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns `true` if the context is deterministic.
+    pub fn is_deterministic(&self) -> bool {
+        self.with_state(|state| state.deterministic.is_some())
+    }
+
+    /// Set the host clock of the deterministic context.
+    pub fn set_clock(&self, time: SystemTime) {
+        let now = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+
+        self.with_state(|state| {
+            if let Some(det) = state.deterministic.as_mut() {
+                det.now = now;
+            }
+        })
+    }
+
+    /// Move the host clock of the deterministic context forward.
+    pub fn advance_clock(&self, duration: Duration) {
+        self.with_state(|state| {
+            if let Some(det) = state.deterministic.as_mut() {
+                det.now += duration;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, Eval, Runtime};
+
+    use super::*;
+
+    #[test]
+    fn deterministic() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let random = |seed| {
+            let ctxt = Context::builder(&rt).with_all().deterministic(seed).build();
+
+            ctxt.eval::<_, String>(
+                "[Math.random(), Math.random(), Math.random()].join()",
+                Eval::GLOBAL,
+            )
+            .unwrap()
+            .unwrap()
+        };
+
+        assert_eq!(random(42), random(42));
+        assert_ne!(random(42), random(7));
+
+        let ctxt = Context::builder(&rt).with_all().deterministic(0).build();
+
+        assert!(ctxt.is_deterministic());
+        assert_eq!(ctxt.eval("Date.now()", Eval::GLOBAL).unwrap(), Some(0));
+
+        ctxt.set_clock(UNIX_EPOCH + Duration::from_secs(86400));
+        ctxt.advance_clock(Duration::from_millis(1500));
+
+        assert_eq!(
+            ctxt.eval::<_, String>("new Date().toISOString()", Eval::GLOBAL)
+                .unwrap()
+                .unwrap(),
+            "1970-01-02T00:00:01.500Z"
+        );
+        assert_eq!(
+            ctxt.eval("new Date(2020, 0, 1).getFullYear()", Eval::GLOBAL)
+                .unwrap(),
+            Some(2020)
+        );
+        assert_eq!(
+            ctxt.eval(
+                "new Date() instanceof Date && Date.UTC(1970, 0, 1) === 0",
+                Eval::GLOBAL
+            )
+            .unwrap(),
+            Some(true)
+        );
+        assert_eq!(
+            ctxt.eval(
+                "Date.length === 7 && Object.getPrototypeOf(Date) === Function.prototype && Date.parse('1970-01-01T00:00:00Z') === 0",
+                Eval::GLOBAL
+            )
+            .unwrap(),
+            Some(true)
+        );
+        assert_eq!(
+            ctxt.eval("Math.random() < 1", Eval::GLOBAL).unwrap(),
+            Some(true)
+        );
+    }
+}
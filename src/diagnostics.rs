@@ -0,0 +1,122 @@
+use std::fmt;
+use std::time::{Duration, Instant, SystemTime};
+
+use foreign_types::ForeignTypeRef;
+
+use crate::{ContextRef, RuntimeRef};
+
+/// The diagnostics of a context, e.g. for the admin endpoints of a host which juggles many sandboxes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostics {
+    /// The identity of the context, which is unique among the alive contexts.
+    pub id: usize,
+    /// The label of the context.
+    pub label: Option<String>,
+    /// When the last evaluation was started.
+    pub last_eval: Option<SystemTime>,
+    /// How long the last evaluation took.
+    pub last_eval_duration: Option<Duration>,
+    /// The number of the pending timers and fetches, plus one if the microtask queue is not empty.
+    pub pending_jobs: usize,
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.label.as_ref().map_or("<unnamed>", |s| s.as_str()))?;
+
+        if let Some(duration) = self.last_eval_duration {
+            let ago = self
+                .last_eval
+                .and_then(|at| at.elapsed().ok())
+                .unwrap_or_default();
+
+            write!(f, ", last eval took {:?} {:?} ago", duration, ago)?;
+        }
+
+        write!(f, ", {} pending jobs", self.pending_jobs)
+    }
+}
+
+impl ContextRef {
+    /// Returns the label of the context.
+    pub fn label(&self) -> Option<String> {
+        self.with_state(|state| state.label.clone())
+    }
+
+    /// Name the context, e.g. the tenant of a sandbox.
+    pub fn set_label<S: Into<String>>(&self, label: S) {
+        self.with_state(|state| state.label = Some(label.into()))
+    }
+
+    /// Returns the diagnostics of the context.
+    ///
+    /// QuickJS accounts the memory per runtime, see `RuntimeRef::memory_usage`.
+    pub fn diagnostics(&self) -> Diagnostics {
+        let (label, last_eval) = self.with_state(|state| (state.label.clone(), state.last_eval));
+
+        Diagnostics {
+            id: self.as_ptr() as usize,
+            label,
+            last_eval: last_eval.map(|(at, _)| at),
+            last_eval_duration: last_eval.map(|(_, duration)| duration),
+            pending_jobs: self.pending_jobs(),
+        }
+    }
+
+    /// Record the start time and duration of the evaluation.
+    pub(crate) fn record_eval<T, F: FnOnce() -> T>(&self, f: F) -> T {
+        let (at, started) = (SystemTime::now(), Instant::now());
+        let res = f();
+        let duration = started.elapsed();
+
+        self.with_state(|state| state.last_eval = Some((at, duration)));
+
+        res
+    }
+}
+
+impl RuntimeRef {
+    /// Returns the diagnostics of the alive contexts of the runtime, in the order of creation.
+    pub fn contexts(&self) -> Vec<Diagnostics> {
+        self.with_state(|state| state.contexts.clone())
+            .into_iter()
+            .map(|ctx| unsafe { ContextRef::from_ptr(ctx as *mut _) }.diagnostics())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, Eval, Runtime};
+
+    #[test]
+    fn diagnostics() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::builder(&rt).with_all().label("tenant-42").build();
+        let other = Context::new(&rt);
+
+        ctxt.init_timers().unwrap();
+        ctxt.eval_script("setTimeout(() => {}, 1000)", "<evalScript>", Eval::GLOBAL)
+            .unwrap();
+
+        let contexts = rt.contexts();
+
+        assert_eq!(contexts.len(), 2);
+
+        let diag = &contexts[0];
+
+        assert_eq!(diag.id, ctxt.diagnostics().id);
+        assert_ne!(diag.id, contexts[1].id);
+        assert_eq!(diag.label.as_deref(), Some("tenant-42"));
+        assert!(diag.last_eval.is_some());
+        assert_eq!(diag.pending_jobs, 1);
+        assert!(diag.to_string().starts_with("tenant-42, last eval took"));
+        assert_eq!(contexts[1].to_string(), "<unnamed>, 0 pending jobs");
+
+        drop(other);
+
+        assert_eq!(rt.contexts().len(), 1);
+    }
+}
@@ -0,0 +1,304 @@
+use std::ffi::CStr;
+use std::fmt;
+use std::mem::MaybeUninit;
+
+use foreign_types::ForeignTypeRef;
+
+use crate::{ffi, ContextRef, Error, Value};
+
+/// An operand of the disassembled instruction.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operand {
+    /// An immediate integer, e.g. the number of the arguments.
+    Int(i32),
+    /// The offset of the jump target.
+    Label(u32),
+    /// The atom, e.g. the property or variable name.
+    Atom(String),
+    /// The index and name of the local variable.
+    Local(u16, Option<String>),
+    /// The index and name of the argument.
+    Arg(u16, Option<String>),
+    /// The index and name of the closure variable.
+    VarRef(u16, Option<String>),
+    /// The index in the constant pool of the function.
+    Const(u32),
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operand::Int(n) => write!(f, "{}", n),
+            Operand::Label(pos) => write!(f, "@{}", pos),
+            Operand::Atom(name) => f.write_str(name),
+            Operand::Local(idx, name) | Operand::Arg(idx, name) | Operand::VarRef(idx, name) => {
+                write!(f, "{}: {}", idx, name.as_deref().unwrap_or("?"))
+            }
+            Operand::Const(idx) => write!(f, "#{}", idx),
+        }
+    }
+}
+
+/// A disassembled instruction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Instruction {
+    /// The offset of the instruction in the bytecode.
+    pub offset: u32,
+    /// The name of the opcode, e.g. `get_loc`.
+    pub opcode: &'static str,
+    /// The decoded operands.
+    pub operands: Vec<Operand>,
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:5}  {}", self.offset, self.opcode)?;
+
+        for (idx, operand) in self.operands.iter().enumerate() {
+            write!(f, "{}{}", if idx == 0 { " " } else { ", " }, operand)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// An item of the constant pool.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Constant {
+    /// A nested function, e.g. a closure or method.
+    Function(Function),
+    /// Other value formatted by `ContextRef::inspect`.
+    Value(String),
+}
+
+/// A disassembled bytecode function, created by `ContextRef::disassemble`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Function {
+    /// The name of the function, or `None` for the anonymous function.
+    pub name: Option<String>,
+    /// The script filename, or `None` if the debug information was stripped.
+    pub filename: Option<String>,
+    /// The line number of the function in the script.
+    pub line: Option<u32>,
+    /// The names of the arguments.
+    pub args: Vec<String>,
+    /// The names of the local variables.
+    pub locals: Vec<String>,
+    /// The names of the variables captured by the closure.
+    pub closure_vars: Vec<String>,
+    /// The constant pool, which the `Operand::Const` refers to.
+    pub constants: Vec<Constant>,
+    /// The instructions of the bytecode.
+    pub instructions: Vec<Instruction>,
+}
+
+impl fmt::Display for Function {
+    /// Format the listing of the function and the nested functions.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "function: {}",
+            self.name.as_deref().unwrap_or("<anonymous>")
+        )?;
+
+        match (self.filename.as_ref(), self.line) {
+            (Some(filename), Some(line)) => writeln!(f, " ({}:{})", filename, line)?,
+            (Some(filename), None) => writeln!(f, " ({})", filename)?,
+            _ => writeln!(f)?,
+        }
+
+        for (label, names) in &[
+            ("args", &self.args),
+            ("locals", &self.locals),
+            ("closure vars", &self.closure_vars),
+        ] {
+            if !names.is_empty() {
+                writeln!(f, "  {}: {}", label, names.join(", "))?;
+            }
+        }
+
+        for (idx, constant) in self.constants.iter().enumerate() {
+            if let Constant::Value(value) = constant {
+                writeln!(f, "  #{}: {}", idx, value)?;
+            }
+        }
+
+        for instr in &self.instructions {
+            writeln!(f, "{}", instr)?;
+        }
+
+        for constant in &self.constants {
+            if let Constant::Function(func) = constant {
+                writeln!(f)?;
+                func.fmt(f)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ContextRef {
+    /// Disassemble a bytecode function, e.g. compiled with `Eval::COMPILE_ONLY` or read by `read_object`,
+    /// or a Javascript function object.
+    ///
+    /// The nested functions in the constant pool are disassembled recursively.
+    pub fn disassemble(&self, func: &Value) -> Result<Function, Error> {
+        let val = func.raw();
+        let mut desc = ffi::JSDisasmFunction::default();
+
+        if unsafe { ffi::JS_DisasmFunction(val, &mut desc) } < 0 {
+            return Err("expected bytecode function".into());
+        }
+
+        let atom = |atom: ffi::JSAtom| {
+            if atom == ffi::JS_ATOM_NULL {
+                None
+            } else {
+                Some(self.atom_to_cstring(atom).to_string_lossy().to_string())
+            }
+        };
+        let var_name = |kind, idx| atom(unsafe { ffi::JS_DisasmVarName(val, kind, idx) });
+        let var_names = |kind, count| {
+            (0..count)
+                .map(|idx| var_name(kind, idx).unwrap_or_default())
+                .collect::<Vec<_>>()
+        };
+
+        let constants = (0..desc.cpool_count)
+            .map(|idx| {
+                let value = self.bind(unsafe { ffi::JS_DisasmConst(self.as_ptr(), val, idx) });
+
+                if value.is_function_bytecode() {
+                    self.disassemble(&value).map(Constant::Function)
+                } else {
+                    Ok(Constant::Value(value.inspect(0)))
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut instructions = vec![];
+        let mut pos = 0;
+
+        while pos < desc.byte_code_len as u32 {
+            let mut instr = MaybeUninit::<ffi::JSDisasmInstr>::uninit();
+            let next = unsafe { ffi::JS_DisasmInstr(val, pos, instr.as_mut_ptr()) };
+
+            if next < 0 {
+                return Err(format!("invalid opcode at {}", pos).into());
+            }
+
+            let instr = unsafe { instr.assume_init() };
+            let opcode = unsafe { CStr::from_ptr(instr.name) }
+                .to_str()
+                .unwrap_or_default();
+            let operands = instr.operands[..instr.operand_count as usize]
+                .iter()
+                .map(|op| match op.kind {
+                    ffi::JS_DISASM_OPERAND_LABEL => Operand::Label(op.value as u32),
+                    ffi::JS_DISASM_OPERAND_ATOM => {
+                        Operand::Atom(atom(op.value as ffi::JSAtom).unwrap_or_default())
+                    }
+                    ffi::JS_DISASM_OPERAND_LOC => {
+                        Operand::Local(op.value as u16, var_name(1, op.value))
+                    }
+                    ffi::JS_DISASM_OPERAND_ARG => {
+                        Operand::Arg(op.value as u16, var_name(0, op.value))
+                    }
+                    ffi::JS_DISASM_OPERAND_VAR_REF => {
+                        Operand::VarRef(op.value as u16, var_name(2, op.value))
+                    }
+                    ffi::JS_DISASM_OPERAND_CONST => Operand::Const(op.value as u32),
+                    _ => Operand::Int(op.value),
+                })
+                .collect();
+
+            instructions.push(Instruction {
+                offset: instr.pos,
+                opcode,
+                operands,
+            });
+
+            pos = next as u32;
+        }
+
+        Ok(Function {
+            name: atom(desc.name),
+            filename: atom(desc.filename),
+            line: if desc.line_num > 0 {
+                Some(desc.line_num as u32)
+            } else {
+                None
+            },
+            args: var_names(0, desc.arg_count),
+            locals: var_names(1, desc.var_count),
+            closure_vars: var_names(2, desc.closure_var_count),
+            constants,
+            instructions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, Eval, Runtime};
+
+    use super::*;
+
+    #[test]
+    fn disassemble() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let func = ctxt
+            .eval_script(
+                "function add(a, b) { let sum = a + b; return sum; }\nadd(1, 2)",
+                "add.js",
+                Eval::GLOBAL | Eval::COMPILE_ONLY,
+            )
+            .unwrap();
+        let script = ctxt.disassemble(&func).unwrap();
+
+        assert_eq!(script.name.as_deref(), Some("<eval>"));
+        assert_eq!(script.filename.as_deref(), Some("add.js"));
+        assert!(script
+            .instructions
+            .iter()
+            .any(|instr| instr.opcode == "call2"
+                || (instr.opcode == "call" && instr.operands == vec![Operand::Int(2)])));
+
+        let add = match &script.constants[..] {
+            [Constant::Function(add)] => add,
+            constants => panic!("unexpected constants: {:?}", constants),
+        };
+
+        assert_eq!(add.name.as_deref(), Some("add"));
+        assert_eq!(add.args, vec!["a", "b"]);
+        assert_eq!(add.locals, vec!["sum"]);
+        assert_eq!(
+            add.instructions
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec![
+                "    0  set_loc_uninitialized 0: sum",
+                "    3  get_arg0 0: a",
+                "    4  get_arg1 1: b",
+                "    5  add",
+                "    6  put_loc0 0: sum",
+                "    7  get_loc_check 0: sum",
+                "   10  return",
+            ]
+        );
+        assert!(script.to_string().contains("function: add (add.js:1)"));
+
+        let obj = ctxt
+            .eval_script("(x => x * 2)", "<evalScript>", Eval::GLOBAL)
+            .unwrap();
+
+        assert!(ctxt.disassemble(&obj).is_ok());
+        assert!(ctxt.disassemble(&ctxt.bind(ctxt.new_object())).is_err());
+    }
+}
@@ -0,0 +1,221 @@
+use std::env;
+use std::ffi::CString;
+use std::os::raw::c_int;
+use std::panic;
+use std::ptr::NonNull;
+
+use foreign_types::ForeignTypeRef;
+
+use crate::{ffi, ContextRef, Error, ModuleDef, NewValue, Value};
+
+/// The exports of the `env` module.
+const EXPORTS: &[&str] = &["getenv", "environ"];
+
+/// The filter of the environment variables exposed to the scripts.
+///
+/// The names ending with `*` match the prefix, and the denied names take precedence over the allowed ones.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EnvFilter {
+    allow: Option<Vec<String>>,
+    deny: Vec<String>,
+}
+
+impl EnvFilter {
+    /// Expose all the environment variables except the denied ones.
+    pub fn allow_all() -> Self {
+        EnvFilter::default()
+    }
+
+    /// Expose only the allowed environment variables.
+    pub fn deny_all() -> Self {
+        EnvFilter {
+            allow: Some(vec![]),
+            deny: vec![],
+        }
+    }
+
+    /// Allow the environment variable, it takes no effect if all the variables are allowed.
+    pub fn allow<S: Into<String>>(mut self, name: S) -> Self {
+        if let Some(allow) = self.allow.as_mut() {
+            allow.push(name.into());
+        }
+        self
+    }
+
+    /// Deny the environment variable.
+    pub fn deny<S: Into<String>>(mut self, name: S) -> Self {
+        self.deny.push(name.into());
+        self
+    }
+
+    /// Returns `true` if the environment variable is exposed to the scripts.
+    pub fn is_allowed(&self, name: &str) -> bool {
+        let matches = |pattern: &String| match pattern.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => pattern == name,
+        };
+
+        !self.deny.iter().any(matches)
+            && match self.allow.as_ref() {
+                Some(allow) => allow.iter().any(matches),
+                None => true,
+            }
+    }
+}
+
+/// Returns the allowed environment variable, or `undefined` if it is denied or missing.
+pub(crate) fn getenv(ctxt: &ContextRef, _this: Option<&Value>, args: &[Value]) -> ffi::JSValue {
+    let name = match ctxt.to_cstring(args.first().unwrap_or_default()) {
+        Some(name) => name.to_string_lossy().into_owned(),
+        None => return ffi::EXCEPTION,
+    };
+
+    if !ctxt.with_state(|state| matches!(state.env, Some(ref f) if f.is_allowed(&name))) {
+        trace!("getenv `{}` is denied", name);
+
+        return ffi::UNDEFINED;
+    }
+
+    match env::var_os(&name) {
+        Some(value) => value.to_string_lossy().into_owned().new_value(ctxt),
+        None => ffi::UNDEFINED,
+    }
+}
+
+/// Returns an object with the allowed environment variables.
+fn environ(ctxt: &ContextRef, _this: Option<&Value>, _args: &[Value]) -> ffi::JSValue {
+    let filter = ctxt
+        .with_state(|state| state.env.clone())
+        .unwrap_or_default();
+    let obj = ctxt.bind(ctxt.new_object());
+
+    for (name, value) in env::vars_os() {
+        let name = name.to_string_lossy();
+
+        if filter.is_allowed(&name) {
+            if let Err(err) = obj.set_property(name.as_ref(), value.to_string_lossy().as_ref()) {
+                return ctxt.throw(err.to_string()).into_inner().raw();
+            }
+        }
+    }
+
+    obj.into_inner().raw()
+}
+
+unsafe extern "C" fn init_module_env(ctx: *mut ffi::JSContext, m: *mut ffi::JSModuleDef) -> c_int {
+    panic::catch_unwind(|| {
+        let ctxt = ContextRef::from_ptr(ctx);
+        let m = NonNull::new_unchecked(m);
+
+        ctxt.new_c_function(getenv, Some("getenv"), 1)
+            .and_then(|func| ctxt.set_module_export(m, "getenv", func))
+            .and_then(|_| ctxt.new_c_function(environ, Some("environ"), 0))
+            .and_then(|func| ctxt.set_module_export(m, "environ", func))
+            .map_or(-1, |_| 0)
+    })
+    .unwrap_or(-1)
+}
+
+impl ContextRef {
+    /// Set the filter of the environment variables exposed to the scripts.
+    ///
+    /// It should be called before `init_module_std` to replace `std.getenv` with the filtered one.
+    pub fn set_env_filter(&self, filter: EnvFilter) {
+        trace!("{:?} set env filter: {:?}", self, filter);
+
+        self.with_state(|state| state.env = Some(filter));
+    }
+
+    /// Initialize the `env` module, which exports `getenv` and `environ` respecting the filter.
+    ///
+    /// All the environment variables are denied without a filter.
+    pub fn init_module_env(&self) -> Result<NonNull<ModuleDef>, Error> {
+        debug!("init `env` module");
+
+        if self.with_state(|state| state.env.is_none()) {
+            self.set_env_filter(EnvFilter::deny_all());
+        }
+
+        let m = self.new_c_module("env", Some(init_module_env))?;
+
+        for name in EXPORTS {
+            let name = CString::new(*name)?;
+
+            self.check_error(unsafe {
+                ffi::JS_AddModuleExport(self.as_ptr(), m.as_ptr(), name.as_ptr())
+            })?;
+        }
+
+        Ok(m)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, Eval, Runtime};
+
+    use super::*;
+
+    #[test]
+    fn env_filter() {
+        let _ = pretty_env_logger::try_init();
+
+        env::set_var("QJS_ENV_TEST_NAME", "foo");
+        env::set_var("QJS_ENV_TEST_SECRET", "bar");
+
+        let filter = EnvFilter::deny_all()
+            .allow("QJS_ENV_TEST_*")
+            .deny("QJS_ENV_TEST_SECRET");
+
+        assert!(filter.is_allowed("QJS_ENV_TEST_NAME"));
+        assert!(!filter.is_allowed("QJS_ENV_TEST_SECRET"));
+        assert!(!filter.is_allowed("PATH"));
+        assert!(EnvFilter::allow_all().is_allowed("PATH"));
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        ctxt.set_env_filter(filter);
+        ctxt.init_module_env().unwrap();
+
+        ctxt.eval::<_, ()>(
+            r#"
+import { getenv, environ } from 'env';
+
+globalThis.result = [
+    getenv('QJS_ENV_TEST_NAME'),
+    getenv('QJS_ENV_TEST_SECRET'),
+    getenv('PATH'),
+    Object.keys(environ()).filter(k => k.startsWith('QJS_ENV_TEST_')),
+];
+"#,
+            Eval::MODULE,
+        )
+        .unwrap();
+
+        assert_eq!(
+            ctxt.eval::<_, String>("JSON.stringify(result)", Eval::GLOBAL)
+                .unwrap()
+                .unwrap(),
+            r#"["foo",null,null,["QJS_ENV_TEST_NAME"]]"#
+        );
+
+        #[cfg(feature = "stdlib")]
+        {
+            ctxt.init_module_std().unwrap();
+
+            assert_eq!(
+                ctxt.eval::<_, String>(
+                    "import * as std from 'std'; globalThis.stdenv = [std.getenv('QJS_ENV_TEST_NAME'), std.getenv('PATH')].join();",
+                    Eval::MODULE,
+                )
+                .unwrap(),
+                None
+            );
+            assert_eq!(
+                ctxt.eval::<_, String>("stdenv", Eval::GLOBAL).unwrap(),
+                Some("foo,".to_owned())
+            );
+        }
+    }
+}
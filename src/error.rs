@@ -1,55 +1,139 @@
 use std::convert::TryFrom;
-use std::ffi::CString;
+use std::error::Error as StdError;
+use std::ffi::{CString, NulError};
+use std::io;
 use std::ptr::NonNull;
 
-use failure::{err_msg, Error};
 use foreign_types::ForeignTypeRef;
 
 use crate::{
     ffi,
+    stack::{
+        annotate_host_locations, format_stack, parse_stack, strip_native_frames, truncate_stack,
+        StackFormat, StackFrame,
+    },
     value::{ToBool, ERR},
-    ContextRef, Local, NewValue, Prop, Value,
+    ContextRef, Eval, ExtractValue, GetProperty, JsArray, Local, NewValue, Prop, Value,
 };
 
+/// The error type of the crate.
+///
+/// `Error` implements `std::error::Error + Send + Sync`,
+/// so it could be converted to `failure::Error` or `Box<dyn std::error::Error>` with the `?` operator,
+/// and the Javascript error could be retrieved with `Error::kind` instead of `failure::Error::downcast`.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The Javascript exception.
+    #[error(transparent)]
+    Js(#[from] ErrorKind),
+
+    /// The string contains an interior nul byte.
+    #[error(transparent)]
+    Nul(#[from] NulError),
+
+    /// The I/O error.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    /// The conversion exceeded the limit, like the nesting depth or the number of elements.
+    #[error("exceeded the {0} limit of {1}")]
+    Limit(&'static str, usize),
+
+    /// The other error with message.
+    #[error("{0}")]
+    Msg(String),
+
+    /// The other Rust error, which `source` chain is thrown as the nested `cause` of the Javascript error.
+    #[error(transparent)]
+    Other(#[from] Box<dyn StdError + Send + Sync>),
+}
+
+impl Error {
+    /// Returns the Javascript error if it is.
+    pub fn kind(&self) -> Option<&ErrorKind> {
+        match self {
+            Error::Js(err) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// Converts to the Javascript error if it is.
+    pub fn into_kind(self) -> Option<ErrorKind> {
+        match self {
+            Error::Js(err) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// Wrap the other Rust error.
+    pub fn other<E: Into<Box<dyn StdError + Send + Sync>>>(err: E) -> Self {
+        Error::Other(err.into())
+    }
+}
+
+impl From<String> for Error {
+    fn from(msg: String) -> Self {
+        Error::Msg(msg)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(msg: &str) -> Self {
+        Error::Msg(msg.to_owned())
+    }
+}
+
 /// Javascript error.
-#[derive(Debug, Clone, Fail, PartialEq)]
+#[derive(Debug, Clone, thiserror::Error, PartialEq)]
 pub enum ErrorKind {
-    #[fail(display = "Throw: {}", _0)]
+    #[error("Throw: {0}")]
     Throw(String),
 
-    #[fail(display = "Error: {}", _0)]
+    #[error("Error: {0}")]
     Error(String, Option<String>),
 
-    #[fail(display = "{}: {}", _0, _1)]
+    #[error("{0}: {1}")]
     Custom(String, String, Option<String>),
 
     /// an error that occurs regarding the global function eval().
-    #[fail(display = "EvalError: {}", _0)]
+    #[error("EvalError: {0}")]
     EvalError(String, Option<String>),
 
     /// an error that occurs when an internal error in the JavaScript engine is thrown.
-    #[fail(display = "InternalError: {}", _0)]
+    #[error("InternalError: {0}")]
     InternalError(String, Option<String>),
 
     /// an error that occurs when a numeric variable or parameter is outside of its valid range.
-    #[fail(display = "RangeError: {}", _0)]
+    #[error("RangeError: {0}")]
     RangeError(String, Option<String>),
 
     /// an error that occurs when de-referencing an invalid reference.
-    #[fail(display = "ReferenceError: {}", _0)]
+    #[error("ReferenceError: {0}")]
     ReferenceError(String, Option<String>),
 
     /// a syntax error that occurs while parsing code in eval().
-    #[fail(display = "SyntaxError: {}", _0)]
+    #[error("SyntaxError: {0}")]
     SyntaxError(String, Option<String>),
 
     /// an error that occurs when a variable or parameter is not of a valid type.
-    #[fail(display = "TypeError: {}", _0)]
+    #[error("TypeError: {0}")]
     TypeError(String, Option<String>),
 
     /// an error that occurs when encodeURI() or decodeURI() are passed invalid parameters.
-    #[fail(display = "URIError: {}", _0)]
+    #[error("URIError: {0}")]
     URIError(String, Option<String>),
+
+    /// an error that groups several errors, e.g. the rejections of `Promise.any`.
+    #[error("AggregateError: {0}")]
+    Aggregate(String, Vec<ErrorKind>, Option<String>),
+
+    /// the bytecode was written by an incompatible engine version or build, with the expected and found engine.
+    #[error("bytecode version mismatch, expected {0}, found {1}")]
+    BytecodeVersionMismatch(String, String),
+
+    /// the scripts ran out of the op budget of the context, with the budget.
+    #[error("op budget of {0} exceeded")]
+    BudgetExceeded(u64),
 }
 
 impl ErrorKind {
@@ -66,7 +150,10 @@ impl ErrorKind {
             | ReferenceError(msg, _)
             | SyntaxError(msg, _)
             | TypeError(msg, _)
-            | URIError(msg, _) => msg.as_str(),
+            | URIError(msg, _)
+            | Aggregate(msg, _, _) => msg.as_str(),
+            BytecodeVersionMismatch(..) => "bytecode version mismatch",
+            BudgetExceeded(_) => "op budget exceeded",
         }
     }
 
@@ -74,7 +161,7 @@ impl ErrorKind {
         use ErrorKind::*;
 
         match self {
-            Throw(_) => None,
+            Throw(_) | BytecodeVersionMismatch(..) | BudgetExceeded(_) => None,
             Error(_, ref stack)
             | Custom(_, _, ref stack)
             | EvalError(_, ref stack)
@@ -83,7 +170,52 @@ impl ErrorKind {
             | ReferenceError(_, ref stack)
             | SyntaxError(_, ref stack)
             | TypeError(_, ref stack)
-            | URIError(_, ref stack) => stack.as_ref().map(|s| s.as_str()),
+            | URIError(_, ref stack)
+            | Aggregate(_, _, ref stack) => stack.as_ref().map(|s| s.as_str()),
+        }
+    }
+
+    pub(crate) fn stack_mut(&mut self) -> Option<&mut String> {
+        use ErrorKind::*;
+
+        match self {
+            Throw(_) | BytecodeVersionMismatch(..) | BudgetExceeded(_) => None,
+            Error(_, ref mut stack)
+            | Custom(_, _, ref mut stack)
+            | EvalError(_, ref mut stack)
+            | InternalError(_, ref mut stack)
+            | RangeError(_, ref mut stack)
+            | ReferenceError(_, ref mut stack)
+            | SyntaxError(_, ref mut stack)
+            | TypeError(_, ref mut stack)
+            | URIError(_, ref mut stack)
+            | Aggregate(_, _, ref mut stack) => stack.as_mut(),
+        }
+    }
+
+    /// Returns the frames parsed from the stack trace of the error, the innermost first.
+    pub fn frames(&self) -> Vec<StackFrame> {
+        self.stack().map(parse_stack).unwrap_or_default()
+    }
+
+    /// Format the stack trace of the error.
+    pub fn format_stack(&self, format: StackFormat) -> Option<String> {
+        self.stack()
+            .map(|stack| format_stack(&parse_stack(stack), format))
+    }
+}
+
+/// Extract a returned value, the `Error` objects are extracted as `Err` and the other values as `Ok`.
+///
+/// The thrown exceptions are never extracted, because the evaluation fails before extracting,
+/// and the returned non-`Error` values, e.g. the rejected `"reason"`, are extracted by `T` or `None`.
+impl<T: ExtractValue> ExtractValue for Result<T, ErrorKind> {
+    /// Extract the `Error` objects, e.g. the caught or rejected errors, as `Err`.
+    fn extract_value(v: &Local<Value>) -> Option<Self> {
+        if v.is_error() {
+            ErrorKind::try_from(v.clone()).ok().map(Err)
+        } else {
+            T::extract_value(v).map(Ok)
         }
     }
 }
@@ -99,13 +231,28 @@ impl TryFrom<Local<'_, Value>> for ErrorKind {
         Ok(if value.is_error() {
             let name = value
                 .get_property("name")
-                .ok_or_else(|| err_msg("missing `name` property"))?
+                .ok_or_else(|| crate::Error::from("missing `name` property"))?
                 .to_string();
             let msg = value
                 .get_property("message")
-                .ok_or_else(|| err_msg("missing `message` property"))?
+                .ok_or_else(|| crate::Error::from("missing `message` property"))?
                 .to_string();
-            let stack = value.get_property("stack").map(|s| s.to_string());
+            let stack = value.get_property("stack").map(|s| {
+                let stack = s.to_string();
+                let rt = value.ctxt.runtime();
+
+                let stack = if rt.hide_native_frames() {
+                    strip_native_frames(&stack)
+                } else {
+                    annotate_host_locations(rt, stack)
+                };
+                let stack = match rt.stack_trace_limit() {
+                    Some(limit) => truncate_stack(&stack, limit),
+                    None => stack,
+                };
+
+                value.ctxt.apply_source_maps(stack)
+            });
 
             match name.as_str() {
                 "EvalError" => EvalError(msg, stack),
@@ -115,6 +262,23 @@ impl TryFrom<Local<'_, Value>> for ErrorKind {
                 "SyntaxError" => SyntaxError(msg, stack),
                 "TypeError" => TypeError(msg, stack),
                 "URIError" => URIError(msg, stack),
+                "AggregateError" => {
+                    let errors = match value.get_property("errors").map(Local::into_array) {
+                        Some(Ok(errors)) => (0..errors
+                            .len()
+                            .min(value.ctxt.conversion_limits().max_elements)
+                            as u32)
+                            .map(|idx| {
+                                ErrorKind::try_from(
+                                    errors.get(idx).unwrap_or_else(|| value.ctxt.undefined()),
+                                )
+                            })
+                            .collect::<Result<Vec<_>, _>>()?,
+                        _ => vec![],
+                    };
+
+                    Aggregate(msg, errors, stack)
+                }
                 "Error" => Error(msg, stack),
                 _ => Custom(name, msg, stack),
             }
@@ -128,10 +292,10 @@ impl NewValue for Result<Local<'_, Value>, Error> {
     fn new_value(self, ctxt: &ContextRef) -> ffi::JSValue {
         match self {
             Ok(v) => v,
-            Err(err) => match err.downcast::<ErrorKind>() {
-                Ok(err) => ctxt.throw(err),
-                Err(err) => ctxt.throw(err.to_string()),
-            },
+            // the error kind throws itself
+            Err(Error::Js(err)) => return err.new_value(ctxt),
+            Err(err) if err.source().is_some() => ctxt.throw_error_chain(&err),
+            Err(err) => ctxt.throw(err.to_string()),
         }
         .into_inner()
         .raw()
@@ -153,13 +317,122 @@ impl NewValue for ErrorKind {
             SyntaxError(msg, _) => ctxt.throw_syntax_error(msg),
             TypeError(msg, _) => ctxt.throw_type_error(msg),
             URIError(msg, stack) => ctxt.throw_custom_error("URIError", msg, stack),
+            Aggregate(msg, errors, stack) => {
+                match ctxt.new_aggregate_error(errors, msg).and_then(|err| {
+                    if let Some(stack) = stack {
+                        err.define_property_value(
+                            "stack",
+                            stack,
+                            Prop::WRITABLE | Prop::CONFIGURABLE,
+                        )?;
+                    }
+
+                    Ok(err)
+                }) {
+                    Ok(err) => ctxt.throw(err),
+                    Err(err) => ctxt.throw_error(err, None),
+                }
+            }
+            err @ BytecodeVersionMismatch(..) | err @ BudgetExceeded(_) => {
+                ctxt.throw_internal_error(err.to_string())
+            }
         }
         .into_inner()
         .raw()
     }
 }
 
+/// The Javascript exception caught in a context.
+#[derive(Debug)]
+pub struct JsException<'a> {
+    /// The thrown value.
+    pub value: Local<'a, Value>,
+    /// The Javascript error converted from the thrown value.
+    pub kind: ErrorKind,
+    /// The stack frames parsed from the backtrace.
+    pub stack: Vec<StackFrame>,
+}
+
+/// The live Javascript value thrown by a script.
+///
+/// The thrown value is kept as is, and only converted to `ErrorKind` on demand,
+/// so the host could inspect the rich payload of the error, e.g. `error.code`.
+#[derive(Debug)]
+pub struct Exception<'a>(Local<'a, Value>);
+
+impl<'a> Exception<'a> {
+    /// The thrown value.
+    pub fn value(&self) -> &Local<'a, Value> {
+        &self.0
+    }
+
+    /// Consumes the exception, returning the thrown value.
+    pub fn into_value(self) -> Local<'a, Value> {
+        self.0
+    }
+
+    /// Converts the thrown value to the Javascript error.
+    pub fn kind(&self) -> ErrorKind {
+        ErrorKind::try_from(self.0.clone()).unwrap_or_else(|_| ErrorKind::Throw(self.0.to_string()))
+    }
+
+    /// The `name` of the thrown error, or `None` if a non-error value was thrown.
+    pub fn name(&self) -> Option<String> {
+        self.error_property("name")
+    }
+
+    /// The `message` of the thrown error, or the string of a non-error value.
+    pub fn message(&self) -> Option<String> {
+        if self.0.is_error() {
+            self.error_property("message")
+        } else {
+            Some(self.0.to_string())
+        }
+    }
+
+    /// The `stack` of the thrown error.
+    pub fn stack(&self) -> Option<String> {
+        self.error_property("stack")
+    }
+
+    /// Returns the property of the thrown object.
+    pub fn property<T: GetProperty>(&self, prop: T) -> Option<Local<'a, Value>> {
+        if self.0.is_object() {
+            self.0.ctxt.get_property(&self.0, prop)
+        } else {
+            None
+        }
+    }
+
+    fn error_property(&self, prop: &str) -> Option<String> {
+        if self.0.is_error() {
+            self.property(prop).map(|v| v.to_string())
+        } else {
+            None
+        }
+    }
+}
+
+impl From<Exception<'_>> for Error {
+    fn from(exc: Exception) -> Self {
+        Error::Js(exc.kind())
+    }
+}
+
 impl<'a> Local<'a, Value> {
+    /// Check the result value, returns the pending exception as a live `Exception`.
+    pub fn check_exception(self) -> Result<Local<'a, Value>, Exception<'a>> {
+        if self.is_exception() {
+            let ctxt = self.ctxt;
+
+            Err(ctxt
+                .catch_exception()
+                .unwrap_or_else(|| Exception(ctxt.undefined())))
+        } else {
+            Ok(self)
+        }
+    }
+
     pub fn ok(self) -> Result<Local<'a, Value>, Error> {
         if self.is_exception() {
             let err = self.ctxt.take_exception()?;
@@ -189,6 +462,38 @@ impl ContextRef {
             .check_undefined()
     }
 
+    /// Catch the pending exception as a structured `JsException`.
+    ///
+    /// Returns `None` if there is no pending exception.
+    pub fn catch(&self) -> Option<JsException> {
+        self.reset_uncatchable_error();
+
+        let value = self.get_exception()?;
+
+        if value.is_null() {
+            return None;
+        }
+
+        let kind = ErrorKind::try_from(value.clone())
+            .unwrap_or_else(|_| ErrorKind::Throw(value.to_string()));
+        let stack = kind.frames();
+
+        trace!("catch {:?} with {} frames", kind, stack.len());
+
+        Some(JsException { value, kind, stack })
+    }
+
+    /// Take the pending exception as a live `Exception`.
+    ///
+    /// Returns `None` if there is no pending exception.
+    pub fn catch_exception(&self) -> Option<Exception> {
+        self.reset_uncatchable_error();
+
+        self.get_exception()
+            .filter(|value| !value.is_null())
+            .map(Exception)
+    }
+
     pub fn enable_is_error_property(&self, enable: bool) {
         unsafe { ffi::JS_EnableIsErrorProperty(self.as_ptr(), enable.to_bool()) }
     }
@@ -219,18 +524,51 @@ impl ContextRef {
         self.throw(err)
     }
 
+    /// Throw the Rust error as an `Error`, the `source` chain is mapped to the nested `cause` properties.
+    pub fn throw_error_chain(&self, err: &(dyn StdError + 'static)) -> Local<Value> {
+        match self.new_error_chain(err) {
+            Ok(err) => self.throw(err),
+            Err(err) => self.throw_error(err, None),
+        }
+    }
+
+    fn new_error_chain(&self, err: &(dyn StdError + 'static)) -> Result<Local<Value>, Error> {
+        let obj = self.new_error();
+
+        obj.define_property_value(
+            "message",
+            err.to_string(),
+            Prop::WRITABLE | Prop::CONFIGURABLE,
+        )?;
+
+        if let Some(source) = err.source() {
+            obj.define_property_value(
+                "cause",
+                self.new_error_chain(source)?,
+                Prop::WRITABLE | Prop::CONFIGURABLE,
+            )?;
+        }
+
+        Ok(obj)
+    }
+
     pub fn throw_out_of_memory(&self) -> Local<Value> {
         self.bind(unsafe { ffi::JS_ThrowOutOfMemory(self.as_ptr()) })
     }
 
+    /// Throw an error of the class `name` with the message, the class extends `Error` is defined if not found.
     pub fn throw_custom_error<T: ToString>(
         &self,
         name: &str,
         msg: T,
         stack: Option<String>,
     ) -> Local<Value> {
-        if let Some(ctor) = self.global_object().get_property(name) {
-            match ctor.call_constructor(msg.to_string()) {
+        let ctor = self
+            .get_property(&self.global_object(), name)
+            .map_or_else(|| self.define_error_class(name), Ok);
+
+        match ctor {
+            Ok(ctor) => match ctor.call_constructor(msg.to_string()) {
                 Ok(err) => {
                     if let Some(stack) = stack {
                         err.define_property_value(
@@ -244,12 +582,107 @@ impl ContextRef {
                     self.throw(err)
                 }
                 Err(err) => self.throw_error(err, stack),
-            }
-        } else {
-            self.throw_error(format!("class `{}` not found", name), stack)
+            },
+            Err(err) => self.throw_error(err, stack),
+        }
+    }
+
+    /// Throw an `AggregateError` which groups the errors, like `new AggregateError(errors, message)`.
+    ///
+    /// The class is defined if the engine doesn't provide it,
+    /// and the errors beyond `Limits::max_elements` are dropped.
+    pub fn throw_aggregate_error<I, T>(&self, errors: I, msg: T) -> Local<Value>
+    where
+        I: IntoIterator<Item = ErrorKind>,
+        T: ToString,
+    {
+        match self.new_aggregate_error(errors, msg) {
+            Ok(err) => self.throw(err),
+            Err(err) => self.throw_error(err, None),
         }
     }
 
+    fn new_aggregate_error<I, T>(&self, errors: I, msg: T) -> Result<Local<Value>, Error>
+    where
+        I: IntoIterator<Item = ErrorKind>,
+        T: ToString,
+    {
+        let errors = errors
+            .into_iter()
+            .take(self.conversion_limits().max_elements)
+            .map(|err| {
+                err.new_value(self);
+
+                self.get_exception().unwrap_or_else(|| self.undefined())
+            })
+            .collect::<Vec<_>>();
+        let errors = JsArray::from_iter(self, errors)?;
+        let ctor = match self.get_property(&self.global_object(), "AggregateError") {
+            Some(ctor) => ctor,
+            None => self.define_aggregate_error_class()?,
+        };
+
+        self.call_constructor(&ctor, (errors, msg.to_string()))
+    }
+
+    /// Define the `AggregateError` class with the `(errors, message)` constructor on the global object.
+    fn define_aggregate_error_class(&self) -> Result<Local<Value>, Error> {
+        trace!("define error class `AggregateError`");
+
+        let ctor = self.eval_script(
+            r#"(function () {
+    class AggregateError extends Error {
+        constructor(errors, message) {
+            super(message);
+
+            Object.defineProperty(this, 'errors', { value: Array.from(errors), writable: true, configurable: true });
+        }
+    }
+
+    Object.defineProperty(AggregateError.prototype, 'name', { value: 'AggregateError', writable: true, configurable: true });
+
+    return AggregateError;
+})()"#,
+            "<defineErrorClass>",
+            Eval::GLOBAL | Eval::STRICT,
+        )?;
+
+        self.global_object().define_property_value(
+            "AggregateError",
+            &ctor,
+            Prop::WRITABLE | Prop::CONFIGURABLE,
+        )?;
+
+        Ok(ctor)
+    }
+
+    /// Define a class extends `Error` with the name on the global object.
+    pub fn define_error_class(&self, name: &str) -> Result<Local<Value>, Error> {
+        trace!("define error class `{}`", name);
+
+        let factory = self.eval_script(
+            r#"(function (name) {
+    class CustomError extends Error {}
+
+    Object.defineProperty(CustomError, 'name', { value: name, configurable: true });
+    Object.defineProperty(CustomError.prototype, 'name', { value: name, writable: true, configurable: true });
+
+    return CustomError;
+})"#,
+            "<defineErrorClass>",
+            Eval::GLOBAL | Eval::STRICT,
+        )?;
+        let ctor = self.call(&factory, None, name)?;
+
+        self.global_object().define_property_value(
+            name,
+            &ctor,
+            Prop::WRITABLE | Prop::CONFIGURABLE,
+        )?;
+
+        Ok(ctor)
+    }
+
     pub fn throw_syntax_error<T: Into<Vec<u8>>>(&self, msg: T) -> Local<Value> {
         self.bind(unsafe {
             ffi::JS_ThrowSyntaxError(
@@ -335,20 +768,26 @@ impl ContextRef {
         self.check_error(ret).map(ToBool::to_bool)
     }
 
-    fn take_exception(&self) -> Result<ErrorKind, Error> {
+    pub(crate) fn take_exception(&self) -> Result<ErrorKind, Error> {
         self.reset_uncatchable_error();
 
         self.get_exception()
-            .ok_or_else(|| err_msg("expected exception"))
+            .ok_or_else(|| Error::from("expected exception"))
             .and_then(ErrorKind::try_from)
+            .map(|kind| match kind {
+                ErrorKind::InternalError(ref msg, _) if msg == "interrupted" => self
+                    .exceeded_op_budget()
+                    .map_or(kind, ErrorKind::BudgetExceeded),
+                kind => kind,
+            })
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{Context, Eval, Runtime};
+    use crate::{Context, Eval, Limits, Local, NewValue, Runtime, StackFormat, StackFrame, Value};
 
-    use super::ErrorKind::{self, *};
+    use super::{Error, ErrorKind, ErrorKind::*};
 
     #[test]
     fn std_error() {
@@ -360,7 +799,7 @@ mod tests {
         assert_eq!(
             ctxt.eval::<_, ()>("foobar", Eval::GLOBAL)
                 .unwrap_err()
-                .downcast::<ErrorKind>()
+                .into_kind()
                 .unwrap(),
             ReferenceError(
                 "foobar is not defined".into(),
@@ -372,7 +811,7 @@ mod tests {
             ctxt.throw_syntax_error("foobar is not defined")
                 .ok()
                 .unwrap_err()
-                .downcast::<ErrorKind>()
+                .into_kind()
                 .unwrap(),
             SyntaxError("foobar is not defined".into(), None)
         );
@@ -381,7 +820,7 @@ mod tests {
             ctxt.throw_out_of_memory()
                 .ok()
                 .unwrap_err()
-                .downcast::<ErrorKind>()
+                .into_kind()
                 .unwrap(),
             InternalError("out of memory".into(), None)
         );
@@ -394,7 +833,7 @@ mod tests {
             )
             .ok()
             .unwrap_err()
-            .downcast::<ErrorKind>()
+            .into_kind()
             .unwrap(),
             URIError(
                 "malformed URI sequence".into(),
@@ -413,7 +852,7 @@ mod tests {
         assert_eq!(
             ctxt.eval::<_, ()>("throw new Error('Whoops!');", Eval::GLOBAL)
                 .unwrap_err()
-                .downcast::<ErrorKind>()
+                .into_kind()
                 .unwrap(),
             Error(
                 "Whoops!".into(),
@@ -425,7 +864,7 @@ mod tests {
             ctxt.throw_error("Whoops!", Some("    at <eval> (<evalScript>)\n".into()))
                 .ok()
                 .unwrap_err()
-                .downcast::<ErrorKind>()
+                .into_kind()
                 .unwrap(),
             Error(
                 "Whoops!".into(),
@@ -458,7 +897,7 @@ class CustomError extends Error {
         assert_eq!(
             ctxt.eval::<_, ()>("throw new CustomError('Whoops!')", Eval::GLOBAL,)
                 .unwrap_err()
-                .downcast::<ErrorKind>()
+                .into_kind()
                 .unwrap(),
             Custom(
                 "CustomError".into(),
@@ -467,14 +906,176 @@ class CustomError extends Error {
             ),
         );
 
-        // assert_eq!(
-        //     ctxt.throw_custom_error("CustomError", "Whoops!", None)
-        //         .ok()
-        //         .unwrap_err()
-        //         .downcast::<ErrorKind>()
-        //         .unwrap(),
-        //     &Custom("CustomError".into(), "Whoops!".into(), None)
-        // );
+        assert_eq!(
+            ctxt.throw_custom_error(
+                "ValidationError",
+                "Whoops!",
+                Some("    at <eval> (<evalScript>)\n".into())
+            )
+            .ok()
+            .unwrap_err()
+            .into_kind()
+            .unwrap(),
+            Custom(
+                "ValidationError".into(),
+                "Whoops!".into(),
+                Some("    at <eval> (<evalScript>)\n".into())
+            )
+        );
+        assert_eq!(
+            ctxt.eval::<_, bool>(
+                "new ValidationError('again') instanceof Error",
+                Eval::GLOBAL
+            )
+            .unwrap(),
+            Some(true)
+        );
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("fail to load config")]
+    struct LoadError(#[source] std::io::Error);
+
+    #[test]
+    fn error_cause() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let err = Error::other(LoadError(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "config.json not found",
+        )));
+
+        Err::<Local<Value>, _>(err).new_value(&ctxt);
+
+        ctxt.global_object()
+            .set_property("err", ctxt.get_exception().unwrap())
+            .unwrap();
+
+        assert_eq!(
+            ctxt.eval::<_, String>(
+                "[err instanceof Error, err.message, err.cause.message, err.cause.cause].join()",
+                Eval::GLOBAL
+            )
+            .unwrap(),
+            Some("true,fail to load config,config.json not found,".to_owned())
+        );
+    }
+
+    #[test]
+    fn aggregate_error() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let err = ctxt
+            .throw_aggregate_error(
+                vec![TypeError("bad type".into(), None), Throw("Whoops!".into())],
+                "all failed",
+            )
+            .ok()
+            .unwrap_err()
+            .into_kind()
+            .unwrap();
+
+        match err {
+            Aggregate(ref msg, ref errors, _) => {
+                assert_eq!(msg, "all failed");
+                assert_eq!(errors.len(), 2);
+                assert_eq!(errors[0].message(), "bad type");
+                assert!(matches!(errors[0], TypeError(..)));
+                assert_eq!(errors[1], Throw("Whoops!".into()));
+            }
+            _ => panic!("unexpected error: {:?}", err),
+        }
+
+        match ctxt
+            .eval::<_, ()>(
+                "throw new AggregateError([new RangeError('out of range')], 'js')",
+                Eval::GLOBAL,
+            )
+            .unwrap_err()
+            .into_kind()
+            .unwrap()
+        {
+            Aggregate(msg, errors, stack) => {
+                assert_eq!(msg, "js");
+                assert!(stack.is_some());
+                assert!(matches!(errors.as_slice(), [RangeError(msg, _)] if msg == "out of range"));
+            }
+            err => panic!("unexpected error: {:?}", err),
+        }
+
+        ctxt.set_conversion_limits(Limits {
+            max_elements: 1,
+            ..Default::default()
+        });
+
+        match ctxt
+            .throw_aggregate_error(vec![Throw("a".into()), Throw("b".into())], "capped")
+            .ok()
+            .unwrap_err()
+            .into_kind()
+            .unwrap()
+        {
+            Aggregate(_, errors, _) => assert_eq!(errors, vec![Throw("a".into())]),
+            err => panic!("unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn catch() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        assert!(ctxt.catch().is_none());
+
+        ctxt.eval_script(
+            r#"
+function foo() {
+    throw new TypeError('Whoops!');
+}
+
+try { foo(); } catch (e) { lastError = e; }
+"#,
+            "test.js",
+            Eval::GLOBAL,
+        )
+        .unwrap();
+
+        ctxt.throw(ctxt.global_object().get_property("lastError").unwrap());
+
+        let exc = ctxt.catch().unwrap();
+
+        assert!(exc.value.is_error());
+        assert_eq!(exc.kind.message(), "Whoops!");
+        assert_eq!(
+            exc.stack,
+            vec![
+                StackFrame {
+                    function: Some("foo".into()),
+                    file: Some("test.js".into()),
+                    line: Some(3),
+                    column: None,
+                    host: None,
+                },
+                StackFrame {
+                    function: Some("<eval>".into()),
+                    file: Some("test.js".into()),
+                    line: Some(6),
+                    column: None,
+                    host: None,
+                },
+            ]
+        );
+        assert_eq!(exc.kind.frames(), exc.stack);
+
+        assert!(ctxt.catch().is_none());
     }
 
     #[test]
@@ -487,17 +1088,13 @@ class CustomError extends Error {
         assert_eq!(
             ctxt.eval::<_, ()>("throw 'Whoops!';", Eval::GLOBAL)
                 .unwrap_err()
-                .downcast::<ErrorKind>()
+                .into_kind()
                 .unwrap(),
             Throw("Whoops!".into())
         );
 
         assert_eq!(
-            ctxt.throw("Whoops!")
-                .ok()
-                .unwrap_err()
-                .downcast::<ErrorKind>()
-                .unwrap(),
+            ctxt.throw("Whoops!").ok().unwrap_err().into_kind().unwrap(),
             Throw("Whoops!".into())
         );
     }
@@ -512,18 +1109,114 @@ class CustomError extends Error {
         assert_eq!(
             ctxt.eval::<_, ()>("throw 123;", Eval::GLOBAL)
                 .unwrap_err()
-                .downcast::<ErrorKind>()
+                .into_kind()
                 .unwrap(),
             Throw("123".into())
         );
 
         assert_eq!(
-            ctxt.throw(123)
-                .ok()
-                .unwrap_err()
-                .downcast::<ErrorKind>()
-                .unwrap(),
+            ctxt.throw(123).ok().unwrap_err().into_kind().unwrap(),
             Throw("123".into())
         );
     }
+
+    #[test]
+    fn crate_error() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        match ctxt.eval_script("foo\0bar", "<evalScript>", Eval::GLOBAL) {
+            Err(Error::Nul(_)) => {}
+            res => panic!("unexpected result: {:?}", res),
+        }
+
+        let err: Box<dyn std::error::Error + Send + Sync> = ctxt
+            .eval::<_, ()>("foobar", Eval::GLOBAL)
+            .unwrap_err()
+            .into();
+
+        assert_eq!(err.to_string(), "ReferenceError: foobar is not defined");
+    }
+
+    #[test]
+    fn stack_trace_limit() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+        let script =
+            "function f(n) {\n  if (n == 0) throw new Error('deep');\n  f(n - 1);\n}\nf(10);";
+
+        let err = ctxt
+            .eval_script(script, "deep.js", Eval::GLOBAL)
+            .unwrap_err()
+            .into_kind()
+            .unwrap();
+
+        assert_eq!(err.stack().unwrap().lines().count(), 12);
+        assert_eq!(
+            err.format_stack(StackFormat::Short).unwrap(),
+            "at f (deep.js:2)\nat f (deep.js:3) (repeated 10 times)\nat <eval> (deep.js:5)"
+        );
+        assert_eq!(
+            err.format_stack(StackFormat::Full).unwrap(),
+            err.stack().unwrap()
+        );
+
+        rt.set_stack_trace_limit(Some(2));
+
+        let err = ctxt
+            .eval_script(script, "deep.js", Eval::GLOBAL)
+            .unwrap_err()
+            .into_kind()
+            .unwrap();
+
+        assert_eq!(
+            err.stack().unwrap(),
+            "    at f (deep.js:2)\n    at f (deep.js:3)\n"
+        );
+        assert_eq!(
+            err.format_stack(StackFormat::Json).unwrap(),
+            r#"[{"function":"f","file":"deep.js","line":2},{"function":"f","file":"deep.js","line":3}]"#
+        );
+    }
+
+    #[test]
+    fn exception() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        assert!(ctxt.catch_exception().is_none());
+
+        let err = ctxt
+            .eval_script(
+                "Object.assign(new TypeError('boom'), { code: 'E_BOOM' })",
+                "<evalScript>",
+                Eval::GLOBAL,
+            )
+            .unwrap();
+        let exc = ctxt.throw(err).check_exception().unwrap_err();
+
+        assert_eq!(exc.name(), Some("TypeError".to_owned()));
+        assert_eq!(exc.message(), Some("boom".to_owned()));
+        assert_eq!(
+            exc.property("code").unwrap().to_string(),
+            "E_BOOM".to_owned()
+        );
+        assert!(exc.property("missing").is_none());
+        assert_eq!(exc.kind(), TypeError("boom".into(), exc.stack()));
+        assert!(matches!(Error::from(exc), Error::Js(TypeError(..))));
+
+        let exc = ctxt.throw(123).check_exception().unwrap_err();
+
+        assert_eq!(exc.name(), None);
+        assert_eq!(exc.message(), Some("123".to_owned()));
+        assert!(exc.property("code").is_none());
+        assert_eq!(exc.kind(), ErrorKind::Throw("123".into()));
+        assert!(ctxt.catch_exception().is_none());
+    }
 }
@@ -3,10 +3,12 @@ use std::fs::File;
 use std::io::prelude::*;
 use std::path::Path;
 
-use failure::{Error, ResultExt};
 use foreign_types::ForeignTypeRef;
 
-use crate::{ffi, Context, ContextRef, ExtractValue, Local, ReadObj, Runtime, Value};
+use crate::{
+    detect_module, ffi, Context, ContextRef, Error, ExtractValue, Local, ReadObj, Runtime,
+    StackTrace, Value,
+};
 
 bitflags! {
     /// Flags for `eval` method.
@@ -29,6 +31,285 @@ bitflags! {
         /// The result is an object with a `JS_TAG_FUNCTION_BYTECODE` or `JS_TAG_MODULE` tag.
         /// It can be executed with `JS_EvalFunction()`.
         const COMPILE_ONLY = ffi::JS_EVAL_FLAG_COMPILE_ONLY;
+        /// don't include the stack frames before this eval in the error backtraces.
+        ///
+        /// QuickJS has no `JS_EVAL_FLAG_BACKTRACE_BARRIER` yet, the flag is stripped before the evaluation,
+        /// and the backtrace of the returned error is cut after the frame of the script.
+        ///
+        /// Only the stack of the `ErrorKind` returned to Rust is cut, the `stack` property seen by the scripts,
+        /// e.g. an error caught by the script itself, still contains the frames of the callers.
+        const BACKTRACE_BARRIER = 1 << 6;
+        /// skip the UTF-8 BOM and the `#!` interpreter line at the beginning of the source.
+        ///
+        /// The flag is handled before the evaluation, the line numbers of the script are not changed.
+        const SHEBANG = 1 << 7;
+    }
+}
+
+/// The options of `ContextRef::eval_with`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EvalOptions {
+    /// The filename reported in the stack traces.
+    pub filename: String,
+    /// The number of the lines before the script in the original file,
+    /// e.g. the script was concatenated or templated.
+    pub line_offset: u32,
+    /// Force the strict mode.
+    pub strict: bool,
+    /// Strip the debug information, like the source code of the functions.
+    pub strip: bool,
+    /// Cut the stack trace of the returned error after the frame of the script,
+    /// the frames of the callers which evaluate the script are hidden.
+    ///
+    /// See `Eval::BACKTRACE_BARRIER`, the `stack` property of the error is not changed.
+    pub backtrace_barrier: bool,
+    /// Evaluate the source as a module.
+    pub module: bool,
+    /// Skip the UTF-8 BOM and the `#!` interpreter line, see `Eval::SHEBANG`.
+    pub shebang: bool,
+}
+
+impl Default for EvalOptions {
+    fn default() -> Self {
+        EvalOptions {
+            filename: "<evalScript>".to_owned(),
+            line_offset: 0,
+            strict: false,
+            strip: false,
+            backtrace_barrier: false,
+            module: false,
+            shebang: false,
+        }
+    }
+}
+
+impl EvalOptions {
+    /// The options with the filename.
+    pub fn new<S: Into<String>>(filename: S) -> Self {
+        EvalOptions {
+            filename: filename.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the eval flags of the options.
+    pub fn flags(&self) -> Eval {
+        let mut flags = if self.module {
+            Eval::MODULE
+        } else {
+            Eval::GLOBAL
+        };
+
+        flags.set(Eval::STRICT, self.strict);
+        flags.set(Eval::STRIP, self.strip);
+        flags.set(Eval::BACKTRACE_BARRIER, self.backtrace_barrier);
+        flags.set(Eval::SHEBANG, self.shebang);
+        flags
+    }
+}
+
+/// The kind of the source, returned by `Source::classify`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SourceKind {
+    /// The global script.
+    Script,
+    /// The ES6 module.
+    Module,
+    /// The JSON document.
+    Json,
+    /// The precompiled bytecode.
+    Bytecode,
+}
+
+impl SourceKind {
+    /// Guess the kind of the source from the extension of the filename.
+    ///
+    /// The `.js` files may be scripts or modules, so the content should be checked instead.
+    pub fn from_extension<P: AsRef<Path>>(filename: P) -> Option<SourceKind> {
+        match filename.as_ref().extension()?.to_str()? {
+            "mjs" => Some(SourceKind::Module),
+            "cjs" => Some(SourceKind::Script),
+            "json" => Some(SourceKind::Json),
+            "jsc" => Some(SourceKind::Bytecode),
+            _ => None,
+        }
+    }
+
+    /// Guess the kind of the source code with the heuristics of the engine.
+    ///
+    /// The engine only looks for the leading `import` statement,
+    /// so the lines starting with the `export` keyword are checked as well.
+    /// Only the valid JSON documents of the non-empty objects are recognized,
+    /// because `{}` and the other JSON values are also valid scripts.
+    pub fn from_source(source: &str) -> SourceKind {
+        let s = source.trim();
+
+        if s.starts_with('{') && !s[1..].trim_start().starts_with('}') && is_json(s) {
+            SourceKind::Json
+        } else if detect_module(s)
+            || s.lines()
+                .any(|line| line.trim_start().starts_with("export "))
+        {
+            SourceKind::Module
+        } else {
+            SourceKind::Script
+        }
+    }
+}
+
+/// Check whether the whole input is a valid JSON document.
+fn is_json(s: &str) -> bool {
+    let mut json = Json(s.as_bytes());
+
+    json.value() && {
+        json.skip_ws();
+        json.0.is_empty()
+    }
+}
+
+/// A minimal JSON validator, which only checks the syntax without building the values.
+struct Json<'a>(&'a [u8]);
+
+impl Json<'_> {
+    fn skip_ws(&mut self) {
+        let n = self
+            .0
+            .iter()
+            .take_while(|b| matches!(b, b' ' | b'\t' | b'\n' | b'\r'))
+            .count();
+
+        self.0 = &self.0[n..];
+    }
+
+    fn eat(&mut self, b: u8) -> bool {
+        self.skip_ws();
+
+        match self.0.split_first() {
+            Some((&c, rest)) if c == b => {
+                self.0 = rest;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn literal(&mut self, lit: &[u8]) -> bool {
+        if self.0.starts_with(lit) {
+            self.0 = &self.0[lit.len()..];
+            true
+        } else {
+            false
+        }
+    }
+
+    fn digits(&mut self) -> bool {
+        let n = self.0.iter().take_while(|b| b.is_ascii_digit()).count();
+
+        self.0 = &self.0[n..];
+        n > 0
+    }
+
+    fn value(&mut self) -> bool {
+        self.skip_ws();
+
+        match self.0.first() {
+            Some(b'{') => {
+                self.members(b'}', |json| json.string() && json.eat(b':') && json.value())
+            }
+            Some(b'[') => self.members(b']', Json::value),
+            Some(b'"') => self.string(),
+            Some(b't') => self.literal(b"true"),
+            Some(b'f') => self.literal(b"false"),
+            Some(b'n') => self.literal(b"null"),
+            Some(_) => self.number(),
+            None => false,
+        }
+    }
+
+    fn members<F: FnMut(&mut Self) -> bool>(&mut self, close: u8, mut member: F) -> bool {
+        self.0 = &self.0[1..];
+
+        if self.eat(close) {
+            return true;
+        }
+
+        loop {
+            if !member(self) {
+                return false;
+            }
+            if self.eat(close) {
+                return true;
+            }
+            if !self.eat(b',') {
+                return false;
+            }
+        }
+    }
+
+    fn string(&mut self) -> bool {
+        if !self.eat(b'"') {
+            return false;
+        }
+
+        while let Some((&b, rest)) = self.0.split_first() {
+            self.0 = rest;
+
+            match b {
+                b'"' => return true,
+                b'\\' => match self.0.split_first() {
+                    Some((b'u', rest))
+                        if rest.len() >= 4 && rest[..4].iter().all(u8::is_ascii_hexdigit) =>
+                    {
+                        self.0 = &rest[4..]
+                    }
+                    Some((b'"', rest)) | Some((b'\\', rest)) | Some((b'/', rest))
+                    | Some((b'b', rest)) | Some((b'f', rest)) | Some((b'n', rest))
+                    | Some((b'r', rest)) | Some((b't', rest)) => self.0 = rest,
+                    _ => return false,
+                },
+                0..=0x1F => return false,
+                _ => {}
+            }
+        }
+
+        false
+    }
+
+    fn number(&mut self) -> bool {
+        self.literal(b"-");
+
+        if !self.literal(b"0") && !self.digits() {
+            return false;
+        }
+        if self.literal(b".") && !self.digits() {
+            return false;
+        }
+        if (self.literal(b"e") || self.literal(b"E")) && {
+            let _ = self.literal(b"+") || self.literal(b"-");
+            !self.digits()
+        } {
+            return false;
+        }
+
+        true
+    }
+}
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Remove the UTF-8 BOM and the `#!` line at the beginning of the source, the line break is kept.
+fn strip_shebang(input: &mut Vec<u8>) {
+    if input.starts_with(UTF8_BOM) {
+        input.drain(..UTF8_BOM.len());
+    }
+
+    if input.starts_with(b"#!") {
+        let end = input
+            .iter()
+            .position(|&b| b == b'\n' || b == b'\r')
+            .unwrap_or(input.len());
+
+        input.drain(..end);
     }
 }
 
@@ -39,6 +320,11 @@ pub trait Source: Sized {
     /// Default eval flags.
     fn default_flags() -> Self::Flags;
 
+    /// Returns the kind of the source, so the caller could dispatch it to the right evaluation.
+    fn classify(&self) -> SourceKind {
+        SourceKind::Script
+    }
+
     /// Evaluate a script or module source.
     fn eval(self, ctxt: &'_ ContextRef, flags: Self::Flags) -> Result<Local<'_, Value>, Error>;
 }
@@ -50,6 +336,10 @@ impl Source for &str {
         Eval::GLOBAL
     }
 
+    fn classify(&self) -> SourceKind {
+        SourceKind::from_source(self)
+    }
+
     fn eval(self, ctxt: &'_ ContextRef, flags: Self::Flags) -> Result<Local<'_, Value>, Error> {
         ctxt.eval_script(self, "<evalScript>", flags)
     }
@@ -62,6 +352,13 @@ impl Source for &Path {
         Eval::GLOBAL
     }
 
+    /// The extension of the file is checked first, then the content of the file.
+    fn classify(&self) -> SourceKind {
+        SourceKind::from_extension(self).unwrap_or_else(|| {
+            load_file(self).map_or(SourceKind::Script, |s| SourceKind::from_source(&s))
+        })
+    }
+
     fn eval(self, ctxt: &'_ ContextRef, flags: Self::Flags) -> Result<Local<'_, Value>, Error> {
         ctxt.eval_file(self, flags)
     }
@@ -74,6 +371,10 @@ impl Source for &[u8] {
         ()
     }
 
+    fn classify(&self) -> SourceKind {
+        SourceKind::Bytecode
+    }
+
     fn eval(self, ctxt: &'_ ContextRef, _flags: Self::Flags) -> Result<Local<'_, Value>, Error> {
         ctxt.eval_binary(self, false)
     }
@@ -103,7 +404,7 @@ impl Source for &[u8] {
 /// assert_eq!(
 ///     qjs::eval::<_, ()>("throw new Error('Whoops!')")
 ///         .unwrap_err()
-///         .downcast::<qjs::ErrorKind>()
+///         .into_kind()
 ///         .unwrap(),
 ///     qjs::ErrorKind::Error(
 ///         "Whoops!".into(),
@@ -149,19 +450,32 @@ pub fn load_file<P: AsRef<Path>>(path: P) -> Result<String, Error> {
 }
 
 impl ContextRef {
+    /// Returns `true` if the source should be evaluated as a module.
+    ///
+    /// The extension of the filename, e.g. `.mjs` or `.cjs`, takes precedence over the heuristics of the engine.
+    pub fn is_module_source(&self, source: &str, filename: &str) -> bool {
+        SourceKind::from_extension(filename).unwrap_or_else(|| SourceKind::from_source(source))
+            == SourceKind::Module
+    }
+
     /// Evaluate a script or module source.
     pub fn eval<T: Source, V: ExtractValue>(
         &self,
         source: T,
         flags: T::Flags,
     ) -> Result<Option<V>, Error> {
-        source.eval(self, flags).map(|v| {
-            if v.is_undefined() {
-                None
-            } else {
-                V::extract_value(&v)
-            }
-        })
+        let pipeline = self.result_pipeline();
+
+        source
+            .eval(self, flags)
+            .and_then(|v| pipeline.process(self, v))
+            .map(|v| {
+                if v.is_undefined() {
+                    None
+                } else {
+                    V::extract_value(&v)
+                }
+            })
     }
 
     /// Evaluate a script or module source.
@@ -171,7 +485,15 @@ impl ContextRef {
         filename: &str,
         flags: Eval,
     ) -> Result<Local<Value>, Error> {
-        let input = CString::new(input).context("input")?;
+        self.check_shutting_down()?;
+
+        let mut input = input.into();
+
+        if flags.contains(Eval::SHEBANG) {
+            strip_shebang(&mut input);
+        }
+
+        let input = CString::new(input)?;
 
         trace!(
             "eval `{}` {:?}: {}",
@@ -181,25 +503,92 @@ impl ContextRef {
         );
 
         let input = input.to_bytes_with_nul();
-        let filename = CString::new(filename).context("filename")?;
+        let cfilename = CString::new(filename)?;
+        let _entered = self.enter();
+
+        let res = self
+            .record_eval(|| {
+                self.bind(unsafe {
+                    ffi::JS_Eval(
+                        self.as_ptr(),
+                        input.as_ptr() as *const _,
+                        input.len() - 1,
+                        cfilename.as_ptr() as *const _,
+                        (flags - Eval::BACKTRACE_BARRIER - Eval::SHEBANG).bits as i32,
+                    )
+                })
+            })
+            .ok();
+
+        match res {
+            Err(Error::Js(mut kind)) if flags.contains(Eval::BACKTRACE_BARRIER) => {
+                if let Some(stack) = kind.stack_mut() {
+                    *stack = cut_backtrace(stack, filename);
+                }
+
+                Err(Error::Js(kind))
+            }
+            res => res,
+        }
+    }
 
-        self.bind(unsafe {
-            ffi::JS_Eval(
-                self.as_ptr(),
-                input.as_ptr() as *const _,
-                input.len() - 1,
-                filename.as_ptr() as *const _,
-                flags.bits as i32,
-            )
-        })
-        .ok()
+    /// Evaluate a script with the options.
+    pub fn eval_with<T: Into<Vec<u8>>>(
+        &self,
+        input: T,
+        opts: &EvalOptions,
+    ) -> Result<Local<Value>, Error> {
+        let mut input = input.into();
+
+        // the interpreter line must be stripped before the script is shifted
+        if opts.shebang {
+            strip_shebang(&mut input);
+        }
+
+        if opts.line_offset > 0 {
+            // QuickJS always starts at the first line, so the script is shifted with the empty lines
+            let mut shifted = vec![b'\n'; opts.line_offset as usize];
+
+            shifted.extend(input);
+
+            self.eval_script(shifted, &opts.filename, opts.flags())
+        } else {
+            self.eval_script(input, &opts.filename, opts.flags())
+        }
+    }
+
+    /// Reject the evaluation if the runtime is shutting down.
+    fn check_shutting_down(&self) -> Result<(), Error> {
+        if self.runtime().is_shutting_down() {
+            Err("runtime is shutting down".into())
+        } else {
+            Ok(())
+        }
     }
 
     /// Evaluate a script or module source in file.
+    ///
+    /// The UTF-8 BOM and the `#!` interpreter line of the executable script are skipped.
     pub fn eval_file<P: AsRef<Path>>(&self, path: P, flags: Eval) -> Result<Local<Value>, Error> {
         let filename = path.as_ref().to_string_lossy().to_string();
 
-        load_file(path).and_then(|s| self.eval_script(s, &filename, flags))
+        load_file(path).and_then(|s| self.eval_script(s, &filename, flags | Eval::SHEBANG))
+    }
+
+    /// Evaluate a script or module source in file with the options.
+    ///
+    /// The filename of the options is replaced with the path of the file.
+    pub fn eval_file_with<P: AsRef<Path>>(
+        &self,
+        path: P,
+        opts: &EvalOptions,
+    ) -> Result<Local<Value>, Error> {
+        let opts = EvalOptions {
+            filename: path.as_ref().to_string_lossy().to_string(),
+            ..opts.clone()
+        };
+
+        load_file(path).and_then(|s| self.eval_with(s, &opts))
     }
 
     /// Evaluate a script or module source in bytecode.
@@ -210,6 +599,8 @@ impl ContextRef {
             if load_only { " (load only)" } else { "" }
         );
 
+        self.check_shutting_down()?;
+
         let obj = self.read_object(buf, ReadObj::BYTECODE)?;
 
         if load_only {
@@ -228,6 +619,26 @@ impl ContextRef {
         }
     }
 
+    /// Evaluate the modules in bytecode compiled by `qjs_bundle`.
+    ///
+    /// The first one is the entry module, the others are its dependencies,
+    /// which will be loaded before evaluating the entry module.
+    pub fn eval_bundle(&self, modules: &[(&str, &[u8])]) -> Result<Local<Value>, Error> {
+        let ((name, entry), deps) = modules
+            .split_first()
+            .ok_or_else(|| Error::from("empty bundle"))?;
+
+        for (name, buf) in deps {
+            trace!("load bundled module `{}`", name);
+
+            self.eval_binary(buf, true)?;
+        }
+
+        trace!("eval bundled module `{}`", name);
+
+        self.eval_binary(entry, false)
+    }
+
     /// Parse JSON expression.
     pub fn parse_json<T: Into<Vec<u8>>>(
         &self,
@@ -250,6 +661,19 @@ impl ContextRef {
     }
 }
 
+/// Cut the backtrace after the frame of the global code in `filename`.
+fn cut_backtrace(stack: &str, filename: &str) -> String {
+    let mut trace = stack.parse::<StackTrace>().unwrap_or_default();
+
+    if let Some(pos) = trace.frames.iter().position(|frame| {
+        frame.function.as_deref() == Some("<eval>") && frame.file.as_deref() == Some(filename)
+    }) {
+        trace.frames.truncate(pos + 1);
+    }
+
+    trace.to_string()
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{ffi::JS_TAG_INT, Context, ErrorKind, Runtime};
@@ -276,7 +700,7 @@ mod tests {
         assert_eq!(
             ctxt.eval::<_, ()>("foobar", Eval::GLOBAL)
                 .unwrap_err()
-                .downcast::<ErrorKind>()
+                .into_kind()
                 .unwrap(),
             ErrorKind::ReferenceError(
                 "foobar is not defined".into(),
@@ -312,7 +736,7 @@ mod tests {
         assert_eq!(
             eval::<_, i32>("throw new Error('Whoops!')")
                 .unwrap_err()
-                .downcast::<ErrorKind>()
+                .into_kind()
                 .unwrap(),
             ErrorKind::Error(
                 "Whoops!".into(),
@@ -321,6 +745,89 @@ mod tests {
         );
     }
 
+    #[test]
+    fn classify() {
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        assert_eq!("1 + 2".classify(), SourceKind::Script);
+        assert_eq!(
+            "// comment\nimport * as std from 'std'".classify(),
+            SourceKind::Module
+        );
+        assert_eq!(r#" { "a": 1 } "#.classify(), SourceKind::Json);
+        assert_eq!(
+            r#"{"a": [1, -2.5e3, true, null, "\u00e9\n"], "b": {}}"#.classify(),
+            SourceKind::Json
+        );
+        assert_eq!("{ a: 1 }".classify(), SourceKind::Script);
+        assert_eq!("{}".classify(), SourceKind::Script);
+        assert_eq!(r#"{ "a": 1, }"#.classify(), SourceKind::Script);
+        assert_eq!(r#"{ "a": 1 } + { "b": 2 }"#.classify(), SourceKind::Script);
+        assert_eq!(r#"{"a"} {"b"}"#.classify(), SourceKind::Script);
+        assert_eq!("[1, 2]".classify(), SourceKind::Script);
+        assert_eq!(Path::new("config.json").classify(), SourceKind::Json);
+        assert_eq!(Path::new("main.mjs").classify(), SourceKind::Module);
+        assert_eq!(b"\x01".as_ref().classify(), SourceKind::Bytecode);
+
+        assert!(ctxt.is_module_source("export default 1", "<input>"));
+        assert!(ctxt.is_module_source("1", "lib.mjs"));
+        assert!(!ctxt.is_module_source("import foo from 'foo'", "lib.cjs"));
+        assert!(ctxt.is_module_source("import foo from 'foo'", "main.js"));
+        assert!(!ctxt.is_module_source("1", "main.js"));
+    }
+
+    #[test]
+    fn eval_file_with_shebang() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+        let dir = std::env::temp_dir().join(format!("qjs-shebang-{}", std::process::id()));
+
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let script = dir.join("hello.js");
+
+        std::fs::write(
+            &script,
+            "\u{FEFF}#!/usr/bin/env qjs\n'hello' + ' ' + 'world'",
+        )
+        .unwrap();
+
+        assert_eq!(
+            ctxt.eval_file(&script, Eval::GLOBAL).unwrap().to_string(),
+            "hello world"
+        );
+        assert!(matches!(
+            ctxt.eval_file_with(&script, &EvalOptions::default()),
+            Err(Error::Js(ErrorKind::SyntaxError(..)))
+        ));
+
+        let module = dir.join("main.mjs");
+
+        std::fs::write(&module, "#!/usr/bin/env qjs\nthrow new Error('line 2')").unwrap();
+
+        let err = ctxt
+            .eval_file_with(
+                &module,
+                &EvalOptions {
+                    module: true,
+                    shebang: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap_err();
+
+        assert!(err
+            .kind()
+            .and_then(|err| err.stack())
+            .unwrap()
+            .contains("main.mjs:2"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn parse_json() {
         let _ = pretty_env_logger::try_init();
@@ -339,4 +846,161 @@ mod tests {
         assert_eq!(obj.get_property("age").unwrap().to_int32().unwrap(), 30);
         assert_eq!(obj.get_property("city").unwrap().to_string(), "New York");
     }
+
+    #[test]
+    fn eval_bundle() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+        let compile = |source, name| {
+            ctxt.eval_script(source, name, Eval::MODULE | Eval::COMPILE_ONLY)
+                .and_then(|module| module.write_bytecode())
+                .unwrap()
+        };
+        let greet = compile(
+            "export const greet = name => `hello ${name}`",
+            "lib/greet.mjs",
+        );
+        let main = compile(
+            "import { greet } from './lib/greet.mjs'; globalThis.message = greet('world')",
+            "main.mjs",
+        );
+
+        let ctxt = Context::new(&rt);
+
+        ctxt.eval_bundle(&[("main.mjs", &main), ("lib/greet.mjs", &greet)])
+            .unwrap();
+
+        assert_eq!(
+            ctxt.eval::<_, String>("message", Eval::GLOBAL).unwrap(),
+            Some("hello world".to_owned())
+        );
+        assert!(ctxt.eval_bundle(&[]).is_err());
+    }
+
+    #[test]
+    fn eval_with() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let opts = EvalOptions {
+            line_offset: 10,
+            ..EvalOptions::new("page.html")
+        };
+
+        assert_eq!(
+            ctxt.eval_with(
+                "function foo() {\n  bar()\n}\nfunction bar() { throw new Error('boom') }\nfoo()",
+                &opts
+            )
+            .unwrap_err()
+            .into_kind()
+            .unwrap()
+            .stack(),
+            Some(
+                "    at bar (page.html)\n    at foo (page.html:12)\n    at <eval> (page.html:15)\n"
+            )
+        );
+
+        assert_eq!(ctxt.eval_with("x = 1", &opts).unwrap().as_int(), Some(1));
+
+        let opts = EvalOptions {
+            strict: true,
+            ..opts
+        };
+
+        assert!(ctxt.eval_with("y = 1", &opts).is_err());
+
+        let opts = EvalOptions {
+            shebang: true,
+            line_offset: 10,
+            ..EvalOptions::new("bin.js")
+        };
+
+        assert_eq!(
+            ctxt.eval_with("\u{FEFF}#!/usr/bin/env qjs\nthrow new Error('boom')", &opts)
+                .unwrap_err()
+                .into_kind()
+                .unwrap()
+                .stack(),
+            Some("    at <eval> (bin.js:12)\n")
+        );
+
+        assert_eq!(
+            cut_backtrace(
+                "    at foo (page.html:12)\n    at <eval> (page.html:15)\n    at run (native)\n    at <eval> (main.js:1)\n",
+                "page.html"
+            ),
+            "    at foo (page.html:12)\n    at <eval> (page.html:15)\n"
+        );
+    }
+
+    thread_local! {
+        static PLUGIN_STACK: std::cell::RefCell<Option<String>> = const { std::cell::RefCell::new(None) };
+    }
+
+    fn run(ctxt: &ContextRef, _this: Option<&Value>, args: &[Value]) -> bool {
+        let script = ctxt.to_cstring(&args[0]).unwrap();
+        let res = ctxt.eval_script(
+            script.to_bytes(),
+            "plugin.js",
+            Eval::GLOBAL | Eval::BACKTRACE_BARRIER,
+        );
+
+        match res {
+            Err(Error::Js(kind)) => {
+                PLUGIN_STACK.with(|s| *s.borrow_mut() = kind.stack().map(|s| s.to_owned()));
+
+                false
+            }
+            _ => true,
+        }
+    }
+
+    #[test]
+    fn backtrace_barrier() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        ctxt.global_object()
+            .set_property("run", ctxt.new_c_function(run, Some("run"), 1).unwrap())
+            .unwrap();
+
+        assert_eq!(
+            ctxt.eval::<_, bool>(
+                "function host() {\n  return run(\"[1].map(x => { throw new Error('boom') })\")\n}\nhost()",
+                Eval::GLOBAL,
+            )
+            .unwrap(),
+            Some(false)
+        );
+        assert_eq!(
+            PLUGIN_STACK.with(|s| s.borrow_mut().take()).as_deref(),
+            Some(
+                "    at <anonymous> (plugin.js)\n    at map (native)\n    at <eval> (plugin.js)\n"
+            )
+        );
+
+        rt.set_hide_native_frames(true);
+
+        let err = ctxt
+            .eval_script(
+                "[1].map(x => { throw new Error('boom') })",
+                "main.js",
+                Eval::GLOBAL | Eval::BACKTRACE_BARRIER,
+            )
+            .unwrap_err()
+            .into_kind()
+            .unwrap();
+
+        assert_eq!(
+            err.stack(),
+            Some("    at <anonymous> (main.js)\n    at <eval> (main.js)\n")
+        );
+    }
 }
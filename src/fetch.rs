@@ -0,0 +1,652 @@
+use std::collections::VecDeque;
+use std::io::Read;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+
+use crate::{ffi, ContextRef, Error, Eval, Local, PromiseCapability, Value};
+
+const GLUE: &str = r#"
+(function (request, decode) {
+    class Headers {
+        constructor(init = {}) {
+            this._map = new Map();
+
+            const entries = typeof init[Symbol.iterator] === 'function' ? init : Object.entries(init);
+
+            for (const [name, value] of entries) this.append(name, value);
+        }
+
+        append(name, value) {
+            name = String(name).toLowerCase();
+            value = String(value);
+
+            const prev = this._map.get(name);
+
+            this._map.set(name, prev === undefined ? value : `${prev}, ${value}`);
+        }
+
+        set(name, value) { this._map.set(String(name).toLowerCase(), String(value)); }
+
+        get(name) {
+            const value = this._map.get(String(name).toLowerCase());
+
+            return value === undefined ? null : value;
+        }
+
+        has(name) { return this._map.has(String(name).toLowerCase()); }
+
+        delete(name) { this._map.delete(String(name).toLowerCase()); }
+
+        forEach(callback, thisArg) {
+            for (const [name, value] of this) callback.call(thisArg, value, name, this);
+        }
+
+        keys() { return this._map.keys(); }
+
+        values() { return this._map.values(); }
+
+        entries() { return this._map.entries(); }
+
+        [Symbol.iterator]() { return this.entries(); }
+    }
+
+    class Response {
+        constructor(status, statusText, url, headers, body) {
+            this.status = status;
+            this.statusText = statusText;
+            this.url = url;
+            this.headers = new Headers(headers);
+            this.bodyUsed = false;
+            this._body = body;
+        }
+
+        get ok() { return this.status >= 200 && this.status < 300; }
+
+        _consume() {
+            if (this.bodyUsed) {
+                return Promise.reject(new TypeError('body stream already read'));
+            }
+
+            this.bodyUsed = true;
+
+            return Promise.resolve(this._body);
+        }
+
+        arrayBuffer() { return this._consume().then(body => body.buffer); }
+
+        text() { return this._consume().then(decode); }
+
+        json() { return this.text().then(JSON.parse); }
+    }
+
+    function fetch(input, init = {}) {
+        return new Promise(resolve => {
+            const method = String(init.method || 'GET').toUpperCase();
+            const headers = [...new Headers(init.headers || {})];
+
+            resolve(request(method, String(input), headers, init.body));
+        }).then(([status, statusText, url, headers, body]) =>
+            new Response(status, statusText, url, headers, body));
+    }
+
+    globalThis.Headers = Headers;
+    globalThis.Response = Response;
+    globalThis.fetch = fetch;
+})
+"#;
+
+/// The hook to allow a request by its method and URL.
+type AllowHook = Box<dyn Fn(&str, &str) -> bool + Send + Sync>;
+
+/// The maximum number of the redirects followed by a request.
+const MAX_REDIRECTS: usize = 5;
+
+/// The default maximum number of the concurrent requests of a context.
+const MAX_REQUESTS: usize = 8;
+
+/// The headers which are not sent to the other hosts when following the redirects.
+const SENSITIVE_HEADERS: &[&str] = &["authorization", "cookie", "proxy-authorization"];
+
+/// The HTTP client of the `fetch` global, which only sends the requests allowed by the host.
+///
+/// The redirects are followed by the client instead of the agent,
+/// so each hop must be allowed as well.
+pub struct Fetch {
+    agent: ureq::Agent,
+    allow: Vec<AllowHook>,
+    max_requests: usize,
+}
+
+impl Default for Fetch {
+    fn default() -> Self {
+        Fetch::new()
+    }
+}
+
+impl Fetch {
+    /// Create a client which denies all requests until some are allowed.
+    pub fn new() -> Self {
+        Fetch::with_agent(ureq::AgentBuilder::new())
+    }
+
+    /// Create a client with the configured `ureq` agent, like timeouts or proxy.
+    ///
+    /// The automatic redirects of the agent are disabled.
+    pub fn with_agent(builder: ureq::AgentBuilder) -> Self {
+        Fetch {
+            agent: builder.redirects(0).build(),
+            allow: vec![],
+            max_requests: MAX_REQUESTS,
+        }
+    }
+
+    /// Allow the requests which the hook returns `true` for the method and URL.
+    pub fn allow<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&str, &str) -> bool + Send + Sync + 'static,
+    {
+        self.allow.push(Box::new(f));
+        self
+    }
+
+    /// Set the maximum number of the concurrent requests of a context, the others are queued.
+    pub fn max_requests(mut self, n: usize) -> Self {
+        self.max_requests = n.max(1);
+        self
+    }
+
+    fn is_allowed(&self, method: &str, url: &str) -> bool {
+        self.allow.iter().any(|f| f(method, url))
+    }
+}
+
+/// The `fetch` client and in-flight requests of a context.
+#[derive(Default)]
+pub(crate) struct Fetches {
+    client: Option<Arc<Fetch>>,
+    pending: Vec<Pending>,
+    queued: VecDeque<(Request, Value, Value)>,
+}
+
+impl Fetches {
+    /// Take the resolving functions of the in-flight and queued requests, which should be freed with the context.
+    pub fn drain(&mut self) -> impl Iterator<Item = Value> + '_ {
+        self.pending
+            .drain(..)
+            .map(|pending| (pending.resolve, pending.reject))
+            .chain(
+                self.queued
+                    .drain(..)
+                    .map(|(_, resolve, reject)| (resolve, reject)),
+            )
+            .flat_map(|(resolve, reject)| vec![resolve, reject])
+    }
+
+    /// Start the queued requests until the number of the in-flight requests reaches the limit.
+    fn start_queued(&mut self) {
+        let max_requests = self
+            .client
+            .as_ref()
+            .map_or(MAX_REQUESTS, |client| client.max_requests);
+
+        while self.pending.len() < max_requests {
+            let (req, resolve, reject) = match self.queued.pop_front() {
+                Some(queued) => queued,
+                None => break,
+            };
+            let (tx, rx) = mpsc::channel();
+
+            trace!("fetch {} {}", req.method, req.url);
+
+            thread::spawn(move || {
+                let _ = tx.send(req.send());
+            });
+
+            self.pending.push(Pending {
+                rx,
+                resolve,
+                reject,
+            });
+        }
+    }
+}
+
+struct Pending {
+    rx: Receiver<Result<Response, String>>,
+    resolve: Value,
+    reject: Value,
+}
+
+struct Request {
+    client: Arc<Fetch>,
+    method: String,
+    url: String,
+    headers: Vec<(String, String)>,
+    body: Option<Vec<u8>>,
+    max_bytes: usize,
+}
+
+struct Response {
+    status: u16,
+    status_text: String,
+    url: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    /// Send the request and follow the allowed redirects.
+    fn send(mut self) -> Result<Response, String> {
+        for _ in 0..=MAX_REDIRECTS {
+            let req = self.headers.iter().fold(
+                self.client.agent.request(&self.method, &self.url),
+                |req, (name, value)| req.set(name, value),
+            );
+            let base = req.request_url().map_err(|err| err.to_string())?;
+            let res = match self.body {
+                Some(ref body) => req.send_bytes(body),
+                None => req.call(),
+            };
+            let res = match res {
+                Ok(res) | Err(ureq::Error::Status(_, res)) => res,
+                Err(err) => return Err(err.to_string()),
+            };
+            let location = match res.header("location") {
+                Some(location) if (300..400).contains(&res.status()) => location,
+                _ => return read_response(res, self.max_bytes),
+            };
+            let next = base
+                .as_url()
+                .join(location)
+                .map_err(|err| err.to_string())?;
+
+            // only `307` and `308` keep the method and body
+            if res.status() == 303
+                || (matches!(res.status(), 301 | 302)
+                    && !matches!(self.method.as_str(), "GET" | "HEAD"))
+            {
+                self.method = "GET".to_owned();
+                self.body = None;
+            }
+
+            if next.host_str() != Some(base.host()) || next.port() != base.port() {
+                self.headers
+                    .retain(|(name, _)| !SENSITIVE_HEADERS.contains(&name.to_lowercase().as_str()));
+            }
+
+            let next = next.to_string();
+
+            if !self.client.is_allowed(&self.method, &next) {
+                return Err(format!(
+                    "redirect to {} {} is not allowed",
+                    self.method, next
+                ));
+            }
+
+            trace!("fetch redirected to {} {}", self.method, next);
+
+            self.url = next;
+        }
+
+        Err(format!("too many redirects to {}", self.url))
+    }
+}
+
+fn read_response(res: ureq::Response, max_bytes: usize) -> Result<Response, String> {
+    let headers = res
+        .headers_names()
+        .into_iter()
+        .filter_map(|name| {
+            res.header(&name)
+                .map(|value| value.to_owned())
+                .map(|value| (name, value))
+        })
+        .collect();
+    let (status, status_text, url) = (
+        res.status(),
+        res.status_text().to_owned(),
+        res.get_url().to_owned(),
+    );
+    let mut body = vec![];
+
+    res.into_reader()
+        .take(max_bytes as u64 + 1)
+        .read_to_end(&mut body)
+        .map_err(|err| err.to_string())?;
+
+    if body.len() > max_bytes {
+        return Err(Error::Limit("string bytes", max_bytes).to_string());
+    }
+
+    Ok(Response {
+        status,
+        status_text,
+        url,
+        headers,
+        body,
+    })
+}
+
+fn to_string(ctxt: &ContextRef, value: Option<&Value>) -> String {
+    value
+        .and_then(|value| ctxt.to_cstring(value))
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn request(ctxt: &ContextRef, _this: Option<&Value>, args: &[Value]) -> ffi::JSValue {
+    let method = to_string(ctxt, args.first());
+    let url = to_string(ctxt, args.get(1));
+    let client = match ctxt.with_state(|state| state.fetches.client.clone()) {
+        Some(client) if client.is_allowed(&method, &url) => client,
+        _ => {
+            return ctxt
+                .throw_type_error(format!("fetch {} {} is not allowed", method, url))
+                .into_inner()
+                .raw()
+        }
+    };
+    let headers = args
+        .get(2)
+        .and_then(|headers| ctxt.get_property(headers, "length"))
+        .and_then(|len| len.to_index())
+        .map_or_else(Vec::new, |len| {
+            (0..len as u32)
+                .filter_map(|idx| ctxt.get_property(&args[2], idx))
+                .map(|entry| {
+                    (
+                        to_string(ctxt, ctxt.get_property(&entry, 0u32).as_deref()),
+                        to_string(ctxt, ctxt.get_property(&entry, 1u32).as_deref()),
+                    )
+                })
+                .collect::<Vec<_>>()
+        });
+    let body = match args.get(3) {
+        None => None,
+        Some(body) if body.is_undefined() || body.is_null() => None,
+        Some(body) if body.is_string() => Some(to_string(ctxt, Some(body)).into_bytes()),
+        Some(body) => match ctxt.with_buffer_source(body, |buf| buf.to_vec()) {
+            Some(body) => Some(body),
+            None => {
+                return ctxt
+                    .throw_type_error("body should be a string, ArrayBuffer or ArrayBufferView")
+                    .into_inner()
+                    .raw()
+            }
+        },
+    };
+    let PromiseCapability {
+        promise,
+        resolve,
+        reject,
+    } = match ctxt.new_promise_capability() {
+        Ok(capability) => capability,
+        Err(err) => return ctxt.throw(err.to_string()).into_inner().raw(),
+    };
+    let req = Request {
+        client,
+        method,
+        url,
+        headers,
+        body,
+        max_bytes: ctxt.conversion_limits().max_string_bytes,
+    };
+
+    ctxt.with_state(|state| {
+        state
+            .fetches
+            .queued
+            .push_back((req, resolve.into_inner(), reject.into_inner()));
+        state.fetches.start_queued();
+    });
+
+    promise.into_inner().raw()
+}
+
+fn decode(ctxt: &ContextRef, _this: Option<&Value>, args: &[Value]) -> ffi::JSValue {
+    match args.first().and_then(|buf| {
+        ctxt.with_buffer_source(buf, |buf| String::from_utf8_lossy(buf).into_owned())
+    }) {
+        Some(s) => ctxt.bind(s).into_inner().raw(),
+        None => ctxt
+            .throw_type_error("expected Uint8Array")
+            .into_inner()
+            .raw(),
+    }
+}
+
+impl ContextRef {
+    /// Install the `fetch`, `Headers` and `Response` globals, which send the HTTP requests by the client.
+    ///
+    /// The requests run in the background threads, and the promises are settled by the host loop,
+    /// which should keep calling `run_jobs_once` while there are `pending_jobs`.
+    ///
+    /// At most `Fetch::max_requests` requests run at the same time, the others wait in the queue.
+    pub fn init_fetch(&self, client: Fetch) -> Result<(), Error> {
+        self.with_state(|state| state.fetches.client = Some(Arc::new(client)));
+
+        self.eval_script(GLUE, "<fetch>", Eval::GLOBAL)?.call(
+            None,
+            (
+                self.new_c_function(request, Some("request"), 4)?,
+                self.new_c_function(decode, Some("decode"), 1)?,
+            ),
+        )?;
+
+        Ok(())
+    }
+
+    pub(crate) fn pending_fetches(&self) -> usize {
+        self.with_state(|state| state.fetches.pending.len() + state.fetches.queued.len())
+    }
+
+    /// Settle the promise of a finished request, returns `false` if there is no finished request.
+    pub(crate) fn poll_fetch(&self) -> Result<bool, Error> {
+        let finished = self.with_state(|state| {
+            let pending = &mut state.fetches.pending;
+
+            for idx in 0..pending.len() {
+                let res = match pending[idx].rx.try_recv() {
+                    Ok(res) => res,
+                    Err(TryRecvError::Disconnected) => Err("request aborted".to_owned()),
+                    Err(TryRecvError::Empty) => continue,
+                };
+
+                let finished = pending.remove(idx);
+
+                state.fetches.start_queued();
+
+                return Some((finished, res));
+            }
+
+            None
+        });
+        let (pending, res) = match finished {
+            Some(finished) => finished,
+            None => return Ok(false),
+        };
+        let resolve = self.bind(pending.resolve);
+        let reject = self.bind(pending.reject);
+
+        trace!("fetch finished: {:?}", res.as_ref().map(|res| res.status));
+
+        match res
+            .map_err(Error::Msg)
+            .and_then(|res| self.response_to_js(res))
+        {
+            Ok(res) => resolve.call(None, res)?,
+            Err(err) => {
+                let ctor = self
+                    .get_property(&self.global_object(), "TypeError")
+                    .ok_or_else(|| Error::from("`TypeError` not found"))?;
+
+                reject.call(None, self.call_constructor(&ctor, err.to_string())?)?
+            }
+        };
+
+        Ok(true)
+    }
+
+    fn response_to_js(&self, res: Response) -> Result<Local<Value>, Error> {
+        let headers = self.bind(self.new_array());
+
+        for (idx, (name, value)) in res.headers.iter().enumerate() {
+            let entry = self.bind(self.new_array());
+
+            entry.set_property(0u32, name.as_str())?;
+            entry.set_property(1u32, value.as_str())?;
+            headers.set_property(idx as u32, entry)?;
+        }
+
+        let arr = self.bind(self.new_array());
+
+        arr.set_property(0u32, i32::from(res.status))?;
+        arr.set_property(1u32, res.status_text.as_str())?;
+        arr.set_property(2u32, res.url.as_str())?;
+        arr.set_property(3u32, headers)?;
+        arr.set_property(4u32, self.new_uint8_array(&res.body)?)?;
+
+        Ok(arr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    use crate::{Context, Runtime};
+
+    use super::*;
+
+    #[test]
+    fn fetch() {
+        let _ = pretty_env_logger::try_init();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 4096];
+            let n = stream.read(&mut buf).unwrap();
+            let req = String::from_utf8_lossy(&buf[..n]).into_owned();
+            let body = format!(
+                r#"{{"method":"{}","agent":{}}}"#,
+                req.split(' ').next().unwrap(),
+                req.to_lowercase().contains("x-agent: qjs")
+            );
+
+            write!(
+                stream,
+                "HTTP/1.1 201 Created\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+        });
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+        let base = format!("http://{}/", addr);
+        let allowed = base.clone();
+
+        ctxt.init_fetch(Fetch::new().allow(move |_, url| url.starts_with(&allowed)))
+            .unwrap();
+        ctxt.global_object()
+            .set_property("base", base.as_str())
+            .unwrap();
+        ctxt.eval_script(
+            r#"
+var log = [];
+fetch(base + 'echo', { method: 'post', headers: { 'X-Agent': 'qjs' }, body: 'hello' })
+    .then(res => { log.push(res.status, res.ok, res.headers.get('content-type')); return res.json(); })
+    .then(json => log.push(json.method, json.agent));
+fetch('http://example.com/').catch(err => log.push(err instanceof TypeError));
+"#,
+            "<evalScript>",
+            Eval::GLOBAL,
+        )
+        .unwrap();
+
+        while ctxt.pending_jobs() > 0 {
+            if !ctxt.run_jobs_once().unwrap() {
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+
+        assert_eq!(
+            ctxt.eval::<_, String>("log.join()", Eval::GLOBAL).unwrap(),
+            Some("true,201,true,application/json,POST,true".to_owned())
+        );
+    }
+
+    #[test]
+    fn redirect() {
+        let _ = pretty_env_logger::try_init();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().take(3) {
+                let mut stream = stream.unwrap();
+                let mut buf = [0; 4096];
+                let n = stream.read(&mut buf).unwrap();
+                let req = String::from_utf8_lossy(&buf[..n]).into_owned();
+                let res = match req.split(' ').nth(1).unwrap() {
+                    "/moved" => "HTTP/1.1 302 Found\r\nLocation: /echo\r\n",
+                    "/leak" => "HTTP/1.1 302 Found\r\nLocation: /private\r\n",
+                    _ => "HTTP/1.1 200 OK\r\n",
+                };
+
+                write!(
+                    stream,
+                    "{}Connection: close\r\nContent-Length: 2\r\n\r\nok",
+                    res
+                )
+                .unwrap();
+            }
+        });
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+        let base = format!("http://{}/", addr);
+        let allowed = base.clone();
+
+        ctxt.init_fetch(
+            Fetch::new()
+                .allow(move |_, url| url.starts_with(&allowed) && !url.ends_with("/private"))
+                .max_requests(1),
+        )
+        .unwrap();
+        ctxt.global_object()
+            .set_property("base", base.as_str())
+            .unwrap();
+        ctxt.eval_script(
+            r#"
+var log = [];
+fetch(base + 'moved').then(res => res.text().then(text => log.push(res.url.endsWith('/echo'), text)));
+fetch(base + 'leak').catch(err => log.push(err instanceof TypeError));
+"#,
+            "<evalScript>",
+            Eval::GLOBAL,
+        )
+        .unwrap();
+
+        assert_eq!(ctxt.pending_fetches(), 2);
+
+        while ctxt.pending_jobs() > 0 {
+            if !ctxt.run_jobs_once().unwrap() {
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+
+        assert_eq!(
+            ctxt.eval::<_, String>("log.join()", Eval::GLOBAL).unwrap(),
+            Some("true,ok,true".to_owned())
+        );
+    }
+}
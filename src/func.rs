@@ -1,7 +1,12 @@
-use failure::Error;
+use std::any::type_name;
+use std::marker::PhantomData;
+use std::ops::Deref;
+
 use foreign_types::ForeignTypeRef;
 
-use crate::{ffi, value::ToBool, ContextRef, Local, NewAtom, NewValue, Value};
+use crate::{
+    ffi, value::ToBool, ContextRef, Error, ExtractValue, JsArray, Local, NewAtom, NewValue, Value,
+};
 
 pub trait Args {
     type Values: AsRef<[ffi::JSValue]>;
@@ -124,6 +129,25 @@ impl<'a> Local<'a, Value> {
         self.ctxt.invoke(self, atom, args)
     }
 
+    /// Call the function with the bound values, which are passed without cloning.
+    pub fn call_with_values(
+        &self,
+        this: Option<&Value>,
+        args: &[Local<Value>],
+    ) -> Result<Local<Value>, Error> {
+        self.ctxt.call_with_values(self, this, args)
+    }
+
+    /// Call the function with `this` and the elements of the array, like `Reflect.apply`.
+    pub fn apply(&self, this: Option<&Value>, args: &JsArray) -> Result<Local<Value>, Error> {
+        self.ctxt.apply(self, this, args)
+    }
+
+    /// Create a bound function with the fixed `this`, like `Function.prototype.bind`.
+    pub fn bind_this(&self, this: &Value) -> Result<Local<Value>, Error> {
+        self.ctxt.bind_this(self, this)
+    }
+
     pub fn call_constructor<T: Args>(&self, args: T) -> Result<Local<Value>, Error> {
         self.ctxt.call_constructor(self, args)
     }
@@ -137,6 +161,68 @@ impl<'a> Local<'a, Value> {
     }
 }
 
+impl<'a> Local<'a, Value> {
+    /// Converts to the `JsFunction` wrapper with the typed call signature if the value is a function.
+    pub fn into_function<A: Args, R: ExtractValue>(self) -> Result<JsFunction<'a, A, R>, Self> {
+        if self.is_function() {
+            Ok(JsFunction(self, PhantomData))
+        } else {
+            Err(self)
+        }
+    }
+}
+
+/// Javascript function with the typed call signature, created by `Local::into_function`.
+#[repr(transparent)]
+#[derive(Debug)]
+pub struct JsFunction<'a, A, R>(Local<'a, Value>, PhantomData<fn(A) -> R>);
+
+impl<'a, A, R> Clone for JsFunction<'a, A, R> {
+    fn clone(&self) -> Self {
+        JsFunction(self.0.clone(), PhantomData)
+    }
+}
+
+impl<'a, A, R> NewValue for JsFunction<'a, A, R> {
+    fn new_value(self, ctxt: &ContextRef) -> ffi::JSValue {
+        self.0.new_value(ctxt)
+    }
+}
+
+impl<'a, A, R> Deref for JsFunction<'a, A, R> {
+    type Target = Local<'a, Value>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'a, A: Args, R: ExtractValue> JsFunction<'a, A, R> {
+    /// Call the function with the arguments and extract the returned value.
+    pub fn call(&self, args: A) -> Result<R, Error> {
+        self.call_with_this(None, args)
+    }
+
+    /// Call the function with `this` and the arguments, and extract the returned value.
+    pub fn call_with_this(&self, this: Option<&Value>, args: A) -> Result<R, Error> {
+        let ret = self.0.ctxt.call(&self.0, this, args)?;
+
+        R::extract_value(&ret).ok_or_else(|| {
+            format!(
+                "expected `{}` returned from the function, got `{}`",
+                type_name::<R>(),
+                ret
+            )
+            .into()
+        })
+    }
+
+    /// Converts back to the untyped value.
+    pub fn into_value(self) -> Local<'a, Value> {
+        self.0
+    }
+}
+
 impl ContextRef {
     pub fn is_function(&self, val: &Value) -> bool {
         unsafe { ffi::JS_IsFunction(self.as_ptr(), val.raw()).to_bool() }
@@ -154,6 +240,7 @@ impl ContextRef {
     ) -> Result<Local<Value>, Error> {
         let args = args.into_values(self);
         let args = args.as_ref();
+        let _entered = self.enter();
         let ret = {
             unsafe {
                 ffi::JS_Call(
@@ -173,26 +260,77 @@ impl ContextRef {
         self.bind(ret).ok()
     }
 
+    /// Call the function with the bound values, which are passed without cloning.
+    pub fn call_with_values(
+        &self,
+        func: &Value,
+        this: Option<&Value>,
+        args: &[Local<Value>],
+    ) -> Result<Local<Value>, Error> {
+        // the arguments are borrowed by the engine
+        let args = args.iter().map(|arg| arg.raw()).collect::<Vec<_>>();
+        let _entered = self.enter();
+
+        self.bind(unsafe {
+            ffi::JS_Call(
+                self.as_ptr(),
+                func.raw(),
+                this.map_or(ffi::UNDEFINED, |v| v.raw()),
+                args.len() as i32,
+                args.as_ptr() as *mut _,
+            )
+        })
+        .ok()
+    }
+
+    /// Call the function with `this` and the elements of the array, like `Reflect.apply`.
+    ///
+    /// The holes of the sparse array are passed as `undefined`,
+    /// and the length of the array is limited by `Limits::max_elements`.
+    pub fn apply(
+        &self,
+        func: &Value,
+        this: Option<&Value>,
+        args: &JsArray,
+    ) -> Result<Local<Value>, Error> {
+        self.conversion_limits().check_elements(args.len())?;
+
+        let args = (0..args.len() as u32)
+            .map(|idx| args.get(idx).unwrap_or_else(|| self.undefined()))
+            .collect::<Vec<_>>();
+
+        self.call_with_values(func, this, &args)
+    }
+
+    /// Create a bound function with the fixed `this`, like `Function.prototype.bind`.
+    pub fn bind_this(&self, func: &Value, this: &Value) -> Result<Local<Value>, Error> {
+        if !self.is_function(func) {
+            return Err("expected function".into());
+        }
+
+        self.invoke(func, "bind", this)
+    }
+
     pub fn invoke<N: NewAtom, T: Args>(
         &self,
         this: &Value,
         atom: N,
         args: T,
     ) -> Result<Local<Value>, Error> {
-        let atom = atom.new_atom(self);
+        let atom = self.try_new_atom(atom)?;
         let args = args.into_values(self);
         let args = args.as_ref();
+        let _entered = self.enter();
 
         let res = self.bind(unsafe {
             ffi::JS_Invoke(
                 self.as_ptr(),
                 this.raw(),
-                atom,
+                *atom,
                 args.len() as i32,
                 args.as_ptr() as *mut _,
             )
         });
-        self.free_atom(atom);
         for arg in args {
             self.free_value(*arg);
         }
@@ -203,6 +341,7 @@ impl ContextRef {
     pub fn call_constructor<T: Args>(&self, func: &Value, args: T) -> Result<Local<Value>, Error> {
         let args = args.into_values(self);
         let args = args.as_ref();
+        let _entered = self.enter();
         let ret = unsafe {
             ffi::JS_CallConstructor(
                 self.as_ptr(),
@@ -227,6 +366,7 @@ impl ContextRef {
     ) -> Result<Local<Value>, Error> {
         let args = args.into_values(self);
         let args = args.as_ref();
+        let _entered = self.enter();
         let ret = unsafe {
             ffi::JS_CallConstructor2(
                 self.as_ptr(),
@@ -247,7 +387,7 @@ impl ContextRef {
 
 #[cfg(test)]
 mod tests {
-    use crate::{Context, Eval, Runtime};
+    use crate::{Context, Error, Eval, JsArray, Limits, Runtime};
 
     #[test]
     fn call() {
@@ -285,6 +425,14 @@ function Product(name, price) {
 
         assert_eq!(fib.call(None, [10]).unwrap().as_int().unwrap(), 55);
 
+        let args = vec![ctxt.bind(10)];
+
+        assert_eq!(
+            fib.call_with_values(None, &args).unwrap().as_int().unwrap(),
+            55
+        );
+        assert_eq!(args[0].as_int(), Some(10));
+
         let product_ctor = global.get_property("Product").unwrap();
 
         assert!(product_ctor.is_function());
@@ -295,4 +443,77 @@ function Product(name, price) {
         assert_eq!(product.get_property("name").unwrap().to_string(), "foobar");
         assert_eq!(product.get_property("price").unwrap().as_int().unwrap(), 30);
     }
+
+    #[test]
+    fn apply_and_bind_this() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let func = ctxt
+            .eval_script(
+                "(function (a, b) { return `${this.name}: ${a}, ${b}` })",
+                "<evalScript>",
+                Eval::GLOBAL,
+            )
+            .unwrap();
+        let this = ctxt
+            .eval_script("({ name: 'foo' })", "<evalScript>", Eval::GLOBAL)
+            .unwrap();
+        let args = JsArray::new(&ctxt);
+
+        args.set(0, 1).unwrap();
+        args.set(2, 3).unwrap();
+
+        assert_eq!(
+            func.apply(Some(&this), &args).unwrap().to_string(),
+            "foo: 1, undefined"
+        );
+
+        ctxt.set_conversion_limits(Limits {
+            max_elements: 2,
+            ..Default::default()
+        });
+
+        assert!(matches!(
+            func.apply(Some(&this), &args),
+            Err(Error::Limit("elements", 2))
+        ));
+
+        ctxt.set_conversion_limits(Default::default());
+
+        let bound = func.bind_this(&this).unwrap();
+
+        assert!(bound.is_function());
+        assert_eq!(bound.call(None, (2, 3)).unwrap().to_string(), "foo: 2, 3");
+        assert!(ctxt.bind_this(&this, &this).is_err());
+    }
+
+    #[test]
+    fn typed_function() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let scale = ctxt
+            .eval_script(
+                "(n, unit) => { if (unit != 'k') throw new RangeError(unit); return n * 1000.5 }",
+                "<evalScript>",
+                Eval::GLOBAL,
+            )
+            .unwrap()
+            .into_function::<(i32, String), f64>()
+            .unwrap();
+
+        assert_eq!(scale.call((2, "k".to_owned())).unwrap(), 2001.0);
+        assert_eq!(scale.call((3, "k".to_owned())).unwrap(), 3001.5);
+        assert!(scale.call((1, "m".to_owned())).is_err());
+
+        assert!(ctxt
+            .bind(ctxt.new_object())
+            .into_function::<(), ()>()
+            .is_err());
+    }
 }
@@ -0,0 +1,152 @@
+use std::ops::Deref;
+
+use crate::{ffi, Args, ContextRef, Error, ExtractValue, Local, NewValue, Value};
+
+impl<'a> Local<'a, Value> {
+    /// Returns `true` if the value is an object with the `next`, `return` and `throw` methods of a generator.
+    pub fn is_generator(&self) -> bool {
+        self.is_object()
+            && ["next", "return", "throw"].iter().all(|&name| {
+                self.get_property(name)
+                    .filter(|method| method.is_function())
+                    .is_some()
+            })
+    }
+
+    /// Converts to the `JsGenerator` wrapper if the value is a generator.
+    pub fn into_generator(self) -> Result<JsGenerator<'a>, Self> {
+        if self.is_generator() {
+            Ok(JsGenerator(self))
+        } else {
+            Err(self)
+        }
+    }
+}
+
+/// A Javascript generator object, which can be resumed from Rust.
+///
+/// Each method returns the extracted `value` and the `done` flag of the iterator result,
+/// the `value` is `None` if it can't be extracted as `R`.
+#[repr(transparent)]
+#[derive(Clone, Debug)]
+pub struct JsGenerator<'a>(Local<'a, Value>);
+
+impl<'a> NewValue for JsGenerator<'a> {
+    fn new_value(self, ctxt: &ContextRef) -> ffi::JSValue {
+        self.0.new_value(ctxt)
+    }
+}
+
+impl<'a> Deref for JsGenerator<'a> {
+    type Target = Local<'a, Value>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'a> JsGenerator<'a> {
+    /// Resume the generator with the argument as the result of `yield`.
+    pub fn next<A: Args, R: ExtractValue>(&self, arg: A) -> Result<(Option<R>, bool), Error> {
+        self.resume("next", arg)
+    }
+
+    /// Finish the generator with the value, the `finally` blocks of the generator are executed.
+    pub fn return_<A: Args, R: ExtractValue>(&self, value: A) -> Result<(Option<R>, bool), Error> {
+        self.resume("return", value)
+    }
+
+    /// Throw the error at the suspended `yield`, which could be caught by the generator.
+    pub fn throw_<A: Args, R: ExtractValue>(&self, err: A) -> Result<(Option<R>, bool), Error> {
+        self.resume("throw", err)
+    }
+
+    /// Converts to the bound value.
+    pub fn into_value(self) -> Local<'a, Value> {
+        self.0
+    }
+
+    fn resume<A: Args, R: ExtractValue>(
+        &self,
+        method: &str,
+        arg: A,
+    ) -> Result<(Option<R>, bool), Error> {
+        let ctxt = self.0.ctxt;
+        let res = ctxt.invoke(&self.0, method, arg)?;
+
+        if !res.is_object() {
+            return Err("iterator result is not an object".into());
+        }
+
+        let done = ctxt
+            .get_property(&res, "done")
+            .and_then(|done| done.to_bool())
+            .unwrap_or_default();
+        let value = ctxt
+            .get_property(&res, "value")
+            .and_then(|value| R::extract_value(&value));
+
+        trace!("generator {} returns ({:?}, {})", method, res, done);
+
+        Ok((value, done))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, Eval, Runtime};
+
+    #[test]
+    fn generator() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let new_gen = || {
+            ctxt.eval_script(
+                r#"
+(function* () {
+    let total = 0;
+    try {
+        while (true) {
+            try {
+                const n = yield total;
+                total += n;
+            } catch (err) {
+                total = -1;
+            }
+        }
+    } finally {
+        globalThis.finished = total;
+    }
+})()
+"#,
+                "<evalScript>",
+                Eval::GLOBAL,
+            )
+            .unwrap()
+            .into_generator()
+            .unwrap()
+        };
+
+        let gen = new_gen();
+
+        assert_eq!(gen.next(()).unwrap(), (Some(0), false));
+        assert_eq!(gen.next(2).unwrap(), (Some(2), false));
+        assert_eq!(gen.next(3).unwrap(), (Some(5), false));
+        assert_eq!(gen.throw_("Whoops!").unwrap(), (Some(-1), false));
+        assert_eq!(gen.return_(42).unwrap(), (Some(42), true));
+        assert_eq!(gen.next::<_, i32>(()).unwrap(), (None, true));
+        assert_eq!(ctxt.eval("finished", Eval::GLOBAL).unwrap(), Some(-1));
+
+        let gen = new_gen();
+
+        assert!(gen.throw_::<_, i32>("Whoops!").is_err());
+        assert!(ctxt
+            .eval_script("[]", "<evalScript>", Eval::GLOBAL)
+            .unwrap()
+            .into_generator()
+            .is_err());
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{CFunction, ContextRef, Error, Local, NewValue, Prop, Value};
+
+/// The batch of the property definitions on the global object, created by `ContextRef::globals`.
+///
+/// The definitions after the first failed one are skipped, and the error is returned by `finish`.
+pub struct Globals<'a> {
+    global: Local<'a, Value>,
+    flags: Prop,
+    err: Option<Error>,
+}
+
+impl ContextRef {
+    /// Define the properties on the global object in a batch.
+    pub fn globals(&self) -> Globals {
+        Globals {
+            global: self.global_object(),
+            flags: Prop::C_W_E,
+            err: None,
+        }
+    }
+
+    /// Define `globalThis.scriptArgs` with the arguments, like `std_add_helpers` but without the `std` helpers.
+    pub fn set_script_args<I: IntoIterator<Item = String>>(&self, args: I) -> Result<(), Error> {
+        let arr = self.bind(self.new_array());
+
+        for (idx, arg) in args.into_iter().enumerate() {
+            arr.set_property(idx as u32, arg)?;
+        }
+
+        trace!("set script args: {:?}", arr);
+
+        self.global_object()
+            .define_property_value("scriptArgs", arr, Prop::C_W_E)
+            .map(|_| ())
+    }
+}
+
+impl<'a> Globals<'a> {
+    /// Define the following properties as enumerable or not, they are enumerable by default.
+    pub fn enumerable(mut self, enumerable: bool) -> Self {
+        self.flags.set(Prop::ENUMERABLE, enumerable);
+        self
+    }
+
+    /// Define a property with the value.
+    pub fn set<V: NewValue>(self, name: &str, value: V) -> Self {
+        self.define(name, || Ok(value))
+    }
+
+    /// Define a C function with the name.
+    #[track_caller]
+    pub fn set_fn<T: NewValue>(self, name: &str, func: CFunction<T>) -> Self {
+        // create the function out of the closure to record the location of the caller
+        let func = self.global.ctxt.new_c_function(func, Some(name), 0);
+
+        self.define(name, || func)
+    }
+
+    /// Define an object with the entries, like a configuration.
+    pub fn set_module<I, K, V>(self, name: &str, entries: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: NewValue,
+    {
+        let ctxt = self.global.ctxt;
+
+        self.define(name, || {
+            let obj = ctxt.bind(ctxt.new_object());
+
+            for (key, value) in entries {
+                obj.set_property(key.as_ref(), value)?;
+            }
+
+            Ok(obj)
+        })
+    }
+
+    /// Returns the first error of the definitions.
+    pub fn finish(self) -> Result<(), Error> {
+        self.err.map_or(Ok(()), Err)
+    }
+
+    fn define<V, F>(mut self, name: &str, f: F) -> Self
+    where
+        V: NewValue,
+        F: FnOnce() -> Result<V, Error>,
+    {
+        if self.err.is_none() {
+            trace!("define global `{}` with {:?}", name, self.flags);
+
+            if let Err(err) = f().and_then(|value| {
+                self.global
+                    .define_property_value(name, value, self.flags)
+                    .map(|_| ())
+            }) {
+                self.err = Some(err);
+            }
+        }
+
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, ContextRef, Eval, Runtime, Value};
+
+    fn add(_ctxt: &ContextRef, _this: Option<&Value>, args: &[Value]) -> i32 {
+        args.iter().flat_map(|arg| arg.as_int()).sum()
+    }
+
+    #[test]
+    fn globals() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        ctxt.globals()
+            .set("a", 1)
+            .set_fn("add", add)
+            .set_module("cfg", vec![("name", "foo"), ("mode", "debug")])
+            .enumerable(false)
+            .set("secret", "bar")
+            .finish()
+            .unwrap();
+
+        assert_eq!(
+            ctxt.eval::<_, String>(
+                "[add(a, 2), cfg.name, cfg.mode, secret, Object.keys(globalThis).includes('secret')].join()",
+                Eval::GLOBAL
+            )
+            .unwrap(),
+            Some("3,foo,debug,bar,false".to_owned())
+        );
+    }
+
+    #[test]
+    fn script_args() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        ctxt.set_script_args(vec!["main.js".to_owned(), "--verbose".to_owned()])
+            .unwrap();
+
+        assert_eq!(
+            ctxt.eval::<_, String>("scriptArgs.join()", Eval::GLOBAL)
+                .unwrap(),
+            Some("main.js,--verbose".to_owned())
+        );
+    }
+}
@@ -25,6 +25,9 @@ where
     T: Unbindable,
 {
     fn drop(&mut self) {
+        #[cfg(feature = "leak-check")]
+        crate::scope::track_local(false);
+
         if let Some(inner) = self.inner.take() {
             T::unbind(self.ctxt, inner)
         }
@@ -66,6 +69,9 @@ where
     {
         let inner = self.inner.take().map(f);
 
+        #[cfg(feature = "leak-check")]
+        crate::scope::track_local(true);
+
         Local {
             ctxt: self.ctxt,
             inner: inner,
@@ -75,6 +81,9 @@ where
 
 impl ContextRef {
     pub fn bind<'a, T: Bindable<'a>>(&'a self, val: T) -> Local<'a, T::Output> {
+        #[cfg(feature = "leak-check")]
+        crate::scope::track_local(true);
+
         Local {
             ctxt: self,
             inner: Some(val.bind_to(self)),
@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+use crate::{ContextRef, Error, ModuleDef, ModuleLoaderBuilder, RuntimeRef};
+
+/// The hook to provide the source of a module, e.g. from a virtual filesystem.
+pub(crate) type ImportHook =
+    Arc<dyn Fn(&ContextRef, &str) -> Result<Option<String>, Error> + Send + Sync>;
+
+/// The hook to customize the `import.meta` object of a module.
+pub(crate) type ImportMetaHook =
+    Arc<dyn Fn(&ContextRef, &ModuleDef) -> Result<(), Error> + Send + Sync>;
+
+impl RuntimeRef {
+    /// Set the hook to provide the source of the modules, both the static `import` and dynamic `import()`.
+    ///
+    /// The hook returns `None` to fall back to the files, and the module loader is installed if not yet.
+    pub fn set_import_hook<F>(&self, hook: F)
+    where
+        F: Fn(&ContextRef, &str) -> Result<Option<String>, Error> + Send + Sync + 'static,
+    {
+        if self.with_state(|state| state.module_loader.is_none()) {
+            ModuleLoaderBuilder::new().build(self);
+        }
+
+        self.with_state(|state| state.import_hook = Some(Arc::new(hook)));
+    }
+
+    /// Set the hook to customize the `import.meta` object of the modules loaded by the module loader.
+    pub fn set_import_meta_hook<F>(&self, hook: F)
+    where
+        F: Fn(&ContextRef, &ModuleDef) -> Result<(), Error> + Send + Sync + 'static,
+    {
+        self.with_state(|state| state.import_meta_hook = Some(Arc::new(hook)));
+    }
+
+    /// Remove the import and `import.meta` hooks.
+    pub fn clear_import_hooks(&self) {
+        self.with_state(|state| {
+            state.import_hook = None;
+            state.import_meta_hook = None;
+        })
+    }
+
+    pub(crate) fn import_hook(&self) -> Option<ImportHook> {
+        self.with_state(|state| state.import_hook.clone())
+    }
+
+    pub(crate) fn import_meta_hook(&self) -> Option<ImportMetaHook> {
+        self.with_state(|state| state.import_meta_hook.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, Eval, Runtime};
+
+    #[test]
+    fn import_hook() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        rt.set_import_hook(|_ctxt, name| {
+            Ok(match name {
+                "virtual:greet" => Some("export default 'hello ' + import.meta.env".to_owned()),
+                "virtual:lazy" => Some("export const answer = 42".to_owned()),
+                _ => None,
+            })
+        });
+        rt.set_import_meta_hook(|ctxt, module| {
+            ctxt.import_meta(module)?.set_property("env", "test")?;
+
+            Ok(())
+        });
+
+        ctxt.eval_script(
+            r#"
+import greet from 'virtual:greet';
+
+globalThis.greeting = greet;
+
+import('virtual:lazy').then(m => globalThis.answer = m.answer);
+import('virtual:missing').catch(err => globalThis.missing = err.message);
+"#,
+            "<evalScript>",
+            Eval::MODULE,
+        )
+        .unwrap();
+
+        while rt.is_job_pending() {
+            rt.execute_pending_job().unwrap();
+        }
+
+        assert_eq!(
+            ctxt.eval::<_, String>("greeting", Eval::GLOBAL).unwrap(),
+            Some("hello test".to_owned())
+        );
+        assert_eq!(
+            ctxt.eval::<_, i32>("answer", Eval::GLOBAL).unwrap(),
+            Some(42)
+        );
+        assert!(ctxt
+            .eval::<_, String>("missing", Eval::GLOBAL)
+            .unwrap()
+            .unwrap()
+            .contains("virtual:missing"));
+    }
+}
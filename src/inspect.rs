@@ -0,0 +1,285 @@
+use std::ptr::NonNull;
+
+use crate::{ffi, prop::Names, ContextRef, Local, Value};
+
+/// The maximum width of the objects and arrays formatted in a single line.
+const LINE_WIDTH: usize = 72;
+
+/// The maximum number of the elements of an array formatted, the rest are abbreviated like `... 1 more item`.
+const MAX_ELEMENTS: usize = 100;
+
+impl<'a> Local<'a, Value> {
+    /// Format the value to a human-readable string like `util.inspect` of Node.js.
+    ///
+    /// The objects nested deeper than `depth` levels are abbreviated, like `[Object]`,
+    /// the circular references are formatted as `[Circular]`,
+    /// and only the first 100 elements of an array are formatted.
+    pub fn inspect(&self, depth: usize) -> String {
+        self.ctxt.inspect(self, depth)
+    }
+}
+
+impl ContextRef {
+    /// Format the value to a human-readable string like `util.inspect` of Node.js.
+    pub fn inspect(&self, value: &Value, depth: usize) -> String {
+        Inspector {
+            ctxt: self,
+            depth,
+            seen: vec![],
+        }
+        .format(value, 0)
+    }
+}
+
+struct Inspector<'a> {
+    ctxt: &'a ContextRef,
+    depth: usize,
+    seen: Vec<NonNull<ffi::JSObject>>,
+}
+
+impl Inspector<'_> {
+    fn format(&mut self, value: &Value, level: usize) -> String {
+        if value.is_string() {
+            return quote(&self.to_string(value));
+        }
+        if value.is_symbol() {
+            let desc = self
+                .ctxt
+                .get_property(value, "description")
+                .map(|desc| desc.to_string())
+                .unwrap_or_default();
+
+            return format!("Symbol({})", desc);
+        }
+
+        let obj = match value.as_object() {
+            Some(obj) => obj,
+            None => return self.to_string(value),
+        };
+
+        if self.ctxt.is_function(value) {
+            return match self.get_string(value, "name") {
+                Some(ref name) if !name.is_empty() => format!("[Function: {}]", name),
+                _ => "[Function (anonymous)]".to_owned(),
+            };
+        }
+        if self.ctxt.is_error(value) {
+            let msg = self.to_string(value);
+
+            return match self.get_string(value, "stack") {
+                Some(stack) => format!("{}\n{}", msg, stack.trim_end()),
+                None => msg,
+            };
+        }
+        if self.seen.contains(&obj) {
+            return "[Circular]".to_owned();
+        }
+
+        let is_array = self.ctxt.is_array(value).unwrap_or(false);
+        let ctor = self
+            .ctxt
+            .get_property(value, "constructor")
+            .filter(|ctor| ctor.is_function())
+            .and_then(|ctor| ctor.get_property("name").map(|name| name.to_string()));
+
+        match ctor.as_deref() {
+            Some("Date") | Some("RegExp") => return self.to_string(value),
+            _ => {}
+        }
+
+        if level > self.depth {
+            return format!(
+                "[{}]",
+                ctor.unwrap_or_else(|| if is_array { "Array" } else { "Object" }.to_owned())
+            );
+        }
+
+        self.seen.push(obj);
+
+        let entries = if is_array {
+            self.format_elements(value, level)
+        } else {
+            self.format_properties(value, level)
+        };
+
+        self.seen.pop();
+
+        let prefix = match ctor {
+            Some(ref ctor) if ctor != if is_array { "Array" } else { "Object" } => {
+                format!("{} ", ctor)
+            }
+            _ => String::new(),
+        };
+        let (open, close) = if is_array { ("[", "]") } else { ("{", "}") };
+
+        if entries.is_empty() {
+            return format!("{}{}{}", prefix, open, close);
+        }
+
+        let width = prefix.len() + entries.iter().map(|s| s.len() + 2).sum::<usize>() + 2;
+
+        if width <= LINE_WIDTH && entries.iter().all(|s| !s.contains('\n')) {
+            format!("{}{} {} {}", prefix, open, entries.join(", "), close)
+        } else {
+            let entries = entries
+                .iter()
+                .map(|s| format!("  {}", s.replace('\n', "\n  ")))
+                .collect::<Vec<_>>();
+
+            format!("{}{}\n{}\n{}", prefix, open, entries.join(",\n"), close)
+        }
+    }
+
+    fn format_elements(&mut self, value: &Value, level: usize) -> Vec<String> {
+        let len = self
+            .ctxt
+            .get_property(value, "length")
+            .and_then(|len| len.to_index())
+            .unwrap_or_default();
+
+        let mut elements = (0..len.min(MAX_ELEMENTS as u64) as u32)
+            .map(|idx| match self.ctxt.get_property(value, idx) {
+                Some(elem) => self.format_property(elem, level),
+                None => "undefined".to_owned(),
+            })
+            .collect::<Vec<_>>();
+
+        match len.saturating_sub(MAX_ELEMENTS as u64) {
+            0 => {}
+            1 => elements.push("... 1 more item".to_owned()),
+            n => elements.push(format!("... {} more items", n)),
+        }
+
+        elements
+    }
+
+    fn format_properties(&mut self, value: &Value, level: usize) -> Vec<String> {
+        let names = self
+            .ctxt
+            .get_own_property_names(value, Names::ENUM_ONLY | Names::STRING)
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+
+        names
+            .into_iter()
+            .map(|name| {
+                let key = name.to_string();
+                let key = if is_identifier(&key) {
+                    key
+                } else {
+                    quote(&key)
+                };
+                let value = match self.ctxt.get_property(value, name) {
+                    Some(value) => self.format_property(value, level),
+                    None => "undefined".to_owned(),
+                };
+
+                format!("{}: {}", key, value)
+            })
+            .collect()
+    }
+
+    fn format_property(&mut self, value: Local<Value>, level: usize) -> String {
+        if value.is_exception() {
+            // the getter has thrown an exception
+            let _ = self.ctxt.get_exception();
+
+            "[Exception]".to_owned()
+        } else {
+            self.format(&value, level + 1)
+        }
+    }
+
+    fn get_string(&self, value: &Value, prop: &str) -> Option<String> {
+        self.ctxt
+            .get_property(value, prop)
+            .filter(|s| s.is_string())
+            .map(|s| s.to_string())
+    }
+
+    fn to_string(&self, value: &Value) -> String {
+        self.ctxt
+            .to_cstring(value)
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+
+    matches!(chars.next(), Some(c) if c.is_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
+/// Quote the string with single quotes.
+fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+
+    quoted.push('\'');
+
+    for c in s.chars() {
+        match c {
+            '\'' => quoted.push_str("\\'"),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('\'');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, Eval, Runtime};
+
+    #[test]
+    fn inspect() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let inspect = |source: &str, depth| {
+            ctxt.eval_script(source, "<evalScript>", Eval::GLOBAL)
+                .unwrap()
+                .inspect(depth)
+        };
+
+        assert_eq!(inspect("'it\\'s'", 2), "'it\\'s'");
+        assert_eq!(inspect("Symbol('foo')", 2), "Symbol(foo)");
+        assert_eq!(
+            inspect("[1, 'a', null, undefined, [], {}, function foo() {}]", 2),
+            "[ 1, 'a', null, undefined, [], {}, [Function: foo] ]"
+        );
+        assert_eq!(
+            inspect(
+                "class Point { constructor() { this.x = 1; this.y = 2 } }; new Point()",
+                2
+            ),
+            "Point { x: 1, y: 2 }"
+        );
+        assert_eq!(
+            inspect(
+                "var obj = { a: { b: { c: {} } }, 'the key': 1 }; obj.self = obj; obj",
+                1
+            ),
+            "{ a: { b: [Object] }, 'the key': 1, self: [Circular] }"
+        );
+        assert_eq!(
+            inspect(
+                "({ name: 'a long long name', items: ['first item', 'second item', 'third item'] })",
+                2
+            ),
+            "{\n  name: 'a long long name',\n  items: [ 'first item', 'second item', 'third item' ]\n}"
+        );
+        assert!(inspect("new Array(1e9).fill(0, 0, 200)", 2)
+            .ends_with("  0,\n  ... 999999900 more items\n]"));
+    }
+}
@@ -0,0 +1,93 @@
+use crate::{Args, Atom, ContextRef, Error, Local, Value};
+
+/// The inline cache to invoke the method of the same name repeatedly, created by `ContextRef::cached_method`.
+///
+/// The atom of the name is created once instead of on every call.
+/// The method is still looked up on every call, which costs no more than validating a cached method
+/// through the public API of the engine, so the methods replaced or deleted on the prototype
+/// and the own properties shadowing the methods are always respected.
+pub struct CachedMethod<'a> {
+    atom: Atom<'a>,
+}
+
+impl ContextRef {
+    /// Create an inline cache to invoke the method of `name` repeatedly, like the script hooks of a host loop.
+    pub fn cached_method(&self, name: &str) -> CachedMethod {
+        CachedMethod {
+            atom: self.new_atom(name),
+        }
+    }
+}
+
+impl<'a> CachedMethod<'a> {
+    /// Returns the name of the method.
+    pub fn name(&self) -> &Atom<'a> {
+        &self.atom
+    }
+
+    /// Invoke the method of the object like `ContextRef::invoke`.
+    pub fn invoke<T: Args>(&self, this: &Value, args: T) -> Result<Local<'a, Value>, Error> {
+        self.atom.ctxt.invoke(this, &self.atom, args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, Eval, Runtime};
+
+    #[test]
+    fn cached_method() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let obj = ctxt
+            .eval_script(
+                r#"
+class Counter {
+    constructor() { this.n = 0 }
+    hook(step) { return this.n += step }
+}
+new Counter()
+"#,
+                "<evalScript>",
+                Eval::GLOBAL,
+            )
+            .unwrap();
+        let method = ctxt.cached_method("hook");
+        let hook = |step| method.invoke(&obj, step).unwrap().as_int().unwrap();
+
+        assert_eq!(method.name().to_string(), "hook");
+        assert_eq!(hook(1), 1);
+        assert_eq!(hook(2), 3);
+
+        ctxt.eval_script(
+            "Counter.prototype.hook = function (step) { return this.n -= step }",
+            "<evalScript>",
+            Eval::GLOBAL,
+        )
+        .unwrap();
+
+        assert_eq!(hook(1), 2);
+
+        obj.set_property(
+            "hook",
+            ctxt.eval_script("step => step * 10", "<evalScript>", Eval::GLOBAL)
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(hook(4), 40);
+
+        ctxt.eval_script(
+            "delete Counter.prototype.hook",
+            "<evalScript>",
+            Eval::GLOBAL,
+        )
+        .unwrap();
+        obj.delete_property("hook").unwrap();
+
+        assert!(method.invoke(&obj, 1).is_err());
+    }
+}
@@ -1,12 +1,79 @@
+use std::cell::Cell;
+use std::os::raw::c_int;
+use std::panic;
 use std::ptr;
+use std::slice;
 
-use failure::Error;
 use foreign_types::ForeignTypeRef;
 
-use crate::{ffi, value::ToBool, Args, ContextRef, RuntimeRef};
+use std::convert::TryFrom;
+
+use crate::{
+    ffi, value::ToBool, Args, ContextRef, Error, ErrorKind, Eval, Local, NewValue, RuntimeRef,
+    Value,
+};
 
 pub use ffi::JSJobFunc as JobFunc;
 
+const SETTLE: &str = r#"
+(promise, state) => Promise.resolve(promise).then(
+    value => { state.settled = true; state.value = value; },
+    error => { state.settled = true; state.rejected = true; state.error = error; })
+"#;
+
+unsafe extern "C" fn callback_job<F>(
+    ctx: *mut ffi::JSContext,
+    _argc: c_int,
+    argv: *mut ffi::JSValue,
+) -> ffi::JSValue
+where
+    F: FnOnce(&ContextRef) + Send + 'static,
+{
+    panic::catch_unwind(|| {
+        let ctxt = ContextRef::from_ptr(ctx);
+        let argv = slice::from_raw_parts(argv as *const Value, 1);
+
+        if let Some(f) = ctxt
+            .downcast_native::<Cell<Option<F>>>(&argv[0])
+            .and_then(Cell::take)
+        {
+            f(ctxt)
+        }
+
+        ffi::UNDEFINED
+    })
+    .unwrap_or_default()
+}
+
+/// Call the wrapped closure of the promise reaction with the settled value.
+unsafe extern "C" fn reaction<F, T>(
+    ctx: *mut ffi::JSContext,
+    _this_val: ffi::JSValue,
+    argc: c_int,
+    argv: *mut ffi::JSValue,
+    _magic: c_int,
+    data: *mut ffi::JSValue,
+) -> ffi::JSValue
+where
+    F: FnOnce(&ContextRef, &Value) -> T + Send + 'static,
+    T: NewValue,
+{
+    panic::catch_unwind(|| {
+        let ctxt = ContextRef::from_ptr(ctx);
+        let args = slice::from_raw_parts(argv as *const Value, argc as usize);
+        let value = args.first().unwrap_or_default();
+
+        match ctxt
+            .downcast_native::<Cell<Option<F>>>(&*(data as *const Value))
+            .and_then(Cell::take)
+        {
+            Some(f) => f(ctxt, value).new_value(ctxt),
+            None => ffi::UNDEFINED,
+        }
+    })
+    .unwrap_or_default()
+}
+
 impl RuntimeRef {
     pub fn is_job_pending(&self) -> bool {
         unsafe { ffi::JS_IsJobPending(self.as_ptr()).to_bool() }
@@ -14,6 +81,13 @@ impl RuntimeRef {
 
     pub fn execute_pending_job(&self) -> Result<Option<&ContextRef>, Error> {
         let mut ctxt = ptr::null_mut();
+        // the job is charged to its context, like the op budget
+        let next = unsafe { ffi::JS_GetPendingJobContext(self.as_ptr()) };
+        let _entered = if next.is_null() {
+            self.enter()
+        } else {
+            unsafe { ContextRef::from_ptr(next) }.enter()
+        };
 
         let ret = unsafe { ffi::JS_ExecutePendingJob(self.as_ptr(), &mut ctxt) };
 
@@ -27,19 +101,332 @@ impl RuntimeRef {
     }
 }
 
+/// A new promise with its resolving functions.
+pub struct PromiseCapability<'a> {
+    /// The pending promise.
+    pub promise: Local<'a, Value>,
+    /// The function to fulfill the promise.
+    pub resolve: Local<'a, Value>,
+    /// The function to reject the promise.
+    pub reject: Local<'a, Value>,
+}
+
 impl ContextRef {
+    /// Create a new pending promise with its resolving functions.
+    pub fn new_promise_capability(&self) -> Result<PromiseCapability, Error> {
+        let mut funcs = [ffi::UNDEFINED; 2];
+
+        let promise = self
+            .bind(unsafe { ffi::JS_NewPromiseCapability(self.as_ptr(), funcs.as_mut_ptr()) })
+            .ok()?;
+        let [resolve, reject] = funcs;
+
+        Ok(PromiseCapability {
+            promise,
+            resolve: self.bind(resolve),
+            reject: self.bind(reject),
+        })
+    }
+
+    /// Schedule the job function with the arguments into the job queue.
+    ///
+    /// The arguments are duplicated by `JS_EnqueueJob`, so the converted values are freed after enqueued,
+    /// and the caller keeps the ownership of the borrowed values.
     pub fn enqueue_job<T: Args>(&self, job_func: JobFunc, args: T) -> Result<(), Error> {
         let args = args.into_values(self);
         let args = args.as_ref();
 
-        self.check_error(unsafe {
+        let ret = unsafe {
             ffi::JS_EnqueueJob(
                 self.as_ptr(),
                 job_func,
                 args.len() as i32,
                 args.as_ptr() as *mut _,
             )
+        };
+
+        // the arguments are duplicated by the job queue
+        for arg in args {
+            self.free_value(*arg);
+        }
+
+        self.check_error(ret).map(|_| ())
+    }
+
+    /// Schedule the closure into the job queue, it runs after the current script like a promise reaction.
+    pub fn enqueue_callback<F>(&self, f: F) -> Result<(), Error>
+    where
+        F: FnOnce(&ContextRef) + Send + 'static,
+    {
+        let data = self.wrap_native(Cell::new(Some(f)))?;
+
+        self.enqueue_job(Some(callback_job::<F>), &data)
+    }
+}
+
+impl ContextRef {
+    /// Returns a promise fulfilled with the values when all of them are fulfilled, like `Promise.all`.
+    pub fn promise_all<I, V>(&self, values: I) -> Result<Local<Value>, Error>
+    where
+        I: IntoIterator<Item = V>,
+        V: NewValue,
+    {
+        self.promise_combinator("all", values)
+    }
+
+    /// Returns a promise settled as the first settled value, like `Promise.race`.
+    pub fn promise_race<I, V>(&self, values: I) -> Result<Local<Value>, Error>
+    where
+        I: IntoIterator<Item = V>,
+        V: NewValue,
+    {
+        self.promise_combinator("race", values)
+    }
+
+    fn promise_combinator<I, V>(&self, name: &str, values: I) -> Result<Local<Value>, Error>
+    where
+        I: IntoIterator<Item = V>,
+        V: NewValue,
+    {
+        let arr = self.bind(self.new_array());
+
+        for (idx, value) in values.into_iter().enumerate() {
+            arr.set_property(idx as u32, value)?;
+        }
+
+        let global = self.global_object();
+        let promise = global.get_property("Promise").ok_or("missing `Promise`")?;
+
+        self.invoke(&promise, name, &arr)
+    }
+
+    /// Create a function calling the closure once with the first argument.
+    fn new_reaction<F, T>(&self, f: F) -> Result<Local<Value>, Error>
+    where
+        F: FnOnce(&ContextRef, &Value) -> T + Send + 'static,
+        T: NewValue,
+    {
+        let data = self.wrap_native(Cell::new(Some(f)))?;
+
+        self.new_c_function_data(reaction::<F, T>, 1, 0, &data)
+    }
+}
+
+impl<'a> Local<'a, Value> {
+    /// Attach the closures to the promise, returns the chained promise like `Promise.prototype.then`.
+    ///
+    /// The chained promise is resolved with the result of the called closure,
+    /// or rejected if the closure returns an error.
+    pub fn then<F, E, T, U>(&self, on_ok: F, on_err: E) -> Result<Local<'a, Value>, Error>
+    where
+        F: FnOnce(&ContextRef, &Value) -> T + Send + 'static,
+        E: FnOnce(&ContextRef, &Value) -> U + Send + 'static,
+        T: NewValue,
+        U: NewValue,
+    {
+        let on_ok = self.ctxt.new_reaction(on_ok)?;
+        let on_err = self.ctxt.new_reaction(on_err)?;
+
+        self.ctxt.invoke(self, "then", (&on_ok, &on_err))
+    }
+
+    /// Run the pending jobs until the promise is settled, returns the fulfilled value or the rejected error.
+    ///
+    /// The value which is not a thenable object will be returned directly.
+    pub fn resolve_promise(self) -> Result<Local<'a, Value>, Error> {
+        self.ctxt.resolve_promise(self)
+    }
+}
+
+impl ContextRef {
+    /// Run the pending jobs until the promise is settled, returns the fulfilled value or the rejected error.
+    ///
+    /// The value which is not a thenable object will be returned directly.
+    pub fn resolve_promise<'a>(
+        &'a self,
+        value: Local<'a, Value>,
+    ) -> Result<Local<'a, Value>, Error> {
+        let thenable = value.is_object()
+            && value
+                .get_property("then")
+                .map_or(false, |then| then.is_function());
+
+        if !thenable {
+            return Ok(value);
+        }
+
+        let state = self.bind(self.new_object());
+
+        self.eval_script(SETTLE, "<promise>", Eval::GLOBAL)?
+            .call(None, (&value, &state))?;
+
+        let rt = self.runtime();
+
+        while state.get_property("settled").is_none() {
+            if !rt.is_job_pending() {
+                return Err("the promise is never settled".into());
+            }
+
+            rt.execute_pending_job()?;
+        }
+
+        if state.get_property("rejected").is_some() {
+            let err = state
+                .get_property("error")
+                .unwrap_or_else(|| self.undefined());
+
+            Err(ErrorKind::try_from(err)?.into())
+        } else {
+            Ok(self
+                .get_property(&state, "value")
+                .unwrap_or_else(|| self.undefined()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    use crate::{Context, ErrorKind, Eval, Runtime};
+
+    #[test]
+    fn resolve_promise() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let v = ctxt
+            .eval_script(
+                "(async () => (await Promise.resolve(40)) + 2)()",
+                "<evalScript>",
+                Eval::GLOBAL,
+            )
+            .unwrap()
+            .resolve_promise()
+            .unwrap();
+
+        assert_eq!(v.as_int(), Some(42));
+
+        let v = ctxt
+            .eval_script("'hello'", "<evalScript>", Eval::GLOBAL)
+            .unwrap()
+            .resolve_promise()
+            .unwrap();
+
+        assert_eq!(v.to_string(), "hello");
+
+        assert_eq!(
+            ctxt.eval_script("Promise.reject('Whoops!')", "<evalScript>", Eval::GLOBAL)
+                .unwrap()
+                .resolve_promise()
+                .unwrap_err()
+                .into_kind()
+                .unwrap(),
+            ErrorKind::Throw("Whoops!".into())
+        );
+
+        assert!(ctxt
+            .eval_script("new Promise(() => {})", "<evalScript>", Eval::GLOBAL)
+            .unwrap()
+            .resolve_promise()
+            .is_err());
+    }
+
+    #[test]
+    fn enqueue_callback() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+        let called = Arc::new(AtomicBool::new(false));
+
+        let flag = called.clone();
+        ctxt.enqueue_callback(move |ctxt| {
+            flag.store(true, Ordering::SeqCst);
+
+            ctxt.eval_script("order.push('callback')", "<evalScript>", Eval::GLOBAL)
+                .unwrap();
         })
-        .map(|_| ())
+        .unwrap();
+
+        ctxt.eval_script(
+            "var order = []; Promise.resolve().then(() => order.push('promise')); order.push('script')",
+            "<evalScript>",
+            Eval::GLOBAL,
+        )
+        .unwrap();
+
+        assert!(!called.load(Ordering::SeqCst));
+
+        while rt.execute_pending_job().unwrap().is_some() {}
+
+        assert!(called.load(Ordering::SeqCst));
+        assert_eq!(
+            ctxt.eval::<_, String>("order.join()", Eval::GLOBAL)
+                .unwrap(),
+            Some("script,callback,promise".to_owned())
+        );
+    }
+
+    #[test]
+    fn promise_combinators() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+        let eval = |s| ctxt.eval_script(s, "<evalScript>", Eval::GLOBAL).unwrap();
+
+        let all = ctxt
+            .promise_all(vec![eval("Promise.resolve(1)"), eval("2")])
+            .unwrap()
+            .then(
+                |ctxt, v| {
+                    ctxt.invoke(v, "join", "+")
+                        .map(|s| s.to_string())
+                        .unwrap_or_default()
+                },
+                |_, _| "failed".to_owned(),
+            )
+            .unwrap()
+            .resolve_promise()
+            .unwrap();
+
+        assert_eq!(all.to_string(), "1+2");
+
+        let race = ctxt
+            .promise_race(vec![
+                eval("new Promise(() => {})"),
+                eval("Promise.resolve('fast')"),
+            ])
+            .unwrap()
+            .resolve_promise()
+            .unwrap();
+
+        assert_eq!(race.to_string(), "fast");
+
+        let recovered = Arc::new(AtomicBool::new(false));
+        let flag = recovered.clone();
+
+        let v = eval("Promise.reject(new Error('Whoops!'))")
+            .then(
+                |_, _| 0,
+                move |ctxt, err| {
+                    flag.store(true, Ordering::SeqCst);
+
+                    ctxt.get_property(err, "message")
+                        .map_or(0, |msg| msg.to_string().len() as i32)
+                },
+            )
+            .unwrap()
+            .resolve_promise()
+            .unwrap();
+
+        assert!(recovered.load(Ordering::SeqCst));
+        assert_eq!(v.as_int(), Some(7));
     }
 }
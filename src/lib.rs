@@ -2,7 +2,7 @@
 //!
 //! # Examples
 //!
-//! `qjs` macro can evalute the Javascript code in an anonymouse context.
+//! `qjs` macro can evalute the Javascript code in an anonymous context.
 //!
 //! ```
 //! use qjs::qjs;
@@ -12,6 +12,10 @@
 //! assert_eq!(v, 3);
 //! ```
 //!
+//! The anonymous context is checked out from a thread-local `ContextPool` to amortize the engine startup,
+//! the global properties added by the script will be deleted before the context is reused,
+//! but it is not an isolation, see `ContextPool` for the details.
+//!
 //! `qjs` macro can also convert a Javascript closure to a rust function.
 //!
 //! ```
@@ -23,6 +27,20 @@
 //! assert_eq!(s, "hello world");
 //! ```
 //!
+//! The returned promise will be resolved by running the pending jobs, and the script with `await` runs in an async function.
+//!
+//! ```
+//! use qjs::qjs;
+//!
+//! let v: i32 = qjs!{ let n = await Promise.resolve(1); n + 2 }.unwrap().unwrap();
+//!
+//! assert_eq!(v, 3);
+//!
+//! let f = qjs!{ (n: i32) -> i32 => { return await Promise.resolve(n + 1); } };
+//!
+//! assert_eq!(f(2).unwrap().unwrap(), 3);
+//! ```
+//!
 //! Variable interpolation is done with `#var` (similar to `$var` in `macro_rules!` macros).
 //! This grabs the var variable that is currently in scope and inserts it in that location in the output tokens.
 //!
@@ -37,6 +55,34 @@
 //! assert_eq!(s, "hello world");
 //! ```
 //!
+//! The struct field could be interpolated with `#var.field`, which access the property of the converted object.
+//! The variable interpolated more than once will only be converted once.
+//!
+//! ```
+//! use qjs::{ffi, qjs, ContextRef, NewValue};
+//!
+//! struct Person {
+//!     name: &'static str,
+//!     age: i32,
+//! }
+//!
+//! impl NewValue for Person {
+//!     fn new_value(self, ctxt: &ContextRef) -> ffi::JSValue {
+//!         let obj = ctxt.new_object();
+//!
+//!         ctxt.set_property(&obj, "name", self.name).unwrap();
+//!         ctxt.set_property(&obj, "age", self.age).unwrap();
+//!
+//!         obj.raw()
+//!     }
+//! }
+//!
+//! let person = Person { name: "Alice", age: 18 };
+//! let s: String = qjs!{ #person.name + " is " + #person.age }.unwrap().unwrap();
+//!
+//! assert_eq!(s, "Alice is 18");
+//! ```
+//!
 //! The primitive types, including `bool`, `i32`, `i64`, `u64`, `f64`, `String` etc,
 //! and other type which implements `NewValue` trait could be used in the variable interpolation.
 //!
@@ -55,11 +101,38 @@
 //!
 //! // assert_eq!(s, "hello world");
 //! ```
+//!
+//! `qjs_compile` macro can compile a Javascript file to bytecode at build time,
+//! the embedded bytecode could be executed without parsing at startup.
+//!
+//! ```
+//! use qjs::{qjs_compile, Context, Runtime};
+//!
+//! let bytecode: &[u8] = qjs_compile!("examples/hello.js");
+//!
+//! let rt = Runtime::new();
+//! let ctxt = Context::new(&rt);
+//!
+//! assert_eq!(ctxt.eval_binary(bytecode, false).unwrap().to_string(), "hello world");
+//! ```
+//!
+//! `qjs_bundle` macro can compile a module with its relative imports to bytecode at build time.
+//!
+//! ```
+//! use qjs::{qjs_bundle, Context, Eval, Runtime};
+//!
+//! let bundle: &[(&str, &[u8])] = qjs_bundle!("examples/modules/main.mjs");
+//!
+//! let rt = Runtime::new();
+//! let ctxt = Context::new(&rt);
+//!
+//! ctxt.eval_bundle(bundle).unwrap();
+//!
+//! assert_eq!(ctxt.eval::<_, String>("message", Eval::GLOBAL).unwrap().unwrap(), "hello world");
+//! ```
 #[macro_use]
 extern crate log;
 #[macro_use]
-extern crate failure;
-#[macro_use]
 extern crate bitflags;
 #[macro_use]
 extern crate foreign_types;
@@ -70,51 +143,154 @@ extern crate cstr;
 
 pub use qjs_sys as ffi;
 
+// the derive macros refer to the crate as `qjs`
+#[cfg(test)]
+extern crate self as qjs;
+
 use proc_macro_hack::proc_macro_hack;
 #[proc_macro_hack]
 pub use qjs_derive::qjs;
+#[proc_macro_hack]
+pub use qjs_derive::qjs_bundle;
+#[proc_macro_hack]
+pub use qjs_derive::qjs_compile;
+pub use qjs_derive::{ExtractValue, NewValue};
 
 #[macro_use]
 mod macros;
+mod accessor;
+mod array;
 mod arraybuf;
+mod async_iter;
 mod atom;
+mod budget;
 mod cfunc;
 mod class;
+mod console;
 mod context;
+#[cfg(feature = "crypto")]
+mod crypto;
+mod deterministic;
+mod diagnostics;
+mod disasm;
+mod env;
 mod error;
 mod eval;
+#[cfg(feature = "http")]
+mod fetch;
 mod func;
+mod generator;
+mod globals;
 mod handle;
+mod import_hook;
+mod inspect;
+mod invoke_cache;
 mod job;
+mod limits;
+mod memo;
 mod module;
+mod path;
+mod pin;
+mod pipeline;
+mod pool;
 mod precompile;
+mod profile;
 mod prop;
+#[cfg(feature = "protobuf")]
+mod protobuf;
+mod proxy;
+#[cfg(feature = "repl")]
+mod repl;
 mod runtime;
+mod scope;
+mod shutdown;
+mod snapshot;
+mod sourcemap;
+mod stack;
 #[cfg(feature = "stdlib")]
 mod stdlib;
+mod storage;
+mod string;
+mod symbol;
+mod thread;
+mod timers;
 mod userdata;
 mod value;
+#[cfg(feature = "stdlib")]
+mod vfs;
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(feature = "watch")]
+mod watch;
+#[cfg(feature = "web-compat")]
+mod web;
 
-pub use arraybuf::{ArrayBuffer, SharedArrayBuffer};
+pub use array::{Elements, JsArray};
+pub use arraybuf::{ArrayBuffer, SharedArrayBuffer, SharedMemory, TypedArray, TypedArrayType};
+pub use async_iter::AsyncIter;
 pub use atom::{Atom, NewAtom};
 pub use cfunc::{CFunc, CFunction, UnsafeCFunction, UnsafeCFunctionData, UnsafeCFunctionMagic};
-pub use class::{ClassDef, ClassId};
+pub use class::{Class, ClassBuilder, ClassDef, ClassId};
+pub use console::{Console, LogConsole};
 pub use context::{Builder as ContextBuilder, Context, ContextRef};
-pub use error::ErrorKind;
-pub use eval::{eval, load_file, Eval, Source};
-pub use func::Args;
+pub use diagnostics::Diagnostics;
+pub use disasm::{Constant, Function as DisasmFunction, Instruction, Operand};
+pub use env::EnvFilter;
+pub use error::{Error, ErrorKind, Exception, JsException};
+pub use eval::{eval, load_file, Eval, EvalOptions, Source, SourceKind};
+#[cfg(feature = "http")]
+pub use fetch::Fetch;
+pub use func::{Args, JsFunction};
+pub use generator::JsGenerator;
+pub use globals::Globals;
 pub use handle::{Bindable, Local, Unbindable};
-pub use job::JobFunc;
-pub use module::{detect_module, ModuleDef, ModuleInitFunc, ModuleLoaderFunc, ModuleNormalizeFunc};
+pub use invoke_cache::CachedMethod;
+pub use job::{JobFunc, PromiseCapability};
+pub use limits::Limits;
+pub use module::{
+    detect_module, Module, ModuleDef, ModuleInitFunc, ModuleLoaderBuilder, ModuleLoaderFunc,
+    ModuleNormalizeFunc, ModuleStatus,
+};
+pub use path::{PathError, PathSegment};
+pub use pin::ValueId;
+pub use pipeline::{AwaitPromise, Pipeline, PostProcessor, UnwrapEnvelope};
+pub use pool::{clear_context_pool, with_context, ContextPool};
 pub use precompile::{ReadObj, WriteObj};
+pub use profile::EvalStats;
 pub use prop::{
     DefinePropertyGetSet, DefinePropertyValue, DeleteProperty, Descriptor as PropertyDescriptor,
-    GetProperty, HasProperty, Names as PropertyNames, Prop, SetProperty,
+    GetProperty, HasProperty, Names as PropertyNames, Prop, SetFailure, SetProperty,
+};
+pub use proxy::ProxyHandler;
+#[cfg(feature = "repl")]
+pub use repl::{ReplOutput, Session as ReplSession};
+pub use runtime::{
+    Interrupt, InterruptHandler, InterruptToken, LeakReport, MallocFunctions, MemoryUsage, Runtime,
+    RuntimeInfo, RuntimeRef,
 };
-pub use runtime::{Interrupt, InterruptHandler, MallocFunctions, MemoryUsage, Runtime, RuntimeRef};
+#[cfg(feature = "leak-check")]
+pub use scope::live_locals;
+pub use scope::Scope;
+pub use shutdown::ShutdownReport;
+pub use snapshot::Snapshot;
+pub use sourcemap::SourceMap;
+pub use stack::{format_stack, parse_stack, StackFormat, StackFrame, StackTrace};
+#[cfg(feature = "stdlib")]
+pub use stdlib::OsCapabilities;
+pub use storage::Storage;
+pub use string::JsStr;
+pub use symbol::WellKnownSymbol;
+pub use userdata::Opaque;
 pub use value::{
-    ExtractValue, NewValue, Value, EXCEPTION, FALSE, NAN, NULL, TRUE, UNDEFINED, UNINITIALIZED,
+    ExtractValue, Int64Policy, NewValue, Value, EXCEPTION, FALSE, NAN, NULL, TRUE, UNDEFINED,
+    UNINITIALIZED,
 };
+#[cfg(feature = "stdlib")]
+pub use vfs::{MemoryFs, VfsHandler};
+#[cfg(feature = "wasm")]
+pub use wasm::WasmModules;
+#[cfg(feature = "watch")]
+pub use watch::Watcher;
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
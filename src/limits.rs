@@ -0,0 +1,66 @@
+use crate::{ContextRef, Error};
+
+/// The limits of the recursive conversions between Rust and Javascript values.
+///
+/// The scripts may return the deep, cyclic or huge structures,
+/// the conversion fails with `Error::Limit` instead of overflowing the stack or running out of memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// The maximum nesting depth of the objects and arrays.
+    pub max_depth: usize,
+    /// The maximum number of the elements of an array, or the entries of an object.
+    pub max_elements: usize,
+    /// The maximum length of a string or a byte buffer in bytes.
+    pub max_string_bytes: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_depth: 64,
+            max_elements: 1 << 20,
+            max_string_bytes: 16 << 20,
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "protobuf"), allow(dead_code))]
+impl Limits {
+    pub(crate) fn check_depth(&self, depth: usize) -> Result<(), Error> {
+        if depth > self.max_depth {
+            Err(Error::Limit("depth", self.max_depth))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub(crate) fn check_elements(&self, len: usize) -> Result<(), Error> {
+        if len > self.max_elements {
+            Err(Error::Limit("elements", self.max_elements))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub(crate) fn check_string_bytes(&self, len: usize) -> Result<(), Error> {
+        if len > self.max_string_bytes {
+            Err(Error::Limit("string bytes", self.max_string_bytes))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl ContextRef {
+    /// Returns the limits of the recursive conversions.
+    pub fn conversion_limits(&self) -> Limits {
+        self.with_state(|state| state.limits)
+    }
+
+    /// Set the limits of the recursive conversions.
+    pub fn set_conversion_limits(&self, limits: Limits) {
+        trace!("set conversion limits {:?}", limits);
+
+        self.with_state(|state| state.limits = limits)
+    }
+}
@@ -0,0 +1,173 @@
+use std::os::raw::c_int;
+use std::panic;
+use std::slice;
+
+use foreign_types::ForeignTypeRef;
+use lru::LruCache;
+
+use crate::{cfunc::c_args, ffi, ContextRef, Error, Local, Value, NULL};
+
+/// The primitive argument of the memoized function.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Key {
+    Undefined,
+    Null,
+    Bool(bool),
+    Int(i32),
+    Float(u64),
+    String(String),
+}
+
+impl Key {
+    fn new(ctxt: &ContextRef, arg: &Value) -> Option<Key> {
+        match arg.tag() {
+            ffi::JS_TAG_UNDEFINED => Some(Key::Undefined),
+            ffi::JS_TAG_NULL => Some(Key::Null),
+            ffi::JS_TAG_BOOL => arg.as_bool().map(Key::Bool),
+            ffi::JS_TAG_INT => arg.as_int().map(Key::Int),
+            ffi::JS_TAG_FLOAT64 => arg.as_float().map(|n| Key::Float(n.to_bits())),
+            ffi::JS_TAG_STRING => ctxt
+                .to_cstring(arg)
+                .map(|s| Key::String(s.to_string_lossy().into_owned())),
+            _ => None,
+        }
+    }
+}
+
+/// The LRU cache of the memoized function.
+///
+/// The cached values are kept in a Javascript object which is traced by GC,
+/// the cache only records the property names in the LRU order.
+struct Memo {
+    cache: LruCache<Vec<Key>, String>,
+}
+
+unsafe extern "C" fn memoized(
+    ctx: *mut ffi::JSContext,
+    this_val: ffi::JSValue,
+    argc: c_int,
+    argv: *mut ffi::JSValue,
+    _magic: c_int,
+    data: *mut ffi::JSValue,
+) -> ffi::JSValue {
+    panic::catch_unwind(|| {
+        let ctxt = ContextRef::from_ptr(ctx);
+        let data = slice::from_raw_parts(data as *const Value, 3);
+        let (func, store) = (&data[1], &data[2]);
+        let memo = ctxt.get_userdata_unchecked::<Memo>(&data[0]).as_mut();
+        let args = c_args(argc, argv);
+        let key = args
+            .iter()
+            .map(|arg| Key::new(ctxt, arg))
+            .collect::<Option<Vec<_>>>();
+
+        if let Some(name) = key.as_ref().and_then(|key| memo.cache.get(key)) {
+            trace!("memoized function hit {}", name);
+
+            return ctxt
+                .get_property(store, name.as_str())
+                .map_or(ffi::UNDEFINED, Into::into);
+        }
+
+        let ret = ffi::JS_Call(ctx, func.raw(), this_val, argc, argv);
+
+        if let Some(key) = key {
+            let ret = Value::from(ret);
+
+            if !ret.is_exception() {
+                if memo.cache.len() == memo.cache.cap() {
+                    if let Some((_, name)) = memo.cache.pop_lru() {
+                        trace!("memoized function evict {}", name);
+
+                        let _ = ctxt.delete_property(store, name.as_str());
+                    }
+                }
+
+                let name = format!("{:?}", key);
+
+                trace!("memoized function cache {}", name);
+
+                let _ = ctxt.set_property(store, name.as_str(), &ret);
+
+                memo.cache.put(key, name);
+            }
+        }
+
+        ret
+    })
+    .unwrap_or_default()
+}
+
+impl<'a> Local<'a, Value> {
+    /// Wrap the function with a LRU cache which keyed by the primitive arguments.
+    pub fn memoize(&self, capacity: usize) -> Result<Local<'a, Value>, Error> {
+        self.ctxt.memoize(self, capacity)
+    }
+}
+
+impl ContextRef {
+    /// Wrap the function with a LRU cache which keyed by the primitive arguments.
+    ///
+    /// The cached value will be returned if the function was called with the same primitive arguments,
+    /// otherwise, the calls with the non-primitive arguments (object, symbol etc) are always forwarded to the function.
+    pub fn memoize(&self, func: &Value, capacity: usize) -> Result<Local<Value>, Error> {
+        if !self.is_function(func) {
+            return Err("memoize a non-function value".into());
+        }
+
+        trace!("memoize function with {} capacity", capacity);
+
+        let memo = self.new_userdata(Memo {
+            cache: LruCache::new(capacity.max(1)),
+        });
+        let store = self.new_object_proto(&NULL);
+
+        self.new_c_function_data(memoized, 0, 0, (memo, func, store))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, Eval, Runtime};
+
+    #[test]
+    fn memoize() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let lookup = ctxt
+            .eval_script(
+                "var calls = 0; (function lookup(a, b) { calls++; return { sum: a + b }; })",
+                "<evalScript>",
+                Eval::GLOBAL,
+            )
+            .unwrap();
+        let global = ctxt.global_object();
+
+        global
+            .set_property("lookup", lookup.memoize(2).unwrap())
+            .unwrap();
+
+        let calls = |script: &str| -> i32 {
+            ctxt.eval_script(script, "<evalScript>", Eval::GLOBAL)
+                .unwrap();
+            ctxt.eval("calls", Eval::GLOBAL).unwrap().unwrap()
+        };
+
+        assert_eq!(calls("lookup(1, 2); lookup(1, 2)"), 1);
+        assert_eq!(calls("lookup(1, 2) === lookup(1, 2)"), 1);
+        assert_eq!(calls("lookup('a', 'b').sum == 'ab'"), 2);
+        assert_eq!(calls("lookup(1.5, 2)"), 3);
+        assert_eq!(calls("lookup('a', 'b')"), 3);
+        assert_eq!(calls("lookup(1, 2)"), 4);
+        assert_eq!(calls("lookup({}, 2); lookup({}, 2)"), 6);
+
+        assert_eq!(
+            ctxt.eval::<_, bool>("lookup(3, 4) === lookup(3, 4)", Eval::GLOBAL)
+                .unwrap(),
+            Some(true)
+        );
+    }
+}
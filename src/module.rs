@@ -1,10 +1,14 @@
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
+use std::fs;
+use std::os::raw::{c_char, c_void};
+use std::panic;
 use std::ptr::{null_mut, NonNull};
 
-use failure::Error;
 use foreign_types::ForeignTypeRef;
 
-use crate::{ffi, value::ToBool, Atom, ContextRef, Local, RuntimeRef, Value};
+use crate::{
+    ffi, prop::Names, value::ToBool, Atom, ContextRef, Error, Eval, Local, RuntimeRef, Value,
+};
 
 /// The C module definition.
 pub type ModuleDef = ffi::JSModuleDef;
@@ -37,6 +41,184 @@ impl RuntimeRef {
     }
 }
 
+/// The native module init function exported by the shared library as `js_init_module`.
+#[cfg(feature = "dlopen")]
+type NativeModuleInitFunc =
+    unsafe extern "C" fn(ctx: *mut ffi::JSContext, module_name: *const c_char) -> *mut ModuleDef;
+
+/// The configuration of the module loader installed by `ModuleLoaderBuilder`.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ModuleLoader {
+    allow_native: bool,
+}
+
+/// Build a module loader which loads the Javascript modules from the files.
+#[derive(Clone, Debug, Default)]
+pub struct ModuleLoaderBuilder {
+    loader: ModuleLoader,
+}
+
+impl ModuleLoaderBuilder {
+    /// Construct a new `ModuleLoaderBuilder`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow to load the native QuickJS modules from the shared libraries (`.so`, `.dylib` or `.dll`).
+    ///
+    /// The shared library must export a `js_init_module` function, like the quickjs-libc modules.
+    /// It will be loaded by `dlopen` and never be unloaded, which requires the `dlopen` feature.
+    pub fn allow_native(mut self, allow: bool) -> Self {
+        self.loader.allow_native = allow;
+        self
+    }
+
+    /// Install the module loader to the runtime.
+    pub fn build(self, rt: &RuntimeRef) {
+        trace!("{:?} set module loader: {:?}", rt, self.loader);
+
+        rt.with_state(|state| state.module_loader = Some(self.loader));
+        rt.set_module_loader::<()>(None, Some(load_module), None);
+    }
+}
+
+fn is_native_module(name: &str) -> bool {
+    [".so", ".dylib", ".dll"]
+        .iter()
+        .any(|ext| name.ends_with(ext))
+}
+
+pub(crate) unsafe extern "C" fn load_module(
+    ctx: *mut ffi::JSContext,
+    module_name: *const c_char,
+    _opaque: *mut c_void,
+) -> *mut ModuleDef {
+    panic::catch_unwind(|| {
+        let ctxt = ContextRef::from_ptr(ctx);
+        let name = CStr::from_ptr(module_name).to_string_lossy();
+        let loader = ctxt
+            .runtime()
+            .with_state(|state| state.module_loader.clone())
+            .unwrap_or_default();
+
+        trace!("load module `{}`", name);
+
+        let res = match ctxt.runtime().import_hook().map(|hook| hook(ctxt, &name)) {
+            Some(Ok(Some(source))) => ctxt.compile_module(&name, source, false),
+            Some(Err(err)) => Err(err),
+            _ => ctxt.load_file_module(&name, &loader),
+        };
+
+        res.unwrap_or_else(|err| {
+            ctxt.throw_custom_error("ReferenceError", err, None);
+
+            null_mut()
+        })
+    })
+    .unwrap_or(null_mut())
+}
+
+impl ContextRef {
+    fn load_file_module(&self, name: &str, loader: &ModuleLoader) -> Result<*mut ModuleDef, Error> {
+        if is_native_module(name) {
+            if loader.allow_native {
+                self.load_native_module(name)
+            } else {
+                Err(format!(
+                    "could not load module filename '{}': native module is not allowed",
+                    name
+                )
+                .into())
+            }
+        } else {
+            self.load_script_module(name)
+        }
+    }
+
+    fn load_script_module(&self, name: &str) -> Result<*mut ModuleDef, Error> {
+        #[cfg(feature = "watch")]
+        let filename = crate::watch::module_filename(name);
+        #[cfg(not(feature = "watch"))]
+        let filename = name;
+
+        let source = fs::read(filename)
+            .map_err(|err| format!("could not load module filename '{}', {}", name, err))?;
+
+        #[cfg(feature = "watch")]
+        crate::watch::touch_module(self.runtime(), filename);
+
+        // the generation suffix of a watched module will be kept in the `import.meta.url` as fragment
+        self.compile_module(name, source, filename == name)
+    }
+
+    fn compile_module<T: Into<Vec<u8>>>(
+        &self,
+        name: &str,
+        source: T,
+        use_realpath: bool,
+    ) -> Result<*mut ModuleDef, Error> {
+        let func = self.eval_script(source, name, Eval::MODULE | Eval::COMPILE_ONLY)?;
+
+        self.set_import_meta(&func, use_realpath, false)?;
+
+        Ok(func.as_ptr::<ModuleDef>().as_ptr())
+    }
+
+    #[cfg(not(feature = "dlopen"))]
+    fn load_native_module(&self, name: &str) -> Result<*mut ModuleDef, Error> {
+        Err(format!(
+            "could not load module filename '{}': native module is not supported, the `dlopen` feature is disabled",
+            name
+        )
+        .into())
+    }
+
+    #[cfg(feature = "dlopen")]
+    fn load_native_module(&self, name: &str) -> Result<*mut ModuleDef, Error> {
+        // the dynamic linker searches the library path for a filename without slash
+        let filename = if name.contains('/') {
+            name.to_owned()
+        } else {
+            format!("./{}", name)
+        };
+        let lib = libloading::Library::new(&filename).map_err(|err| {
+            format!(
+                "could not load module filename '{}' as shared library, {}",
+                name, err
+            )
+        })?;
+        let module_name = CString::new(name)?;
+
+        let module = unsafe {
+            let init = lib
+                .get::<NativeModuleInitFunc>(b"js_init_module\0")
+                .map_err(|err| {
+                    format!(
+                        "could not load module filename '{}': js_init_module not found, {}",
+                        name, err
+                    )
+                })?;
+
+            init(self.as_ptr(), module_name.as_ptr())
+        };
+
+        if module.is_null() {
+            Err(format!(
+                "could not load module filename '{}': initialization error",
+                name
+            )
+            .into())
+        } else {
+            trace!("loaded native module `{}` @ {:p}", name, module);
+
+            // the module functions may be referenced until the runtime is freed
+            std::mem::forget(lib);
+
+            Ok(module)
+        }
+    }
+}
+
 /// return true if `input` contains the source of a module (heuristic).
 ///
 /// Heuristic: skip comments and expect 'import' keyword not followed by '(' or '.'
@@ -62,6 +244,26 @@ impl ContextRef {
         })
     }
 
+    /// Replace the export of the C module, which is only allowed when the module is initializing.
+    pub(crate) fn set_module_export(
+        &self,
+        m: NonNull<ModuleDef>,
+        name: &str,
+        value: Local<Value>,
+    ) -> Result<(), Error> {
+        let name = CString::new(name)?;
+
+        self.check_error(unsafe {
+            ffi::JS_SetModuleExport(
+                self.as_ptr(),
+                m.as_ptr(),
+                name.as_ptr(),
+                value.into_inner().raw(),
+            )
+        })
+        .map(|_| ())
+    }
+
     /// return the name of a module
     pub fn module_name(&self, module: &ModuleDef) -> Atom {
         self.bind_atom(unsafe {
@@ -76,6 +278,8 @@ impl ContextRef {
     }
 
     /// set the `import.meta` object of a module
+    ///
+    /// The `import.meta` hook of the runtime will be called after the `url` and `main` properties are set.
     pub fn set_import_meta(
         &self,
         module: &Value,
@@ -89,8 +293,12 @@ impl ContextRef {
                 use_realpath.to_bool(),
                 is_main.to_bool(),
             )
-        })
-        .map(|_| ())
+        })?;
+
+        match self.runtime().import_meta_hook() {
+            Some(hook) => hook(self, unsafe { module.as_ptr::<ModuleDef>().as_ref() }),
+            None => Ok(()),
+        }
     }
 
     /// load the dependencies of the module 'obj'.
@@ -101,3 +309,275 @@ impl ContextRef {
             .map(|_| ())
     }
 }
+
+/// The evaluated ES module, created by `ContextRef::eval_module`.
+pub struct Module<'a> {
+    ctxt: &'a ContextRef,
+    def: NonNull<ModuleDef>,
+    ns: Local<'a, Value>,
+    status: ModuleStatus,
+}
+
+/// The status of the evaluated module, returned by `Module::status`.
+#[derive(Debug)]
+pub enum ModuleStatus {
+    /// The module was evaluated.
+    Evaluated,
+    /// The evaluation promise was rejected, e.g. interrupted.
+    ///
+    /// The module body was evaluated, so the namespace is still available.
+    Failed(Error),
+}
+
+impl ContextRef {
+    /// Returns the namespace object of the module, which must have been evaluated.
+    pub fn get_module_namespace(&self, module: &Value) -> Result<Local<Value>, Error> {
+        if module.is_module() {
+            self.module_namespace(module.as_ptr())
+        } else {
+            Err("not a module".into())
+        }
+    }
+
+    fn module_namespace(&self, module: NonNull<ModuleDef>) -> Result<Local<Value>, Error> {
+        self.bind(unsafe { ffi::JS_GetModuleNamespace(self.as_ptr(), module.as_ptr()) })
+            .ok()
+    }
+
+    /// Evaluate the module source, the exports can be accessed from the returned `Module`.
+    ///
+    /// If the evaluation returns a promise, the pending jobs are executed until it is settled,
+    /// and the rejection is reported by `Module::status` instead of an error.
+    /// The other pending jobs, e.g. the `then` callbacks queued by the module, are left to the caller.
+    pub fn eval_module<T: Into<Vec<u8>>>(&self, input: T, filename: &str) -> Result<Module, Error> {
+        let func = self.eval_script(input, filename, Eval::MODULE | Eval::COMPILE_ONLY)?;
+        let def = func.as_ptr();
+
+        self.resolve_module(&func)?;
+        self.set_import_meta(&func, false, true)?;
+
+        // the module is kept alive by the context after evaluated
+        let res = self.eval_function(func)?;
+        // the engines with the top-level await return a promise
+        let status = match self.resolve_promise(res) {
+            Ok(_) => ModuleStatus::Evaluated,
+            Err(err) => ModuleStatus::Failed(err),
+        };
+
+        trace!("module `{}` {:?}", filename, status);
+
+        Ok(Module {
+            ctxt: self,
+            def,
+            ns: self.module_namespace(def)?,
+            status,
+        })
+    }
+}
+
+impl<'a> Module<'a> {
+    /// Returns the name of the module.
+    pub fn name(&self) -> Atom<'a> {
+        self.ctxt.module_name(unsafe { self.def.as_ref() })
+    }
+
+    /// Returns the namespace object of the module.
+    pub fn namespace(&self) -> &Local<'a, Value> {
+        &self.ns
+    }
+
+    /// Returns the status of the module evaluation.
+    pub fn status(&self) -> &ModuleStatus {
+        &self.status
+    }
+
+    /// Returns the exported value of the name.
+    pub fn get(&self, name: &str) -> Option<Local<'a, Value>> {
+        self.ctxt.get_property(&self.ns, name)
+    }
+
+    /// Returns the names and values of the exports, in the order of names.
+    pub fn exports(&self) -> Result<Vec<(String, Local<'a, Value>)>, Error> {
+        let names = self
+            .ctxt
+            .get_own_property_names(&self.ns, Names::STRING | Names::ENUM_ONLY)?
+            .unwrap_or_default();
+
+        Ok(names
+            .into_iter()
+            .map(|name| {
+                let key = name.to_string();
+                let value = self
+                    .ctxt
+                    .get_property(&self.ns, name)
+                    .unwrap_or_else(|| self.ctxt.undefined());
+
+                (key, value)
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::os::raw::c_int;
+
+    use crate::{Context, ErrorKind, Eval, Runtime};
+
+    use super::*;
+
+    #[test]
+    fn module_loader() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        ModuleLoaderBuilder::new().build(&rt);
+
+        let mut file = tempfile::Builder::new().suffix(".js").tempfile().unwrap();
+
+        write!(file, "export const answer = 42;").unwrap();
+
+        ctxt.eval_script(
+            format!(
+                "import {{ answer }} from '{}'; globalThis.answer = answer;",
+                file.path().display()
+            ),
+            "<evalScript>",
+            Eval::MODULE,
+        )
+        .unwrap();
+
+        assert_eq!(
+            ctxt.eval::<_, i32>("answer", Eval::GLOBAL).unwrap(),
+            Some(42)
+        );
+
+        assert_eq!(
+            ctxt.eval_script("import 'libfoo.so';", "<evalScript>", Eval::MODULE)
+                .unwrap_err()
+                .into_kind()
+                .unwrap(),
+            ErrorKind::ReferenceError(
+                "could not load module filename 'libfoo.so': native module is not allowed".into(),
+                None
+            )
+        );
+
+        ModuleLoaderBuilder::new().allow_native(true).build(&rt);
+
+        let err = ctxt
+            .eval_script("import 'libfoo.so';", "<evalScript>", Eval::MODULE)
+            .unwrap_err()
+            .into_kind()
+            .unwrap();
+
+        assert!(matches!(
+            err,
+            ErrorKind::ReferenceError(ref msg, _) if msg.contains(if cfg!(feature = "dlopen") {
+                "as shared library"
+            } else {
+                "the `dlopen` feature is disabled"
+            })
+        ));
+    }
+
+    unsafe extern "C" fn failing_job(
+        ctx: *mut ffi::JSContext,
+        _argc: c_int,
+        _argv: *mut ffi::JSValue,
+    ) -> ffi::JSValue {
+        ContextRef::from_ptr(ctx)
+            .throw("job failed")
+            .into_inner()
+            .raw()
+    }
+
+    #[test]
+    fn module_exports() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let module = ctxt
+            .eval_module(
+                "export const version = '1.0'; export function add(a, b) { return a + b }",
+                "math.mjs",
+            )
+            .unwrap();
+
+        assert_eq!(module.name().to_string(), "math.mjs");
+        assert!(matches!(module.status(), ModuleStatus::Evaluated));
+        assert_eq!(
+            module
+                .get("add")
+                .unwrap()
+                .call(None, (1, 2))
+                .unwrap()
+                .as_int(),
+            Some(3)
+        );
+        assert_eq!(
+            module
+                .exports()
+                .unwrap()
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>(),
+            vec!["add", "version"]
+        );
+
+        let func = ctxt
+            .eval_script(
+                "export let n = 1",
+                "n.mjs",
+                Eval::MODULE | Eval::COMPILE_ONLY,
+            )
+            .unwrap();
+
+        assert!(ctxt.get_module_namespace(&func).is_err());
+
+        ctxt.eval_function(func.clone()).unwrap();
+
+        assert_eq!(
+            ctxt.get_module_namespace(&func)
+                .unwrap()
+                .get_property("n")
+                .unwrap()
+                .as_int(),
+            Some(1)
+        );
+        assert!(ctxt
+            .get_module_namespace(&ctxt.bind(ctxt.new_object()))
+            .is_err());
+
+        let module = ctxt
+            .eval_module(
+                "export let answer; Promise.resolve(42).then(v => answer = v);",
+                "answer.mjs",
+            )
+            .unwrap();
+
+        assert!(module.get("answer").is_none());
+        assert!(rt.is_job_pending());
+
+        rt.execute_pending_job().unwrap();
+
+        assert_eq!(module.get("answer").unwrap().as_int(), Some(42));
+
+        ctxt.enqueue_job(Some(failing_job), ()).unwrap();
+
+        let module = ctxt.eval_module("export const n = 1", "n2.mjs").unwrap();
+
+        assert_eq!(module.get("n").unwrap().as_int(), Some(1));
+        assert!(matches!(module.status(), ModuleStatus::Evaluated));
+        assert!(rt.is_job_pending());
+        assert!(matches!(
+            rt.execute_pending_job(),
+            Err(Error::Js(ErrorKind::Throw(ref msg))) if msg == "job failed"
+        ));
+    }
+}
@@ -0,0 +1,307 @@
+use std::fmt;
+
+use crate::{ContextRef, Error, Local, NewValue, Value};
+
+/// A segment of the property path, the property name or the array index.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    /// The property name.
+    Key(String),
+    /// The array index.
+    Index(u32),
+}
+
+impl From<&str> for PathSegment {
+    fn from(key: &str) -> Self {
+        PathSegment::Key(key.to_owned())
+    }
+}
+
+impl From<String> for PathSegment {
+    fn from(key: String) -> Self {
+        PathSegment::Key(key)
+    }
+}
+
+impl From<u32> for PathSegment {
+    fn from(idx: u32) -> Self {
+        PathSegment::Index(idx)
+    }
+}
+
+impl fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PathSegment::Key(key) => f.write_str(key),
+            PathSegment::Index(idx) => write!(f, "[{}]", idx),
+        }
+    }
+}
+
+/// The reason why a property path can't be accessed, with the path to the failed segment.
+#[derive(Debug, thiserror::Error)]
+pub enum PathError {
+    /// The path is empty.
+    #[error("empty property path")]
+    Empty,
+    /// The property is missing.
+    #[error("property `{0}` is missing")]
+    Missing(String),
+    /// The property is not an object, so the rest segments can't be accessed.
+    #[error("property `{0}` is not an object")]
+    NotObject(String),
+    /// The assignment of the property was rejected.
+    #[error("assignment to property `{0}` was rejected")]
+    Rejected(String),
+    /// Accessing the property throws an exception.
+    #[error("property `{0}`: {1}")]
+    Error(String, #[source] Error),
+}
+
+impl PathError {
+    /// Returns the path to the failed segment.
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            PathError::Empty => None,
+            PathError::Missing(path)
+            | PathError::NotObject(path)
+            | PathError::Rejected(path)
+            | PathError::Error(path, _) => Some(path),
+        }
+    }
+}
+
+impl From<PathError> for Error {
+    fn from(err: PathError) -> Self {
+        Error::other(err)
+    }
+}
+
+/// Format the segments as `a.b[0].c`.
+fn format_path(segments: &[PathSegment]) -> String {
+    segments
+        .iter()
+        .enumerate()
+        .map(|(i, segment)| match segment {
+            PathSegment::Key(_) if i > 0 => format!(".{}", segment),
+            _ => segment.to_string(),
+        })
+        .collect()
+}
+
+impl<'a> Local<'a, Value> {
+    /// Get the value at the property path, e.g. `["a", "b", 0, "c"]` for `a.b[0].c`.
+    pub fn get_path<I>(&self, path: I) -> Result<Local<'a, Value>, PathError>
+    where
+        I: IntoIterator,
+        I::Item: Into<PathSegment>,
+    {
+        self.ctxt.get_path(self, path)
+    }
+
+    /// Set the value at the property path, the missing objects or arrays on the path are created.
+    pub fn set_path<I, V>(&self, path: I, val: V) -> Result<(), PathError>
+    where
+        I: IntoIterator,
+        I::Item: Into<PathSegment>,
+        V: NewValue,
+    {
+        self.ctxt.set_path(self, path, val)
+    }
+}
+
+impl ContextRef {
+    /// Get the value at the property path of the object.
+    pub fn get_path<I>(&self, this: &Value, path: I) -> Result<Local<Value>, PathError>
+    where
+        I: IntoIterator,
+        I::Item: Into<PathSegment>,
+    {
+        let segments = path.into_iter().map(Into::into).collect::<Vec<_>>();
+        let mut cur = self.clone_value(this);
+
+        for (i, segment) in segments.iter().enumerate() {
+            if !cur.is_object() {
+                return Err(PathError::NotObject(format_path(&segments[..i])));
+            }
+
+            cur = self
+                .get_segment(&cur, segment)
+                .map_err(|err| PathError::Error(format_path(&segments[..=i]), err))?
+                .ok_or_else(|| PathError::Missing(format_path(&segments[..=i])))?;
+        }
+
+        trace!("get path `{}` -> {:?}", format_path(&segments), cur);
+
+        Ok(cur)
+    }
+
+    /// Set the value at the property path of the object.
+    ///
+    /// The missing intermediate properties are created as arrays if followed by an index, or objects otherwise.
+    pub fn set_path<I, V>(&self, this: &Value, path: I, val: V) -> Result<(), PathError>
+    where
+        I: IntoIterator,
+        I::Item: Into<PathSegment>,
+        V: NewValue,
+    {
+        let segments = path.into_iter().map(Into::into).collect::<Vec<_>>();
+        let (last, parents) = segments.split_last().ok_or(PathError::Empty)?;
+        let mut cur = self.clone_value(this);
+
+        for (i, segment) in parents.iter().enumerate() {
+            if !cur.is_object() {
+                return Err(PathError::NotObject(format_path(&segments[..i])));
+            }
+
+            let path = || format_path(&segments[..=i]);
+
+            cur = match self
+                .get_segment(&cur, segment)
+                .map_err(|err| PathError::Error(path(), err))?
+            {
+                Some(next) => next,
+                None => {
+                    let next = self.bind(match segments[i + 1] {
+                        PathSegment::Index(_) => self.new_array(),
+                        PathSegment::Key(_) => self.new_object(),
+                    });
+
+                    trace!("create `{}` = {:?}", path(), next);
+
+                    self.set_segment(&cur, segment, next.clone())
+                        .map_err(|err| PathError::Error(path(), err))
+                        .and_then(|ok| {
+                            if ok {
+                                Ok(())
+                            } else {
+                                Err(PathError::Rejected(path()))
+                            }
+                        })?;
+
+                    next
+                }
+            };
+        }
+
+        let path = format_path(&segments);
+
+        if !cur.is_object() {
+            return Err(PathError::NotObject(format_path(parents)));
+        }
+
+        trace!("set path `{}`", path);
+
+        match self.set_segment(&cur, last, val) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(PathError::Rejected(path)),
+            Err(err) => Err(PathError::Error(path, err)),
+        }
+    }
+
+    fn get_segment(
+        &self,
+        this: &Value,
+        segment: &PathSegment,
+    ) -> Result<Option<Local<Value>>, Error> {
+        match segment {
+            PathSegment::Key(key) => self.get_property(this, key.as_str()),
+            PathSegment::Index(idx) => self.get_property(this, *idx),
+        }
+        .map(Local::ok)
+        .transpose()
+    }
+
+    fn set_segment<V: NewValue>(
+        &self,
+        this: &Value,
+        segment: &PathSegment,
+        val: V,
+    ) -> Result<bool, Error> {
+        match segment {
+            PathSegment::Key(key) => self.set_property(this, key.as_str(), val),
+            PathSegment::Index(idx) => self.set_property(this, *idx, val),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, Eval, Runtime};
+
+    use super::*;
+
+    #[test]
+    fn path() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let config = ctxt
+            .eval_script(
+                r#"({ server: { hosts: [{ name: "foo", port: 80 }], get broken() { throw new Error("Whoops!") } } })"#,
+                "<evalScript>",
+                Eval::GLOBAL,
+            )
+            .unwrap();
+
+        let path = |p: &[PathSegment]| p.to_vec();
+        let host = path(&["server".into(), "hosts".into(), 0.into(), "name".into()]);
+
+        assert_eq!(config.get_path(host.clone()).unwrap().to_string(), "foo");
+        assert!(config
+            .get_path(Vec::<PathSegment>::new())
+            .unwrap()
+            .is_object());
+
+        match config.get_path(path(&[
+            "server".into(),
+            "hosts".into(),
+            1.into(),
+            "name".into(),
+        ])) {
+            Err(PathError::Missing(p)) => assert_eq!(p, "server.hosts[1]"),
+            res => panic!("unexpected result: {:?}", res),
+        }
+        match config.get_path(path(&[
+            "server".into(),
+            "hosts".into(),
+            0.into(),
+            "name".into(),
+            "x".into(),
+        ])) {
+            Err(PathError::NotObject(p)) => assert_eq!(p, "server.hosts[0].name"),
+            res => panic!("unexpected result: {:?}", res),
+        }
+        match config.get_path(vec!["server", "broken"]) {
+            Err(err @ PathError::Error(..)) => {
+                assert_eq!(err.path(), Some("server.broken"));
+                assert!(err.to_string().contains("Whoops!"), "{}", err);
+            }
+            res => panic!("unexpected result: {:?}", res),
+        }
+
+        config.set_path(host, "bar").unwrap();
+        config
+            .set_path(path(&["client".into(), "retries".into(), 1.into()]), 3)
+            .unwrap();
+
+        ctxt.global_object().set_property("config", config).unwrap();
+
+        assert_eq!(
+            ctxt.eval::<_, String>(
+                "JSON.stringify([config.server.hosts[0].name, config.client])",
+                Eval::GLOBAL
+            )
+            .unwrap()
+            .unwrap(),
+            r#"["bar",{"retries":[null,3]}]"#
+        );
+
+        assert!(matches!(
+            ctxt.global_object().set_path(Vec::<PathSegment>::new(), 1),
+            Err(PathError::Empty)
+        ));
+    }
+}
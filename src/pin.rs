@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{ContextRef, Local, Value};
+
+/// The integer handle of a pinned value, which could be passed through the FFI, job userdata or thread messages.
+///
+/// The handle is only meaningful to the context which pinned the value, and zero is never a valid handle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ValueId(u64);
+
+impl ValueId {
+    /// Create a handle from the raw integer.
+    pub fn from_raw(id: u64) -> Self {
+        ValueId(id)
+    }
+
+    /// Returns the raw integer of the handle.
+    pub fn into_raw(self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for ValueId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// The values pinned by their handles, which are released with the context.
+#[derive(Default)]
+pub(crate) struct Pins {
+    next_id: u64,
+    values: HashMap<u64, Value>,
+}
+
+impl Pins {
+    /// Take the pinned values, which should be freed with the context.
+    pub fn drain(&mut self) -> impl Iterator<Item = Value> + '_ {
+        self.values.drain().map(|(_, value)| value)
+    }
+}
+
+impl ContextRef {
+    /// Pin the value and returns its handle, the value is kept alive until it is unpinned.
+    pub fn pin_value(&self, value: &Value) -> ValueId {
+        let value = self.clone_value(value).into_inner();
+
+        let id = self.with_state(|state| {
+            let pins = &mut state.pins;
+
+            pins.next_id += 1;
+            pins.values.insert(pins.next_id, value);
+            pins.next_id
+        });
+
+        trace!("pin value #{}", id);
+
+        ValueId(id)
+    }
+
+    /// Returns the pinned value of the handle, or `None` if it was unpinned or never pinned.
+    pub fn resolve(&self, id: ValueId) -> Option<Local<Value>> {
+        self.try_with_state(|state| state.pins.values.get(&id.0).map(|value| value.raw()))
+            .flatten()
+            .map(|value| self.clone_value(&Value::from(value)))
+    }
+
+    /// Unpin the value of the handle, returns `false` if it was unpinned or never pinned.
+    pub fn unpin(&self, id: ValueId) -> bool {
+        // the values were freed with the context
+        let value = self
+            .try_with_state(|state| state.pins.values.remove(&id.0))
+            .flatten();
+
+        trace!("unpin value #{}", id.0);
+
+        value.map(|value| self.free_value(value)).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, Eval, Runtime};
+
+    use super::*;
+
+    #[test]
+    fn pin_value() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let id = {
+            let obj = ctxt
+                .eval_script("({ answer: 42 })", "<evalScript>", Eval::GLOBAL)
+                .unwrap();
+
+            ctxt.pin_value(&obj)
+        };
+        let raw = id.into_raw();
+
+        assert_ne!(raw, 0);
+
+        let obj = ctxt.resolve(ValueId::from_raw(raw)).unwrap();
+
+        assert_eq!(obj.get_property("answer").unwrap().as_int(), Some(42));
+        assert!(ctxt.unpin(id));
+        assert!(!ctxt.unpin(id));
+        assert!(ctxt.resolve(id).is_none());
+        assert_eq!(obj.get_property("answer").unwrap().as_int(), Some(42));
+
+        ctxt.pin_value(&obj);
+    }
+}
@@ -0,0 +1,179 @@
+use std::convert::TryFrom;
+use std::rc::Rc;
+
+use crate::{ContextRef, Error, ErrorKind, Local, Value};
+
+/// The post-processor applied to the `eval` result before extracting the value.
+pub trait PostProcessor: 'static {
+    /// Transform the result value, or fail the evaluation.
+    fn process<'a>(
+        &self,
+        ctxt: &'a ContextRef,
+        value: Local<'a, Value>,
+    ) -> Result<Local<'a, Value>, Error>;
+}
+
+impl<F> PostProcessor for F
+where
+    F: for<'a> Fn(&'a ContextRef, Local<'a, Value>) -> Result<Local<'a, Value>, Error> + 'static,
+{
+    fn process<'a>(
+        &self,
+        ctxt: &'a ContextRef,
+        value: Local<'a, Value>,
+    ) -> Result<Local<'a, Value>, Error> {
+        self(ctxt, value)
+    }
+}
+
+/// The `PostProcessor` which runs the pending jobs until the promise is settled.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AwaitPromise;
+
+impl PostProcessor for AwaitPromise {
+    fn process<'a>(
+        &self,
+        ctxt: &'a ContextRef,
+        value: Local<'a, Value>,
+    ) -> Result<Local<'a, Value>, Error> {
+        ctxt.resolve_promise(value)
+    }
+}
+
+/// The `PostProcessor` which unwraps the conventional `{ ok, err }` envelope.
+///
+/// The `err` property which is not `null` or `undefined` fails the evaluation,
+/// otherwise the `ok` property is returned. The other values are returned directly.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UnwrapEnvelope;
+
+impl PostProcessor for UnwrapEnvelope {
+    fn process<'a>(
+        &self,
+        ctxt: &'a ContextRef,
+        value: Local<'a, Value>,
+    ) -> Result<Local<'a, Value>, Error> {
+        if !value.is_object() || !(value.has_property("ok")? || value.has_property("err")?) {
+            return Ok(value);
+        }
+
+        match ctxt.get_property(&value, "err") {
+            Some(err) if !err.is_null() => Err(ErrorKind::try_from(err)?.into()),
+            _ => Ok(ctxt
+                .get_property(&value, "ok")
+                .unwrap_or_else(|| ctxt.undefined())),
+        }
+    }
+}
+
+/// The post-processors applied in order to every result of `ContextRef::eval`.
+#[derive(Clone, Default)]
+pub struct Pipeline(Vec<Rc<dyn PostProcessor>>);
+
+impl Pipeline {
+    /// Create an empty pipeline which returns the results directly.
+    pub fn new() -> Self {
+        Pipeline::default()
+    }
+
+    /// Append a post-processor to the pipeline.
+    pub fn then<P: PostProcessor>(mut self, processor: P) -> Self {
+        self.0.push(Rc::new(processor));
+        self
+    }
+
+    /// Append a closure to the pipeline.
+    pub fn map<F>(self, f: F) -> Self
+    where
+        F: for<'a> Fn(&'a ContextRef, Local<'a, Value>) -> Result<Local<'a, Value>, Error>
+            + 'static,
+    {
+        self.then(f)
+    }
+
+    /// Await the promise results, see `AwaitPromise`.
+    pub fn await_promise(self) -> Self {
+        self.then(AwaitPromise)
+    }
+
+    /// Unwrap the `{ ok, err }` envelope results, see `UnwrapEnvelope`.
+    pub fn unwrap_envelope(self) -> Self {
+        self.then(UnwrapEnvelope)
+    }
+
+    /// Returns `true` if the pipeline has no post-processor.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn process<'a>(
+        &self,
+        ctxt: &'a ContextRef,
+        value: Local<'a, Value>,
+    ) -> Result<Local<'a, Value>, Error> {
+        self.0
+            .iter()
+            .try_fold(value, |value, processor| processor.process(ctxt, value))
+    }
+}
+
+impl ContextRef {
+    /// Returns the post-processors of the `eval` results.
+    pub fn result_pipeline(&self) -> Pipeline {
+        self.with_state(|state| state.pipeline.clone())
+    }
+
+    /// Set the post-processors of the `eval` results.
+    pub fn set_result_pipeline(&self, pipeline: Pipeline) {
+        self.with_state(|state| state.pipeline = pipeline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, Eval, Runtime};
+
+    use super::*;
+
+    #[test]
+    fn result_pipeline() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::builder(&rt)
+            .with_all()
+            .result_pipeline(Pipeline::new().await_promise().unwrap_envelope().map(
+                |ctxt, value| {
+                    Ok(match value.as_int() {
+                        Some(n) => ctxt.bind(n * 2),
+                        None => value,
+                    })
+                },
+            ))
+            .build();
+
+        assert_eq!(
+            ctxt.eval::<_, i32>("Promise.resolve({ ok: 21 })", Eval::GLOBAL)
+                .unwrap(),
+            Some(42)
+        );
+        assert_eq!(
+            ctxt.eval::<_, String>("({ ok: 'hello', err: null })", Eval::GLOBAL)
+                .unwrap(),
+            Some("hello".to_owned())
+        );
+        assert!(matches!(
+            ctxt.eval::<_, i32>("(async () => ({ err: new RangeError('boom') }))()", Eval::GLOBAL),
+            Err(Error::Js(ErrorKind::RangeError(ref msg, _))) if msg == "boom"
+        ));
+
+        ctxt.set_result_pipeline(Pipeline::new());
+
+        assert!(ctxt.result_pipeline().is_empty());
+        assert_eq!(
+            ctxt.eval::<_, String>("({ ok: 21 }).ok + ''", Eval::GLOBAL)
+                .unwrap(),
+            Some("21".to_owned())
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+
+use crate::{prop::Names, Context, ContextRef, Runtime, RuntimeRef};
+
+thread_local! {
+    static POOL: ContextPool = ContextPool::new();
+}
+
+/// Run the function with a context checked out from the thread-local `ContextPool`.
+///
+/// The `qjs!` macro use it to amortize the engine startup.
+pub fn with_context<T, F: FnOnce(&ContextRef) -> T>(f: F) -> T {
+    POOL.with(|pool| pool.with(f))
+}
+
+/// Clear the idle contexts of the thread-local `ContextPool`.
+pub fn clear_context_pool() {
+    POOL.with(ContextPool::clear)
+}
+
+struct PooledContext {
+    ctxt: Context,
+    builtins: HashSet<String>,
+}
+
+impl PooledContext {
+    fn new(rt: &RuntimeRef) -> Self {
+        let ctxt = Context::new(rt);
+        let builtins = global_names(&ctxt).into_iter().collect();
+
+        PooledContext { ctxt, builtins }
+    }
+
+    /// Delete the global properties added after the context was created.
+    ///
+    /// Returns `false` if some of them can't be deleted, e.g. the non-configurable `var` declarations.
+    fn reset(&self) -> bool {
+        let global = self.ctxt.global_object();
+
+        global_names(&self.ctxt)
+            .into_iter()
+            .filter(|name| !self.builtins.contains(name))
+            .fold(true, |deleted, name| {
+                trace!("delete global property `{}`", name);
+
+                match global.delete_property(name.as_str()) {
+                    Ok(true) => deleted,
+                    _ => {
+                        debug!("failed to delete global property `{}`", name);
+
+                        false
+                    }
+                }
+            })
+    }
+}
+
+fn global_names(ctxt: &ContextRef) -> Vec<String> {
+    let global = ctxt.global_object();
+
+    ctxt.get_own_property_names(&global, Names::STRING)
+        .ok()
+        .and_then(|names| names)
+        .map(|names| names.iter().map(|name| name.to_string()).collect())
+        .unwrap_or_default()
+}
+
+/// A pool of the contexts which share a `Runtime`.
+///
+/// The context is reused after the global properties added by the previous user were deleted,
+/// or dropped if some of them can't be deleted.
+///
+/// The reset is not an isolation, the top-level `let`, `const` and `class` bindings
+/// and the modified builtins (e.g. `Array.prototype.x = 1`) are visible to the next user.
+/// Use a fresh `Context` to evaluate the untrusted scripts.
+pub struct ContextPool {
+    // the contexts must be dropped before the runtime
+    contexts: RefCell<Vec<PooledContext>>,
+    rt: Runtime,
+}
+
+impl Default for ContextPool {
+    fn default() -> Self {
+        ContextPool::new()
+    }
+}
+
+impl ContextPool {
+    /// Construct a new `ContextPool` with a new `Runtime`.
+    pub fn new() -> Self {
+        ContextPool {
+            contexts: RefCell::new(Vec::new()),
+            rt: Runtime::new(),
+        }
+    }
+
+    /// The runtime shared by the pooled contexts.
+    pub fn runtime(&self) -> &RuntimeRef {
+        &self.rt
+    }
+
+    /// Returns the number of the idle contexts.
+    pub fn len(&self) -> usize {
+        self.contexts.borrow().len()
+    }
+
+    /// Returns `true` if there is no idle context.
+    pub fn is_empty(&self) -> bool {
+        self.contexts.borrow().is_empty()
+    }
+
+    /// Drop all the idle contexts.
+    pub fn clear(&self) {
+        trace!("clear {} pooled contexts", self.len());
+
+        let contexts = self.contexts.replace(Vec::new());
+
+        drop(contexts);
+    }
+
+    /// Run the function with a checked out context, which will be reset and returned to the pool.
+    pub fn with<T, F: FnOnce(&ContextRef) -> T>(&self, f: F) -> T {
+        let pooled = self.contexts.borrow_mut().pop();
+        let pooled = pooled.unwrap_or_else(|| {
+            trace!("create pooled context");
+
+            PooledContext::new(&self.rt)
+        });
+
+        let res = f(&pooled.ctxt);
+
+        if pooled.reset() {
+            self.contexts.borrow_mut().push(pooled);
+        } else {
+            trace!("drop pooled context");
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Eval;
+
+    use super::*;
+
+    #[test]
+    fn pool() {
+        let _ = pretty_env_logger::try_init();
+
+        let pool = ContextPool::new();
+
+        assert!(pool.is_empty());
+
+        let n: Option<i32> = pool.with(|ctxt| {
+            ctxt.global_object().set_property("foo", 123).unwrap();
+
+            let s: Option<String> = pool
+                .with(|nested| nested.eval("typeof foo", Eval::GLOBAL))
+                .unwrap();
+
+            assert_eq!(s, Some("undefined".to_owned()));
+
+            ctxt.eval("foo", Eval::GLOBAL).unwrap()
+        });
+
+        assert_eq!(n, Some(123));
+        assert_eq!(pool.len(), 2);
+
+        let s: Option<String> = pool.with(|ctxt| ctxt.eval("typeof foo", Eval::GLOBAL).unwrap());
+
+        assert_eq!(s, Some("undefined".to_owned()));
+
+        // the `var` declarations are not configurable
+        pool.with(|ctxt| ctxt.eval::<_, ()>("var bar = 1", Eval::GLOBAL).unwrap());
+
+        assert_eq!(pool.len(), 1);
+
+        pool.clear();
+
+        assert!(pool.is_empty());
+    }
+}
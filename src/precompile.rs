@@ -1,9 +1,98 @@
+use std::convert::TryInto;
+use std::fmt;
 use std::slice;
 
-use failure::Error;
 use foreign_types::ForeignTypeRef;
 
-use crate::{ffi, ContextRef, Local, Value};
+use crate::{ffi, ContextRef, Error, ErrorKind, Local, Value};
+
+/// The magic of the bytecode container.
+const MAGIC: &[u8] = b"QJSB";
+
+/// The version of the bytecode container format.
+const FORMAT_VERSION: u8 = 1;
+
+/// The engine was built with the `bignum` extension.
+const FLAG_BIGNUM: u32 = 1;
+/// The bytecode is in the big endian byte order.
+const FLAG_BIG_ENDIAN: u32 = 2;
+
+/// The header of the bytecode container, which identify the engine wrote it.
+#[derive(Clone, Debug, PartialEq)]
+struct Header {
+    version: String,
+    flags: u32,
+}
+
+impl Header {
+    fn current(bswap: bool) -> Self {
+        let mut flags = 0;
+
+        if cfg!(feature = "bignum") {
+            flags |= FLAG_BIGNUM;
+        }
+        if cfg!(target_endian = "big") != bswap {
+            flags |= FLAG_BIG_ENDIAN;
+        }
+
+        Header {
+            version: ffi::VERSION.to_string(),
+            flags,
+        }
+    }
+
+    fn write(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(MAGIC);
+        buf.push(FORMAT_VERSION);
+        buf.push(self.version.len() as u8);
+        buf.extend_from_slice(self.version.as_bytes());
+        buf.extend_from_slice(&self.flags.to_le_bytes());
+    }
+
+    /// Parse the header, returns the header and payload, or `None` if it is not a container.
+    fn read(buf: &[u8]) -> Result<Option<(Header, &[u8])>, Error> {
+        if !buf.starts_with(MAGIC) {
+            return Ok(None);
+        }
+
+        let invalid = || Error::from("invalid bytecode container");
+        let buf = &buf[MAGIC.len()..];
+
+        if buf.first() != Some(&FORMAT_VERSION) {
+            return Err(invalid());
+        }
+
+        let len = *buf.get(1).ok_or_else(invalid)? as usize;
+        let version = buf.get(2..2 + len).ok_or_else(invalid)?;
+        let flags = buf.get(2 + len..6 + len).ok_or_else(invalid)?;
+        let header = Header {
+            version: String::from_utf8_lossy(version).into_owned(),
+            flags: u32::from_le_bytes(flags.try_into().map_err(|_| invalid())?),
+        };
+
+        Ok(Some((header, &buf[6 + len..])))
+    }
+}
+
+impl fmt::Display for Header {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}bignum, {} endian)",
+            self.version,
+            if self.flags & FLAG_BIGNUM == 0 {
+                "no "
+            } else {
+                ""
+            },
+            if self.flags & FLAG_BIG_ENDIAN == 0 {
+                "little"
+            } else {
+                "big"
+            }
+        )
+    }
+}
 
 bitflags! {
     pub struct WriteObj: u32 {
@@ -30,8 +119,19 @@ impl Local<'_, Value> {
 }
 
 impl ContextRef {
-    /// Write the script or module to bytecode
+    /// Write the script or module to bytecode, wrapped in a container with the engine version and flags.
     pub fn write_object(&self, obj: &Value, flags: WriteObj) -> Result<Vec<u8>, Error> {
+        let data = self.write_raw_object(obj, flags)?;
+        let mut buf = Vec::with_capacity(data.len() + 32);
+
+        Header::current(flags.contains(WriteObj::BSWAP)).write(&mut buf);
+        buf.extend_from_slice(&data);
+
+        Ok(buf)
+    }
+
+    /// Write the script or module to the raw QuickJS bytecode, which could be loaded by `js_std_eval_binary`.
+    pub fn write_raw_object(&self, obj: &Value, flags: WriteObj) -> Result<Vec<u8>, Error> {
         let mut len = 0;
 
         self.check_null(unsafe {
@@ -46,8 +146,29 @@ impl ContextRef {
         })
     }
 
-    /// Read the script or module from bytecode
+    /// Read the script or module from bytecode.
+    ///
+    /// The engine version and flags in the container will be verified,
+    /// `ErrorKind::BytecodeVersionMismatch` will be returned if the bytecode was written by an incompatible engine.
+    /// The raw QuickJS bytecode without container, e.g. the embedded images, is read directly.
     pub fn read_object(&self, buf: &[u8], flags: ReadObj) -> Result<Local<Value>, Error> {
+        let buf = match Header::read(buf)? {
+            Some((header, payload)) => {
+                let expected = Header::current(false);
+
+                if header != expected {
+                    return Err(ErrorKind::BytecodeVersionMismatch(
+                        expected.to_string(),
+                        header.to_string(),
+                    )
+                    .into());
+                }
+
+                payload
+            }
+            None => buf,
+        };
+
         self.bind(unsafe {
             ffi::JS_ReadObject(
                 self.as_ptr(),
@@ -59,9 +180,116 @@ impl ContextRef {
         .ok()
     }
 
+    /// Serialize a value to the binary format, e.g. to pass it to another runtime or persist it.
+    ///
+    /// Only the primitive values, arrays and plain objects are supported,
+    /// the shared objects are duplicated, and the circular references are rejected with a `TypeError`.
+    pub fn serialize(&self, value: &Value) -> Result<Vec<u8>, Error> {
+        self.write_object(value, WriteObj::empty())
+    }
+
+    /// Deserialize a value written by `ContextRef::serialize`.
+    ///
+    /// The functions and modules are rejected, use `read_object` to load the bytecode.
+    pub fn deserialize(&self, buf: &[u8]) -> Result<Local<Value>, Error> {
+        self.read_object(buf, ReadObj::empty())
+    }
+
     /// Evaluate a script or module source in bytecode.
     pub fn eval_function<T: Into<ffi::JSValue>>(&self, func: T) -> Result<Local<Value>, Error> {
-        self.bind(unsafe { ffi::JS_EvalFunction(self.as_ptr(), func.into()) })
+        let func = func.into();
+        let _entered = self.enter();
+
+        self.record_eval(|| self.bind(unsafe { ffi::JS_EvalFunction(self.as_ptr(), func) }))
             .ok()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, Eval, Runtime};
+
+    use super::*;
+
+    #[test]
+    fn serialize() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let value = ctxt
+            .eval_script(
+                "const shared = { b: null }; ({ a: [1, 'x', shared], c: shared, d: 1.5 })",
+                "<evalScript>",
+                Eval::GLOBAL,
+            )
+            .unwrap();
+        let buf = ctxt.serialize(&value).unwrap();
+
+        // deserialize in another runtime, like passing a message to a worker
+        let rt2 = Runtime::new();
+        let ctxt2 = Context::new(&rt2);
+        let copy = ctxt2.deserialize(&buf).unwrap();
+
+        assert_eq!(
+            copy.inspect(3),
+            "{ a: [ 1, 'x', { b: null } ], c: { b: null }, d: 1.5 }"
+        );
+
+        let cyclic = ctxt
+            .eval_script("const o = {}; o.self = o; o", "<evalScript>", Eval::GLOBAL)
+            .unwrap();
+
+        assert!(matches!(
+            ctxt.serialize(&cyclic),
+            Err(Error::Js(ErrorKind::TypeError(ref msg, _))) if msg == "circular reference"
+        ));
+
+        let func = ctxt
+            .eval_script("1 + 2", "<evalScript>", Eval::GLOBAL | Eval::COMPILE_ONLY)
+            .unwrap();
+
+        assert!(ctxt.serialize(&func).is_err());
+        assert!(ctxt.deserialize(&func.write_bytecode().unwrap()).is_err());
+    }
+
+    #[test]
+    fn bytecode_container() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let func = ctxt
+            .eval_script("1 + 2", "<evalScript>", Eval::GLOBAL | Eval::COMPILE_ONLY)
+            .unwrap();
+        let buf = func.write_bytecode().unwrap();
+
+        assert!(buf.starts_with(MAGIC));
+        assert_eq!(ctxt.eval_binary(&buf, false).unwrap().as_int(), Some(3));
+
+        let raw = ctxt.write_raw_object(&func, WriteObj::BYTECODE).unwrap();
+
+        assert_eq!(&buf[buf.len() - raw.len()..], raw.as_slice());
+        assert_eq!(ctxt.eval_binary(&raw, false).unwrap().as_int(), Some(3));
+
+        let mut buf = buf;
+        let version = ffi::VERSION.to_string();
+        let pos = MAGIC.len() + 2;
+
+        buf[pos..pos + version.len()].copy_from_slice(&vec![b'0'; version.len()]);
+
+        assert!(matches!(
+            ctxt.read_object(&buf, ReadObj::BYTECODE)
+                .unwrap_err()
+                .into_kind()
+                .unwrap(),
+            ErrorKind::BytecodeVersionMismatch(ref expected, ref found)
+                if expected.starts_with(&version) && found.starts_with("000")
+        ));
+        assert!(ctxt
+            .read_object(&buf[..MAGIC.len() + 3], ReadObj::BYTECODE)
+            .is_err());
+    }
+}
@@ -0,0 +1,119 @@
+use std::mem;
+use std::time::{Duration, Instant};
+
+use crate::{ContextRef, Error, Eval, Local, RuntimeRef, Value};
+
+/// The statistics of an evaluation, returned by `ContextRef::eval_profiled`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EvalStats {
+    /// How long the evaluation took.
+    pub wall_time: Duration,
+    /// The number of the interrupt checks, QuickJS checks the interrupt every 10000 operations or so.
+    pub interrupts: u64,
+    /// The net number of the memory blocks allocated by the evaluation.
+    pub allocations: i64,
+    /// The peak of the allocated memory in bytes, sampled at the interrupt checks.
+    pub peak_memory: usize,
+}
+
+/// The counters of the running profile.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Profile {
+    interrupts: u64,
+    peak_memory: usize,
+}
+
+impl ContextRef {
+    /// Evaluate a script and collect the statistics of the evaluation.
+    ///
+    /// The interrupts are counted by the interrupt handler of the engine,
+    /// which still calls the interrupt closure or handler of the runtime.
+    pub fn eval_profiled<T: Into<Vec<u8>>>(
+        &self,
+        input: T,
+    ) -> Result<(Local<Value>, EvalStats), Error> {
+        let rt = self.runtime();
+        let usage = rt.memory_usage();
+
+        rt.install_interrupt_trampoline();
+
+        let outer = rt.with_interrupts(|interrupts| {
+            interrupts.profile.replace(Profile {
+                interrupts: 0,
+                peak_memory: usage.malloc_size as usize,
+            })
+        });
+
+        let started = Instant::now();
+        let res = self.eval_script(input, "<evalScript>", Eval::GLOBAL);
+        let wall_time = started.elapsed();
+
+        let profile = rt
+            .with_interrupts(|interrupts| mem::replace(&mut interrupts.profile, outer))
+            .unwrap_or_default();
+
+        let end = rt.memory_usage();
+        let stats = EvalStats {
+            wall_time,
+            interrupts: profile.interrupts,
+            allocations: end.malloc_count - usage.malloc_count,
+            peak_memory: profile.peak_memory.max(end.malloc_size as usize),
+        };
+
+        trace!("eval profiled: {:?}", stats);
+
+        res.map(|value| (value, stats))
+    }
+}
+
+impl Profile {
+    /// Count the interrupt check and sample the memory.
+    pub(crate) fn record(&mut self, rt: &RuntimeRef) {
+        let size = rt.compute_memory_usage().malloc_size as usize;
+
+        self.interrupts += 1;
+        self.peak_memory = self.peak_memory.max(size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::{Context, Interrupt, Runtime, RuntimeRef};
+
+    static INTERRUPTS: AtomicUsize = AtomicUsize::new(0);
+
+    fn count_interrupts(_rt: &RuntimeRef) -> Interrupt {
+        INTERRUPTS.fetch_add(1, Ordering::SeqCst);
+
+        Interrupt::Continue
+    }
+
+    #[test]
+    fn eval_profiled() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+        let before = rt.memory_usage().malloc_size as usize;
+
+        rt.set_interrupt_handler(Some(count_interrupts));
+
+        let (value, stats) = ctxt
+            .eval_profiled(
+                "globalThis.items = []; for (let i = 0; i < 100000; i++) items.push({ i }); items.length",
+            )
+            .unwrap();
+
+        assert_eq!(value.as_int(), Some(100000));
+        assert!(stats.interrupts > 0);
+        assert!(stats.allocations > 0);
+        assert!(stats.peak_memory > before);
+        assert!(stats.wall_time.as_nanos() > 0);
+
+        // the interrupt handler of the runtime is still called
+        assert_eq!(INTERRUPTS.load(Ordering::SeqCst) as u64, stats.interrupts);
+        assert!(rt.with_interrupts(|interrupts| interrupts.handler.is_some()));
+    }
+}
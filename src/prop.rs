@@ -3,10 +3,9 @@ use std::mem::MaybeUninit;
 use std::ptr;
 use std::slice;
 
-use failure::Error;
 use foreign_types::ForeignTypeRef;
 
-use crate::{ffi, Atom, ContextRef, Local, NewAtom, NewValue, Value};
+use crate::{ffi, Atom, ContextRef, Error, ExtractValue, Local, NewAtom, NewValue, Value};
 
 bitflags! {
     /// Flags for property
@@ -95,6 +94,16 @@ impl GetProperty for Local<'_, ffi::JSAtom> {
     }
 }
 
+impl GetProperty for &Value {
+    fn get_property<'a>(&self, ctxt: &'a ContextRef, this: &Value) -> Option<Local<'a, Value>> {
+        match (*self).new_atom(ctxt) {
+            // leave the exception pending, like the other failed property accesses
+            ffi::JS_ATOM_NULL => Some(ctxt.bind(ffi::EXCEPTION)),
+            atom => ctxt.bind_atom(atom).get_property(ctxt, this),
+        }
+    }
+}
+
 /// Set a property value on an object.
 pub trait SetProperty {
     /// Set a property value on an object.
@@ -173,6 +182,40 @@ impl SetProperty for Local<'_, ffi::JSAtom> {
     }
 }
 
+impl SetProperty for &Value {
+    fn set_property<T: NewValue>(
+        &self,
+        ctxt: &ContextRef,
+        this: &Value,
+        val: T,
+    ) -> Result<bool, Error> {
+        ctxt.try_new_atom(*self)?.set_property(ctxt, this, val)
+    }
+}
+
+/// The reason why a property assignment failed.
+#[derive(Debug, thiserror::Error)]
+pub enum SetFailure {
+    /// The target is not an object.
+    #[error("cannot set property `{0}` on a non-object value")]
+    NotObject(String),
+    /// The data property is not writable.
+    #[error("property `{0}` is read-only")]
+    ReadOnly(String),
+    /// The accessor property has a getter but no setter.
+    #[error("property `{0}` has only a getter")]
+    NoSetter(String),
+    /// The object is not extensible, the new property can't be added.
+    #[error("cannot add property `{0}`, object is not extensible")]
+    NotExtensible(String),
+    /// The assignment was vetoed for other reasons, e.g. the `set` trap of a proxy returns `false`.
+    #[error("assignment to property `{0}` was rejected")]
+    Rejected(String),
+    /// The assignment throws an exception.
+    #[error(transparent)]
+    Error(#[from] Error),
+}
+
 /// Check if a property on an object.
 pub trait HasProperty {
     /// Check if a property on an object.
@@ -184,10 +227,9 @@ where
     T: NewAtom,
 {
     fn has_property(self, ctxt: &ContextRef, this: &Value) -> Result<bool, Error> {
-        let atom = self.new_atom(ctxt);
-        let ret = unsafe { ffi::JS_HasProperty(ctxt.as_ptr(), this.raw(), atom) };
+        let atom = ctxt.try_new_atom(self)?;
+        let ret = unsafe { ffi::JS_HasProperty(ctxt.as_ptr(), this.raw(), *atom) };
 
-        ctxt.free_atom(atom);
         ctxt.check_bool(ret)
     }
 }
@@ -205,12 +247,11 @@ where
     T: NewAtom,
 {
     fn delete_property(self, ctxt: &ContextRef, this: &Value) -> Result<bool, Error> {
-        let atom = self.new_atom(ctxt);
+        let atom = ctxt.try_new_atom(self)?;
         let ret = unsafe {
-            ffi::JS_DeleteProperty(ctxt.as_ptr(), this.raw(), atom, ffi::JS_PROP_THROW as i32)
+            ffi::JS_DeleteProperty(ctxt.as_ptr(), this.raw(), *atom, ffi::JS_PROP_THROW as i32)
         };
 
-        ctxt.free_atom(atom);
         ctxt.check_bool(ret)
     }
 }
@@ -242,7 +283,7 @@ where
         setter: Option<&Value>,
         mut flags: Prop,
     ) -> Result<bool, Error> {
-        let atom = self.new_atom(ctxt);
+        let atom = ctxt.try_new_atom(self)?;
         if val.is_some() {
             flags |= Prop::HAS_VALUE;
         }
@@ -256,14 +297,13 @@ where
             ffi::JS_DefineProperty(
                 ctxt.as_ptr(),
                 this.raw(),
-                atom,
+                *atom,
                 val.map_or(ffi::UNDEFINED, |v| v.raw()),
                 getter.map_or(ffi::UNDEFINED, |v| v.raw()),
                 setter.map_or(ffi::UNDEFINED, |v| v.raw()),
                 flags.bits as i32,
             )
         };
-        ctxt.free_atom(atom);
         ctxt.check_bool(ret)
     }
 }
@@ -339,6 +379,19 @@ impl DefinePropertyValue for Local<'_, ffi::JSAtom> {
     }
 }
 
+impl DefinePropertyValue for &Value {
+    fn define_property<T: NewValue>(
+        self,
+        ctxt: &ContextRef,
+        this: &Value,
+        val: T,
+        flags: Prop,
+    ) -> Result<bool, Error> {
+        ctxt.try_new_atom(self)?
+            .define_property(ctxt, this, val, flags)
+    }
+}
+
 pub trait DefinePropertyGetSet {
     /// Defines a new property with getter and setter directly on an object, or modifies an existing property on an object.
     fn define_property(
@@ -363,7 +416,7 @@ where
         setter: Option<&Value>,
         mut flags: Prop,
     ) -> Result<bool, Error> {
-        let atom = self.new_atom(ctxt);
+        let atom = ctxt.try_new_atom(self)?;
         if getter.is_some() {
             flags |= Prop::HAS_GET;
         }
@@ -374,13 +427,12 @@ where
             ffi::JS_DefinePropertyGetSet(
                 ctxt.as_ptr(),
                 this.raw(),
-                atom,
+                *atom,
                 getter.map_or(ffi::UNDEFINED, |v| v.raw()),
                 setter.map_or(ffi::UNDEFINED, |v| v.raw()),
                 flags.bits as i32,
             )
         };
-        ctxt.free_atom(atom);
         ctxt.check_bool(ret)
     }
 }
@@ -431,6 +483,15 @@ impl<'a> Local<'a, Value> {
         self.ctxt.get_property(self, prop)
     }
 
+    /// Extract a property value on an object, the missing property is extracted from `undefined`.
+    pub fn extract_property<T: GetProperty, V: ExtractValue>(&self, prop: T) -> Option<V> {
+        let value = self
+            .get_property(prop)
+            .unwrap_or_else(|| self.ctxt.undefined());
+
+        V::extract_value(&value)
+    }
+
     /// Set a property value on an object.
     pub fn set_property<T: SetProperty, V: NewValue>(
         &self,
@@ -440,6 +501,15 @@ impl<'a> Local<'a, Value> {
         self.ctxt.set_property(self, prop, val)
     }
 
+    /// Set a property value on an object, returns the reason if the assignment failed.
+    pub fn set_property_checked<T: NewAtom, V: NewValue>(
+        &self,
+        prop: T,
+        val: V,
+    ) -> Result<(), SetFailure> {
+        self.ctxt.set_property_checked(self, prop, val)
+    }
+
     /// Check if a property on an object.
     pub fn has_property<T: HasProperty>(&self, prop: T) -> Result<bool, Error> {
         self.ctxt.has_property(self, prop)
@@ -498,6 +568,26 @@ impl<'a> Local<'a, Value> {
     pub fn prevent_extensions(&self) -> Result<bool, Error> {
         self.ctxt.prevent_extensions(self)
     }
+
+    /// Seals an object, like `Object.seal`.
+    pub fn seal(&self) -> Result<(), Error> {
+        self.ctxt.seal(self)
+    }
+
+    /// Freezes an object, like `Object.freeze`.
+    pub fn freeze(&self) -> Result<(), Error> {
+        self.ctxt.freeze(self)
+    }
+
+    /// Determines if an object is sealed, like `Object.isSealed`.
+    pub fn is_sealed(&self) -> Result<bool, Error> {
+        self.ctxt.is_sealed(self)
+    }
+
+    /// Determines if an object is frozen, like `Object.isFrozen`.
+    pub fn is_frozen(&self) -> Result<bool, Error> {
+        self.ctxt.is_frozen(self)
+    }
 }
 
 impl ContextRef {
@@ -540,11 +630,11 @@ impl ContextRef {
         value: &Value,
         prop: T,
     ) -> Result<Option<Descriptor>, Error> {
-        let atom = prop.new_atom(self);
+        let atom = self.try_new_atom(prop)?;
         let mut desc = MaybeUninit::<ffi::JSPropertyDescriptor>::uninit();
         let res =
-            unsafe { ffi::JS_GetOwnProperty(self.as_ptr(), desc.as_mut_ptr(), value.raw(), atom) };
-        self.free_atom(atom);
+            unsafe { ffi::JS_GetOwnProperty(self.as_ptr(), desc.as_mut_ptr(), value.raw(), *atom) };
+        drop(atom);
 
         self.check_bool(res).map(|exists| {
             if exists {
@@ -577,9 +667,95 @@ impl ContextRef {
         prop: T,
         val: V,
     ) -> Result<bool, Error> {
+        // the value may be an exception, e.g. an integer rejected by the `Int64Policy`
+        let val = self.bind(val.new_value(self)).ok()?;
+
         prop.set_property(self, this, val)
     }
 
+    /// Set a property value on an object, returns the reason if the assignment failed.
+    ///
+    /// The assignment doesn't throw when it fails silently (non-writable, proxy veto etc),
+    /// the property descriptors on the prototype chain will be inspected to find out the reason.
+    pub fn set_property_checked<T: NewAtom, V: NewValue>(
+        &self,
+        this: &Value,
+        prop: T,
+        val: V,
+    ) -> Result<(), SetFailure> {
+        let atom = self.try_new_atom(prop)?;
+        let name = atom.to_string();
+        let val = self.bind(val.new_value(self)).ok()?.into_inner().raw();
+
+        if !this.is_object() {
+            self.free_value(val);
+
+            return Err(SetFailure::NotObject(name));
+        }
+
+        let found = match self.lookup_property(this, &atom) {
+            Ok(found) => found,
+            Err(err) => {
+                self.free_value(val);
+
+                return Err(err.into());
+            }
+        };
+
+        if let Some((ref desc, _)) = found {
+            // QuickJS ignores the assignment to an accessor without setter
+            if desc.getter.is_some() && desc.setter.is_none() {
+                self.free_value(val);
+
+                return Err(SetFailure::NoSetter(name));
+            }
+        }
+
+        let ret = unsafe { ffi::JS_SetPropertyInternal(self.as_ptr(), this.raw(), *atom, val, 0) };
+
+        if self.check_bool(ret)? {
+            return Ok(());
+        }
+
+        match found {
+            Some((ref desc, _)) if !desc.writable && desc.setter.is_none() => {
+                Err(SetFailure::ReadOnly(name))
+            }
+            Some((_, true)) => Err(SetFailure::Rejected(name)),
+            _ if !self.is_extensible(this)? => Err(SetFailure::NotExtensible(name)),
+            _ => Err(SetFailure::Rejected(name)),
+        }
+    }
+
+    /// Find the property descriptor on the prototype chain, and whether it is an own property.
+    fn lookup_property(
+        &self,
+        this: &Value,
+        atom: &Atom,
+    ) -> Result<Option<(Descriptor, bool)>, Error> {
+        let mut obj = Value::from(this.raw());
+        let mut own = true;
+
+        loop {
+            if let Some(desc) = self.get_own_property_descriptor(&obj, atom)? {
+                return Ok(Some((desc, own)));
+            }
+
+            // the prototype is not reference counted
+            let proto = Value::from(unsafe { ffi::JS_GetPrototype(self.as_ptr(), obj.raw()) });
+
+            if proto.is_exception() {
+                return Err(self.take_exception()?.into());
+            }
+            if !proto.is_object() {
+                return Ok(None);
+            }
+
+            obj = proto;
+            own = false;
+        }
+    }
+
     /// Check if a property on an object.
     pub fn has_property<T: HasProperty>(&self, this: &Value, prop: T) -> Result<bool, Error> {
         prop.has_property(self, this)
@@ -639,12 +815,145 @@ impl ContextRef {
     pub fn prevent_extensions(&self, obj: &Value) -> Result<bool, Error> {
         self.check_bool(unsafe { ffi::JS_PreventExtensions(self.as_ptr(), obj.raw()) })
     }
+
+    /// Seals an object, like `Object.seal`.
+    ///
+    /// New properties can't be added and the existing properties are marked as non-configurable.
+    pub fn seal(&self, obj: &Value) -> Result<(), Error> {
+        self.set_integrity_level(obj, false)
+    }
+
+    /// Freezes an object, like `Object.freeze`.
+    ///
+    /// The object is sealed and the values of the data properties can't be changed.
+    pub fn freeze(&self, obj: &Value) -> Result<(), Error> {
+        self.set_integrity_level(obj, true)
+    }
+
+    /// Determines if an object is sealed, like `Object.isSealed`.
+    pub fn is_sealed(&self, obj: &Value) -> Result<bool, Error> {
+        self.test_integrity_level(obj, false)
+    }
+
+    /// Determines if an object is frozen, like `Object.isFrozen`.
+    pub fn is_frozen(&self, obj: &Value) -> Result<bool, Error> {
+        self.test_integrity_level(obj, true)
+    }
+
+    fn set_integrity_level(&self, obj: &Value, frozen: bool) -> Result<(), Error> {
+        if !obj.is_object() {
+            return Ok(());
+        }
+
+        if !self.prevent_extensions(obj)? {
+            return Err("fail to prevent extensions".into());
+        }
+
+        for name in self
+            .get_own_property_names(obj, Names::STRING | Names::SYMBOL)?
+            .unwrap_or_default()
+        {
+            let mut flags = Prop::HAS_CONFIGURABLE | Prop::THROW;
+
+            // the accessor properties have no writable attribute
+            if frozen {
+                let is_data = matches!(
+                    self.get_own_property_descriptor(obj, &name)?,
+                    Some(ref desc) if desc.getter.is_none() && desc.setter.is_none()
+                );
+
+                if is_data {
+                    flags |= Prop::HAS_WRITABLE;
+                }
+            }
+
+            self.define_property(obj, &name, None, None, None, flags)?;
+        }
+
+        Ok(())
+    }
+
+    fn test_integrity_level(&self, obj: &Value, frozen: bool) -> Result<bool, Error> {
+        if !obj.is_object() {
+            return Ok(true);
+        }
+
+        if self.is_extensible(obj)? {
+            return Ok(false);
+        }
+
+        for name in self
+            .get_own_property_names(obj, Names::STRING | Names::SYMBOL)?
+            .unwrap_or_default()
+        {
+            if let Some(desc) = self.get_own_property_descriptor(obj, &name)? {
+                let is_data = desc.getter.is_none() && desc.setter.is_none();
+
+                if desc.configurable || (frozen && is_data && desc.writable) {
+                    return Ok(false);
+                }
+            }
+        }
+
+        Ok(true)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{Context, ErrorKind, Eval, Runtime};
 
+    use super::*;
+
+    #[test]
+    fn seal_and_freeze() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let sealed = ctxt
+            .eval_script(
+                "globalThis.sealed = { a: 1, get b() { return 2 } }",
+                "<evalScript>",
+                Eval::GLOBAL,
+            )
+            .unwrap();
+
+        assert!(!sealed.is_sealed().unwrap());
+        sealed.seal().unwrap();
+        assert!(sealed.is_sealed().unwrap());
+        assert!(!sealed.is_frozen().unwrap());
+        assert_eq!(
+            ctxt.eval::<_, String>(
+                "sealed.a = 3; [Object.isSealed(sealed), delete sealed.a, sealed.a, sealed.b].join()",
+                Eval::GLOBAL
+            )
+            .unwrap(),
+            Some("true,false,3,2".to_owned())
+        );
+
+        let frozen = ctxt
+            .eval_script(
+                "globalThis.frozen = { a: 1, get b() { return 2 }, [Symbol.iterator]: null }",
+                "<evalScript>",
+                Eval::GLOBAL,
+            )
+            .unwrap();
+
+        frozen.freeze().unwrap();
+        assert!(frozen.is_frozen().unwrap());
+        assert_eq!(
+            ctxt.eval::<_, String>(
+                "frozen.a = 3; frozen.c = 4; [Object.isFrozen(frozen), frozen.a, frozen.b, frozen.c].join()",
+                Eval::GLOBAL
+            )
+            .unwrap(),
+            Some("true,1,2,".to_owned())
+        );
+        assert!(ctxt.bind(1).is_frozen().unwrap());
+    }
+
     #[test]
     fn set_property() {
         let _ = pretty_env_logger::try_init();
@@ -685,6 +994,71 @@ mod tests {
         assert!(!obj.has_property("foo").unwrap());
     }
 
+    #[test]
+    fn set_property_checked() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let obj = ctxt
+            .eval_script(
+                r#"
+var base = Object.defineProperty({}, "inherited", { value: 1 });
+var obj = Object.create(base, {
+    readonly: { value: 1 },
+    getter: { get() { return 1; } },
+    writable: { value: 1, writable: true },
+});
+obj"#,
+                "<evalScript>",
+                Eval::GLOBAL,
+            )
+            .unwrap();
+
+        assert!(obj.set_property_checked("writable", 2).is_ok());
+        assert!(matches!(
+            obj.set_property_checked("readonly", 2),
+            Err(SetFailure::ReadOnly(ref name)) if name == "readonly"
+        ));
+        assert!(matches!(
+            obj.set_property_checked("inherited", 2),
+            Err(SetFailure::ReadOnly(_))
+        ));
+        assert!(matches!(
+            obj.set_property_checked("getter", 2),
+            Err(SetFailure::NoSetter(_))
+        ));
+
+        obj.prevent_extensions().unwrap();
+
+        assert!(matches!(
+            obj.set_property_checked("foo", 2),
+            Err(SetFailure::NotExtensible(_))
+        ));
+        assert!(matches!(
+            ctxt.set_property_checked(&Value::from(1), "foo", 2),
+            Err(SetFailure::NotObject(_))
+        ));
+
+        let proxy = ctxt
+            .eval_script(
+                "new Proxy({}, { set() { return false; } })",
+                "<evalScript>",
+                Eval::GLOBAL,
+            )
+            .unwrap();
+
+        assert!(matches!(
+            proxy.set_property_checked("foo", 2),
+            Err(SetFailure::Rejected(_))
+        ));
+
+        let err = proxy.set_property_checked("foo", 2).unwrap_err();
+
+        assert_eq!(err.to_string(), "assignment to property `foo` was rejected");
+    }
+
     #[test]
     fn extensible() {
         let _ = pretty_env_logger::try_init();
@@ -703,7 +1077,7 @@ mod tests {
         assert_eq!(
             obj.set_property("foo", "bar")
                 .unwrap_err()
-                .downcast::<ErrorKind>()
+                .into_kind()
                 .unwrap(),
             ErrorKind::TypeError("object is not extensible".into(), None)
         );
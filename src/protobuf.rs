@@ -0,0 +1,442 @@
+use std::collections::HashMap;
+
+use prost_reflect::{
+    bytes::Bytes, DynamicMessage, Kind, MapKey, MessageDescriptor, ReflectMessage, Value as Field,
+};
+
+use crate::{prop::Names, ContextRef, Error, Limits, Local, Value};
+
+fn map_key_to_string(key: &MapKey) -> String {
+    match key {
+        MapKey::Bool(b) => b.to_string(),
+        MapKey::I32(n) => n.to_string(),
+        MapKey::I64(n) => n.to_string(),
+        MapKey::U32(n) => n.to_string(),
+        MapKey::U64(n) => n.to_string(),
+        MapKey::String(s) => s.clone(),
+    }
+}
+
+fn map_key_from_str(kind: &Kind, key: &str) -> Option<MapKey> {
+    match kind {
+        Kind::Bool => key.parse().ok().map(MapKey::Bool),
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => key.parse().ok().map(MapKey::I32),
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => key.parse().ok().map(MapKey::I64),
+        Kind::Uint32 | Kind::Fixed32 => key.parse().ok().map(MapKey::U32),
+        Kind::Uint64 | Kind::Fixed64 => key.parse().ok().map(MapKey::U64),
+        Kind::String => Some(MapKey::String(key.to_owned())),
+        _ => None,
+    }
+}
+
+impl ContextRef {
+    /// Convert a protobuf message to a plain Javascript object.
+    ///
+    /// The fields are named in the JSON style (`lowerCamelCase`), the `bytes` fields are converted to `Uint8Array`,
+    /// the enums are converted to their names, and the 64-bit integers are converted to numbers.
+    pub fn to_js_message<T: ReflectMessage>(&self, msg: &T) -> Result<Local<Value>, Error> {
+        self.message_to_js(&msg.transcode_to_dynamic())
+    }
+
+    /// Convert a plain Javascript object to a protobuf message.
+    ///
+    /// The fields could be named in the JSON style or the original style,
+    /// the `null` or `undefined` fields are skipped, and the enums could be their names or numbers.
+    pub fn from_js_message<T: ReflectMessage + Default>(&self, value: &Value) -> Result<T, Error> {
+        self.from_js_dynamic_message(value, T::default().descriptor())?
+            .transcode_to()
+            .map_err(|err| Error::Msg(err.to_string()))
+    }
+
+    /// Convert a plain Javascript object to a dynamic protobuf message of the descriptor.
+    ///
+    /// The conversion is restricted by the `conversion_limits` of the context.
+    pub fn from_js_dynamic_message(
+        &self,
+        value: &Value,
+        desc: MessageDescriptor,
+    ) -> Result<DynamicMessage, Error> {
+        self.js_to_message(value, desc, &self.conversion_limits(), 0)
+    }
+
+    fn js_to_message(
+        &self,
+        value: &Value,
+        desc: MessageDescriptor,
+        limits: &Limits,
+        depth: usize,
+    ) -> Result<DynamicMessage, Error> {
+        limits.check_depth(depth)?;
+
+        if !value.is_object() {
+            return Err(format!("expected `{}` object", desc.full_name()).into());
+        }
+
+        let mut msg = DynamicMessage::new(desc.clone());
+
+        for field in desc.fields() {
+            let prop = match self
+                .get_property(value, field.json_name())
+                .or_else(|| self.get_property(value, field.name()))
+            {
+                Some(prop) if !prop.is_null() => prop,
+                _ => continue,
+            };
+            let kind = field.kind();
+            let value = if field.is_map() {
+                self.js_to_map(&kind, &prop, limits, depth + 1)?
+            } else if field.is_list() {
+                self.js_to_list(&kind, &prop, limits, depth + 1)?
+            } else {
+                self.js_to_field(&kind, &prop, limits, depth)?
+            };
+
+            msg.try_set_field(&field, value)
+                .map_err(|err| format!("`{}`: {}", field.full_name(), err))?;
+        }
+
+        Ok(msg)
+    }
+
+    fn message_to_js(&self, msg: &DynamicMessage) -> Result<Local<Value>, Error> {
+        let obj = self.bind(self.new_object());
+
+        for field in msg.descriptor().fields() {
+            if field.supports_presence() && !msg.has_field(&field) {
+                continue;
+            }
+
+            obj.set_property(
+                field.json_name(),
+                self.field_to_js(&field.kind(), &msg.get_field(&field))?,
+            )?;
+        }
+
+        Ok(obj)
+    }
+
+    fn field_to_js(&self, kind: &Kind, field: &Field) -> Result<Local<Value>, Error> {
+        Ok(match field {
+            Field::Bool(b) => self.bind(*b),
+            Field::I32(n) => self.bind(*n),
+            Field::I64(n) => self.bind(*n),
+            Field::U32(n) => self.bind(*n),
+            Field::U64(n) => self.bind(*n as f64),
+            Field::F32(n) => self.bind(*n),
+            Field::F64(n) => self.bind(*n),
+            Field::String(s) => self.bind(s.as_str()),
+            Field::Bytes(b) => self.new_uint8_array(b)?,
+            Field::EnumNumber(n) => match kind.as_enum().and_then(|desc| desc.get_value(*n)) {
+                Some(value) => self.bind(value.name()),
+                None => self.bind(*n),
+            },
+            Field::Message(msg) => self.message_to_js(msg)?,
+            Field::List(fields) => {
+                let arr = self.bind(self.new_array());
+
+                for (idx, field) in fields.iter().enumerate() {
+                    arr.set_property(idx as u32, self.field_to_js(kind, field)?)?;
+                }
+
+                arr
+            }
+            Field::Map(entries) => {
+                let kind = kind
+                    .as_message()
+                    .map(|entry| entry.map_entry_value_field().kind())
+                    .ok_or_else(|| Error::from("expected map entry"))?;
+                let obj = self.bind(self.new_object());
+
+                for (key, field) in entries {
+                    obj.set_property(
+                        map_key_to_string(key).as_str(),
+                        self.field_to_js(&kind, field)?,
+                    )?;
+                }
+
+                obj
+            }
+        })
+    }
+
+    fn js_to_field(
+        &self,
+        kind: &Kind,
+        value: &Value,
+        limits: &Limits,
+        depth: usize,
+    ) -> Result<Field, Error> {
+        let field = match kind {
+            Kind::Double => self.to_float64(value).map(Field::F64),
+            Kind::Float => self.to_float64(value).map(|n| Field::F32(n as f32)),
+            Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => self.to_int32(value).map(Field::I32),
+            Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => self.to_int64(value).map(Field::I64),
+            Kind::Uint32 | Kind::Fixed32 => self.to_int64(value).map(|n| Field::U32(n as u32)),
+            Kind::Uint64 | Kind::Fixed64 => self.to_int64(value).map(|n| Field::U64(n as u64)),
+            Kind::Bool => self.to_bool(value).map(Field::Bool),
+            Kind::String => match self.to_cstring(value) {
+                Some(s) => {
+                    limits.check_string_bytes(s.as_bytes().len())?;
+
+                    Some(Field::String(s.to_string_lossy().into_owned()))
+                }
+                None => None,
+            },
+            Kind::Bytes => return self.js_to_bytes(value, limits).map(Field::Bytes),
+            Kind::Enum(desc) if value.is_string() => self
+                .to_cstring(value)
+                .and_then(|name| desc.get_value_by_name(&name.to_string_lossy()))
+                .map(|value| Field::EnumNumber(value.number())),
+            Kind::Enum(_) => self.to_int32(value).map(Field::EnumNumber),
+            Kind::Message(desc) => {
+                return self
+                    .js_to_message(value, desc.clone(), limits, depth + 1)
+                    .map(Field::Message)
+            }
+        };
+
+        field.ok_or_else(|| format!("invalid {:?} value", kind).into())
+    }
+
+    fn js_to_list(
+        &self,
+        kind: &Kind,
+        value: &Value,
+        limits: &Limits,
+        depth: usize,
+    ) -> Result<Field, Error> {
+        limits.check_depth(depth)?;
+
+        let len = self
+            .get_property(value, "length")
+            .and_then(|len| len.to_index())
+            .ok_or_else(|| Error::from("expected array"))?;
+
+        limits.check_elements(len as usize)?;
+
+        (0..len as u32)
+            .map(|idx| {
+                self.get_property(value, idx)
+                    .ok_or_else(|| format!("missing element #{}", idx).into())
+                    .and_then(|elem| self.js_to_field(kind, &elem, limits, depth))
+            })
+            .collect::<Result<Vec<_>, Error>>()
+            .map(Field::List)
+    }
+
+    fn js_to_map(
+        &self,
+        kind: &Kind,
+        value: &Value,
+        limits: &Limits,
+        depth: usize,
+    ) -> Result<Field, Error> {
+        limits.check_depth(depth)?;
+
+        let entry = kind
+            .as_message()
+            .ok_or_else(|| Error::from("expected map entry"))?;
+        let (key_kind, value_kind) = (
+            entry.map_entry_key_field().kind(),
+            entry.map_entry_value_field().kind(),
+        );
+        let names = self
+            .get_own_property_names(value, Names::STRING | Names::ENUM_ONLY)?
+            .unwrap_or_default();
+        let mut entries = HashMap::new();
+
+        limits.check_elements(names.len())?;
+
+        for name in names {
+            let key = name.to_string();
+            let field = self
+                .get_property(value, name)
+                .ok_or_else(|| format!("missing entry `{}`", key))?;
+
+            entries.insert(
+                map_key_from_str(&key_kind, &key)
+                    .ok_or_else(|| format!("invalid {:?} key `{}`", key_kind, key))?,
+                self.js_to_field(&value_kind, &field, limits, depth)?,
+            );
+        }
+
+        Ok(Field::Map(entries))
+    }
+
+    fn js_to_bytes(&self, value: &Value, limits: &Limits) -> Result<Bytes, Error> {
+        self.with_buffer_source(value, |buf| {
+            limits.check_string_bytes(buf.len())?;
+
+            Ok(Bytes::copy_from_slice(buf))
+        })
+        .unwrap_or_else(|| Err("expected Uint8Array or ArrayBuffer".into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost_reflect::prost_types::{
+        field_descriptor_proto::{Label, Type},
+        DescriptorProto, EnumDescriptorProto, EnumValueDescriptorProto, FieldDescriptorProto,
+        FileDescriptorProto, MessageOptions,
+    };
+    use prost_reflect::DescriptorPool;
+
+    use crate::{Context, Eval, Runtime};
+
+    use super::*;
+
+    fn field(name: &str, number: i32, ty: Type, type_name: Option<&str>) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            name: Some(name.to_owned()),
+            number: Some(number),
+            label: Some(Label::Optional as i32),
+            r#type: Some(ty as i32),
+            type_name: type_name.map(ToOwned::to_owned),
+            ..Default::default()
+        }
+    }
+
+    fn point() -> MessageDescriptor {
+        let repeated = |field: FieldDescriptorProto| FieldDescriptorProto {
+            label: Some(Label::Repeated as i32),
+            ..field
+        };
+        let file = FileDescriptorProto {
+            name: Some("point.proto".to_owned()),
+            package: Some("test".to_owned()),
+            syntax: Some("proto3".to_owned()),
+            message_type: vec![DescriptorProto {
+                name: Some("Point".to_owned()),
+                field: vec![
+                    field("point_name", 1, Type::String, None),
+                    field("data", 2, Type::Bytes, None),
+                    field("color", 3, Type::Enum, Some(".test.Color")),
+                    repeated(field("values", 4, Type::Int32, None)),
+                    repeated(field(
+                        "tags",
+                        5,
+                        Type::Message,
+                        Some(".test.Point.TagsEntry"),
+                    )),
+                    field("child", 6, Type::Message, Some(".test.Point")),
+                ],
+                nested_type: vec![DescriptorProto {
+                    name: Some("TagsEntry".to_owned()),
+                    field: vec![
+                        field("key", 1, Type::String, None),
+                        field("value", 2, Type::Int32, None),
+                    ],
+                    options: Some(MessageOptions {
+                        map_entry: Some(true),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            enum_type: vec![EnumDescriptorProto {
+                name: Some("Color".to_owned()),
+                value: vec![
+                    EnumValueDescriptorProto {
+                        name: Some("RED".to_owned()),
+                        number: Some(0),
+                        ..Default::default()
+                    },
+                    EnumValueDescriptorProto {
+                        name: Some("GREEN".to_owned()),
+                        number: Some(1),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let mut pool = DescriptorPool::new();
+
+        pool.add_file_descriptor_proto(file).unwrap();
+        pool.get_message_by_name("test.Point").unwrap()
+    }
+
+    #[test]
+    fn protobuf() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+        let desc = point();
+
+        let obj = ctxt
+            .eval_script(
+                r#"({
+    pointName: "origin",
+    data: new Uint8Array([1, 2, 3]).subarray(1),
+    color: "GREEN",
+    values: [1, 2],
+    tags: { a: 1 },
+    child: { point_name: "child", color: 0 },
+})"#,
+                "<evalScript>",
+                Eval::GLOBAL,
+            )
+            .unwrap();
+        let msg = ctxt.from_js_dynamic_message(&obj, desc.clone()).unwrap();
+
+        assert_eq!(
+            msg.get_field_by_name("data").unwrap().as_bytes().unwrap(),
+            &[2, 3][..]
+        );
+        assert_eq!(
+            msg.get_field_by_name("color").unwrap().as_enum_number(),
+            Some(1)
+        );
+
+        ctxt.global_object()
+            .set_property("msg", ctxt.to_js_message(&msg).unwrap())
+            .unwrap();
+
+        assert_eq!(
+            ctxt.eval::<_, String>(
+                "[msg.pointName, msg.data instanceof Uint8Array, msg.data.join('|'), msg.color, \
+                 msg.values, msg.tags.a, msg.child.pointName, msg.child.color, msg.child.child].join()",
+                Eval::GLOBAL
+            )
+            .unwrap(),
+            Some("origin,true,2|3,GREEN,1,2,1,child,RED,".to_owned())
+        );
+
+        assert!(ctxt
+            .from_js_dynamic_message(&ctxt.bind(42), desc.clone())
+            .is_err());
+
+        let cyclic = ctxt
+            .eval_script(
+                "var p = { values: [1, 2, 3] }; p.child = p",
+                "<evalScript>",
+                Eval::GLOBAL,
+            )
+            .unwrap();
+
+        assert!(matches!(
+            ctxt.from_js_dynamic_message(&cyclic, desc.clone()),
+            Err(Error::Limit("depth", 64))
+        ));
+
+        ctxt.set_conversion_limits(Limits {
+            max_elements: 2,
+            ..Default::default()
+        });
+
+        assert!(matches!(
+            ctxt.from_js_dynamic_message(&cyclic, desc.clone()),
+            Err(Error::Limit("elements", 2))
+        ));
+
+        ctxt.set_conversion_limits(Default::default());
+        assert!(ctxt
+            .eval_script("({ color: 'BLUE' })", "<evalScript>", Eval::GLOBAL)
+            .and_then(|obj| ctxt.from_js_dynamic_message(&obj, desc))
+            .is_err());
+    }
+}
@@ -0,0 +1,378 @@
+use std::os::raw::c_int;
+use std::panic::{self, AssertUnwindSafe};
+
+use foreign_types::ForeignTypeRef;
+
+use crate::{cfunc::c_args, ffi, ContextRef, Error, JsArray, Local, NewValue, Value, UNDEFINED};
+
+/// The traps of the handler, indexed by the magic of the trap functions.
+const TRAPS: [&str; 8] = [
+    "get",
+    "set",
+    "has",
+    "deleteProperty",
+    "ownKeys",
+    "getOwnPropertyDescriptor",
+    "apply",
+    "construct",
+];
+
+const GET: c_int = 0;
+const SET: c_int = 1;
+const HAS: c_int = 2;
+const DELETE: c_int = 3;
+const OWN_KEYS: c_int = 4;
+const DESCRIPTOR: c_int = 5;
+const APPLY: c_int = 6;
+const CONSTRUCT: c_int = 7;
+
+/// The Rust handler of a Javascript `Proxy`, created by `ContextRef::new_proxy`.
+///
+/// Each trap returns `None` to forward the operation to the target like `Reflect`,
+/// the symbol keys are always forwarded to the target.
+///
+/// The properties reported by `has` are exposed as the own enumerable properties,
+/// so `Object.keys` and `JSON.stringify` work with the keys from `own_keys`.
+pub trait ProxyHandler: Send + 'static {
+    /// Get the property value.
+    fn get<'a>(
+        &self,
+        _ctxt: &'a ContextRef,
+        _target: &Value,
+        _key: &str,
+    ) -> Result<Option<Local<'a, Value>>, Error> {
+        Ok(None)
+    }
+
+    /// Set the property value, returns `false` if the assignment failed.
+    fn set(
+        &self,
+        _ctxt: &ContextRef,
+        _target: &Value,
+        _key: &str,
+        _value: &Value,
+    ) -> Result<Option<bool>, Error> {
+        Ok(None)
+    }
+
+    /// Check if the property exists, for the `in` operator.
+    fn has(&self, _ctxt: &ContextRef, _target: &Value, _key: &str) -> Result<Option<bool>, Error> {
+        Ok(None)
+    }
+
+    /// Delete the property, returns `false` if the property can't be deleted.
+    fn delete(
+        &self,
+        _ctxt: &ContextRef,
+        _target: &Value,
+        _key: &str,
+    ) -> Result<Option<bool>, Error> {
+        Ok(None)
+    }
+
+    /// Returns the own property keys.
+    fn own_keys(&self, _ctxt: &ContextRef, _target: &Value) -> Result<Option<Vec<String>>, Error> {
+        Ok(None)
+    }
+
+    /// Call the proxy as a function, the target must be a function.
+    fn apply<'a>(
+        &self,
+        _ctxt: &'a ContextRef,
+        _target: &Value,
+        _this: &Value,
+        _args: &[Value],
+    ) -> Result<Option<Local<'a, Value>>, Error> {
+        Ok(None)
+    }
+
+    /// Call the proxy as a constructor, the target must be a constructor.
+    fn construct<'a>(
+        &self,
+        _ctxt: &'a ContextRef,
+        _target: &Value,
+        _args: &[Value],
+    ) -> Result<Option<Local<'a, Value>>, Error> {
+        Ok(None)
+    }
+}
+
+impl ContextRef {
+    /// Create a `Proxy` of the target, which operations are handled by the Rust handler.
+    ///
+    /// The handler is owned by the proxy, and dropped when the proxy is finalized.
+    pub fn new_proxy<H: ProxyHandler>(
+        &self,
+        target: &Value,
+        handler: H,
+    ) -> Result<Local<Value>, Error> {
+        let handler = self.wrap_native(handler)?;
+        let traps = self.bind(self.new_object());
+
+        for (magic, name) in TRAPS.iter().enumerate() {
+            let func = self.new_c_function_data(proxy_trap::<H>, 0, magic as i32, &handler)?;
+
+            traps.set_property(*name, func)?;
+        }
+
+        let proxy = self
+            .get_property(&self.global_object(), "Proxy")
+            .ok_or("`Proxy` is not available")?;
+
+        self.call_constructor(&proxy, [target, &traps])
+    }
+}
+
+unsafe extern "C" fn proxy_trap<H: ProxyHandler>(
+    ctx: *mut ffi::JSContext,
+    _this_val: ffi::JSValue,
+    argc: c_int,
+    argv: *mut ffi::JSValue,
+    magic: c_int,
+    data: *mut ffi::JSValue,
+) -> ffi::JSValue {
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        let ctxt = ContextRef::from_ptr(ctx);
+        let args = c_args(argc, argv);
+
+        match ctxt.downcast_native::<H>(&*(data as *const Value)) {
+            Some(handler) => trap(ctxt, handler, magic, args),
+            None => Err("expected proxy handler".into()),
+        }
+        .new_value(ctxt)
+    }))
+    .unwrap_or_default()
+}
+
+fn trap<'a, H: ProxyHandler>(
+    ctxt: &'a ContextRef,
+    handler: &H,
+    magic: c_int,
+    args: &[Value],
+) -> Result<Local<'a, Value>, Error> {
+    let arg = |idx: usize| args.get(idx).unwrap_or(&UNDEFINED);
+    let target = arg(0);
+    let key = arg(1);
+    let key = if key.is_symbol() {
+        None
+    } else {
+        ctxt.to_cstring(key)
+            .map(|s| s.to_string_lossy().to_string())
+    };
+    let new_bool = |b| ctxt.bind(ctxt.new_value(b));
+
+    let handled = match (magic, key) {
+        (GET, Some(key)) => handler.get(ctxt, target, &key)?,
+        (SET, Some(key)) => handler.set(ctxt, target, &key, arg(2))?.map(new_bool),
+        (HAS, Some(key)) => handler.has(ctxt, target, &key)?.map(new_bool),
+        (DELETE, Some(key)) => handler.delete(ctxt, target, &key)?.map(new_bool),
+        (OWN_KEYS, _) => match handler.own_keys(ctxt, target)? {
+            Some(keys) => Some(ctxt.bind(ctxt.new_value(JsArray::from_iter(ctxt, keys)?))),
+            None => None,
+        },
+        // the own properties of the target must be reported as is
+        (DESCRIPTOR, Some(key))
+            if ctxt
+                .get_own_property_descriptor(target, key.as_str())?
+                .is_none() =>
+        {
+            match handler.has(ctxt, target, &key)? {
+                Some(true) => {
+                    let desc = ctxt.bind(ctxt.new_object());
+                    let value = handler.get(ctxt, target, &key)?;
+
+                    desc.set_property("value", value.unwrap_or_else(|| ctxt.undefined()))?;
+                    desc.set_property("writable", true)?;
+                    desc.set_property("enumerable", true)?;
+                    desc.set_property("configurable", true)?;
+
+                    Some(desc)
+                }
+                Some(false) => Some(ctxt.undefined()),
+                None => None,
+            }
+        }
+        (APPLY, _) => {
+            let values = array_values(ctxt, arg(2))?;
+            let res = handler.apply(ctxt, target, arg(1), &values);
+
+            values.into_iter().for_each(|v| ctxt.free_value(v));
+
+            res?
+        }
+        (CONSTRUCT, _) => {
+            let values = array_values(ctxt, arg(1))?;
+            let res = handler.construct(ctxt, target, &values);
+
+            values.into_iter().for_each(|v| ctxt.free_value(v));
+
+            res?
+        }
+        _ => None,
+    };
+
+    match handled {
+        Some(value) => Ok(value),
+        None => {
+            let name = TRAPS[magic as usize];
+
+            trace!("forward `{}` trap to the target", name);
+
+            let func = ctxt
+                .get_property(&ctxt.global_object(), "Reflect")
+                .and_then(|reflect| ctxt.get_property(&reflect, name))
+                .ok_or_else(|| format!("`Reflect.{}` is not available", name))?;
+
+            ctxt.call(&func, None, args.iter().collect::<Vec<_>>().as_slice())
+        }
+    }
+}
+
+/// Take the elements of the arguments array, which should be freed by the caller.
+fn array_values(ctxt: &ContextRef, arr: &Value) -> Result<Vec<Value>, Error> {
+    match ctxt.clone_value(arr).into_array() {
+        Ok(arr) => {
+            ctxt.conversion_limits().check_elements(arr.len())?;
+
+            Ok((0..arr.len() as u32)
+                .map(|idx| arr.get(idx).map_or(UNDEFINED, Local::into_inner))
+                .collect())
+        }
+        Err(_) => Ok(vec![]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::BTreeMap;
+
+    use crate::{Context, Eval, Runtime};
+
+    use super::*;
+
+    struct Config(RefCell<BTreeMap<String, String>>);
+
+    impl ProxyHandler for Config {
+        fn get<'a>(
+            &self,
+            ctxt: &'a ContextRef,
+            _target: &Value,
+            key: &str,
+        ) -> Result<Option<Local<'a, Value>>, Error> {
+            Ok(self
+                .0
+                .borrow()
+                .get(key)
+                .map(|value| ctxt.bind(ctxt.new_value(value.as_str()))))
+        }
+
+        fn set(
+            &self,
+            ctxt: &ContextRef,
+            _target: &Value,
+            key: &str,
+            value: &Value,
+        ) -> Result<Option<bool>, Error> {
+            let value = ctxt.to_cstring(value).ok_or("expected string")?;
+
+            self.0
+                .borrow_mut()
+                .insert(key.to_owned(), value.to_string_lossy().to_string());
+
+            Ok(Some(true))
+        }
+
+        fn has(
+            &self,
+            _ctxt: &ContextRef,
+            _target: &Value,
+            key: &str,
+        ) -> Result<Option<bool>, Error> {
+            Ok(Some(self.0.borrow().contains_key(key)))
+        }
+
+        fn delete(
+            &self,
+            _ctxt: &ContextRef,
+            _target: &Value,
+            key: &str,
+        ) -> Result<Option<bool>, Error> {
+            Ok(Some(self.0.borrow_mut().remove(key).is_some()))
+        }
+
+        fn own_keys(
+            &self,
+            _ctxt: &ContextRef,
+            _target: &Value,
+        ) -> Result<Option<Vec<String>>, Error> {
+            Ok(Some(self.0.borrow().keys().cloned().collect()))
+        }
+
+        fn apply<'a>(
+            &self,
+            ctxt: &'a ContextRef,
+            _target: &Value,
+            _this: &Value,
+            args: &[Value],
+        ) -> Result<Option<Local<'a, Value>>, Error> {
+            let key = ctxt.to_cstring(&args[0]).ok_or("expected key")?;
+
+            self.get(ctxt, &UNDEFINED, &key.to_string_lossy())
+        }
+    }
+
+    #[test]
+    fn proxy() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let cfg = Config(RefCell::new(
+            vec![("name".to_owned(), "foo".to_owned())]
+                .into_iter()
+                .collect(),
+        ));
+        let target = ctxt.bind(ctxt.new_object());
+        let proxy = ctxt.new_proxy(&target, cfg).unwrap();
+
+        ctxt.global_object().set_property("cfg", proxy).unwrap();
+
+        assert_eq!(
+            ctxt.eval::<_, String>(
+                r#"
+cfg.mode = 'debug';
+[cfg.name, cfg.mode, 'name' in cfg, 'other' in cfg, Object.keys(cfg), JSON.stringify(cfg)].join()
+"#,
+                Eval::GLOBAL
+            )
+            .unwrap(),
+            Some(r#"foo,debug,true,false,mode,name,{"mode":"debug","name":"foo"}"#.to_owned())
+        );
+        assert_eq!(
+            ctxt.eval::<_, String>("delete cfg.name; Object.keys(cfg).join()", Eval::GLOBAL)
+                .unwrap(),
+            Some("mode".to_owned())
+        );
+
+        let target = ctxt
+            .eval_script("(function () {})", "<evalScript>", Eval::GLOBAL)
+            .unwrap();
+        let lookup = Config(RefCell::new(
+            vec![("answer".to_owned(), "42".to_owned())]
+                .into_iter()
+                .collect(),
+        ));
+
+        ctxt.global_object()
+            .set_property("lookup", ctxt.new_proxy(&target, lookup).unwrap())
+            .unwrap();
+
+        assert_eq!(
+            ctxt.eval::<_, String>("lookup('answer')", Eval::GLOBAL)
+                .unwrap(),
+            Some("42".to_owned())
+        );
+    }
+}
@@ -0,0 +1,304 @@
+use crate::{ContextRef, Error, ErrorKind, Eval};
+
+/// The filename of the inputs in the stack traces.
+const FILENAME: &str = "<repl>";
+
+/// The output of a line fed to the `ReplSession`.
+#[derive(Debug)]
+pub enum ReplOutput {
+    /// The line is blank, nothing was evaluated.
+    Empty,
+    /// The input is incomplete, e.g. an unclosed bracket, more lines are expected.
+    Incomplete,
+    /// The result of the evaluation, formatted by `ContextRef::inspect`.
+    Value(String),
+    /// The evaluation failed.
+    Error(Error),
+}
+
+/// The hook to provide the extra completions of a word.
+type Completer<'a> = Box<dyn Fn(&ContextRef, &str) -> Vec<String> + 'a>;
+
+/// An interactive session evaluating the input line by line, created by `ReplSession::new`.
+///
+/// The session does not read the terminal, so it could be embedded in the TUI or GUI of the application.
+pub struct Session<'a> {
+    ctxt: &'a ContextRef,
+    buf: String,
+    depth: usize,
+    completers: Vec<Completer<'a>>,
+}
+
+impl<'a> Session<'a> {
+    /// Create a session of the context.
+    pub fn new(ctxt: &'a ContextRef) -> Self {
+        Session {
+            ctxt,
+            buf: String::new(),
+            depth: 2,
+            completers: vec![],
+        }
+    }
+
+    /// Set the depth of the nested objects in the formatted results.
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Add a hook to provide the extra completions of a word, e.g. the commands of the application.
+    pub fn completer<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&ContextRef, &str) -> Vec<String> + 'a,
+    {
+        self.completers.push(Box::new(f));
+        self
+    }
+
+    /// Returns the prompt of the next line, `"... "` if the input is incomplete.
+    pub fn prompt(&self) -> &'static str {
+        if self.buf.is_empty() {
+            "> "
+        } else {
+            "... "
+        }
+    }
+
+    /// Returns `true` if the input is incomplete and waiting for more lines.
+    pub fn is_pending(&self) -> bool {
+        !self.buf.is_empty()
+    }
+
+    /// Discard the incomplete input, e.g. when the user presses `Ctrl-C`.
+    pub fn reset(&mut self) {
+        self.buf.clear();
+    }
+
+    /// Feed a line, the input is evaluated when it is complete.
+    pub fn feed_line(&mut self, line: &str) -> ReplOutput {
+        if self.buf.is_empty() && line.trim().is_empty() {
+            return ReplOutput::Empty;
+        }
+
+        self.buf.push_str(line);
+        self.buf.push('\n');
+
+        if is_incomplete(&self.buf) {
+            return ReplOutput::Incomplete;
+        }
+
+        let input = std::mem::take(&mut self.buf);
+
+        trace!("repl eval: {}", input.trim_end());
+
+        match self
+            .ctxt
+            .eval_script(input.as_str(), FILENAME, Eval::GLOBAL)
+        {
+            Ok(value) => ReplOutput::Value(value.inspect(self.depth)),
+            Err(Error::Js(ErrorKind::SyntaxError(ref msg, _)))
+                if msg == "unexpected end of input" =>
+            {
+                self.buf = input;
+
+                ReplOutput::Incomplete
+            }
+            Err(err) => ReplOutput::Error(err),
+        }
+    }
+
+    /// Returns the completions of the word at the end of the line, e.g. `console.lo`.
+    ///
+    /// The properties of the object, including the inherited ones, are completed,
+    /// which may call the getters of the objects on the path.
+    pub fn complete(&self, line: &str) -> Vec<String> {
+        let start = line
+            .rfind(|c: char| !(is_ident_char(c) || c == '.'))
+            .map_or(0, |idx| idx + 1);
+        let word = &line[start..];
+        let (path, prefix) = match word.rfind('.') {
+            Some(idx) => (&word[..idx], &word[..=idx]),
+            None => ("globalThis", ""),
+        };
+
+        let mut completions = if path.split('.').all(is_ident) {
+            self.property_names(path)
+                .into_iter()
+                .map(|name| format!("{}{}", prefix, name))
+                .filter(|s| s.starts_with(word))
+                .collect()
+        } else {
+            vec![]
+        };
+
+        for completer in &self.completers {
+            completions.extend(completer(self.ctxt, word));
+        }
+
+        completions.sort();
+        completions.dedup();
+        completions
+    }
+
+    fn property_names(&self, path: &str) -> Vec<String> {
+        let script = format!(
+            r#"(o => {{
+    const names = [];
+    for (; o != null; o = Object.getPrototypeOf(o)) names.push(...Object.getOwnPropertyNames(o));
+    return names.join('\n');
+}})({})"#,
+            path
+        );
+
+        self.ctxt
+            .eval_script(script, FILENAME, Eval::GLOBAL)
+            .ok()
+            .map(|names| names.to_string())
+            .map_or_else(Vec::new, |names| {
+                names
+                    .lines()
+                    .filter(|s| is_ident(s))
+                    .map(String::from)
+                    .collect()
+            })
+    }
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+fn is_ident(s: &str) -> bool {
+    !s.is_empty() && !s.starts_with(|c: char| c.is_ascii_digit()) && s.chars().all(is_ident_char)
+}
+
+/// Check if the input has the unclosed brackets, template literals or comments.
+///
+/// The regular expression literals are not recognized, so `/[/` is treated as an unclosed bracket.
+fn is_incomplete(input: &str) -> bool {
+    let mut stack = vec![];
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if stack.last() == Some(&'`') {
+            match c {
+                '\\' => {
+                    chars.next();
+                }
+                '`' => {
+                    stack.pop();
+                }
+                '$' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    stack.push('{');
+                }
+                _ => {}
+            }
+
+            continue;
+        }
+
+        match c {
+            '\'' | '"' => {
+                // the strings never span lines without the escaped newline
+                while let Some(next) = chars.next() {
+                    match next {
+                        // the escaped newline at the end continues the string
+                        '\\' if chars.next() == Some('\n') && chars.peek().is_none() => {
+                            return true
+                        }
+                        '\n' => break,
+                        _ if next == c => break,
+                        _ => {}
+                    }
+                }
+            }
+            '`' | '(' | '[' | '{' => stack.push(c),
+            ')' | ']' | '}' => {
+                // the mismatched brackets are reported by the parser
+                stack.pop();
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+
+                let mut closed = false;
+
+                while let Some(c) = chars.next() {
+                    if c == '*' && chars.peek() == Some(&'/') {
+                        chars.next();
+                        closed = true;
+                        break;
+                    }
+                }
+
+                if !closed {
+                    return true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    !stack.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, Runtime};
+
+    use super::*;
+
+    #[test]
+    fn session() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+        let mut session = Session::new(&ctxt).completer(|_, word| {
+            if ".help".starts_with(word) {
+                vec![".help".to_owned()]
+            } else {
+                vec![]
+            }
+        });
+
+        assert!(matches!(session.feed_line(""), ReplOutput::Empty));
+        assert!(matches!(
+            session.feed_line("function add(a, b) {"),
+            ReplOutput::Incomplete
+        ));
+        assert_eq!(session.prompt(), "... ");
+        assert!(matches!(
+            session.feed_line("  return `${a} + ${b} = ${a + b}` /* sum"),
+            ReplOutput::Incomplete
+        ));
+        assert!(matches!(session.feed_line("*/ }"), ReplOutput::Value(ref s) if s == "undefined"));
+        assert!(!session.is_pending());
+        assert!(
+            matches!(session.feed_line("add(1, 2)"), ReplOutput::Value(ref s) if s == "'1 + 2 = 3'")
+        );
+        assert!(
+            matches!(session.feed_line("({ a: [1, 2], b: 'c' })"), ReplOutput::Value(ref s) if s == "{ a: [ 1, 2 ], b: 'c' }")
+        );
+        assert!(matches!(
+            session.feed_line("foo"),
+            ReplOutput::Error(Error::Js(ErrorKind::ReferenceError(..)))
+        ));
+
+        assert_eq!(session.complete("1 + ad"), vec!["add"]);
+        assert_eq!(session.complete("Math.ma"), vec!["Math.max".to_owned()]);
+        assert_eq!(session.complete(".he"), vec![".help"]);
+
+        session.feed_line("[1,");
+        session.reset();
+
+        assert_eq!(session.prompt(), "> ");
+    }
+}
@@ -1,11 +1,26 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::fmt;
 use std::mem::MaybeUninit;
 use std::os::raw::{c_int, c_void};
-use std::panic;
+use std::panic::{self, Location};
 use std::ptr::{null_mut, NonNull};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
 use foreign_types::{ForeignType, ForeignTypeRef};
 
-use crate::{ffi, value::ToBool, Value};
+use crate::{
+    budget::OpBudget,
+    ffi,
+    import_hook::{ImportHook, ImportMetaHook},
+    module::ModuleLoader,
+    profile::Profile,
+    thread::Entry,
+    value::ToBool,
+    Value,
+};
 
 pub use crate::ffi::{JSMallocFunctions as MallocFunctions, JSMemoryUsage as MemoryUsage};
 
@@ -16,10 +31,118 @@ foreign_type! {
     ///
     /// Several runtimes can exist at the same time but they cannot exchange objects.
     /// Inside a given runtime, no multi-threading is supported.
+    /// The runtime and its contexts may be moved to another thread, but must not be used
+    /// by two threads at the same time, which panics in all the builds.
     pub type Runtime : Send {
         type CType = ffi::JSRuntime;
 
-        fn drop = ffi::JS_FreeRuntime;
+        fn drop = free_runtime;
+    }
+}
+
+lazy_static! {
+    static ref RUNTIME_STATES: Mutex<HashMap<usize, State>> = Mutex::new(HashMap::new());
+}
+
+/// The Rust side states of a `Runtime`, which will be released with the runtime.
+#[derive(Default)]
+pub(crate) struct State {
+    pub stack_trace_limit: Option<usize>,
+    pub hide_native_frames: bool,
+    pub module_loader: Option<ModuleLoader>,
+    pub import_hook: Option<ImportHook>,
+    pub import_meta_hook: Option<ImportMetaHook>,
+    pub interrupts: Arc<Mutex<Interrupts>>,
+    pub contexts: Vec<usize>,
+    pub shutting_down: bool,
+    pub std_handlers: bool,
+    pub entry: Arc<Entry>,
+    pub user_data: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+    pub name: Option<CString>,
+    pub memory_usage: Option<MemoryUsage>,
+    pub host_locations: Option<HashMap<String, Option<&'static Location<'static>>>>,
+    #[cfg(feature = "watch")]
+    pub watched_modules: Option<Vec<std::path::PathBuf>>,
+}
+
+pub(crate) type InterruptCallback = Box<dyn FnMut() -> bool + Send>;
+
+/// The states of the interrupt checks, which are passed to the interrupt handler of the engine
+/// as its opaque pointer, so the checks never take the lock of the runtime states.
+#[derive(Default)]
+pub(crate) struct Interrupts {
+    pub callback: Option<InterruptCallback>,
+    pub handler: InterruptHandler,
+    pub profile: Option<Profile>,
+    pub op_budgets: HashMap<usize, OpBudget>,
+}
+
+unsafe fn free_runtime(rt: *mut ffi::JSRuntime) {
+    let report = RuntimeRef::from_ptr(rt).leak_report();
+
+    if !release_runtime(rt, &report) && cfg!(debug_assertions) && !std::thread::panicking() {
+        panic!("runtime dropped with {}", report);
+    }
+}
+
+/// Release the states and free the runtime, returns `false` if the runtime was leaked instead.
+pub(crate) unsafe fn release_runtime(rt: *mut ffi::JSRuntime, report: &LeakReport) -> bool {
+    {
+        // release the states out of the lock and before the runtime
+        let _state = RUNTIME_STATES
+            .lock()
+            .expect("runtime states")
+            .remove(&(rt as usize));
+    }
+
+    if report.is_empty() {
+        ffi::JS_FreeRuntime(rt);
+
+        true
+    } else {
+        // the surviving contexts or values will be freed after the runtime, so leak it instead
+        error!("leak runtime @ {:p}: {}", rt, report);
+
+        false
+    }
+}
+
+/// The information of a live `Runtime`, returned by `Runtime::list`.
+#[derive(Clone, Debug)]
+pub struct RuntimeInfo {
+    /// The address of the runtime, which identifies the runtime while it is alive.
+    pub id: usize,
+    /// The name of the runtime, set by `Runtime::with_name` or `RuntimeRef::set_name`.
+    pub name: Option<String>,
+    /// The snapshot of the memory usage, or `None` if it was never computed.
+    pub memory_usage: Option<MemoryUsage>,
+}
+
+/// The contexts and objects still alive in a `Runtime`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LeakReport {
+    /// The number of the contexts which have not been dropped.
+    pub contexts: usize,
+    /// The number of the objects which have not been freed after the garbage collection.
+    ///
+    /// The objects are leaked if there is no alive context, e.g. a value was forgotten without freeing.
+    pub objects: usize,
+}
+
+impl LeakReport {
+    /// Returns `true` if there is no context alive and no object leaked.
+    pub fn is_empty(&self) -> bool {
+        self.contexts == 0 && self.objects == 0
+    }
+}
+
+impl fmt::Display for LeakReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} contexts and {} objects alive",
+            self.contexts, self.objects
+        )
     }
 }
 
@@ -36,9 +159,40 @@ impl Runtime {
     pub fn new() -> Self {
         let runtime = unsafe { Runtime::from_ptr(ffi::JS_NewRuntime()) };
         runtime.register_userdata_class();
+        runtime.set_last_thread();
+        runtime
+    }
+
+    /// Construct a new `Runtime` with a name, which labels the runtime in `Runtime::list`.
+    pub fn with_name<S: Into<String>>(name: S) -> Self {
+        let runtime = Runtime::new();
+        runtime.set_name(name);
         runtime
     }
 
+    /// Returns the information of the live runtimes in the process.
+    ///
+    /// The runtimes may be running in the other threads, so they are never inspected here,
+    /// the memory usage is the snapshot taken by the last `RuntimeRef::memory_usage`.
+    pub fn list() -> Vec<RuntimeInfo> {
+        let mut runtimes = RUNTIME_STATES
+            .lock()
+            .expect("runtime states")
+            .iter()
+            .map(|(&id, state)| RuntimeInfo {
+                id,
+                name: state
+                    .name
+                    .as_ref()
+                    .map(|name| name.to_string_lossy().into_owned()),
+                memory_usage: state.memory_usage,
+            })
+            .collect::<Vec<_>>();
+
+        runtimes.sort_by_key(|info| info.id);
+        runtimes
+    }
+
     /// Construct a new `Runtime` with custom memory allocation functions.
     pub fn with_malloc_funcs<T>(
         malloc_funcs: &MallocFunctions,
@@ -51,11 +205,54 @@ impl Runtime {
             ))
         };
         runtime.register_userdata_class();
+        runtime.set_last_thread();
         runtime
     }
 }
 
 impl RuntimeRef {
+    pub(crate) fn with_state<T, F: FnOnce(&mut State) -> T>(&self, f: F) -> T {
+        f(RUNTIME_STATES
+            .lock()
+            .expect("runtime states")
+            .entry(self.as_ptr() as usize)
+            .or_default())
+    }
+
+    /// Access the states of the interrupt checks.
+    pub(crate) fn with_interrupts<T, F: FnOnce(&mut Interrupts) -> T>(&self, f: F) -> T {
+        let interrupts = self.with_state(|state| state.interrupts.clone());
+        let mut interrupts = interrupts.lock().expect("interrupts");
+
+        f(&mut interrupts)
+    }
+
+    /// Set the name of a given `Runtime`, which is also reported by `JS_DumpMemoryUsage`.
+    pub fn set_name<S: Into<String>>(&self, name: S) -> &Self {
+        let name = name.into();
+
+        trace!("{:?} set name to {}", self, name);
+
+        // the interior NUL bytes are dropped, the name is only a label
+        let name = CString::new(name.replace('\0', "")).expect("name");
+
+        // the info string is borrowed by the runtime, so it is kept in the states
+        unsafe { ffi::JS_SetRuntimeInfo(self.as_ptr(), name.as_ptr()) };
+
+        self.with_state(|state| state.name = Some(name));
+        self
+    }
+
+    /// Returns the name of the runtime.
+    pub fn name(&self) -> Option<String> {
+        self.with_state(|state| {
+            state
+                .name
+                .as_ref()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+    }
+
     /// Set a global memory allocation limit to a given `Runtime`.
     pub fn set_memory_limit(&self, limit: Option<usize>) -> &Self {
         trace!("{:?} set memory limit to {:?}", self, limit);
@@ -66,6 +263,37 @@ impl RuntimeRef {
         self
     }
 
+    /// Set the maximum number of frames kept in the stack trace of the Javascript errors.
+    ///
+    /// QuickJS always records the full backtrace, the frames beyond the limit
+    /// will be dropped when the exception is converted to `ErrorKind`.
+    pub fn set_stack_trace_limit(&self, limit: Option<usize>) -> &Self {
+        trace!("{:?} set stack trace limit to {:?}", self, limit);
+
+        self.with_state(|state| state.stack_trace_limit = limit);
+        self
+    }
+
+    /// Returns the maximum number of frames kept in the stack trace.
+    pub fn stack_trace_limit(&self) -> Option<usize> {
+        self.with_state(|state| state.stack_trace_limit)
+    }
+
+    /// Hide the frames of the native functions, e.g. the host functions, from the stack traces.
+    ///
+    /// The frames are dropped when the exception is converted to `ErrorKind`, like the stack trace limit.
+    pub fn set_hide_native_frames(&self, hide: bool) -> &Self {
+        trace!("{:?} hide native frames: {}", self, hide);
+
+        self.with_state(|state| state.hide_native_frames = hide);
+        self
+    }
+
+    /// Returns `true` if the frames of the native functions are hidden from the stack traces.
+    pub fn hide_native_frames(&self) -> bool {
+        self.with_state(|state| state.hide_native_frames)
+    }
+
     /// Set the GC threshold to a given `Runtime`.
     pub fn set_gc_threshold(&self, gc_threshold: usize) -> &Self {
         trace!("{:?} set GC threshold to {}", self, gc_threshold);
@@ -76,6 +304,19 @@ impl RuntimeRef {
         self
     }
 
+    /// Allow `Atomics.wait` to block the thread of a given `Runtime`, it is disallowed by default.
+    ///
+    /// The waiters are shared by all the runtimes, so `Atomics.notify` wakes up the threads
+    /// waiting on the same `SharedMemory`.
+    pub fn set_can_block(&self, can_block: bool) -> &Self {
+        trace!("{:?} set can block to {}", self, can_block);
+
+        unsafe {
+            ffi::JS_SetCanBlock(self.as_ptr(), can_block as i32);
+        }
+        self
+    }
+
     /// Force to run GC to a given `Runtime`.
     pub fn run_gc(&self) {
         trace!("{:?} run GC", self);
@@ -91,8 +332,36 @@ impl RuntimeRef {
         unsafe { ffi::JS_IsInGCSweep(self.as_ptr()).to_bool() }
     }
 
+    /// Report the contexts and objects still alive, which will be leaked if the runtime is dropped now.
+    ///
+    /// The `Runtime` checks it when dropped, the runtime will be leaked instead of freed if anything is alive,
+    /// and panics in the debug builds.
+    pub fn leak_report(&self) -> LeakReport {
+        let contexts = self.with_state(|state| state.contexts.len());
+        let objects = if contexts == 0 {
+            self.run_gc();
+
+            self.memory_usage().obj_count as usize
+        } else {
+            0
+        };
+
+        LeakReport { contexts, objects }
+    }
+
     /// Compute memory used by various object types.
+    ///
+    /// The result is also kept as the snapshot reported by `Runtime::list`.
     pub fn memory_usage(&self) -> MemoryUsage {
+        let usage = self.compute_memory_usage();
+
+        self.with_state(|state| state.memory_usage = Some(usage));
+
+        usage
+    }
+
+    /// Compute memory used by various object types without keeping the snapshot.
+    pub(crate) fn compute_memory_usage(&self) -> MemoryUsage {
         let mut usage = MaybeUninit::<ffi::JSMemoryUsage>::uninit();
 
         unsafe {
@@ -105,29 +374,127 @@ impl RuntimeRef {
     /// Set a callback which is regularly called by the engine when it is executing code.
     ///
     /// This callback can be used to implement an execution timeout.
+    /// The interrupt closure of the runtime will be replaced.
     pub fn set_interrupt_handler(&self, handler: InterruptHandler) {
-        unsafe {
-            if let Some(func) = handler {
-                unsafe extern "C" fn stub(rt: *mut ffi::JSRuntime, opaque: *mut c_void) -> c_int {
-                    panic::catch_unwind(|| {
-                        let rt = RuntimeRef::from_ptr(rt);
-                        let func: fn(rt: &RuntimeRef) -> Interrupt = *(opaque as *mut _);
-
-                        match func(rt) {
-                            Interrupt::Break => true,
-                            Interrupt::Continue => false,
-                        }
-                    })
-                    .unwrap_or(true)
-                    .to_bool()
+        self.with_interrupts(|interrupts| {
+            interrupts.handler = handler;
+            interrupts.callback = None;
+        });
+
+        if handler.is_some() {
+            self.install_interrupt_trampoline();
+        }
+    }
+}
+
+impl RuntimeRef {
+    /// Set a closure which is regularly called by the engine when it is executing code.
+    ///
+    /// The execution will be interrupted with an uncatchable `InternalError` if the closure returns `true`.
+    /// The interrupt handler of the runtime will be replaced.
+    pub fn set_interrupt_callback<F: FnMut() -> bool + Send + 'static>(&self, callback: F) {
+        self.with_interrupts(|interrupts| {
+            interrupts.callback = Some(Box::new(callback));
+            interrupts.handler = None;
+        });
+
+        self.install_interrupt_trampoline();
+    }
+
+    /// Remove the interrupt closure or handler.
+    ///
+    /// The op budgets and profiles still count the interrupt checks.
+    pub fn clear_interrupt_callback(&self) {
+        self.with_interrupts(|interrupts| {
+            interrupts.callback = None;
+            interrupts.handler = None;
+        });
+    }
+
+    /// Install the interrupt handler of the engine, which dispatches to the closure or handler of the runtime.
+    ///
+    /// The installed closure or handler is kept, so the op budgets and profiles could count the interrupt checks.
+    pub(crate) fn install_interrupt_trampoline(&self) {
+        unsafe extern "C" fn trampoline(rt: *mut ffi::JSRuntime, opaque: *mut c_void) -> c_int {
+            panic::catch_unwind(|| {
+                let rt = RuntimeRef::from_ptr(rt);
+                let interrupts = &*(opaque as *const Mutex<Interrupts>);
+                let running = rt.entry().contexts.lock().expect("entry").last().cloned();
+
+                // the callback is taken out of the states, so it could access the runtime
+                let (mut callback, handler) = {
+                    let mut interrupts = interrupts.lock().expect("interrupts");
+
+                    if let Some(profile) = interrupts.profile.as_mut() {
+                        profile.record(rt);
+                    }
+
+                    if interrupts.charge_op_budget(running) {
+                        return true;
+                    }
+
+                    (interrupts.callback.take(), interrupts.handler)
+                };
+                let interrupted = match (callback.as_mut(), handler) {
+                    (Some(f), _) => f(),
+                    (None, Some(handler)) => matches!(handler(rt), Interrupt::Break),
+                    (None, None) => false,
+                };
+
+                if callback.is_some() {
+                    let mut interrupts = interrupts.lock().expect("interrupts");
+
+                    if interrupts.callback.is_none() {
+                        interrupts.callback = callback
+                    }
                 }
 
-                ffi::JS_SetInterruptHandler(self.as_ptr(), Some(stub), func as *mut _)
-            } else {
-                ffi::JS_SetInterruptHandler(self.as_ptr(), None, null_mut())
-            }
+                interrupted
+            })
+            .unwrap_or(true)
+            .to_bool()
+        }
+
+        let interrupts = self.with_state(|state| Arc::as_ptr(&state.interrupts));
+
+        // the interrupts are released with the runtime states, after the last script
+        unsafe {
+            ffi::JS_SetInterruptHandler(self.as_ptr(), Some(trampoline), interrupts as *mut _)
         }
     }
+
+    /// Returns a new `InterruptToken` which could cancel the execution of the runtime from another thread.
+    ///
+    /// The interrupt closure of the runtime will be replaced.
+    pub fn interrupt_token(&self) -> InterruptToken {
+        let token = InterruptToken::default();
+        let interrupted = token.clone();
+
+        self.set_interrupt_callback(move || interrupted.is_interrupted());
+
+        token
+    }
+}
+
+/// A token to interrupt the execution of a runtime, which could be shared between threads.
+#[derive(Clone, Debug, Default)]
+pub struct InterruptToken(Arc<AtomicBool>);
+
+impl InterruptToken {
+    /// Interrupt the execution, the runtime will keep interrupting until the token was reset.
+    pub fn interrupt(&self) {
+        self.0.store(true, Ordering::SeqCst)
+    }
+
+    /// Returns `true` if the token has been interrupted.
+    pub fn is_interrupted(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Reset the token, so the runtime could execute code again.
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::SeqCst)
+    }
 }
 
 /// Interrupt the execution code.
@@ -141,7 +508,9 @@ pub type InterruptHandler = Option<fn(rt: &RuntimeRef) -> Interrupt>;
 
 #[cfg(test)]
 mod tests {
-    use crate::Context;
+    use std::mem;
+
+    use crate::{Context, ErrorKind, Eval};
 
     use super::*;
 
@@ -172,4 +541,109 @@ mod tests {
         assert!(usage4.memory_used_size < usage3.memory_used_size);
         assert!(usage4.memory_used_size > usage.memory_used_size);
     }
+
+    #[test]
+    fn list() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::with_name("worker-1");
+        let _ctxt = Context::new(&rt);
+
+        assert_eq!(rt.name().as_deref(), Some("worker-1"));
+
+        let usage = rt.memory_usage();
+
+        let info = Runtime::list()
+            .into_iter()
+            .find(|info| info.id == rt.as_ptr() as usize)
+            .unwrap();
+
+        assert_eq!(info.name.as_deref(), Some("worker-1"));
+        assert_eq!(
+            info.memory_usage.map(|usage| usage.memory_used_size),
+            Some(usage.memory_used_size)
+        );
+
+        let id = info.id;
+
+        mem::drop(_ctxt);
+        mem::drop(rt);
+
+        assert!(Runtime::list().iter().all(|info| info.id != id));
+    }
+
+    #[test]
+    fn interrupt() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+        let mut calls = 0;
+
+        rt.set_interrupt_callback(move || {
+            calls += 1;
+            calls > 3
+        });
+
+        assert!(ctxt
+            .eval_script("while (true) {}", "<evalScript>", Eval::GLOBAL)
+            .is_err());
+
+        let token = rt.interrupt_token();
+        let interrupter = token.clone();
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+
+            interrupter.interrupt()
+        });
+
+        assert!(matches!(
+            ctxt.eval::<_, ()>("while (true) {}", Eval::GLOBAL)
+                .unwrap_err()
+                .into_kind(),
+            Some(ErrorKind::InternalError(msg, _)) if msg == "interrupted"
+        ));
+
+        handle.join().unwrap();
+
+        assert!(token.is_interrupted());
+
+        token.reset();
+
+        assert_eq!(ctxt.eval::<_, i32>("1 + 2", Eval::GLOBAL).unwrap(), Some(3));
+
+        rt.clear_interrupt_callback();
+    }
+
+    #[test]
+    fn leak_report() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        assert_eq!(rt.leak_report().contexts, 1);
+
+        let leaked = ctxt.new_object();
+
+        drop(ctxt);
+
+        let report = rt.leak_report();
+
+        assert_eq!(report.contexts, 0);
+        // the leaked object keeps its prototypes alive
+        assert!(report.objects > 0);
+
+        rt.free_value(leaked);
+
+        assert!(rt.leak_report().is_empty());
+
+        let ctxt = Context::new(&rt);
+        let res = panic::catch_unwind(panic::AssertUnwindSafe(move || drop(rt)));
+
+        assert_eq!(res.is_err(), cfg!(debug_assertions));
+
+        // the runtime was leaked, so the context must not be freed
+        mem::forget(ctxt);
+    }
 }
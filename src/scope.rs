@@ -0,0 +1,149 @@
+use std::cell::RefCell;
+
+use crate::{ContextRef, Value};
+
+/// The scope to release the temporary values in bulk, created by `ContextRef::scope`.
+///
+/// The values bound to the scope are freed at the scope exit, like the `HandleScope` of V8.
+pub struct Scope<'a> {
+    ctxt: &'a ContextRef,
+    // the values are boxed to keep their addresses when the vector grows
+    #[allow(clippy::vec_box)]
+    values: RefCell<Vec<Box<Value>>>,
+}
+
+impl ContextRef {
+    /// Run the closure in a scope, the values bound through the scope are freed at the scope exit.
+    ///
+    /// With the `leak-check` feature, it asserts that no `Local` created in the scope outlives it,
+    /// so the results should be returned as Rust values.
+    pub fn scope<'a, F, R>(&'a self, f: F) -> R
+    where
+        F: FnOnce(&Scope<'a>) -> R,
+    {
+        #[cfg(feature = "leak-check")]
+        let live = live_locals();
+
+        let res = {
+            let scope = Scope {
+                ctxt: self,
+                values: RefCell::new(Vec::new()),
+            };
+
+            f(&scope)
+        };
+
+        #[cfg(feature = "leak-check")]
+        {
+            let leaked = live_locals().saturating_sub(live);
+
+            assert_eq!(leaked, 0, "{} locals leaked from the scope", leaked);
+        }
+
+        res
+    }
+}
+
+impl<'a> Scope<'a> {
+    /// Returns the context of the scope.
+    pub fn context(&self) -> &'a ContextRef {
+        self.ctxt
+    }
+
+    /// Bind the value to the scope, it will be freed at the scope exit.
+    pub fn bind<T: Into<Value>>(&self, value: T) -> &Value {
+        let value = Box::new(value.into());
+        let ptr = &*value as *const Value;
+
+        self.values.borrow_mut().push(value);
+
+        // the boxed values are never moved or freed before the scope
+        unsafe { &*ptr }
+    }
+
+    /// Returns the number of the values bound to the scope.
+    pub fn len(&self) -> usize {
+        self.values.borrow().len()
+    }
+
+    /// Returns `true` if no value is bound to the scope.
+    pub fn is_empty(&self) -> bool {
+        self.values.borrow().is_empty()
+    }
+}
+
+impl Drop for Scope<'_> {
+    fn drop(&mut self) {
+        let values = self.values.get_mut();
+
+        trace!("free {} values of the scope", values.len());
+
+        while let Some(value) = values.pop() {
+            self.ctxt.free_value(*value)
+        }
+    }
+}
+
+#[cfg(feature = "leak-check")]
+thread_local! {
+    static LIVE_LOCALS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Returns the number of the alive `Local`s created by the current thread.
+#[cfg(feature = "leak-check")]
+pub fn live_locals() -> usize {
+    LIVE_LOCALS.with(|n| n.get())
+}
+
+#[cfg(feature = "leak-check")]
+pub(crate) fn track_local(created: bool) {
+    LIVE_LOCALS.with(|n| {
+        n.set(if created {
+            n.get() + 1
+        } else {
+            n.get().saturating_sub(1)
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, Runtime};
+
+    #[test]
+    fn scope() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+        let before = rt.memory_usage().obj_count;
+
+        let sum = ctxt.scope(|s| {
+            let mut sum = 0;
+
+            for i in 0..100 {
+                let obj = s.bind(ctxt.new_object());
+
+                ctxt.set_property(obj, "n", i).unwrap();
+
+                sum += s
+                    .bind(ctxt.get_property(obj, "n").unwrap())
+                    .as_int()
+                    .unwrap();
+            }
+
+            assert_eq!(s.len(), 200);
+
+            sum
+        });
+
+        assert_eq!(sum, 4950);
+        assert_eq!(rt.memory_usage().obj_count, before);
+
+        #[cfg(feature = "leak-check")]
+        assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            ctxt.scope(|_| ctxt.new_atom("leaked"))
+        }))
+        .is_err());
+    }
+}
@@ -0,0 +1,176 @@
+use std::cmp;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use foreign_types::ForeignType;
+
+use crate::{runtime::release_runtime, Context, Error, LeakReport, Runtime, RuntimeRef};
+
+/// The interval to poll the in-flight jobs which have no deadline, like the fetches.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The result of `Runtime::shutdown`.
+#[derive(Debug, Default)]
+pub struct ShutdownReport {
+    /// The number of the jobs, timers and fetches still pending at the deadline.
+    pub pending: usize,
+    /// The errors thrown by the jobs and timers while draining.
+    pub errors: Vec<Error>,
+    /// The contexts and objects still alive after the contexts were dropped,
+    /// the runtime is leaked instead of freed if anything is alive.
+    pub leaks: LeakReport,
+}
+
+impl ShutdownReport {
+    /// Returns `true` if all the jobs were drained and nothing was leaked.
+    pub fn is_clean(&self) -> bool {
+        self.pending == 0 && self.errors.is_empty() && self.leaks.is_empty()
+    }
+}
+
+impl RuntimeRef {
+    /// Returns `true` if the runtime is shutting down, the new evaluations are rejected.
+    pub fn is_shutting_down(&self) -> bool {
+        self.with_state(|state| state.shutting_down)
+    }
+}
+
+impl Runtime {
+    /// Shut down the runtime gracefully with its contexts.
+    ///
+    /// The new evaluations are rejected from now on, the pending jobs, timers and fetches of the contexts
+    /// are drained until the `deadline`, then the `os` handlers are freed, the contexts are dropped,
+    /// and the runtime is freed after the garbage collection if nothing is leaked.
+    pub fn shutdown<I>(self, contexts: I, deadline: Instant) -> ShutdownReport
+    where
+        I: IntoIterator<Item = Context>,
+    {
+        let contexts = contexts.into_iter().collect::<Vec<_>>();
+        let mut report = ShutdownReport::default();
+
+        debug!("shutdown {:?} with {} contexts", self, contexts.len());
+
+        self.with_state(|state| state.shutting_down = true);
+
+        loop {
+            let mut ran = false;
+
+            for ctxt in &contexts {
+                match ctxt.run_jobs_once() {
+                    Ok(true) => ran = true,
+                    Ok(false) => {}
+                    Err(err) => {
+                        warn!("job failed while shutting down: {}", err);
+
+                        report.errors.push(err);
+                        ran = true;
+                    }
+                }
+            }
+
+            report.pending = contexts.iter().map(|ctxt| ctxt.pending_jobs()).sum();
+
+            let now = Instant::now();
+
+            if report.pending == 0 || now >= deadline {
+                break;
+            }
+
+            if !ran {
+                let wakeup = contexts
+                    .iter()
+                    .flat_map(|ctxt| ctxt.next_timer_deadline())
+                    .min()
+                    .unwrap_or(now + POLL_INTERVAL);
+
+                thread::sleep(cmp::min(wakeup, deadline).saturating_duration_since(now));
+            }
+        }
+
+        if report.pending > 0 {
+            warn!(
+                "{} jobs still pending at the shutdown deadline",
+                report.pending
+            );
+        }
+
+        if self.with_state(|state| state.std_handlers) {
+            self.std_free_handlers();
+        }
+
+        drop(contexts);
+
+        report.leaks = self.leak_report();
+
+        let rt = self.as_ptr();
+
+        // the runtime was checked above, it should not panic when leaked
+        std::mem::forget(self);
+
+        unsafe { release_runtime(rt, &report.leaks) };
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use crate::{ffi, ContextRef, Eval, Value};
+
+    use super::*;
+
+    static REJECTED: AtomicBool = AtomicBool::new(false);
+
+    fn probe(ctxt: &ContextRef, _this: Option<&Value>, _args: &[Value]) -> ffi::JSValue {
+        REJECTED.store(
+            ctxt.eval::<_, i32>("1", Eval::GLOBAL).is_err(),
+            Ordering::SeqCst,
+        );
+
+        ffi::UNDEFINED
+    }
+
+    #[test]
+    fn shutdown() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        ctxt.init_timers().unwrap();
+        ctxt.global_object()
+            .set_property(
+                "probe",
+                ctxt.new_c_function(probe, Some("probe"), 0).unwrap(),
+            )
+            .unwrap();
+        ctxt.eval_script(
+            "Promise.resolve().then(() => setTimeout(probe, 10)); setTimeout(() => { throw new Error('boom') }, 1)",
+            "<evalScript>",
+            Eval::GLOBAL,
+        )
+        .unwrap();
+
+        let report = rt.shutdown(vec![ctxt], Instant::now() + Duration::from_secs(5));
+
+        assert!(REJECTED.load(Ordering::SeqCst));
+        assert_eq!(report.pending, 0);
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.leaks.is_empty());
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        ctxt.init_timers().unwrap();
+        ctxt.eval_script("setInterval(() => {}, 1)", "<evalScript>", Eval::GLOBAL)
+            .unwrap();
+
+        let report = rt.shutdown(vec![ctxt], Instant::now() + Duration::from_millis(20));
+
+        assert_eq!(report.pending, 1);
+        assert!(report.errors.is_empty());
+        assert!(report.leaks.is_empty());
+    }
+}
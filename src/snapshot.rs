@@ -0,0 +1,109 @@
+use crate::{ContextRef, Error, Eval, Local, Value};
+
+const SNAPSHOT: &str = r#"
+(function (global) {
+    const snapshot = Object.create(null);
+
+    for (const name of Reflect.ownKeys(global)) {
+        snapshot[name] = Object.getOwnPropertyDescriptor(global, name);
+    }
+
+    return snapshot;
+})
+"#;
+
+const RESTORE: &str = r#"
+(function (global, snapshot) {
+    for (const name of Reflect.ownKeys(global)) {
+        if (!(name in snapshot) && !delete global[name]) {
+            try { global[name] = undefined; } catch (e) {}
+        }
+    }
+
+    for (const name of Reflect.ownKeys(snapshot)) {
+        const desc = Object.getOwnPropertyDescriptor(global, name);
+
+        if (!desc || desc.configurable) {
+            Object.defineProperty(global, name, snapshot[name]);
+        } else if (desc.writable && 'value' in snapshot[name]) {
+            global[name] = snapshot[name].value;
+        }
+    }
+})
+"#;
+
+/// The snapshot of the global properties of a context.
+///
+/// Only the property descriptors of the global object are recorded,
+/// the objects referenced by the globals are shared instead of copied.
+pub struct Snapshot<'a>(Local<'a, Value>);
+
+impl ContextRef {
+    /// Take a snapshot of the global properties, e.g. after the libraries were preloaded.
+    pub fn snapshot_globals(&self) -> Result<Snapshot, Error> {
+        trace!("snapshot global properties");
+
+        let snapshot = self.eval_script(SNAPSHOT, "<snapshot>", Eval::GLOBAL)?;
+
+        self.call(&snapshot, None, self.global_object())
+            .map(Snapshot)
+    }
+
+    /// Restore the global properties to the snapshot.
+    ///
+    /// The globals added after the snapshot will be deleted,
+    /// and the changed globals will be redefined with the recorded descriptors,
+    /// so the context could be reused by the next request without running the prelude again.
+    pub fn restore_globals(&self, snapshot: &Snapshot) -> Result<(), Error> {
+        trace!("restore global properties");
+
+        self.eval_script(RESTORE, "<restore>", Eval::GLOBAL)?
+            .call(None, (self.global_object(), &snapshot.0))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, Eval, Runtime};
+
+    #[test]
+    fn snapshot() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        ctxt.eval_script(
+            "var lib = { version: 1 }; function helper() { return lib.version; }",
+            "<prelude>",
+            Eval::GLOBAL,
+        )
+        .unwrap();
+
+        let snapshot = ctxt.snapshot_globals().unwrap();
+
+        for _ in 0..2 {
+            assert_eq!(
+                ctxt.eval::<_, String>("typeof user", Eval::GLOBAL).unwrap(),
+                Some("undefined".to_owned())
+            );
+
+            ctxt.eval_script(
+                "user = 'alice'; lib = null; helper = null; JSON = undefined;",
+                "<request>",
+                Eval::GLOBAL,
+            )
+            .unwrap();
+
+            ctxt.restore_globals(&snapshot).unwrap();
+
+            assert_eq!(
+                ctxt.eval::<_, i32>("helper() + JSON.parse('1')", Eval::GLOBAL)
+                    .unwrap(),
+                Some(2)
+            );
+        }
+    }
+}
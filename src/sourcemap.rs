@@ -0,0 +1,239 @@
+use crate::{ContextRef, Error, StackTrace};
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[derive(Clone, Debug, PartialEq)]
+enum Mapping {
+    Offset {
+        file: String,
+        line: u32,
+    },
+    Lines {
+        sources: Vec<String>,
+        lines: Vec<Option<(usize, u32)>>,
+    },
+}
+
+/// The mapping from the lines of an evaluated chunk to the original files and lines.
+///
+/// QuickJS reports no columns in the backtrace, so only the lines are mapped.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceMap(Mapping);
+
+impl SourceMap {
+    /// The chunk was extracted from the original file, and its first line is the `line` of the file.
+    pub fn offset<S: Into<String>>(file: S, line: u32) -> Self {
+        SourceMap(Mapping::Offset {
+            file: file.into(),
+            line,
+        })
+    }
+
+    /// Decode the `mappings` field of the Source Map v3 with the `sources` files.
+    ///
+    /// Each generated line is mapped to the original position of its first segment.
+    pub fn from_mappings(sources: Vec<String>, mappings: &str) -> Result<Self, Error> {
+        let mut lines = vec![];
+        let (mut source, mut line) = (0i64, 0i64);
+
+        for generated in mappings.split(';') {
+            let mut first = None;
+
+            for segment in generated.split(',').filter(|s| !s.is_empty()) {
+                let fields = decode_vlq(segment)
+                    .ok_or_else(|| Error::from(format!("invalid segment `{}`", segment)))?;
+
+                if fields.len() >= 4 {
+                    source += fields[1];
+                    line += fields[2];
+
+                    if first.is_none() && source >= 0 && (source as usize) < sources.len() {
+                        first = Some((source as usize, line.max(0) as u32));
+                    }
+                }
+            }
+
+            lines.push(first);
+        }
+
+        Ok(SourceMap(Mapping::Lines { sources, lines }))
+    }
+
+    /// Returns the original file and line (1-based) of the generated line (1-based).
+    pub fn lookup(&self, line: u32) -> Option<(&str, u32)> {
+        match self.0 {
+            Mapping::Offset {
+                ref file,
+                line: first,
+            } => Some((file.as_str(), line.checked_add(first)?.checked_sub(1)?)),
+            Mapping::Lines {
+                ref sources,
+                ref lines,
+            } => lines
+                .get(line.checked_sub(1)? as usize)
+                .and_then(|&mapped| mapped)
+                .map(|(source, line)| (sources[source].as_str(), line + 1)),
+        }
+    }
+}
+
+/// Decode the Base64 VLQ fields of a Source Map segment.
+fn decode_vlq(s: &str) -> Option<Vec<i64>> {
+    let mut fields = vec![];
+    let (mut n, mut shift) = (0i64, 0);
+
+    for c in s.bytes() {
+        let digit = BASE64.iter().position(|&b| b == c)? as i64;
+
+        if shift > 60 {
+            return None;
+        }
+
+        n |= (digit & 0x1f) << shift;
+
+        if digit & 0x20 == 0 {
+            fields.push(if n & 1 == 1 { -(n >> 1) } else { n >> 1 });
+            n = 0;
+            shift = 0;
+        } else {
+            shift += 5;
+        }
+    }
+
+    if shift == 0 {
+        Some(fields)
+    } else {
+        None
+    }
+}
+
+impl ContextRef {
+    /// Parse the JSON of the Source Map v3, the `sourceRoot` is prepended to the `sources`.
+    pub fn parse_source_map(&self, json: &str) -> Result<SourceMap, Error> {
+        let map = self.parse_json(json, "<sourcemap>")?;
+        let root = map
+            .get_property("sourceRoot")
+            .and_then(|root| root.to_cstring())
+            .map(|root| root.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let sources = map
+            .get_property("sources")
+            .filter(|sources| sources.is_object())
+            .ok_or_else(|| Error::from("missing `sources` property"))?
+            .iter_elements::<String>()
+            .map(|source| format!("{}{}", root, source.unwrap_or_default()))
+            .collect();
+        let mappings = map
+            .get_property("mappings")
+            .and_then(|mappings| mappings.to_cstring())
+            .ok_or_else(|| Error::from("missing `mappings` property"))?;
+
+        SourceMap::from_mappings(sources, &mappings.to_string_lossy())
+    }
+
+    /// Register the source map of the chunk evaluated with the `filename`,
+    /// the stack traces of the errors thrown from the chunk will be rewritten to the original positions.
+    pub fn set_source_map<S: Into<String>>(&self, filename: S, map: SourceMap) {
+        self.with_state(|state| state.source_maps.insert(filename.into(), map));
+    }
+
+    /// Unregister the source map of the chunk evaluated with the `filename`.
+    pub fn remove_source_map(&self, filename: &str) -> Option<SourceMap> {
+        self.with_state(|state| state.source_maps.remove(filename))
+    }
+
+    /// Rewrite the frames of the stack trace with the registered source maps.
+    pub fn map_stack_trace(&self, stack: &mut StackTrace) {
+        self.with_state(|state| {
+            stack.map_locations(|file, line| {
+                state
+                    .source_maps
+                    .get(file)
+                    .and_then(|map| map.lookup(line))
+                    .map(|(file, line)| (file.to_owned(), line))
+            })
+        })
+    }
+
+    /// Rewrite the QuickJS backtrace with the registered source maps, if any.
+    pub(crate) fn apply_source_maps(&self, stack: String) -> String {
+        if self.with_state(|state| state.source_maps.is_empty()) {
+            return stack;
+        }
+
+        let mut trace = stack.parse::<StackTrace>().unwrap_or_default();
+
+        self.map_stack_trace(&mut trace);
+
+        trace.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, ErrorKind, Eval, Runtime};
+
+    use super::*;
+
+    #[test]
+    fn source_map() {
+        let _ = pretty_env_logger::try_init();
+
+        let map =
+            SourceMap::from_mappings(vec!["a.js".into(), "b.js".into()], "AAAA;ACAA;ADEA").unwrap();
+
+        assert_eq!(map.lookup(1), Some(("a.js", 1)));
+        assert_eq!(map.lookup(2), Some(("b.js", 1)));
+        assert_eq!(map.lookup(3), Some(("a.js", 3)));
+        assert_eq!(map.lookup(4), None);
+        assert_eq!(
+            SourceMap::offset("app.js", 10).lookup(3),
+            Some(("app.js", 12))
+        );
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        ctxt.set_source_map(
+            "bundle.js",
+            ctxt.parse_source_map(
+                r#"{"version":3,"sourceRoot":"src/","sources":["a.js","b.js"],"mappings":"AAAA;ACAA;ADEA"}"#,
+            )
+            .unwrap(),
+        );
+        ctxt.set_source_map("chunk.js", SourceMap::offset("app.js", 10));
+
+        let stack = |source, filename| match ctxt.eval_script(source, filename, Eval::GLOBAL) {
+            Err(Error::Js(ErrorKind::Error(_, Some(stack)))) => stack,
+            res => panic!("unexpected result: {:?}", res.map(|v| v.to_string())),
+        };
+
+        assert_eq!(
+            stack(
+                "function foo() {\n  bar()\n}\nfunction bar() { throw new Error('boom') }\nfoo()",
+                "bundle.js"
+            ),
+            "    at bar (bundle.js)\n    at foo (src/b.js:1)\n    at <eval> (bundle.js:5)\n"
+        );
+        assert_eq!(
+            stack(
+                "\n\nfunction foo() {\n  bar()\n}\nfunction bar() { throw new Error('boom') }\nfoo()",
+                "chunk.js"
+            ),
+            "    at bar (chunk.js)\n    at foo (app.js:13)\n    at <eval> (app.js:16)\n"
+        );
+
+        assert!(ctxt.remove_source_map("chunk.js").is_some());
+
+        let mut trace = "    at foo (chunk.js:3)\n    at bar (bundle.js:1)\n"
+            .parse::<StackTrace>()
+            .unwrap();
+
+        ctxt.map_stack_trace(&mut trace);
+
+        assert_eq!(
+            trace.to_string(),
+            "    at foo (chunk.js:3)\n    at bar (src/a.js:1)\n"
+        );
+    }
+}
@@ -0,0 +1,369 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::RuntimeRef;
+
+/// A frame of the Javascript backtrace.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StackFrame {
+    /// The function name, or `None` for the location of a syntax error.
+    pub function: Option<String>,
+    /// The script filename, or `None` for a native function.
+    pub file: Option<String>,
+    /// The line number in the script.
+    pub line: Option<u32>,
+    /// The column number in the line, QuickJS only reports the line but the mapped locations may have it.
+    pub column: Option<u32>,
+    /// The Rust location where the host function of a native frame was registered,
+    /// if the runtime records the host locations.
+    pub host: Option<String>,
+}
+
+impl StackFrame {
+    /// Returns `true` if the frame is a native function.
+    pub fn is_native(&self) -> bool {
+        self.function.is_some() && self.file.is_none()
+    }
+}
+
+/// Split the trailing number of the location.
+fn split_number(loc: &str) -> Option<(&str, u32)> {
+    let pos = loc.rfind(':')?;
+
+    loc[pos + 1..].parse().ok().map(|n| (&loc[..pos], n))
+}
+
+/// Split the location to filename and optional line and column numbers.
+fn parse_location(loc: &str) -> (String, Option<u32>, Option<u32>) {
+    match split_number(loc) {
+        Some((rest, n)) => match split_number(rest) {
+            Some((file, line)) => (file.to_owned(), Some(line), Some(n)),
+            None => (rest.to_owned(), Some(n), None),
+        },
+        None => (loc.to_owned(), None, None),
+    }
+}
+
+impl FromStr for StackFrame {
+    type Err = ();
+
+    /// Parse a line of the QuickJS backtrace, like `    at func (file.js:12)`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        if !s.starts_with("at ") {
+            return Err(());
+        }
+
+        let s = s[3..].trim();
+
+        if s.ends_with(')') {
+            if let Some(pos) = s.find(" (") {
+                let function = Some(s[..pos].to_owned());
+                let loc = &s[pos + 2..s.len() - 1];
+
+                // the native frame may be formatted with the host location
+                return Ok(if loc == "native" || loc.starts_with("native ") {
+                    StackFrame {
+                        function,
+                        host: loc.strip_prefix("native ").map(ToOwned::to_owned),
+                        ..Default::default()
+                    }
+                } else {
+                    let (file, line, column) = parse_location(loc);
+
+                    StackFrame {
+                        function,
+                        file: Some(file),
+                        line,
+                        column,
+                        host: None,
+                    }
+                });
+            }
+        }
+
+        Ok(match parse_location(s) {
+            (file, Some(line), column) => StackFrame {
+                function: None,
+                file: Some(file),
+                line: Some(line),
+                column,
+                host: None,
+            },
+            (function, None, _) => StackFrame {
+                function: Some(function),
+                ..Default::default()
+            },
+        })
+    }
+}
+
+impl fmt::Display for StackFrame {
+    /// Format the frame as QuickJS does, without the indent.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("at ")?;
+
+        match (&self.function, &self.file) {
+            (Some(function), Some(file)) => {
+                write!(f, "{} ({}", function, file)?;
+                self.fmt_position(f)?;
+                f.write_str(")")
+            }
+            (Some(function), None) => match self.host {
+                Some(ref host) => write!(f, "{} (native {})", function, host),
+                None => write!(f, "{} (native)", function),
+            },
+            (None, file) => {
+                f.write_str(file.as_ref().map_or("<unknown>", |s| s.as_str()))?;
+                self.fmt_position(f)
+            }
+        }
+    }
+}
+
+impl StackFrame {
+    fn fmt_position(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+
+            if let Some(column) = self.column {
+                write!(f, ":{}", column)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse the QuickJS backtrace to the stack frames.
+pub fn parse_stack(stack: &str) -> Vec<StackFrame> {
+    stack.lines().flat_map(str::parse).collect()
+}
+
+/// The parsed QuickJS backtrace.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StackTrace {
+    /// The stack frames, the innermost first.
+    pub frames: Vec<StackFrame>,
+}
+
+impl StackTrace {
+    /// Rewrite the file and line of the frames with the mapping function,
+    /// the frames which are not mapped are kept unchanged.
+    pub fn map_locations<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&str, u32) -> Option<(String, u32)>,
+    {
+        for frame in &mut self.frames {
+            if let (Some(file), Some(line)) = (frame.file.as_ref(), frame.line) {
+                if let Some((file, line)) = f(file, line) {
+                    frame.file = Some(file);
+                    frame.line = Some(line);
+                    frame.column = None;
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for StackTrace {
+    type Err = ();
+
+    /// Parse the QuickJS backtrace, the lines which are not a frame are skipped.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(StackTrace {
+            frames: parse_stack(s),
+        })
+    }
+}
+
+impl fmt::Display for StackTrace {
+    /// Format the frames as the QuickJS backtrace.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&format_stack(&self.frames, StackFormat::Full))
+    }
+}
+
+/// Keep the first `limit` frames of the QuickJS backtrace.
+pub(crate) fn truncate_stack(stack: &str, limit: usize) -> String {
+    stack
+        .lines()
+        .take(limit)
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+/// Append the recorded host locations to the native frames of the QuickJS backtrace.
+pub(crate) fn annotate_host_locations(rt: &RuntimeRef, stack: String) -> String {
+    if !rt.is_recording_host_locations() {
+        return stack;
+    }
+
+    let mut trace = stack.parse::<StackTrace>().unwrap_or_default();
+
+    for frame in trace.frames.iter_mut().filter(|frame| frame.is_native()) {
+        if let Some(location) = frame
+            .function
+            .as_ref()
+            .and_then(|name| rt.host_location(name))
+        {
+            frame.host = Some(location.to_string());
+        }
+    }
+
+    trace.to_string()
+}
+
+/// Drop the native frames of the QuickJS backtrace.
+pub(crate) fn strip_native_frames(stack: &str) -> String {
+    let mut trace = stack.parse::<StackTrace>().unwrap_or_default();
+
+    trace.frames.retain(|frame| !frame.is_native());
+    trace.to_string()
+}
+
+/// The format of the stack trace.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StackFormat {
+    /// One frame per line, the consecutive recursive frames are collapsed.
+    Short,
+    /// The QuickJS backtrace, one indented frame per line.
+    Full,
+    /// A JSON array of the frames.
+    Json,
+}
+
+/// Format the stack frames.
+pub fn format_stack(frames: &[StackFrame], format: StackFormat) -> String {
+    match format {
+        StackFormat::Short => {
+            let mut lines = vec![];
+            let mut frames = frames.iter().peekable();
+
+            while let Some(frame) = frames.next() {
+                let mut repeated = 0;
+
+                while frames.peek() == Some(&frame) {
+                    frames.next();
+                    repeated += 1;
+                }
+
+                if repeated > 0 {
+                    lines.push(format!("{} (repeated {} times)", frame, repeated + 1));
+                } else {
+                    lines.push(frame.to_string());
+                }
+            }
+
+            lines.join("\n")
+        }
+        StackFormat::Full => frames
+            .iter()
+            .map(|frame| format!("    {}\n", frame))
+            .collect(),
+        StackFormat::Json => {
+            let frames = frames
+                .iter()
+                .map(|frame| {
+                    format!(
+                        r#"{{"function":{},"file":{},"line":{}{}{}}}"#,
+                        frame.function.as_ref().map_or("null".into(), |s| quote(s)),
+                        frame.file.as_ref().map_or("null".into(), |s| quote(s)),
+                        frame.line.map_or("null".into(), |n| n.to_string()),
+                        frame
+                            .column
+                            .map_or(String::new(), |n| format!(r#","column":{}"#, n)),
+                        frame
+                            .host
+                            .as_ref()
+                            .map_or(String::new(), |host| format!(r#","host":{}"#, quote(host)))
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            format!("[{}]", frames.join(","))
+        }
+    }
+}
+
+/// Quote the string as a JSON string.
+fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+
+    quoted.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_frames() {
+        let frames = parse_stack(
+            "    at foo.js:3\n    at bar (foo.js:12)\n    at <eval> (<evalScript>)\n    at parseInt (native)\n    at baz (app.ts:7:15)\n",
+        );
+
+        assert_eq!(
+            frames,
+            vec![
+                StackFrame {
+                    function: None,
+                    file: Some("foo.js".into()),
+                    line: Some(3),
+                    column: None,
+                    host: None,
+                },
+                StackFrame {
+                    function: Some("bar".into()),
+                    file: Some("foo.js".into()),
+                    line: Some(12),
+                    column: None,
+                    host: None,
+                },
+                StackFrame {
+                    function: Some("<eval>".into()),
+                    file: Some("<evalScript>".into()),
+                    line: None,
+                    column: None,
+                    host: None,
+                },
+                StackFrame {
+                    function: Some("parseInt".into()),
+                    file: None,
+                    line: None,
+                    column: None,
+                    host: None,
+                },
+                StackFrame {
+                    function: Some("baz".into()),
+                    file: Some("app.ts".into()),
+                    line: Some(7),
+                    column: Some(15),
+                    host: None,
+                },
+            ]
+        );
+
+        assert!(frames[3].is_native());
+        assert_eq!(frames[0].to_string(), "at foo.js:3");
+        assert_eq!(frames[1].to_string(), "at bar (foo.js:12)");
+        assert_eq!(frames[3].to_string(), "at parseInt (native)");
+        assert_eq!(frames[4].to_string(), "at baz (app.ts:7:15)");
+    }
+}
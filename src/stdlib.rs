@@ -1,22 +1,339 @@
 use std::ffi::CString;
+use std::io::{self, Write};
+use std::os::raw::c_int;
+use std::panic;
 use std::ptr::NonNull;
+use std::time::Duration;
 
-use failure::Error;
 use foreign_types::ForeignTypeRef;
 
-use crate::{ffi, ContextRef, ModuleDef, RuntimeRef};
+use crate::context::State;
+use crate::{ffi, ContextRef, Error, ModuleDef, RuntimeRef, Value};
+
+/// The capabilities of the `os` and `std` modules, the functions of the denied capabilities throw a `TypeError`.
+///
+/// The denied functions are still exported, so the scripts importing them could be linked.
+///
+/// Both modules should be initialized with the same capabilities, e.g. `std.popen` and `std.urlGet` run the processes
+/// like `os.exec`, see `ContextRef::init_module_os_with` and `ContextRef::init_module_std_with`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OsCapabilities {
+    /// Inspect the file system, e.g. `stat`, `readdir` and `getcwd`.
+    pub fs_read: bool,
+    /// Modify the file system, e.g. `remove`, `rename` and `mkdir`.
+    pub fs_write: bool,
+    /// Open and operate the file descriptors, e.g. `open`, `read`, `write` and `pipe`.
+    pub fd: bool,
+    /// Control the terminal, e.g. `ttySetRaw`.
+    pub tty: bool,
+    /// Run and signal the processes, e.g. `exec`, `waitpid` and `kill`.
+    pub exec: bool,
+    /// Handle the signals with `signal`.
+    pub signals: bool,
+    /// Schedule and sleep with `setTimeout`, `clearTimeout` and `sleep`.
+    pub timers: bool,
+}
+
+impl Default for OsCapabilities {
+    fn default() -> Self {
+        OsCapabilities::ALL
+    }
+}
+
+impl OsCapabilities {
+    /// All the functions are allowed, like `ContextRef::init_module_os`.
+    pub const ALL: OsCapabilities = OsCapabilities {
+        fs_read: true,
+        fs_write: true,
+        fd: true,
+        tty: true,
+        exec: true,
+        signals: true,
+        timers: true,
+    };
+
+    /// Only `platform` is allowed.
+    pub const NONE: OsCapabilities = OsCapabilities {
+        fs_read: false,
+        fs_write: false,
+        fd: false,
+        tty: false,
+        exec: false,
+        signals: false,
+        timers: false,
+    };
+
+    /// Returns the names of the denied functions of the `os` module.
+    pub fn denied(&self) -> Vec<&'static str> {
+        let groups: [(bool, &[&'static str]); 7] = [
+            (
+                self.fs_read,
+                &["getcwd", "realpath", "stat", "lstat", "readlink", "readdir"],
+            ),
+            (
+                self.fs_write,
+                &["remove", "rename", "mkdir", "symlink", "utimes"],
+            ),
+            (
+                self.fd,
+                &[
+                    "open",
+                    "close",
+                    "seek",
+                    "read",
+                    "write",
+                    "isatty",
+                    "setReadHandler",
+                    "setWriteHandler",
+                    "pipe",
+                    "dup",
+                    "dup2",
+                ],
+            ),
+            (self.tty, &["ttyGetWinSize", "ttySetRaw"]),
+            (self.exec, &["exec", "waitpid", "kill"]),
+            (self.signals, &["signal"]),
+            (self.timers, &["setTimeout", "clearTimeout", "sleep"]),
+        ];
+
+        denied_names(&groups)
+    }
+
+    /// Returns the names of the denied functions of the `std` module.
+    pub fn denied_std(&self) -> Vec<&'static str> {
+        let groups: [(bool, &[&'static str]); 4] = [
+            (self.fs_read, &["open", "loadScript"]),
+            (self.fs_write, &["open", "tmpfile"]),
+            (self.fd, &["fdopen"]),
+            (self.exec, &["popen", "urlGet"]),
+        ];
+
+        denied_names(&groups)
+    }
+}
+
+fn denied_names(groups: &[(bool, &[&'static str])]) -> Vec<&'static str> {
+    let mut denied = Vec::new();
+
+    for name in groups
+        .iter()
+        .filter(|(allowed, _)| !allowed)
+        .flat_map(|(_, names)| names.iter().cloned())
+    {
+        if !denied.contains(&name) {
+            denied.push(name);
+        }
+    }
+
+    denied
+}
+
+unsafe extern "C" fn not_permitted(
+    ctx: *mut ffi::JSContext,
+    _this_val: ffi::JSValue,
+    _argc: c_int,
+    _argv: *mut ffi::JSValue,
+    _magic: c_int,
+    data: *mut ffi::JSValue,
+) -> ffi::JSValue {
+    panic::catch_unwind(|| {
+        let ctxt = ContextRef::from_ptr(ctx);
+        let name = ctxt.bind(ctxt.clone_value(&*(data as *const Value)));
+
+        ctxt.throw_type_error(format!("`{}` is not permitted", name))
+            .into_inner()
+            .raw()
+    })
+    .unwrap_or_default()
+}
+
+/// Print the arguments separated by spaces, like `print` of the `std` helpers.
+fn print(ctxt: &ContextRef, _this: Option<&Value>, args: &[Value]) -> ffi::JSValue {
+    let mut line = Vec::new();
+
+    for (idx, arg) in args.iter().enumerate() {
+        if idx > 0 {
+            line.push(b' ');
+        }
+
+        match ctxt.to_cstring(arg) {
+            Some(s) => line.extend_from_slice(s.as_bytes()),
+            None => return ffi::EXCEPTION,
+        }
+    }
+
+    line.push(b'\n');
+
+    let res = write_redirected(ctxt, |state| &mut state.stdout, &line).unwrap_or_else(|| {
+        let stdout = io::stdout();
+        let mut w = stdout.lock();
+
+        w.write_all(&line).and_then(|_| w.flush())
+    });
+
+    match res {
+        Ok(_) => ffi::UNDEFINED,
+        Err(err) => ctxt.throw_error(err, None).into_inner().raw(),
+    }
+}
+
+/// Write to the redirected stream, or returns `None` if the stream was not redirected.
+///
+/// The writer is taken out of the states while writing, so the states are not locked by a slow writer.
+fn write_redirected<F>(ctxt: &ContextRef, stream: F, buf: &[u8]) -> Option<io::Result<()>>
+where
+    F: Fn(&mut State) -> &mut Option<Box<dyn Write + Send>>,
+{
+    let mut w = ctxt.with_state(|state| stream(state).take())?;
+    let res = w.write_all(buf).and_then(|_| w.flush());
+
+    // the stream may be redirected again while writing
+    ctxt.with_state(|state| {
+        stream(state).get_or_insert(w);
+    });
+
+    Some(res)
+}
+
+/// The hook to patch the exports of a C module after it was initialized.
+pub(crate) type ModuleHook =
+    Box<dyn FnOnce(&ContextRef, NonNull<ModuleDef>) -> Result<(), Error> + Send>;
+
+/// Initialize the C module with the original init function, then run the hooks of the module.
+unsafe extern "C" fn init_with_hooks(ctx: *mut ffi::JSContext, m: *mut ffi::JSModuleDef) -> c_int {
+    panic::catch_unwind(|| {
+        let ctxt = ContextRef::from_ptr(ctx);
+        let (init, hooks) = match ctxt.with_state(|state| state.module_hooks.remove(&(m as usize)))
+        {
+            Some((Some(init), hooks)) => (init, hooks),
+            _ => {
+                ctxt.throw_internal_error("the hooks of the module are missing");
+
+                return -1;
+            }
+        };
+
+        if init(ctx, m) < 0 {
+            return -1;
+        }
+
+        for hook in hooks {
+            if let Err(err) = hook(ctxt, NonNull::new_unchecked(m)) {
+                ctxt.throw(err.to_string());
+
+                return -1;
+            }
+        }
+
+        0
+    })
+    .unwrap_or(-1)
+}
 
 impl ContextRef {
     pub fn init_module_std(&self) -> Result<NonNull<ModuleDef>, Error> {
         debug!("init `std` module");
 
-        self.check_null(unsafe { ffi::js_init_module_std(self.as_ptr(), cstr!(std).as_ptr()) })
+        let m = self
+            .check_null(unsafe { ffi::js_init_module_std(self.as_ptr(), cstr!(std).as_ptr()) })?;
+
+        if self.with_state(|state| state.vfs.is_some()) {
+            self.add_std_vfs_hook(m)?;
+        }
+
+        if self.with_state(|state| state.env.is_some()) {
+            // replace `std.getenv` with the filtered one
+            self.add_module_hook(
+                m,
+                Box::new(|ctxt, m| {
+                    let func = ctxt.new_c_function(crate::env::getenv, Some("getenv"), 1)?;
+
+                    ctxt.set_module_export(m, "getenv", func)
+                }),
+            );
+        }
+
+        Ok(m)
     }
 
     pub fn init_module_os(&self) -> Result<NonNull<ModuleDef>, Error> {
         debug!("init `os` module");
 
-        self.check_null(unsafe { ffi::js_init_module_os(self.as_ptr(), cstr!(os).as_ptr()) })
+        // the `os` handlers should be freed before the contexts when shutting down
+        self.runtime().with_state(|state| state.std_handlers = true);
+
+        let m =
+            self.check_null(unsafe { ffi::js_init_module_os(self.as_ptr(), cstr!(os).as_ptr()) })?;
+
+        if self.with_state(|state| state.vfs.is_some()) {
+            self.add_os_vfs_hook(m)?;
+        }
+
+        Ok(m)
+    }
+
+    /// Initialize the `std` module with the capabilities, the denied functions throw a `TypeError`.
+    pub fn init_module_std_with(&self, caps: OsCapabilities) -> Result<NonNull<ModuleDef>, Error> {
+        let m = self.init_module_std()?;
+
+        debug!("init `std` module with {:?}", caps);
+
+        self.deny_module_exports(m, "std", caps.denied_std());
+
+        Ok(m)
+    }
+
+    /// Initialize the `os` module with the capabilities, the denied functions throw a `TypeError`.
+    pub fn init_module_os_with(&self, caps: OsCapabilities) -> Result<NonNull<ModuleDef>, Error> {
+        let m = self.init_module_os()?;
+
+        debug!("init `os` module with {:?}", caps);
+
+        self.deny_module_exports(m, "os", caps.denied());
+
+        Ok(m)
+    }
+
+    /// Replace the exported functions with the stubs throwing a `TypeError`.
+    fn deny_module_exports(
+        &self,
+        m: NonNull<ModuleDef>,
+        module: &'static str,
+        denied: Vec<&'static str>,
+    ) {
+        self.add_module_hook(
+            m,
+            Box::new(move |ctxt, m| {
+                for name in denied {
+                    let qualified = format!("{}.{}", module, name);
+
+                    trace!("deny `{}`", qualified);
+
+                    let stub = ctxt.new_c_function_data(not_permitted, 0, 0, qualified)?;
+
+                    ctxt.set_module_export(m, name, stub)?;
+                }
+
+                Ok(())
+            }),
+        );
+    }
+
+    /// Run the hook after the C module was initialized, which happens when it is imported at the first time.
+    pub(crate) fn add_module_hook(&self, m: NonNull<ModuleDef>, hook: ModuleHook) {
+        let key = m.as_ptr() as usize;
+
+        if !self.with_state(|state| state.module_hooks.contains_key(&key)) {
+            let init = unsafe { ffi::JS_SetModuleInitFunc(m.as_ptr(), Some(init_with_hooks)) };
+
+            self.with_state(|state| state.module_hooks.insert(key, (init, vec![])));
+        }
+
+        self.with_state(|state| {
+            if let Some((_, hooks)) = state.module_hooks.get_mut(&key) {
+                hooks.push(hook)
+            }
+        })
     }
 
     pub fn std_add_helpers<I: IntoIterator<Item = S>, S: Into<Vec<u8>>>(
@@ -36,15 +353,105 @@ impl ContextRef {
             ffi::js_std_add_helpers(self.as_ptr(), args.len() as i32, args.as_ptr() as *mut _);
         }
 
+        let redirected = self.with_state(|state| {
+            state.std_helpers = true;
+            state.stdout.is_some()
+        });
+
+        if redirected {
+            self.redirect_print()?;
+        }
+
+        Ok(())
+    }
+
+    /// Redirect the output of `print` and `console.log` added by `std_add_helpers`.
+    ///
+    /// The `std.out` file of the `std` module still writes to the process stdout.
+    pub fn set_stdout<W: Write + Send + 'static>(&self, w: W) -> Result<(), Error> {
+        let helpers = self.with_state(|state| {
+            state.stdout = Some(Box::new(w));
+            state.std_helpers
+        });
+
+        if helpers {
+            self.redirect_print()?;
+        }
+
         Ok(())
     }
 
+    /// Replace `print` and `console.log` of the `std` helpers to write to the redirected stdout.
+    fn redirect_print(&self) -> Result<(), Error> {
+        let global = self.global_object();
+
+        global.set_property("print", self.new_c_function(print, Some("print"), 1)?)?;
+
+        if let Some(console) = global.get_property("console") {
+            console.set_property("log", self.new_c_function(print, Some("log"), 1)?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Redirect the errors dumped by `std_dump_error`.
+    ///
+    /// The errors of the pending jobs dumped by `std_loop` are still written to the process stderr.
+    pub fn set_stderr<W: Write + Send + 'static>(&self, w: W) {
+        self.with_state(|state| state.stderr = Some(Box::new(w)));
+    }
+
     pub fn std_loop(&self) {
         unsafe { ffi::js_std_loop(self.as_ptr()) }
     }
 
+    /// Run the pending jobs and one ready handler of the `os` module without blocking.
+    ///
+    /// Returns `true` if some timers or handlers are still registered,
+    /// the host could wait for `RuntimeRef::next_timer_deadline` before polling again.
+    ///
+    /// The timers and handlers of `quickjs-libc` are shared by all the runtimes of the process,
+    /// and the handler is called with this context, so it should only be used with a single runtime.
+    pub fn std_poll(&self) -> bool {
+        self.run_pending_jobs();
+
+        let ret = unsafe { ffi::js_std_poll(self.as_ptr()) };
+
+        // the jobs queued by the handler, e.g. the promise reactions
+        if ret > 0 {
+            self.run_pending_jobs();
+        }
+
+        ret >= 0
+    }
+
+    fn run_pending_jobs(&self) {
+        let rt = self.runtime();
+
+        while rt.is_job_pending() {
+            if let Err(err) = rt.execute_pending_job() {
+                warn!("fail to execute pending job, {}", err);
+            }
+        }
+    }
+
     pub fn std_dump_error(&self) {
-        unsafe { ffi::js_std_dump_error(self.as_ptr()) }
+        if self.with_state(|state| state.stderr.is_none()) {
+            return unsafe { ffi::js_std_dump_error(self.as_ptr()) };
+        }
+
+        let msg = match self.take_exception() {
+            Ok(err) => err.to_string(),
+            Err(err) => err.to_string(),
+        };
+
+        if let Some(Err(err)) = write_redirected(
+            self,
+            |state| &mut state.stderr,
+            format!("{}\n", msg).as_bytes(),
+        ) {
+            warn!("fail to write stderr, {}", err);
+        }
     }
 }
 
@@ -52,4 +459,257 @@ impl RuntimeRef {
     pub fn std_free_handlers(&self) {
         unsafe { ffi::js_std_free_handlers(self.as_ptr()) }
     }
+
+    /// Returns the duration until the next timer of the `os` module fires, or `None` without timers.
+    ///
+    /// The timers and signal handlers of `quickjs-libc` are shared by all the runtimes of the process.
+    pub fn next_timer_deadline(&self) -> Option<Duration> {
+        let delay = unsafe { ffi::js_os_next_timer_deadline() };
+
+        if delay < 0 {
+            None
+        } else {
+            Some(Duration::from_millis(delay as u64))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, Eval, Runtime};
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Buffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn redirect_output() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+        let stdout = Buffer::default();
+        let stderr = Buffer::default();
+
+        ctxt.std_add_helpers::<_, String>(None).unwrap();
+        ctxt.set_stdout(stdout.clone()).unwrap();
+        ctxt.set_stderr(stderr.clone());
+
+        ctxt.eval::<_, ()>("print('hello', 42); console.log({})", Eval::GLOBAL)
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8_lossy(&stdout.0.lock().unwrap()),
+            "hello 42\n[object Object]\n"
+        );
+
+        ctxt.throw_type_error("Whoops!");
+        ctxt.std_dump_error();
+
+        assert!(String::from_utf8_lossy(&stderr.0.lock().unwrap()).contains("Whoops!"));
+
+        // the stdout redirected before the helpers are added
+        let ctxt = Context::new(&rt);
+        let stdout = Buffer::default();
+
+        ctxt.set_stdout(stdout.clone()).unwrap();
+        ctxt.std_add_helpers::<_, String>(None).unwrap();
+
+        ctxt.eval::<_, ()>("print('again')", Eval::GLOBAL).unwrap();
+
+        assert_eq!(
+            String::from_utf8_lossy(&stdout.0.lock().unwrap()),
+            "again\n"
+        );
+
+        rt.std_free_handlers();
+    }
+
+    #[test]
+    fn poll_timers() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        ctxt.init_module_os().unwrap();
+
+        assert_eq!(rt.next_timer_deadline(), None);
+        assert!(!ctxt.std_poll());
+
+        ctxt.eval::<_, ()>(
+            r#"
+import * as os from 'os';
+
+globalThis.fired = [];
+os.setTimeout(() => fired.push('now'), 0);
+os.setTimeout(() => fired.push('later'), 60000);
+Promise.resolve().then(() => fired.push('job'));
+"#,
+            Eval::MODULE,
+        )
+        .unwrap();
+
+        assert!(rt.next_timer_deadline().is_some());
+        assert!(ctxt.std_poll());
+        assert_eq!(
+            ctxt.eval::<_, String>("fired.join()", Eval::GLOBAL)
+                .unwrap()
+                .unwrap(),
+            "job,now"
+        );
+        assert!(rt.next_timer_deadline().unwrap() > Duration::from_secs(50));
+
+        // the timer rescheduling itself is called once per poll
+        ctxt.eval::<_, ()>(
+            r#"
+import * as os from 'os';
+
+globalThis.ticks = 0;
+const tick = () => { ticks++; os.setTimeout(tick, 0); };
+os.setTimeout(tick, 0);
+"#,
+            Eval::MODULE,
+        )
+        .unwrap();
+
+        assert!(ctxt.std_poll());
+        assert_eq!(ctxt.eval("ticks", Eval::GLOBAL).unwrap(), Some(1));
+
+        rt.std_free_handlers();
+    }
+
+    #[test]
+    fn os_capabilities() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let caps = OsCapabilities {
+            exec: false,
+            fs_write: false,
+            ..OsCapabilities::ALL
+        };
+
+        ctxt.init_module_os_with(caps).unwrap();
+        ctxt.init_module_std_with(caps).unwrap();
+
+        ctxt.eval_module(
+            r#"
+import * as os from 'os';
+import * as std from 'std';
+
+globalThis.cwd = os.getcwd()[0];
+globalThis.platform = os.platform;
+
+try {
+    os.exec(['ls']);
+} catch (err) {
+    globalThis.denied = err.message;
+}
+
+globalThis.denied_std = ['popen', 'urlGet', 'open', 'tmpfile'].map(name => {
+    try {
+        std[name]('/dev/null', 'r');
+    } catch (err) {
+        return err.message;
+    }
+}).join();
+globalThis.sprintf = std.sprintf('%d', 1);
+"#,
+            "<evalModule>",
+        )
+        .unwrap();
+
+        assert!(!ctxt
+            .eval::<_, String>("cwd", Eval::GLOBAL)
+            .unwrap()
+            .unwrap()
+            .is_empty());
+        assert!(ctxt
+            .eval::<_, String>("platform", Eval::GLOBAL)
+            .unwrap()
+            .is_some());
+        assert_eq!(
+            ctxt.eval::<_, String>("denied", Eval::GLOBAL)
+                .unwrap()
+                .unwrap(),
+            "`os.exec` is not permitted"
+        );
+        assert_eq!(
+            ctxt.eval::<_, String>("denied_std", Eval::GLOBAL)
+                .unwrap()
+                .unwrap(),
+            "`std.popen` is not permitted,`std.urlGet` is not permitted,\
+             `std.open` is not permitted,`std.tmpfile` is not permitted"
+        );
+        assert_eq!(
+            ctxt.eval::<_, String>("sprintf", Eval::GLOBAL)
+                .unwrap()
+                .unwrap(),
+            "1"
+        );
+        assert!(OsCapabilities::ALL.denied().is_empty());
+        assert!(OsCapabilities::ALL.denied_std().is_empty());
+
+        rt.std_free_handlers();
+    }
+
+    #[test]
+    fn os_capabilities_cover_all_functions() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        ctxt.init_module_os().unwrap();
+        ctxt.init_module_std().unwrap();
+        ctxt.eval_module(
+            r#"
+import * as os from 'os';
+import * as std from 'std';
+
+globalThis.funcs = Object.keys(os).filter(name => typeof os[name] === 'function').sort().join();
+globalThis.std_funcs = Object.keys(std).filter(name => typeof std[name] === 'function');
+"#,
+            "<evalModule>",
+        )
+        .unwrap();
+
+        let mut denied = OsCapabilities::NONE.denied();
+
+        denied.sort_unstable();
+
+        assert_eq!(
+            ctxt.eval::<_, String>("funcs", Eval::GLOBAL)
+                .unwrap()
+                .unwrap(),
+            denied.join(",")
+        );
+
+        let denied_std = OsCapabilities::NONE.denied_std();
+
+        assert_eq!(denied_std.len(), 6);
+        assert!(ctxt
+            .eval::<_, bool>(
+                format!("{:?}.every(name => std_funcs.includes(name))", denied_std).as_str(),
+                Eval::GLOBAL
+            )
+            .unwrap()
+            .unwrap());
+
+        rt.std_free_handlers();
+    }
 }
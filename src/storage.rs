@@ -0,0 +1,170 @@
+use std::any::{Any, TypeId};
+
+use crate::{ffi, prop::Names, ContextRef, Error, Local, NewValue, Prop, Value, NULL};
+
+/// The name of the global accessor which exposes the host values to the scripts.
+const HOST: &str = "__host";
+
+/// The per-context storage shared by Rust and Javascript.
+///
+/// The Rust values are keyed by their types, like the per-request data (user id, trace id etc),
+/// and the exposed values could be read by the scripts through a frozen `__host` object.
+pub struct Storage<'a> {
+    ctxt: &'a ContextRef,
+}
+
+impl ContextRef {
+    /// Returns the per-context storage shared by Rust and Javascript.
+    pub fn storage(&self) -> Storage {
+        Storage { ctxt: self }
+    }
+}
+
+impl<'a> Storage<'a> {
+    /// Insert a value into the storage, returns the previous value of the type.
+    pub fn insert<T: Any + Send>(&self, value: T) -> Option<T> {
+        self.ctxt
+            .with_state(|state| state.storage.insert(TypeId::of::<T>(), Box::new(value)))
+            .and_then(|prev| prev.downcast().ok())
+            .map(|prev| *prev)
+    }
+
+    /// Returns a copy of the value of the type.
+    pub fn get<T: Any + Send + Clone>(&self) -> Option<T> {
+        self.ctxt.with_state(|state| {
+            state
+                .storage
+                .get(&TypeId::of::<T>())
+                .and_then(|value| value.downcast_ref::<T>())
+                .cloned()
+        })
+    }
+
+    /// Returns `true` if the storage contains a value of the type.
+    pub fn contains<T: Any + Send>(&self) -> bool {
+        self.ctxt
+            .with_state(|state| state.storage.contains_key(&TypeId::of::<T>()))
+    }
+
+    /// Remove the value of the type from the storage.
+    pub fn remove<T: Any + Send>(&self) -> Option<T> {
+        self.ctxt
+            .with_state(|state| state.storage.remove(&TypeId::of::<T>()))
+            .and_then(|value| value.downcast().ok())
+            .map(|value| *value)
+    }
+
+    /// Expose a value to the scripts as `__host[key]`.
+    ///
+    /// Only the exposed keys are visible to the scripts, and the `__host` object is frozen.
+    pub fn expose<V: NewValue>(&self, key: &str, value: V) -> Result<(), Error> {
+        let ctxt = self.ctxt;
+        let host = ctxt.bind(ctxt.new_object_proto(&NULL));
+
+        if let Some(prev) = self.host() {
+            for name in ctxt
+                .get_own_property_names(&prev, Names::STRING)?
+                .unwrap_or_default()
+            {
+                if let Some(value) = prev.get_property(name.clone()) {
+                    host.set_property(name, value)?;
+                }
+            }
+        } else {
+            let getter = ctxt.new_c_function(get_host, Some(HOST), 0)?;
+
+            ctxt.global_object().define_property(
+                HOST,
+                None,
+                Some(&*getter),
+                None,
+                Prop::empty(),
+            )?;
+        }
+
+        trace!("expose `{}` to scripts", key);
+
+        host.set_property(key, value)?;
+
+        ctxt.global_object()
+            .get_property("Object")
+            .and_then(|object| ctxt.get_property(&object, "freeze"))
+            .ok_or_else(|| Error::from("`Object.freeze` not found"))?
+            .call(None, &host)?;
+
+        let prev = ctxt.with_state(|state| state.host.replace(host.into_inner()));
+
+        if let Some(prev) = prev {
+            ctxt.free_value(prev);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the value exposed to the scripts.
+    pub fn exposed(&self, key: &str) -> Option<Local<'a, Value>> {
+        self.host()
+            .and_then(|host| self.ctxt.get_property(&host, key))
+    }
+
+    fn host(&self) -> Option<Local<'a, Value>> {
+        self.ctxt
+            .with_state(|state| state.host.as_ref().map(|host| host.raw()))
+            .map(|host| self.ctxt.clone_value(&Value::from(host)))
+    }
+}
+
+fn get_host(ctxt: &ContextRef, _this: Option<&Value>, _args: &[Value]) -> ffi::JSValue {
+    ctxt.storage()
+        .host()
+        .map_or(ffi::UNDEFINED, |host| host.into_inner().raw())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, Eval, Runtime};
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct TraceId(u64);
+
+    #[test]
+    fn storage() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+        let storage = ctxt.storage();
+
+        assert!(storage.get::<TraceId>().is_none());
+        assert!(storage.insert(TraceId(1)).is_none());
+        assert_eq!(storage.insert(TraceId(2)), Some(TraceId(1)));
+        assert_eq!(storage.get::<TraceId>(), Some(TraceId(2)));
+        assert!(storage.contains::<TraceId>());
+        assert_eq!(storage.remove::<TraceId>(), Some(TraceId(2)));
+        assert!(!storage.contains::<TraceId>());
+
+        assert_eq!(
+            ctxt.eval::<_, String>("typeof __host", Eval::GLOBAL)
+                .unwrap(),
+            Some("undefined".to_owned())
+        );
+
+        storage.expose("user", "alice").unwrap();
+        storage.expose("trace", 123).unwrap();
+
+        assert_eq!(storage.exposed("user").unwrap().to_string(), "alice");
+        assert_eq!(
+            ctxt.eval::<_, String>(
+                "__host.user = 'mallory'; __host = null; __host.user + ':' + __host.trace",
+                Eval::GLOBAL
+            )
+            .unwrap(),
+            Some("alice:123".to_owned())
+        );
+        assert_eq!(
+            ctxt.eval::<_, bool>("Object.isFrozen(__host)", Eval::GLOBAL)
+                .unwrap(),
+            Some(true)
+        );
+    }
+}
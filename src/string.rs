@@ -0,0 +1,235 @@
+use std::fmt;
+use std::ops::Deref;
+use std::os::raw::c_char;
+use std::slice;
+use std::str;
+
+use foreign_types::ForeignTypeRef;
+
+use crate::{ffi, ContextRef, Local, Value};
+
+/// A UTF-8 view of the Javascript `String`, created by `Local<Value>::as_str_utf8`.
+///
+/// The ASCII strings are borrowed from the engine without copying,
+/// the other strings are converted to UTF-8 in a temporary buffer of the engine.
+pub struct JsStr<'a> {
+    ctxt: &'a ContextRef,
+    ptr: *const c_char,
+    len: usize,
+}
+
+impl Deref for JsStr<'_> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        // the bytes were validated when the view is created
+        unsafe { str::from_utf8_unchecked(slice::from_raw_parts(self.ptr as *const u8, self.len)) }
+    }
+}
+
+impl AsRef<str> for JsStr<'_> {
+    fn as_ref(&self) -> &str {
+        self
+    }
+}
+
+impl fmt::Display for JsStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self)
+    }
+}
+
+impl fmt::Debug for JsStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl Drop for JsStr<'_> {
+    fn drop(&mut self) {
+        unsafe { ffi::JS_FreeCString(self.ctxt.as_ptr(), self.ptr) }
+    }
+}
+
+impl<'a> Local<'a, Value> {
+    /// Returns a UTF-8 view of the string without allocating a `CString`.
+    ///
+    /// Returns `None` if the value is not a string, or it contains the lone surrogates which are not valid UTF-8.
+    pub fn as_str_utf8(&self) -> Option<JsStr<'a>> {
+        if !self.is_string() {
+            return None;
+        }
+
+        let mut len = 0;
+        let ptr = unsafe {
+            ffi::JS_ToCStringLen2(self.ctxt.as_ptr(), &mut len, self.raw(), ffi::FALSE_VALUE)
+        };
+
+        if ptr.is_null() {
+            return None;
+        }
+
+        let s = JsStr {
+            ctxt: self.ctxt,
+            ptr,
+            len,
+        };
+
+        if str::from_utf8(unsafe { slice::from_raw_parts(ptr as *const u8, s.len) }).is_ok() {
+            Some(s)
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a> Local<'a, Value> {
+    /// Convert the value to a string in UTF-16 code units, the unpaired surrogates are kept.
+    pub fn to_utf16(&self) -> Option<Vec<u16>> {
+        let mut len = 0;
+        let ptr = unsafe {
+            ffi::JS_ToCStringLen2(self.ctxt.as_ptr(), &mut len, self.raw(), ffi::TRUE_VALUE)
+        };
+
+        if ptr.is_null() {
+            return None;
+        }
+
+        let units = decode_cesu8(unsafe { slice::from_raw_parts(ptr as *const u8, len) });
+
+        unsafe { ffi::JS_FreeCString(self.ctxt.as_ptr(), ptr) };
+
+        Some(units)
+    }
+}
+
+impl ContextRef {
+    /// Creates a new string from the UTF-16 code units, the unpaired surrogates are kept.
+    pub fn new_string_utf16(&self, units: &[u16]) -> Local<Value> {
+        let buf = encode_cesu8(units);
+
+        self.bind(unsafe {
+            ffi::JS_NewStringLen(self.as_ptr(), buf.as_ptr() as *const _, buf.len())
+        })
+    }
+
+    /// Returns the interned string, the strings interned with the same content share the same memory.
+    ///
+    /// Unlike `new_atom_string`, the string may contain the internal 0 bytes.
+    pub fn intern<T: AsRef<str>>(&self, s: T) -> Local<Value> {
+        self.atom_to_string(*self.new_atom(s.as_ref()))
+    }
+}
+
+/// Encode each UTF-16 code unit separately, like CESU-8, which the engine decodes back to the same units.
+fn encode_cesu8(units: &[u16]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(units.len());
+
+    for &c in units {
+        match c {
+            0..=0x7F => buf.push(c as u8),
+            0x80..=0x7FF => {
+                buf.extend_from_slice(&[0xC0 | (c >> 6) as u8, 0x80 | (c & 0x3F) as u8])
+            }
+            _ => buf.extend_from_slice(&[
+                0xE0 | (c >> 12) as u8,
+                0x80 | ((c >> 6) & 0x3F) as u8,
+                0x80 | (c & 0x3F) as u8,
+            ]),
+        }
+    }
+
+    buf
+}
+
+/// Decode the CESU-8 bytes written by the engine, each sequence is a UTF-16 code unit.
+fn decode_cesu8(buf: &[u8]) -> Vec<u16> {
+    let mut units = Vec::with_capacity(buf.len());
+    let mut bytes = buf.iter().map(|&b| b as u16);
+
+    while let Some(b) = bytes.next() {
+        let mut next = || bytes.next().unwrap_or_default() & 0x3F;
+
+        units.push(match b {
+            0..=0x7F => b,
+            0xC0..=0xDF => ((b & 0x1F) << 6) | next(),
+            _ => ((b & 0x0F) << 12) | (next() << 6) | next(),
+        });
+    }
+
+    units
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, Eval, Runtime};
+
+    #[test]
+    fn as_str_utf8() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let value = ctxt
+            .eval_script("'hello' + ' world'", "<evalScript>", Eval::GLOBAL)
+            .unwrap();
+        let s = value.as_str_utf8().unwrap();
+
+        assert_eq!(&*s, "hello world");
+        // the ASCII string is borrowed from the engine
+        assert_eq!(value.as_str_utf8().unwrap().as_ptr(), s.as_ptr());
+
+        let value = ctxt
+            .eval_script("'héllo'", "<evalScript>", Eval::GLOBAL)
+            .unwrap();
+
+        assert_eq!(value.as_str_utf8().unwrap().to_string(), "héllo");
+
+        let value = ctxt
+            .eval_script("'\\uD800'", "<evalScript>", Eval::GLOBAL)
+            .unwrap();
+
+        assert!(value.as_str_utf8().is_none());
+        assert!(ctxt.bind(1).as_str_utf8().is_none());
+
+        let foo = ctxt.intern("foo\0bar");
+        let foo2 = ctxt.intern(String::from("foo\0bar"));
+
+        assert_eq!(foo.as_str_utf8().unwrap().len(), 7);
+        assert_eq!(foo.as_ptr::<u8>(), foo2.as_ptr::<u8>());
+    }
+
+    #[test]
+    fn utf16() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let units = [0x61, 0xE9, 0x4E2D, 0xD83D, 0xDE00, 0xD800, 0x62];
+        let s = ctxt.new_string_utf16(&units);
+
+        assert!(ctxt.global_object().set_property("s", &s).unwrap());
+        assert_eq!(ctxt.eval("s.length", Eval::GLOBAL).unwrap(), Some(7));
+        assert_eq!(
+            ctxt.eval("s.charCodeAt(5)", Eval::GLOBAL).unwrap(),
+            Some(0xD800)
+        );
+        assert_eq!(
+            ctxt.eval("s.codePointAt(3)", Eval::GLOBAL).unwrap(),
+            Some(0x1F600)
+        );
+        assert_eq!(s.to_utf16().unwrap(), units);
+
+        let value = ctxt
+            .eval_script("'caf\\u00e9 \\uDC00'", "<evalScript>", Eval::GLOBAL)
+            .unwrap();
+
+        assert_eq!(
+            value.to_utf16().unwrap(),
+            [0x63, 0x61, 0x66, 0xE9, 0x20, 0xDC00]
+        );
+        assert_eq!(ctxt.bind(42).to_utf16().unwrap(), [0x34, 0x32]);
+    }
+}
@@ -0,0 +1,186 @@
+use std::os::raw::c_int;
+use std::ptr::null;
+
+use foreign_types::ForeignTypeRef;
+
+use crate::{ffi, ContextRef, Error, Local, Value};
+
+/// The well-known symbols, which are the static properties of `Symbol`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WellKnownSymbol {
+    /// `Symbol.asyncIterator`
+    AsyncIterator,
+    /// `Symbol.hasInstance`
+    HasInstance,
+    /// `Symbol.isConcatSpreadable`
+    IsConcatSpreadable,
+    /// `Symbol.iterator`
+    Iterator,
+    /// `Symbol.match`
+    Match,
+    /// `Symbol.matchAll`
+    MatchAll,
+    /// `Symbol.replace`
+    Replace,
+    /// `Symbol.search`
+    Search,
+    /// `Symbol.species`
+    Species,
+    /// `Symbol.split`
+    Split,
+    /// `Symbol.toPrimitive`
+    ToPrimitive,
+    /// `Symbol.toStringTag`
+    ToStringTag,
+    /// `Symbol.unscopables`
+    Unscopables,
+}
+
+impl WellKnownSymbol {
+    /// Returns the property name on `Symbol`.
+    pub fn name(self) -> &'static str {
+        use WellKnownSymbol::*;
+
+        match self {
+            AsyncIterator => "asyncIterator",
+            HasInstance => "hasInstance",
+            IsConcatSpreadable => "isConcatSpreadable",
+            Iterator => "iterator",
+            Match => "match",
+            MatchAll => "matchAll",
+            Replace => "replace",
+            Search => "search",
+            Species => "species",
+            Split => "split",
+            ToPrimitive => "toPrimitive",
+            ToStringTag => "toStringTag",
+            Unscopables => "unscopables",
+        }
+    }
+
+    /// Returns the index of the well-known symbol in the atoms of QuickJS.
+    fn index(self) -> c_int {
+        use WellKnownSymbol::*;
+
+        match self {
+            ToPrimitive => 0,
+            Iterator => 1,
+            Match => 2,
+            MatchAll => 3,
+            Replace => 4,
+            Search => 5,
+            Split => 6,
+            ToStringTag => 7,
+            IsConcatSpreadable => 8,
+            HasInstance => 9,
+            Species => 10,
+            Unscopables => 11,
+            AsyncIterator => 12,
+        }
+    }
+}
+
+impl ContextRef {
+    /// Create a new unique `Symbol` with the optional description.
+    ///
+    /// The symbol can be used as a property key with the property methods.
+    pub fn new_symbol(&self, description: Option<&str>) -> Result<Local<Value>, Error> {
+        let (descr, len) = description.map_or((null(), 0), |s| (s.as_ptr(), s.len()));
+
+        self.bind(unsafe {
+            ffi::JS_NewSymbolWithDescription(self.as_ptr(), descr as *const _, len)
+        })
+        .ok()
+    }
+
+    /// Returns the well-known symbol.
+    ///
+    /// The symbols are taken from the engine, so they are not affected by the scripts modifying `Symbol`.
+    pub fn well_known_symbol(&self, symbol: WellKnownSymbol) -> Result<Local<Value>, Error> {
+        self.bind(unsafe { ffi::JS_GetWellKnownSymbol(self.as_ptr(), symbol.index()) })
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, Eval, Prop, Runtime};
+
+    use super::*;
+
+    #[test]
+    fn symbol() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let obj = ctxt.bind(ctxt.new_object());
+        let secret = ctxt.new_symbol(Some("secret")).unwrap();
+
+        assert!(secret.is_symbol());
+        assert!(obj.set_property(&*secret, 42).unwrap());
+        assert_eq!(obj.get_property(&*secret).unwrap().as_int(), Some(42));
+        assert!(obj.has_property(&*secret).unwrap());
+        assert!(!obj.has_property("secret").unwrap());
+
+        let tag = ctxt
+            .well_known_symbol(WellKnownSymbol::ToStringTag)
+            .unwrap();
+
+        assert_eq!(secret.inspect(0), "Symbol(secret)");
+        assert_eq!(ctxt.new_symbol(None).unwrap().inspect(0), "Symbol()");
+
+        obj.define_property_value(&*tag, "Custom", Prop::CONFIGURABLE)
+            .unwrap();
+
+        ctxt.global_object().set_property("obj", obj).unwrap();
+        ctxt.global_object()
+            .set_property(
+                "iter",
+                ctxt.well_known_symbol(WellKnownSymbol::Iterator).unwrap(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            ctxt.eval::<_, String>(
+                "[Object.prototype.toString.call(obj), iter === Symbol.iterator, Object.keys(obj).length].join()",
+                Eval::GLOBAL
+            )
+            .unwrap(),
+            Some("[object Custom],true,0".to_owned())
+        );
+
+        ctxt.eval_script(
+            "Symbol = function () { throw new Error('Whoops!') }",
+            "<evalScript>",
+            Eval::GLOBAL,
+        )
+        .unwrap();
+
+        assert!(ctxt.new_symbol(Some("other")).unwrap().is_symbol());
+        assert_eq!(
+            ctxt.well_known_symbol(WellKnownSymbol::AsyncIterator)
+                .unwrap()
+                .inspect(0),
+            "Symbol(Symbol.asyncIterator)"
+        );
+
+        let key = ctxt
+            .eval_script(
+                "({ toString() { throw new Error('bad key') } })",
+                "<evalScript>",
+                Eval::GLOBAL,
+            )
+            .unwrap();
+
+        let obj = ctxt.bind(ctxt.new_object());
+
+        assert!(obj.get_property(&*key).unwrap().ok().is_err());
+        assert!(matches!(
+            obj.set_property(&*key, 1),
+            Err(err) if err.to_string().contains("bad key")
+        ));
+        assert!(obj.has_property(&*key).is_err());
+    }
+}
@@ -0,0 +1,263 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread::{self, JoinHandle, ThreadId};
+
+use foreign_types::ForeignTypeRef;
+
+use crate::{ffi, ContextRef, Runtime, RuntimeRef};
+
+/// The distance from the frame of `RuntimeRef::enter` to the stack top of the contexts.
+const STACK_TOP_SLACK: usize = 32 * 1024;
+
+static NEXT_THREAD: AtomicUsize = AtomicUsize::new(1);
+
+thread_local! {
+    /// The non-zero number of the current thread, which fits in an atomic unlike `ThreadId`.
+    static THREAD: usize = NEXT_THREAD.fetch_add(1, Ordering::Relaxed);
+
+    /// The entries of the runtimes used by the current thread, to skip the lock of the runtime states.
+    static ENTRIES: RefCell<HashMap<usize, Weak<Entry>>> = RefCell::new(HashMap::new());
+}
+
+/// The threads entered a runtime, which is released with the runtime states.
+#[derive(Debug, Default)]
+pub(crate) struct Entry {
+    /// The number of the thread running in the runtime, or zero.
+    owner: AtomicUsize,
+    /// The nesting depth of the running thread.
+    depth: AtomicUsize,
+    /// The number of the last thread entered the runtime.
+    last: AtomicUsize,
+    /// The thread running in the runtime.
+    thread: Mutex<Option<ThreadId>>,
+    /// The running contexts, the innermost is the last.
+    pub contexts: Mutex<Vec<usize>>,
+}
+
+/// The guard of the current thread running in a runtime, created by `RuntimeRef::enter`.
+pub(crate) struct Entered {
+    entry: Arc<Entry>,
+    ctx: Option<usize>,
+}
+
+impl RuntimeRef {
+    /// Returns the entry of the runtime, which is cached by the thread.
+    pub(crate) fn entry(&self) -> Arc<Entry> {
+        let rt = self.as_ptr() as usize;
+
+        ENTRIES.with(|entries| {
+            let mut entries = entries.borrow_mut();
+
+            // the entry of a freed runtime can't be upgraded, even if the address is reused
+            if let Some(entry) = entries.get(&rt).and_then(Weak::upgrade) {
+                return entry;
+            }
+
+            entries.retain(|_, entry| entry.strong_count() > 0);
+
+            let entry = self.with_state(|state| state.entry.clone());
+
+            entries.insert(rt, Arc::downgrade(&entry));
+
+            entry
+        })
+    }
+
+    /// Mark the current thread as the last one used the runtime.
+    pub(crate) fn set_last_thread(&self) {
+        self.entry()
+            .last
+            .store(THREAD.with(|id| *id), Ordering::Relaxed)
+    }
+
+    /// Mark the current thread running in the runtime until the guard is dropped.
+    ///
+    /// QuickJS supports no multi-threading inside a runtime, the `Runtime` and its `Context`s may be moved
+    /// to other threads, but they must not be used by two threads at the same time.
+    /// Such misuse panics in all the builds instead of corrupting the engine.
+    ///
+    /// Each context records the stack top of the thread which created it to detect the stack overflow,
+    /// so the stack tops are moved to the current frame when the runtime is entered from another thread.
+    pub(crate) fn enter(&self) -> Entered {
+        let current = THREAD.with(|id| *id);
+        let entry = self.entry();
+
+        match entry
+            .owner
+            .compare_exchange(0, current, Ordering::Acquire, Ordering::Relaxed)
+        {
+            Ok(_) => {
+                entry.depth.store(1, Ordering::Relaxed);
+                *entry.thread.lock().expect("entry") = Some(thread::current().id());
+
+                if entry.last.swap(current, Ordering::Relaxed) != current {
+                    self.move_stack_top();
+                }
+            }
+            Err(owner) if owner == current => {
+                entry.depth.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(_) => {
+                // panic out of the lock of the entry
+                let running = *entry.thread.lock().expect("entry");
+
+                panic!(
+                    "{:?} is running in thread {:?}, but used by thread {:?}",
+                    self,
+                    running,
+                    thread::current().id()
+                );
+            }
+        }
+
+        Entered { entry, ctx: None }
+    }
+
+    fn move_stack_top(&self) {
+        let contexts = self.with_state(|state| state.contexts.clone());
+        // the callers of the entry point may have smaller frames than this one
+        let stack_top = (&contexts as *const _ as usize + STACK_TOP_SLACK) as *const u8;
+
+        trace!(
+            "{:?} entered from thread {:?}, stack top @ {:p}",
+            self,
+            thread::current().id(),
+            stack_top
+        );
+
+        for ctx in contexts {
+            unsafe { ffi::JS_SetContextStackTop(ctx as *mut _, stack_top) }
+        }
+    }
+
+    /// Returns the thread running in the runtime.
+    pub fn running_thread(&self) -> Option<ThreadId> {
+        *self.entry().thread.lock().expect("entry")
+    }
+}
+
+impl ContextRef {
+    /// Mark the current thread running in the context until the guard is dropped.
+    ///
+    /// The running contexts are tracked, so the interrupt checks could be charged to the context.
+    pub(crate) fn enter(&self) -> Entered {
+        let mut entered = self.runtime().enter();
+        let ctx = self.as_ptr() as usize;
+
+        entered.entry.contexts.lock().expect("entry").push(ctx);
+        entered.ctx = Some(ctx);
+
+        entered
+    }
+}
+
+impl Drop for Entered {
+    fn drop(&mut self) {
+        let entry = &self.entry;
+
+        if self.ctx.is_some() {
+            entry.contexts.lock().expect("entry").pop();
+        }
+
+        if entry.depth.fetch_sub(1, Ordering::Relaxed) == 1 {
+            *entry.thread.lock().expect("entry") = None;
+            entry.owner.store(0, Ordering::Release);
+        }
+    }
+}
+
+impl Runtime {
+    /// Spawn a thread which owns a new runtime, the runtime is freed before the thread exits.
+    ///
+    /// The values of the runtime can't escape from the closure, so it's never shared between threads.
+    pub fn new_on_thread<F, T>(f: F) -> JoinHandle<T>
+    where
+        F: FnOnce(&Runtime) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        thread::spawn(move || {
+            let rt = Runtime::new();
+
+            f(&rt)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::Barrier;
+
+    use crate::{Context, ContextRef, Eval, Value};
+
+    use super::*;
+
+    lazy_static! {
+        static ref BARRIER: Barrier = Barrier::new(2);
+    }
+
+    fn block(_ctxt: &ContextRef, _this: Option<&Value>, _args: &[Value]) -> i32 {
+        BARRIER.wait();
+        BARRIER.wait();
+
+        1
+    }
+
+    #[test]
+    fn new_on_thread() {
+        let _ = pretty_env_logger::try_init();
+
+        let v = Runtime::new_on_thread(|rt| {
+            Context::new(rt)
+                .eval::<_, i32>("1 + 2", Eval::GLOBAL)
+                .unwrap()
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(v, Some(3));
+    }
+
+    #[test]
+    fn concurrent_use() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+        let other = Context::new(&rt);
+
+        other
+            .global_object()
+            .set_property(
+                "block",
+                other.new_c_function(block, Some("block"), 0).unwrap(),
+            )
+            .unwrap();
+
+        let handle = thread::spawn(move || {
+            let v = other.eval::<_, i32>("block()", Eval::GLOBAL).unwrap();
+
+            (other, v)
+        });
+
+        BARRIER.wait();
+
+        assert!(rt.running_thread().is_some());
+        assert!(
+            panic::catch_unwind(AssertUnwindSafe(|| ctxt.eval::<_, i32>("1", Eval::GLOBAL)))
+                .is_err()
+        );
+
+        BARRIER.wait();
+
+        let (other, v) = handle.join().unwrap();
+
+        assert_eq!(v, Some(1));
+        assert!(rt.running_thread().is_none());
+        assert_eq!(ctxt.eval::<_, i32>("2", Eval::GLOBAL).unwrap(), Some(2));
+
+        drop(other);
+    }
+}
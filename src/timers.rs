@@ -0,0 +1,372 @@
+use std::time::{Duration, Instant};
+
+use crate::{ffi, ContextRef, Error, NewValue, Value};
+
+/// The minimum interval of the repeating timers, which prevents them from starving the loop.
+const MIN_INTERVAL: Duration = Duration::from_millis(1);
+
+/// The timers registered by `setTimeout` and `setInterval`, which are driven by the host loop.
+///
+/// The deadlines are measured by the wall clock plus the time advanced by the host.
+#[derive(Default)]
+pub(crate) struct Timers {
+    next_id: i32,
+    advanced: Duration,
+    timers: Vec<Timer>,
+}
+
+struct Timer {
+    id: i32,
+    deadline: Instant,
+    interval: Option<Duration>,
+    callback: Value,
+    args: Vec<Value>,
+}
+
+impl Timers {
+    fn now(&self) -> Instant {
+        Instant::now() + self.advanced
+    }
+
+    /// Take the values of the pending timers, which should be freed with the context.
+    pub fn drain(&mut self) -> impl Iterator<Item = Value> + '_ {
+        self.timers
+            .drain(..)
+            .flat_map(|timer| Some(timer.callback).into_iter().chain(timer.args))
+    }
+}
+
+fn add_timer(ctxt: &ContextRef, args: &[Value], repeat: bool) -> ffi::JSValue {
+    let callback = match args.first() {
+        Some(callback) if ctxt.is_function(callback) => callback,
+        _ => return ctxt.throw_type_error("not a function").into_inner().raw(),
+    };
+    let delay = args
+        .get(1)
+        .and_then(|delay| ctxt.to_float64(delay))
+        .filter(|ms| ms.is_finite() && *ms > 0.0)
+        .map_or(Duration::from_millis(0), |ms| {
+            Duration::from_micros((ms * 1000.0) as u64)
+        });
+    let callback = ctxt.clone_value(callback).into_inner();
+    let args = args
+        .iter()
+        .skip(2)
+        .map(|arg| ctxt.clone_value(arg).into_inner())
+        .collect();
+
+    let id = ctxt.with_state(|state| {
+        let timers = &mut state.timers;
+        let deadline = timers.now() + delay;
+
+        timers.next_id = timers.next_id.checked_add(1).unwrap_or(1);
+        timers.timers.push(Timer {
+            id: timers.next_id,
+            deadline,
+            interval: if repeat {
+                Some(delay.max(MIN_INTERVAL))
+            } else {
+                None
+            },
+            callback,
+            args,
+        });
+
+        timers.next_id
+    });
+
+    trace!("add timer #{} after {:?}", id, delay);
+
+    id.new_value(ctxt)
+}
+
+fn set_timeout(ctxt: &ContextRef, _this: Option<&Value>, args: &[Value]) -> ffi::JSValue {
+    add_timer(ctxt, args, false)
+}
+
+fn set_interval(ctxt: &ContextRef, _this: Option<&Value>, args: &[Value]) -> ffi::JSValue {
+    add_timer(ctxt, args, true)
+}
+
+fn clear_timer(ctxt: &ContextRef, _this: Option<&Value>, args: &[Value]) -> ffi::JSValue {
+    if let Some(id) = args.first().and_then(|id| ctxt.to_int32(id)) {
+        let timer = ctxt.with_state(|state| {
+            let timers = &mut state.timers.timers;
+
+            timers
+                .iter()
+                .position(|timer| timer.id == id)
+                .map(|idx| timers.remove(idx))
+        });
+
+        if let Some(timer) = timer {
+            trace!("clear timer #{}", id);
+
+            ctxt.free_timer(timer);
+        }
+    }
+
+    ffi::UNDEFINED
+}
+
+impl ContextRef {
+    /// Install the `setTimeout`, `setInterval`, `clearTimeout` and `clearInterval` global functions.
+    ///
+    /// The timers are not fired by themselves, the host loop should call `run_jobs_once`
+    /// and wait until the `next_timer_deadline` when there is no job to run,
+    /// or drive the timers with a virtual clock by `advance_time`.
+    pub fn init_timers(&self) -> Result<(), Error> {
+        let global = self.global_object();
+
+        global.set_property(
+            "setTimeout",
+            self.new_c_function(set_timeout, Some("setTimeout"), 2)?,
+        )?;
+        global.set_property(
+            "setInterval",
+            self.new_c_function(set_interval, Some("setInterval"), 2)?,
+        )?;
+        global.set_property(
+            "clearTimeout",
+            self.new_c_function(clear_timer, Some("clearTimeout"), 1)?,
+        )?;
+        global.set_property(
+            "clearInterval",
+            self.new_c_function(clear_timer, Some("clearInterval"), 1)?,
+        )?;
+
+        Ok(())
+    }
+
+    /// Returns the number of the pending timers and in-flight fetches,
+    /// plus one if there are pending jobs in the microtask queue.
+    ///
+    /// The engine doesn't expose the length of the microtask queue, so it only counts as one.
+    pub fn pending_jobs(&self) -> usize {
+        self.with_state(|state| state.timers.timers.len())
+            + self.pending_fetches()
+            + self.runtime().is_job_pending() as usize
+    }
+
+    /// Returns the deadline of the earliest timer in the wall clock.
+    pub fn next_timer_deadline(&self) -> Option<Instant> {
+        self.with_state(|state| {
+            let advanced = state.timers.advanced;
+
+            state
+                .timers
+                .timers
+                .iter()
+                .map(|timer| timer.deadline)
+                .min()
+                .map(|deadline| deadline.checked_sub(advanced).unwrap_or_else(Instant::now))
+        })
+    }
+
+    /// Advance the clock of the timers, then run the pending jobs and the expired timers.
+    ///
+    /// Returns the number of the fired timers.
+    pub fn advance_time(&self, dt: Duration) -> Result<usize, Error> {
+        trace!("advance timers {:?}", dt);
+
+        self.with_state(|state| state.timers.advanced += dt);
+
+        let rt = self.runtime();
+        let mut fired = 0;
+
+        loop {
+            if rt.is_job_pending() {
+                rt.execute_pending_job()?;
+            } else if self.poll_fetch()? {
+                continue;
+            } else if self.fire_timer()? {
+                fired += 1;
+            } else {
+                return Ok(fired);
+            }
+        }
+    }
+
+    /// Run a pending job of the microtask queue, or settle a finished fetch,
+    /// or fire an expired timer if there is nothing else to run.
+    ///
+    /// Returns `false` if there was nothing to run, the host loop could sleep until the `next_timer_deadline`.
+    /// Note that the microtask queue is shared by all contexts of the runtime.
+    pub fn run_jobs_once(&self) -> Result<bool, Error> {
+        let rt = self.runtime();
+
+        if rt.is_job_pending() {
+            rt.execute_pending_job().map(|_| true)
+        } else if self.poll_fetch()? {
+            Ok(true)
+        } else {
+            self.fire_timer()
+        }
+    }
+
+    #[cfg(not(feature = "http"))]
+    fn pending_fetches(&self) -> usize {
+        0
+    }
+
+    #[cfg(not(feature = "http"))]
+    fn poll_fetch(&self) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    /// Fire the earliest expired timer, returns `false` if there is no expired timer.
+    fn fire_timer(&self) -> Result<bool, Error> {
+        let (timer, callback, args) = match self.with_state(|state| {
+            let now = state.timers.now();
+            let timers = &mut state.timers.timers;
+            let idx = timers
+                .iter()
+                .enumerate()
+                .filter(|(_, timer)| timer.deadline <= now)
+                .min_by_key(|(_, timer)| (timer.deadline, timer.id))
+                .map(|(idx, _)| idx)?;
+            let timer = &mut timers[idx];
+            let callback = timer.callback.raw();
+            let args = timer.args.iter().map(|arg| arg.raw()).collect::<Vec<_>>();
+
+            trace!("fire timer #{}", timer.id);
+
+            // the repeating timer still owns its values, which may be cleared by itself
+            Some(match timer.interval {
+                Some(interval) => {
+                    // the missed ticks are skipped instead of firing all of them
+                    timer.deadline = (timer.deadline + interval).max(now);
+
+                    (None, callback, args)
+                }
+                None => (Some(timers.remove(idx)), callback, args),
+            })
+        }) {
+            Some(timer) => timer,
+            None => return Ok(false),
+        };
+
+        let callback = self.clone_value(&Value::from(callback));
+        let args = args
+            .into_iter()
+            .map(|arg| self.clone_value(&Value::from(arg)))
+            .collect::<Vec<_>>();
+
+        if let Some(timer) = timer {
+            self.free_timer(timer);
+        }
+
+        callback.call_with_values(None, &args).map(|_| true)
+    }
+
+    fn free_timer(&self, timer: Timer) {
+        self.free_value(timer.callback);
+
+        for arg in timer.args {
+            self.free_value(arg);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use crate::{Context, Eval, Runtime};
+
+    use super::*;
+
+    #[test]
+    fn timers() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        ctxt.init_timers().unwrap();
+        ctxt.eval_script(
+            r#"
+var log = [];
+var ticks = 0;
+var interval = setInterval(() => { if (++ticks == 3) clearInterval(interval); }, 1);
+var cancelled = setTimeout(() => log.push('cancelled'), 1);
+setTimeout(() => log.push('timeout'), 5);
+Promise.resolve().then(() => log.push('job'));
+clearTimeout(cancelled);
+"#,
+            "<evalScript>",
+            Eval::GLOBAL,
+        )
+        .unwrap();
+
+        assert_eq!(ctxt.pending_jobs(), 3);
+        assert!(ctxt.next_timer_deadline().is_some());
+
+        while ctxt.pending_jobs() > 0 {
+            if !ctxt.run_jobs_once().unwrap() {
+                if let Some(deadline) = ctxt.next_timer_deadline() {
+                    thread::sleep(deadline.saturating_duration_since(Instant::now()));
+                }
+            }
+        }
+
+        assert_eq!(
+            ctxt.eval::<_, String>("log.join() + ':' + ticks", Eval::GLOBAL)
+                .unwrap(),
+            Some("job,timeout:3".to_owned())
+        );
+        assert!(ctxt.next_timer_deadline().is_none());
+        assert!(!ctxt.run_jobs_once().unwrap());
+    }
+
+    #[test]
+    fn advance_time() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        ctxt.init_timers().unwrap();
+        ctxt.eval_script(
+            r#"
+var log = [];
+setInterval((name) => log.push(name), 1000, 'tick');
+setTimeout((a, b) => log.push(a + b), 1500, 1, 2);
+"#,
+            "<evalScript>",
+            Eval::GLOBAL,
+        )
+        .unwrap();
+
+        assert_eq!(ctxt.advance_time(Duration::from_millis(500)).unwrap(), 0);
+        assert_eq!(ctxt.advance_time(Duration::from_millis(1000)).unwrap(), 2);
+        assert_eq!(ctxt.advance_time(Duration::from_millis(600)).unwrap(), 1);
+        assert_eq!(
+            ctxt.eval::<_, String>("log.join()", Eval::GLOBAL).unwrap(),
+            Some("tick,3,tick".to_owned())
+        );
+        assert_eq!(ctxt.pending_jobs(), 1);
+    }
+
+    #[test]
+    fn skip_missed_ticks() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        ctxt.init_timers().unwrap();
+        ctxt.eval_script(
+            "var ticks = 0; setInterval(() => ticks++, 1000);",
+            "<evalScript>",
+            Eval::GLOBAL,
+        )
+        .unwrap();
+
+        // the first tick and one more for the missed ticks
+        assert_eq!(ctxt.advance_time(Duration::from_secs(10)).unwrap(), 2);
+        assert_eq!(ctxt.advance_time(Duration::from_millis(500)).unwrap(), 0);
+        assert_eq!(ctxt.advance_time(Duration::from_millis(600)).unwrap(), 1);
+        assert_eq!(ctxt.eval::<_, i32>("ticks", Eval::GLOBAL).unwrap(), Some(3));
+    }
+}
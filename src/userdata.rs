@@ -1,9 +1,17 @@
+use std::any::{type_name, Any, TypeId};
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::marker::PhantomData;
 use std::mem;
 use std::ptr::{null_mut, NonNull};
+use std::sync::Arc;
 
 use foreign_types::ForeignTypeRef;
 
-use crate::{ffi, ClassId, ContextRef, Local, Runtime, Value};
+use crate::{
+    ffi, ClassDef, ClassId, ContextRef, Error, Local, Prop, PropertyDescriptor, Runtime,
+    RuntimeRef, Value,
+};
 
 lazy_static! {
     static ref RUNTIME_USERDATA_CLASS_ID: ClassId = Runtime::new_class_id();
@@ -57,6 +65,180 @@ impl ContextRef {
     }
 }
 
+/// The marker of the Javascript class which owns a native Rust value.
+///
+/// Each Rust type has its own class, the value is dropped when the object is finalized.
+pub struct Opaque<T>(PhantomData<T>);
+
+impl<T: 'static> Opaque<T> {
+    /// The Class ID of the wrapped Rust type, which is allocated on the first use.
+    pub fn class_id() -> ClassId {
+        Runtime::type_class_id::<Opaque<T>>()
+    }
+}
+
+unsafe extern "C" fn opaque_finalizer<T: 'static>(_rt: *mut ffi::JSRuntime, obj: ffi::JSValue) {
+    let ptr = ffi::JS_GetOpaque(obj, Opaque::<T>::class_id()) as *mut T;
+
+    trace!("free native {} @ {:p}", type_name::<T>(), ptr);
+
+    if !ptr.is_null() {
+        mem::drop(Box::from_raw(ptr));
+    }
+}
+
+impl ContextRef {
+    /// Wrap a native Rust value in a Javascript object, which owns the value.
+    ///
+    /// The value must be `Send`, because the runtime could be moved to another thread with its objects.
+    pub fn wrap_native<T: Send + 'static>(&self, v: T) -> Result<Local<'_, Value>, Error> {
+        let rt = self.runtime();
+        let class_id = Opaque::<T>::class_id();
+
+        if !rt.is_registered_class(class_id) {
+            let class_name = CString::new(type_name::<T>())?;
+
+            rt.new_class(
+                class_id,
+                &ClassDef {
+                    class_name: class_name.as_ptr(),
+                    finalizer: Some(opaque_finalizer::<T>),
+                    gc_mark: None,
+                    call: None,
+                    exotic: null_mut(),
+                },
+            );
+
+            if !rt.is_registered_class(class_id) {
+                return Err(format!("fail to register class for `{}`", type_name::<T>()).into());
+            }
+        }
+
+        let obj = self.new_object_class(class_id);
+        let ptr = Box::into_raw(Box::new(v));
+
+        trace!(
+            "wrap native {} {:p} @ {:?}",
+            type_name::<T>(),
+            ptr,
+            obj.as_ptr::<()>()
+        );
+
+        obj.set_opaque(ptr);
+
+        Ok(self.bind(obj))
+    }
+
+    /// Returns the native Rust value if the object was created by `wrap_native` with the same type.
+    pub fn downcast_native<'v, T: 'static>(&self, obj: &'v Value) -> Option<&'v T> {
+        unsafe { obj.get_opaque::<T>(Opaque::<T>::class_id()).as_ref() }
+    }
+}
+
+impl Local<'_, Value> {
+    /// Returns the native Rust value if the object was created by `wrap_native` with the same type.
+    pub fn downcast_native<T: 'static>(&self) -> Option<&T> {
+        self.ctxt.downcast_native(self)
+    }
+}
+
+/// The finalizers of an object, which are called when the native wrapper is finalized.
+#[derive(Default)]
+struct Finalizers(RefCell<Vec<Box<dyn FnOnce() + Send>>>);
+
+impl Drop for Finalizers {
+    fn drop(&mut self) {
+        for callback in self.0.get_mut().drain(..) {
+            callback()
+        }
+    }
+}
+
+impl ContextRef {
+    /// Register a callback which will be called when the object was garbage collected.
+    ///
+    /// The callbacks are kept by a native wrapper in a private symbol property of the object,
+    /// which is invisible to the scripts, and called in the finalizer,
+    /// so they must not call back into the engine.
+    pub fn on_finalize<F: FnOnce() + Send + 'static>(
+        &self,
+        obj: &Value,
+        callback: F,
+    ) -> Result<(), Error> {
+        if !obj.is_object() {
+            return Err("finalize a non-object value".into());
+        }
+
+        let key = self.finalizers_key()?;
+        let finalizers = match self.get_own_property_descriptor(obj, &*key)? {
+            Some(PropertyDescriptor {
+                value: Some(finalizers),
+                ..
+            }) if finalizers.downcast_native::<Finalizers>().is_some() => finalizers,
+            _ => {
+                let finalizers = self.wrap_native(Finalizers::default())?;
+
+                self.define_property_value(obj, &*key, &finalizers, Prop::empty())?;
+
+                finalizers
+            }
+        };
+        let finalizers = finalizers
+            .downcast_native::<Finalizers>()
+            .expect("finalizers");
+
+        trace!(
+            "register #{} finalizer @ {:?}",
+            finalizers.0.borrow().len(),
+            obj.as_ptr::<()>()
+        );
+
+        finalizers.0.borrow_mut().push(Box::new(callback));
+
+        Ok(())
+    }
+
+    /// Returns the private symbol of the finalizers, which is created once for the context.
+    fn finalizers_key(&self) -> Result<Local<Value>, Error> {
+        if let Some(key) = self.with_state(|state| state.finalizers_key.as_ref().map(Value::raw)) {
+            return Ok(self.clone_value(&Value::from(key)));
+        }
+
+        let key = self
+            .bind(unsafe { ffi::JS_NewPrivateSymbol(self.as_ptr(), cstr!(finalizers).as_ptr()) })
+            .ok()?;
+
+        self.with_state(|state| state.finalizers_key = Some(self.clone_value(&key).into_inner()));
+
+        Ok(key)
+    }
+}
+
+impl Local<'_, Value> {
+    /// Register a callback which will be called when the object was garbage collected.
+    pub fn on_finalize<F: FnOnce() + Send + 'static>(&self, callback: F) -> Result<(), Error> {
+        self.ctxt.on_finalize(self, callback)
+    }
+}
+
+impl RuntimeRef {
+    /// Attach the host state to the runtime, which can be retrieved by `user_data` in the native callbacks.
+    ///
+    /// QuickJS has no opaque pointer of the runtime yet, so the data is kept in the Rust side states,
+    /// and replaces the previous data of the same type.
+    pub fn set_user_data<T: Any + Send + Sync>(&self, data: T) {
+        trace!("{:?} set {} user data", self, type_name::<T>());
+
+        self.with_state(|state| state.user_data.insert(TypeId::of::<T>(), Arc::new(data)));
+    }
+
+    /// Returns the host state of type `T` attached to the runtime.
+    pub fn user_data<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        self.with_state(|state| state.user_data.get(&TypeId::of::<T>()).cloned())
+            .and_then(|data| data.downcast().ok())
+    }
+}
+
 impl Value {
     pub fn set_opaque<T>(&self, opaque: *mut T) {
         unsafe { ffi::JS_SetOpaque(self.raw(), opaque as *mut _) }
@@ -78,3 +260,172 @@ impl ContextRef {
         unsafe { ffi::JS_GetOpaque2(self.as_ptr(), obj.raw(), class_id) as *mut _ }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use crate::{Context, ContextRef, Eval, Runtime, Value};
+
+    struct Handle(Arc<AtomicBool>);
+
+    impl Drop for Handle {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst)
+        }
+    }
+
+    #[test]
+    fn native() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+        let dropped = Arc::new(AtomicBool::new(false));
+
+        let obj = ctxt.wrap_native(Handle(dropped.clone())).unwrap();
+
+        assert!(obj.is_object());
+        assert!(obj.downcast_native::<Handle>().is_some());
+        assert!(obj.downcast_native::<String>().is_none());
+        assert!(ctxt.global_object().downcast_native::<Handle>().is_none());
+
+        let s = ctxt.wrap_native("hello".to_owned()).unwrap();
+
+        assert_eq!(s.downcast_native::<String>().unwrap(), "hello");
+
+        ctxt.global_object().set_property("handle", obj).unwrap();
+
+        let obj = ctxt
+            .eval_script("handle", "<evalScript>", Eval::GLOBAL)
+            .unwrap();
+
+        assert!(obj.downcast_native::<Handle>().is_some());
+
+        drop(obj);
+        ctxt.eval_script("handle = null", "<evalScript>", Eval::GLOBAL)
+            .unwrap();
+        rt.run_gc();
+
+        assert!(dropped.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn on_finalize() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+        let finalized = Arc::new(AtomicUsize::new(0));
+
+        let obj = ctxt
+            .eval_script("var cache = {}; cache", "<evalScript>", Eval::GLOBAL)
+            .unwrap();
+        let child = ctxt
+            .eval_script(
+                "var child = Object.create(cache); child",
+                "<evalScript>",
+                Eval::GLOBAL,
+            )
+            .unwrap();
+
+        for _ in 0..2 {
+            let finalized = finalized.clone();
+
+            obj.on_finalize(move || {
+                finalized.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap();
+        }
+
+        {
+            let finalized = finalized.clone();
+
+            // the finalizers of the prototype are not shared
+            child
+                .on_finalize(move || {
+                    finalized.fetch_add(10, Ordering::SeqCst);
+                })
+                .unwrap();
+        }
+
+        assert!(ctxt.on_finalize(&ctxt.bind(1), || {}).is_err());
+        assert_eq!(
+            ctxt.eval::<_, i32>("Reflect.ownKeys(cache).length", Eval::GLOBAL)
+                .unwrap(),
+            Some(0)
+        );
+
+        drop(obj);
+        drop(child);
+        rt.run_gc();
+
+        assert_eq!(finalized.load(Ordering::SeqCst), 0);
+
+        ctxt.eval_script("child = null", "<evalScript>", Eval::GLOBAL)
+            .unwrap();
+        rt.run_gc();
+
+        assert_eq!(finalized.load(Ordering::SeqCst), 10);
+
+        ctxt.eval_script("cache = null", "<evalScript>", Eval::GLOBAL)
+            .unwrap();
+        rt.run_gc();
+
+        assert_eq!(finalized.load(Ordering::SeqCst), 12);
+    }
+
+    struct AllowList(Vec<&'static str>);
+
+    fn is_allowed(ctxt: &ContextRef, _this: Option<&Value>, args: &[Value]) -> bool {
+        let name = ctxt
+            .to_cstring(&args[0])
+            .unwrap()
+            .to_string_lossy()
+            .into_owned();
+
+        ctxt.runtime()
+            .user_data::<AllowList>()
+            .map_or(false, |list| list.0.contains(&name.as_str()))
+    }
+
+    #[test]
+    fn user_data() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        assert!(rt.user_data::<AllowList>().is_none());
+
+        rt.set_user_data(AllowList(vec!["fetch"]));
+
+        assert!(rt.user_data::<String>().is_none());
+
+        ctxt.global_object()
+            .set_property(
+                "isAllowed",
+                ctxt.new_c_function(is_allowed, Some("isAllowed"), 1)
+                    .unwrap(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            ctxt.eval::<_, bool>("isAllowed('fetch') && !isAllowed('exec')", Eval::GLOBAL)
+                .unwrap(),
+            Some(true)
+        );
+
+        let list = rt.user_data::<AllowList>().unwrap();
+
+        rt.set_user_data("other".to_owned());
+        rt.set_user_data(AllowList(vec!["exec"]));
+
+        assert_eq!(list.0, vec!["fetch"]);
+        assert_eq!(*rt.user_data::<String>().unwrap(), "other");
+        assert_eq!(rt.user_data::<AllowList>().unwrap().0, vec!["exec"]);
+    }
+}
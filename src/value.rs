@@ -1,20 +1,20 @@
 #![allow(clippy::cast_lossless)]
 
 use std::cmp::Ordering;
+use std::convert::TryInto;
 use std::ffi::{CStr, CString};
 use std::fmt;
 use std::ops::{Deref, DerefMut};
-use std::os::raw::c_char;
+use std::os::raw::{c_char, c_int};
 use std::ptr::NonNull;
 use std::slice;
 
-use failure::Error;
 use foreign_types::ForeignTypeRef;
 
 use crate::{
     ffi,
     handle::{Bindable, Unbindable},
-    ClassId, ContextRef, Local, RuntimeRef,
+    ClassId, ContextRef, Error, ErrorKind, Local, RuntimeRef,
 };
 
 pub const ERR: i32 = -1;
@@ -229,9 +229,54 @@ impl<'a> Local<'a, Value> {
         self.ctxt.to_cstring(self)
     }
 
+    pub fn try_to_bool(&self) -> Result<bool, Error> {
+        self.ctxt.try_to_bool(self)
+    }
+
+    pub fn try_to_int32(&self) -> Result<i32, Error> {
+        self.ctxt.try_to_int32(self)
+    }
+
+    pub fn try_to_int64(&self) -> Result<i64, Error> {
+        self.ctxt.try_to_int64(self)
+    }
+
+    pub fn try_to_index(&self) -> Result<u64, Error> {
+        self.ctxt.try_to_index(self)
+    }
+
+    pub fn try_to_float64(&self) -> Result<f64, Error> {
+        self.ctxt.try_to_float64(self)
+    }
+
+    #[cfg(feature = "bignum")]
+    pub fn try_to_bigint64(&self) -> Result<i64, Error> {
+        self.ctxt.try_to_bigint64(self)
+    }
+
+    pub fn try_to_cstring(&self) -> Result<CString, Error> {
+        self.ctxt.try_to_cstring(self)
+    }
+
     pub fn instance_of(&self, obj: &Value) -> Result<bool, Error> {
         self.ctxt.is_instance_of(self, obj)
     }
+
+    /// Compare two values with the `SameValue` algorithm, like `Object.is`.
+    pub fn same_value(&self, other: &Value) -> bool {
+        self.ctxt.same_value(self, other)
+    }
+
+    /// Compare two values with the `SameValueZero` algorithm, like the keys of `Map` and `Set`.
+    pub fn same_value_zero(&self, other: &Value) -> bool {
+        self.ctxt.same_value_zero(self, other)
+    }
+}
+
+impl PartialEq for Local<'_, Value> {
+    fn eq(&self, other: &Self) -> bool {
+        self.same_value(other)
+    }
 }
 
 impl ContextRef {
@@ -327,50 +372,83 @@ impl ContextRef {
         Value(unsafe { ffi::JS_NewBigUint64(self.as_ptr(), n) })
     }
 
+    /// Convert the value to a boolean, see `ContextRef::try_to_bool` for the error.
     pub fn to_bool(&self, val: &Value) -> Option<bool> {
+        self.try_to_bool(val).ok()
+    }
+
+    /// Convert the value to a 32-bit integer, see `ContextRef::try_to_int32` for the error.
+    pub fn to_int32(&self, val: &Value) -> Option<i32> {
+        self.try_to_int32(val).ok()
+    }
+
+    /// Convert the value to a 64-bit integer, see `ContextRef::try_to_int64` for the error.
+    pub fn to_int64(&self, val: &Value) -> Option<i64> {
+        self.try_to_int64(val).ok()
+    }
+
+    /// Convert the value to an index, see `ContextRef::try_to_index` for the error.
+    pub fn to_index(&self, val: &Value) -> Option<u64> {
+        self.try_to_index(val).ok()
+    }
+
+    /// Convert the value to a float, see `ContextRef::try_to_float64` for the error.
+    pub fn to_float64(&self, val: &Value) -> Option<f64> {
+        self.try_to_float64(val).ok()
+    }
+
+    /// Convert the value to a 64-bit integer, see `ContextRef::try_to_bigint64` for the error.
+    #[cfg(feature = "bignum")]
+    pub fn to_bigint64(&self, val: &Value) -> Option<i64> {
+        self.try_to_bigint64(val).ok()
+    }
+
+    /// Convert the value to a boolean, returns the thrown exception if the conversion failed.
+    pub fn try_to_bool(&self, val: &Value) -> Result<bool, Error> {
         self.check_error(unsafe { ffi::JS_ToBool(self.as_ptr(), val.0) })
-            .ok()
             .map(ToBool::to_bool)
     }
 
-    pub fn to_int32(&self, val: &Value) -> Option<i32> {
+    /// Convert the value to a 32-bit integer, returns the thrown exception if the conversion failed.
+    ///
+    /// e.g. a `TypeError` is thrown when converting a `Symbol`.
+    pub fn try_to_int32(&self, val: &Value) -> Result<i32, Error> {
         let mut n = 0;
 
         self.check_error(unsafe { ffi::JS_ToInt32(self.as_ptr(), &mut n, val.0) })
-            .ok()
             .map(|_| n)
     }
 
-    pub fn to_int64(&self, val: &Value) -> Option<i64> {
+    /// Convert the value to a 64-bit integer, returns the thrown exception if the conversion failed.
+    pub fn try_to_int64(&self, val: &Value) -> Result<i64, Error> {
         let mut n = 0;
 
         self.check_error(unsafe { ffi::JS_ToInt64(self.as_ptr(), &mut n, val.0) })
-            .ok()
             .map(|_| n)
     }
 
-    pub fn to_index(&self, val: &Value) -> Option<u64> {
+    /// Convert the value to an index, returns the thrown `RangeError` if it is negative or too large.
+    pub fn try_to_index(&self, val: &Value) -> Result<u64, Error> {
         let mut n = 0;
 
         self.check_error(unsafe { ffi::JS_ToIndex(self.as_ptr(), &mut n, val.0) })
-            .ok()
             .map(|_| n)
     }
 
-    pub fn to_float64(&self, val: &Value) -> Option<f64> {
+    /// Convert the value to a float, returns the thrown exception if the conversion failed.
+    pub fn try_to_float64(&self, val: &Value) -> Result<f64, Error> {
         let mut n = 0.0;
 
         self.check_error(unsafe { ffi::JS_ToFloat64(self.as_ptr(), &mut n, val.0) })
-            .ok()
             .map(|_| n)
     }
 
+    /// Convert the `BigInt` to a 64-bit integer, returns the thrown exception if the conversion failed.
     #[cfg(feature = "bignum")]
-    pub fn to_bigint64(&self, val: &Value) -> Option<i64> {
+    pub fn try_to_bigint64(&self, val: &Value) -> Result<i64, Error> {
         let mut n = 0;
 
         self.check_error(unsafe { ffi::JS_ToBigInt64(self.as_ptr(), &mut n, val.0) })
-            .ok()
             .map(|_| n)
     }
 
@@ -383,6 +461,8 @@ impl ContextRef {
     }
 
     /// Convert Javascript String to C UTF-8 encoded strings.
+    ///
+    /// The exception is left pending if the conversion failed, so the C function could return `EXCEPTION`.
     pub fn to_cstring(&self, val: &Value) -> Option<CString> {
         let mut len = 0;
 
@@ -405,9 +485,31 @@ impl ContextRef {
         }
     }
 
+    /// Convert Javascript String to C UTF-8 encoded strings, returns the thrown exception if the conversion failed.
+    pub fn try_to_cstring(&self, val: &Value) -> Result<CString, Error> {
+        match self.to_cstring(val) {
+            Some(s) => Ok(s),
+            None => Err(self.take_exception()?.into()),
+        }
+    }
+
     pub fn is_instance_of(&self, val: &Value, obj: &Value) -> Result<bool, Error> {
         self.check_bool(unsafe { ffi::JS_IsInstanceOf(self.as_ptr(), val.raw(), obj.raw()) })
     }
+
+    /// Compare two values with the `SameValue` algorithm, `NaN` equals to `NaN` and `+0` differs from `-0`.
+    pub fn same_value(&self, x: &Value, y: &Value) -> bool {
+        self.same_value_with(x, y, false)
+    }
+
+    /// Compare two values with the `SameValueZero` algorithm, `NaN` equals to `NaN` and `+0` equals to `-0`.
+    pub fn same_value_zero(&self, x: &Value, y: &Value) -> bool {
+        self.same_value_with(x, y, true)
+    }
+
+    fn same_value_with(&self, x: &Value, y: &Value, zero: bool) -> bool {
+        unsafe { ffi::JS_SameValue(self.as_ptr(), x.raw(), y.raw(), zero.to_bool()) }.to_bool()
+    }
 }
 
 impl<'a, T> Bindable<'a> for T
@@ -447,6 +549,77 @@ pub trait NewValue {
     fn new_value(self, ctxt: &ContextRef) -> ffi::JSValue;
 }
 
+/// The largest integer `n` that `n` and `n + 1` are both exactly representable as a `Number`.
+const MAX_SAFE_INTEGER: i64 = (1 << 53) - 1;
+
+/// The policy to create the 64-bit integers beyond the safe range of `Number`, see `ContextRef::set_int64_policy`.
+///
+/// The integers in the safe range are always created as `Number` with a policy.
+/// The `NewValue` conversions return `JS_EXCEPTION` for the unsafe integers with `Int64Policy::Throw`,
+/// or use `ContextRef::new_safe_integer` to get the `RangeError` as an `Error`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Int64Policy {
+    /// Create the unsafe integers as `BigInt`.
+    #[cfg(feature = "bignum")]
+    BigInt = 1,
+    /// Throw a `RangeError` for the unsafe integers.
+    Throw = 2,
+    /// Convert the unsafe integers to the nearest `Number`, which may lose precision.
+    Lossy = 3,
+}
+
+impl Int64Policy {
+    fn from_raw(policy: c_int) -> Option<Int64Policy> {
+        match policy {
+            #[cfg(feature = "bignum")]
+            1 => Some(Int64Policy::BigInt),
+            2 => Some(Int64Policy::Throw),
+            3 => Some(Int64Policy::Lossy),
+            _ => None,
+        }
+    }
+}
+
+impl ContextRef {
+    /// Set the policy to create `i64` and `u64` values beyond `Number.MAX_SAFE_INTEGER`.
+    ///
+    /// Without a policy, all the 64-bit integers are created as `BigInt` with the `bignum` feature,
+    /// or converted to `Number` silently without it.
+    pub fn set_int64_policy(&self, policy: Int64Policy) {
+        trace!("{:?} set int64 policy: {:?}", self, policy);
+
+        unsafe { ffi::JS_SetContextInt64Policy(self.as_ptr(), policy as c_int) }
+    }
+
+    /// Returns the policy to create the 64-bit integers.
+    pub fn int64_policy(&self) -> Option<Int64Policy> {
+        Int64Policy::from_raw(unsafe { ffi::JS_GetContextInt64Policy(self.as_ptr()) })
+    }
+
+    fn throw_unsafe_integer<T: fmt::Display>(&self, n: T) -> ffi::JSValue {
+        self.throw_range_error(format!("{} is beyond the safe integer range", n))
+            .into_inner()
+            .raw()
+    }
+
+    /// Create a `Number` of the integer, or returns a `RangeError` if it is beyond the safe range of `Number`.
+    pub fn new_safe_integer<T>(&self, n: T) -> Result<Local<Value>, Error>
+    where
+        T: TryInto<i64> + fmt::Display + Copy,
+    {
+        match n.try_into() {
+            Ok(n) if (-MAX_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(&n) => {
+                Ok(self.bind(unsafe { ffi::JS_NewInt64(self.as_ptr(), n) }))
+            }
+            _ => Err(ErrorKind::RangeError(
+                format!("{} is beyond the safe integer range", n),
+                None,
+            )
+            .into()),
+        }
+    }
+}
+
 impl NewValue for bool {
     fn new_value(self, _ctxt: &ContextRef) -> ffi::JSValue {
         Value::from(self).0
@@ -473,10 +646,18 @@ impl NewValue for u32 {
 
 impl NewValue for u64 {
     fn new_value(self, ctxt: &ContextRef) -> ffi::JSValue {
-        if cfg!(feature = "bignum") {
-            unsafe { ffi::JS_NewBigUint64(ctxt.as_ptr(), self) }
-        } else {
-            (self as i64).new_value(ctxt)
+        match ctxt.int64_policy() {
+            None if cfg!(feature = "bignum") => unsafe {
+                ffi::JS_NewBigUint64(ctxt.as_ptr(), self)
+            },
+            None => (self as i64).new_value(ctxt),
+            Some(_) if self <= MAX_SAFE_INTEGER as u64 => unsafe {
+                ffi::JS_NewInt64(ctxt.as_ptr(), self as i64)
+            },
+            #[cfg(feature = "bignum")]
+            Some(Int64Policy::BigInt) => unsafe { ffi::JS_NewBigUint64(ctxt.as_ptr(), self) },
+            Some(Int64Policy::Throw) => ctxt.throw_unsafe_integer(self),
+            Some(Int64Policy::Lossy) => (self as f64).new_value(ctxt),
         }
     }
 }
@@ -501,10 +682,16 @@ impl NewValue for i32 {
 
 impl NewValue for i64 {
     fn new_value(self, ctxt: &ContextRef) -> ffi::JSValue {
-        if cfg!(feature = "bignum") {
-            unsafe { ffi::JS_NewBigInt64(ctxt.as_ptr(), self) }
-        } else {
-            unsafe { ffi::JS_NewInt64(ctxt.as_ptr(), self) }
+        match ctxt.int64_policy() {
+            None if cfg!(feature = "bignum") => unsafe { ffi::JS_NewBigInt64(ctxt.as_ptr(), self) },
+            None => unsafe { ffi::JS_NewInt64(ctxt.as_ptr(), self) },
+            Some(_) if (-MAX_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(&self) => unsafe {
+                ffi::JS_NewInt64(ctxt.as_ptr(), self)
+            },
+            #[cfg(feature = "bignum")]
+            Some(Int64Policy::BigInt) => unsafe { ffi::JS_NewBigInt64(ctxt.as_ptr(), self) },
+            Some(Int64Policy::Throw) => ctxt.throw_unsafe_integer(self),
+            Some(Int64Policy::Lossy) => unsafe { ffi::JS_NewInt64(ctxt.as_ptr(), self) },
         }
     }
 }
@@ -569,6 +756,57 @@ impl<'a> NewValue for &'a Local<'a, Value> {
     }
 }
 
+impl<T: NewValue> NewValue for Option<T> {
+    /// Create `null` for `None`.
+    fn new_value(self, ctxt: &ContextRef) -> ffi::JSValue {
+        match self {
+            Some(v) => v.new_value(ctxt),
+            None => ffi::NULL,
+        }
+    }
+}
+
+/// Create an array with the elements, `&[T]` is not supported since it is spread as the `Args`.
+impl<T: NewValue> NewValue for Vec<T> {
+    fn new_value(self, ctxt: &ContextRef) -> ffi::JSValue {
+        let arr = ctxt.bind(ctxt.new_array());
+
+        self.into_iter()
+            .enumerate()
+            .try_for_each(|(idx, v)| arr.set_property(idx as u32, v).map(|_| ()))
+            .map(|_| arr)
+            .new_value(ctxt)
+    }
+}
+
+#[cfg(feature = "json")]
+impl NewValue for serde_json::Value {
+    fn new_value(self, ctxt: &ContextRef) -> ffi::JSValue {
+        use serde_json::Value::*;
+        use std::convert::TryFrom;
+
+        match self {
+            Null => ffi::NULL,
+            Bool(b) => b.new_value(ctxt),
+            Number(n) => match n.as_i64() {
+                Some(n) if i32::try_from(n).is_ok() => (n as i32).new_value(ctxt),
+                _ => n.as_f64().unwrap_or(f64::NAN).new_value(ctxt),
+            },
+            String(s) => s.new_value(ctxt),
+            Array(values) => values.new_value(ctxt),
+            Object(entries) => {
+                let obj = ctxt.bind(ctxt.new_object());
+
+                entries
+                    .into_iter()
+                    .try_for_each(|(key, v)| obj.set_property(key.as_str(), v).map(|_| ()))
+                    .map(|_| obj)
+                    .new_value(ctxt)
+            }
+        }
+    }
+}
+
 /// Extract primitive from `Local<Value>`.
 pub trait ExtractValue: Sized {
     /// Extract primitive from `Local<Value>`.
@@ -621,6 +859,42 @@ impl ExtractValue for String {
     }
 }
 
+impl<T: ExtractValue> ExtractValue for Option<T> {
+    /// Extract `null` and `undefined` as `None`.
+    fn extract_value(v: &Local<Value>) -> Option<Self> {
+        if v.is_null() || v.is_undefined() {
+            Some(None)
+        } else {
+            T::extract_value(v).map(Some)
+        }
+    }
+}
+
+macro_rules! extract_tuple {
+    ($len:expr; $($Item:ident : $idx:expr),*) => {
+        impl<$($Item: ExtractValue),*> ExtractValue for ($($Item,)*) {
+            /// Extract the elements of an array with the exact length.
+            fn extract_value(v: &Local<Value>) -> Option<Self> {
+                if !v.is_array().unwrap_or_default()
+                    || v.get_property("length").and_then(|len| len.as_int()) != Some($len)
+                {
+                    return None;
+                }
+
+                Some(($(
+                    $Item::extract_value(
+                        &v.get_property($idx).unwrap_or_else(|| v.ctxt.undefined()),
+                    )?,
+                )*))
+            }
+        }
+    };
+}
+
+extract_tuple!(2; A: 0, B: 1);
+extract_tuple!(3; A: 0, B: 1, C: 2);
+extract_tuple!(4; A: 0, B: 1, C: 2, D: 3);
+
 impl<T: ExtractValue + PartialEq> PartialEq<T> for Local<'_, Value> {
     fn eq(&self, other: &T) -> bool {
         T::extract_value(self).map_or(false, |v| v.eq(other))
@@ -744,6 +1018,11 @@ impl Value {
         }
     }
 
+    /// Returns the number value of an integer or float value.
+    pub fn as_number(&self) -> Option<f64> {
+        self.as_int().map(f64::from).or_else(|| self.as_float())
+    }
+
     pub fn as_object(&self) -> Option<NonNull<ffi::JSObject>> {
         if self.tag() == ffi::JS_TAG_OBJECT {
             Some(self.as_ptr())
@@ -779,7 +1058,9 @@ impl Value {
 
 #[cfg(test)]
 mod tests {
-    use crate::{Context, Eval, Runtime};
+    use crate::{Context, ErrorKind, Eval, Runtime};
+
+    use super::*;
 
     #[test]
     fn instance_of() {
@@ -817,4 +1098,298 @@ new Car('Honda', 'Accord', 1998)"#,
             .instance_of(&global.get_property("Person").unwrap())
             .unwrap());
     }
+
+    #[test]
+    fn same_value() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let eval = |s| ctxt.eval_script(s, "<evalScript>", Eval::GLOBAL).unwrap();
+
+        let obj = eval("var o = {}; o");
+
+        assert!(obj.same_value(&eval("o")));
+        assert!(!obj.same_value(&eval("({})")));
+        assert!(eval("NaN").same_value(&eval("0 / 0")));
+        assert!(eval("1").same_value(&eval("1.0 * 1")));
+        assert!(!eval("0").same_value(&eval("-0")));
+        assert!(eval("0").same_value_zero(&eval("-0")));
+        assert!(eval("'foo'") == eval("'f' + 'oo'"));
+        assert!(eval("'1'") != eval("1"));
+        assert!(eval("undefined") != eval("null"));
+        assert!(eval("'a\\0b'") != eval("'a\\0c'"));
+        assert!(eval("'\\ud800'") != eval("'\\ud801'"));
+        assert!(eval("var s = Symbol('foo'); s") == eval("s"));
+        assert!(eval("Symbol('foo')") != eval("Symbol('foo')"));
+    }
+
+    #[test]
+    fn extract_shapes() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        assert_eq!(
+            ctxt.eval::<_, Option<i32>>("null", Eval::GLOBAL).unwrap(),
+            Some(None)
+        );
+        assert_eq!(
+            ctxt.eval::<_, Option<i32>>("42", Eval::GLOBAL).unwrap(),
+            Some(Some(42))
+        );
+        assert_eq!(
+            ctxt.eval::<_, (String, i32)>("['foo', 42]", Eval::GLOBAL)
+                .unwrap(),
+            Some(("foo".to_owned(), 42))
+        );
+        assert_eq!(
+            ctxt.eval::<_, (i32, Option<bool>, String)>("[1, undefined, 'bar']", Eval::GLOBAL)
+                .unwrap(),
+            Some((1, None, "bar".to_owned()))
+        );
+        assert_eq!(
+            ctxt.eval::<_, (i32, i32)>("[1, 2, 3]", Eval::GLOBAL)
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            ctxt.eval::<_, (i32, i32)>("({ 0: 1, 1: 2, length: 2 })", Eval::GLOBAL)
+                .unwrap(),
+            None
+        );
+
+        assert_eq!(
+            ctxt.eval::<_, Result<i32, ErrorKind>>("42", Eval::GLOBAL)
+                .unwrap(),
+            Some(Ok(42))
+        );
+
+        let err = ctxt
+            .eval::<_, Result<i32, ErrorKind>>("try { null.foo } catch (err) { err }", Eval::GLOBAL)
+            .unwrap()
+            .unwrap()
+            .unwrap_err();
+
+        assert!(matches!(err, ErrorKind::TypeError(..)), "{:?}", err);
+    }
+
+    #[test]
+    fn new_composite() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+        let global = ctxt.global_object();
+
+        global.set_property("list", vec![1, 2, 3]).unwrap();
+        global
+            .set_property("nested", vec![vec![Some("foo")], vec![None]])
+            .unwrap();
+        global.set_property("missing", None::<i32>).unwrap();
+
+        assert_eq!(
+            ctxt.eval::<_, String>(
+                "JSON.stringify([list, nested, missing, Array.isArray(list)])",
+                Eval::GLOBAL
+            )
+            .unwrap(),
+            Some(r#"[[1,2,3],[["foo"],[null]],null,true]"#.to_owned())
+        );
+
+        #[cfg(feature = "json")]
+        {
+            let value = serde_json::json!({ "name": "foo", "tags": ["a", "b"], "n": 1.5, "big": 10_000_000_000_i64 });
+
+            global.set_property("config", value.clone()).unwrap();
+
+            let s = ctxt
+                .eval::<_, String>("JSON.stringify(config)", Eval::GLOBAL)
+                .unwrap()
+                .unwrap();
+
+            assert_eq!(
+                serde_json::from_str::<serde_json::Value>(&s).unwrap(),
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn int64_policy() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+        let global = ctxt.global_object();
+        let typeof_n = || {
+            ctxt.eval::<_, String>("typeof n + ':' + n", Eval::GLOBAL)
+                .unwrap()
+                .unwrap()
+        };
+
+        assert_eq!(ctxt.int64_policy(), None);
+
+        ctxt.set_int64_policy(Int64Policy::Lossy);
+
+        global.set_property("n", 42i64).unwrap();
+        assert_eq!(typeof_n(), "number:42");
+
+        global.set_property("n", 1u64 << 60).unwrap();
+        assert_eq!(typeof_n(), "number:1152921504606847000");
+
+        assert_eq!(
+            ctxt.new_safe_integer(1 - (1i64 << 53)).unwrap().to_string(),
+            "-9007199254740991"
+        );
+        assert!(matches!(
+            ctxt.new_safe_integer(i64::MIN),
+            Err(Error::Js(ErrorKind::RangeError(..)))
+        ));
+        assert!(ctxt.new_safe_integer(u64::MAX).is_err());
+
+        ctxt.set_int64_policy(Int64Policy::Throw);
+
+        global.set_property("n", 1 - (1i64 << 53)).unwrap();
+        assert_eq!(typeof_n(), "number:-9007199254740991");
+
+        let err = global.set_property("n", i64::MIN).unwrap_err();
+
+        assert!(err.to_string().contains("RangeError"), "{}", err);
+        assert!(ctxt
+            .bind(u64::MAX.new_value(&ctxt))
+            .ok()
+            .unwrap_err()
+            .to_string()
+            .contains("18446744073709551615 is beyond the safe integer range"));
+        assert_eq!(typeof_n(), "number:-9007199254740991");
+
+        #[cfg(feature = "bignum")]
+        {
+            ctxt.set_int64_policy(Int64Policy::BigInt);
+
+            global.set_property("n", u64::MAX).unwrap();
+            assert_eq!(typeof_n(), "bigint:18446744073709551615");
+
+            global.set_property("n", 7i64).unwrap();
+            assert_eq!(typeof_n(), "number:7");
+        }
+    }
+
+    #[test]
+    fn derive() {
+        let _ = pretty_env_logger::try_init();
+
+        #[derive(Debug, PartialEq, crate::NewValue, crate::ExtractValue)]
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+
+        #[derive(Debug, PartialEq, crate::NewValue, crate::ExtractValue)]
+        struct Meters(f64);
+
+        #[derive(Debug, PartialEq, crate::NewValue, crate::ExtractValue)]
+        enum Shape {
+            Empty,
+            Circle { center: Point, radius: Option<i32> },
+            Line(Point, Point),
+            Width(Meters),
+        }
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+        let global = ctxt.global_object();
+
+        let shapes = vec![
+            Shape::Empty,
+            Shape::Circle {
+                center: Point { x: 1, y: 2 },
+                radius: None,
+            },
+            Shape::Line(Point { x: 0, y: 0 }, Point { x: 3, y: 4 }),
+            Shape::Width(Meters(1.5)),
+        ];
+
+        global.set_property("shapes", shapes).unwrap();
+
+        assert_eq!(
+            ctxt.eval::<_, String>("JSON.stringify(shapes)", Eval::GLOBAL)
+                .unwrap()
+                .unwrap(),
+            r#"["Empty",{"type":"Circle","center":{"x":1,"y":2},"radius":null},{"type":"Line","value":[{"x":0,"y":0},{"x":3,"y":4}]},{"type":"Width","value":1.5}]"#
+        );
+
+        let extract = |s: &str| ctxt.eval::<_, Shape>(s, Eval::GLOBAL).unwrap();
+
+        assert_eq!(extract("shapes[0]"), Some(Shape::Empty));
+        assert_eq!(
+            extract("({ type: 'Circle', center: { x: 5, y: 6 }, radius: 7 })"),
+            Some(Shape::Circle {
+                center: Point { x: 5, y: 6 },
+                radius: Some(7)
+            })
+        );
+        assert_eq!(
+            extract("shapes[2]"),
+            Some(Shape::Line(Point { x: 0, y: 0 }, Point { x: 3, y: 4 }))
+        );
+        assert_eq!(extract("shapes[3]"), Some(Shape::Width(Meters(1.5))));
+        assert_eq!(extract("'Square'"), None);
+        assert_eq!(extract("({ type: 'Circle' })"), None);
+
+        #[derive(Debug, PartialEq, crate::NewValue, crate::ExtractValue)]
+        struct Token {
+            r#type: String,
+        }
+
+        global
+            .set_property(
+                "token",
+                Token {
+                    r#type: "ident".to_owned(),
+                },
+            )
+            .unwrap();
+
+        assert_eq!(
+            ctxt.eval::<_, Token>("token", Eval::GLOBAL).unwrap(),
+            Some(Token {
+                r#type: "ident".to_owned()
+            })
+        );
+        assert_eq!(
+            ctxt.eval::<_, String>("token.type", Eval::GLOBAL).unwrap(),
+            Some("ident".to_owned())
+        );
+    }
+
+    #[test]
+    fn try_to() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        let eval = |s| ctxt.eval_script(s, "<evalScript>", Eval::GLOBAL).unwrap();
+
+        assert_eq!(eval("'42'").try_to_int32().unwrap(), 42);
+        assert_eq!(eval("1.5").try_to_float64().unwrap(), 1.5);
+        assert!(eval("'foo'").try_to_bool().unwrap());
+        assert_eq!(eval("123").try_to_cstring().unwrap().to_str(), Ok("123"));
+
+        let err = eval("Symbol('foo')").try_to_int32().unwrap_err();
+
+        assert!(err.to_string().contains("TypeError"), "{}", err);
+        assert_eq!(eval("Symbol('foo')").to_int32(), None);
+
+        assert!(eval("-1").try_to_index().is_err());
+        assert!(eval("({ toString() { throw new Error('Whoops!') } })")
+            .try_to_cstring()
+            .unwrap_err()
+            .to_string()
+            .contains("Whoops!"));
+    }
 }
@@ -0,0 +1,445 @@
+use std::collections::BTreeMap;
+use std::ffi::CString;
+use std::io;
+use std::ptr::NonNull;
+use std::sync::{Arc, Mutex};
+
+use foreign_types::ForeignTypeRef;
+
+use crate::{ffi, ContextRef, Error, Eval, Local, ModuleDef, Prop, Value};
+
+/// The files opened by `std.open` are buffered in memory and written back when flushed or closed.
+const GLUE: &str = r#"
+(read, write, readdir, utf8) => {
+    class File {
+        constructor(path, mode, data) {
+            this.path = path;
+            this.mode = mode;
+            this.data = data;
+            this.pos = 0;
+            this.dirty = mode[0] === 'w';
+        }
+
+        close() { this.flush(); return 0; }
+        flush() {
+            if (this.dirty) {
+                write(this.path, this.data);
+                this.dirty = false;
+            }
+            return 0;
+        }
+        tell() { return this.pos; }
+        seek(offset, whence) {
+            const base = whence === 1 ? this.pos : whence === 2 ? this.data.length : 0;
+            this.pos = Math.max(0, base + offset);
+            return 0;
+        }
+        eof() { return this.pos >= this.data.length; }
+        fileno() { return -1; }
+        read(buffer, position, length) {
+            const bytes = this.data.subarray(this.pos, this.pos + length);
+            new Uint8Array(buffer, position, bytes.length).set(bytes);
+            this.pos += bytes.length;
+            return bytes.length;
+        }
+        write(buffer, position, length) {
+            this.put(new Uint8Array(buffer, position, length));
+            return length;
+        }
+        getByte() { return this.eof() ? -1 : this.data[this.pos++]; }
+        putByte(c) { this.put(Uint8Array.of(c)); return c; }
+        puts(s) { this.put(utf8(String(s))); }
+        getline() {
+            if (this.eof()) return null;
+            const end = this.data.indexOf(10, this.pos);
+            const line = utf8(this.data.subarray(this.pos, end < 0 ? this.data.length : end));
+            this.pos = end < 0 ? this.data.length : end + 1;
+            return line;
+        }
+        readAsString(max_size) {
+            const end = max_size === undefined ? this.data.length : Math.min(this.data.length, this.pos + max_size);
+            const s = utf8(this.data.subarray(this.pos, end));
+            this.pos = end;
+            return s;
+        }
+        put(bytes) {
+            if (this.mode[0] === 'r' && this.mode.indexOf('+') < 0) {
+                throw new TypeError('the file is not writable');
+            }
+            if (this.mode[0] === 'a') this.pos = this.data.length;
+            if (this.pos + bytes.length > this.data.length) {
+                const data = new Uint8Array(this.pos + bytes.length);
+                data.set(this.data);
+                this.data = data;
+            }
+            this.data.set(bytes, this.pos);
+            this.pos += bytes.length;
+            this.dirty = true;
+        }
+    }
+
+    return {
+        open(path, mode) {
+            path = String(path);
+            mode = String(mode);
+            if (!/^[rwa][+b]*$/.test(mode)) {
+                const err = new Error('Invalid argument');
+                err.errno = 22;
+                throw err;
+            }
+            if (mode[0] === 'w') return new File(path, mode, new Uint8Array(0));
+            try {
+                return new File(path, mode, read(path));
+            } catch (err) {
+                if (mode[0] === 'a' && err.errno === 2) return new File(path, mode, new Uint8Array(0));
+                throw err;
+            }
+        },
+        loadFile(path) {
+            try {
+                return utf8(read(String(path)));
+            } catch (err) {
+                return null;
+            }
+        },
+        readdir(path) {
+            try {
+                return [readdir(String(path)), 0];
+            } catch (err) {
+                return [[], -err.errno];
+            }
+        },
+    };
+}
+"#;
+
+/// The virtual filesystem seen by the `std` and `os` modules, installed with `ContextRef::set_vfs_handler`.
+///
+/// The paths are passed as they are written in the scripts, so the handler should normalize them.
+pub trait VfsHandler: Send + Sync + 'static {
+    /// Read the whole content of the file.
+    fn read(&self, path: &str) -> io::Result<Vec<u8>>;
+
+    /// Replace the whole content of the file, the files are read-only by default.
+    fn write(&self, _path: &str, _data: &[u8]) -> io::Result<()> {
+        Err(io::ErrorKind::PermissionDenied.into())
+    }
+
+    /// Returns the names of the entries in the directory.
+    fn read_dir(&self, _path: &str) -> io::Result<Vec<String>> {
+        Err(io::ErrorKind::NotFound.into())
+    }
+}
+
+impl<T: VfsHandler + ?Sized> VfsHandler for Arc<T> {
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        (**self).read(path)
+    }
+
+    fn write(&self, path: &str, data: &[u8]) -> io::Result<()> {
+        (**self).write(path, data)
+    }
+
+    fn read_dir(&self, path: &str) -> io::Result<Vec<String>> {
+        (**self).read_dir(path)
+    }
+}
+
+/// An in-memory filesystem, the directories are implied by the `/` separated paths of the files.
+#[derive(Debug, Default)]
+pub struct MemoryFs(Mutex<BTreeMap<String, Vec<u8>>>);
+
+impl MemoryFs {
+    /// Create an empty filesystem.
+    pub fn new() -> Self {
+        MemoryFs::default()
+    }
+
+    /// Add or replace a file.
+    pub fn insert<P: Into<String>, T: Into<Vec<u8>>>(&self, path: P, data: T) -> &Self {
+        self.0
+            .lock()
+            .expect("files")
+            .insert(path.into(), data.into());
+        self
+    }
+
+    /// Returns the content of a file.
+    pub fn get(&self, path: &str) -> Option<Vec<u8>> {
+        self.0.lock().expect("files").get(path).cloned()
+    }
+}
+
+impl VfsHandler for MemoryFs {
+    fn read(&self, path: &str) -> io::Result<Vec<u8>> {
+        self.get(path).ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+
+    fn write(&self, path: &str, data: &[u8]) -> io::Result<()> {
+        self.insert(path, data);
+
+        Ok(())
+    }
+
+    fn read_dir(&self, path: &str) -> io::Result<Vec<String>> {
+        let prefix = format!("{}/", path.trim_end_matches('/'));
+        let mut names = self
+            .0
+            .lock()
+            .expect("files")
+            .keys()
+            .filter_map(|file| file.strip_prefix(&prefix))
+            .map(|name| name.split('/').next().unwrap_or(name).to_owned())
+            .collect::<Vec<_>>();
+
+        names.dedup();
+
+        if names.is_empty() {
+            Err(io::ErrorKind::NotFound.into())
+        } else {
+            Ok(names)
+        }
+    }
+}
+
+/// Throw the `Error` with the `errno` property, like the errors of the `std` module.
+fn throw_io_error(ctxt: &ContextRef, err: io::Error) -> ffi::JSValue {
+    let errno = err.raw_os_error().unwrap_or(match err.kind() {
+        io::ErrorKind::NotFound => 2,
+        io::ErrorKind::PermissionDenied => 13,
+        io::ErrorKind::AlreadyExists => 17,
+        io::ErrorKind::InvalidInput => 22,
+        _ => 5,
+    });
+    let obj = ctxt.new_error();
+
+    for (name, value) in [
+        ("message", ctxt.bind(err.to_string())),
+        ("errno", ctxt.bind(errno)),
+    ] {
+        if let Err(err) =
+            obj.define_property_value(name, value, Prop::WRITABLE | Prop::CONFIGURABLE)
+        {
+            return ctxt.throw(err.to_string()).into_inner().raw();
+        }
+    }
+
+    ctxt.throw(obj).into_inner().raw()
+}
+
+fn with_vfs<F>(ctxt: &ContextRef, args: &[Value], f: F) -> ffi::JSValue
+where
+    F: FnOnce(&dyn VfsHandler, &str) -> io::Result<ffi::JSValue>,
+{
+    let vfs = match ctxt.with_state(|state| state.vfs.clone()) {
+        Some(vfs) => vfs,
+        None => return ctxt.throw_type_error("no VFS handler").into_inner().raw(),
+    };
+    let path = args
+        .first()
+        .and_then(|path| ctxt.to_cstring(path))
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    f(&*vfs, &path).unwrap_or_else(|err| throw_io_error(ctxt, err))
+}
+
+fn read(ctxt: &ContextRef, _this: Option<&Value>, args: &[Value]) -> ffi::JSValue {
+    with_vfs(ctxt, args, |vfs, path| {
+        let data = vfs.read(path)?;
+
+        trace!("vfs read {} bytes from {}", data.len(), path);
+
+        ctxt.new_uint8_array(&data)
+            .map(|arr| arr.into_inner().raw())
+            .map_err(|err| io::Error::other(err.to_string()))
+    })
+}
+
+fn write(ctxt: &ContextRef, _this: Option<&Value>, args: &[Value]) -> ffi::JSValue {
+    with_vfs(ctxt, args, |vfs, path| {
+        let res = args
+            .get(1)
+            .and_then(|data| ctxt.with_buffer_source(data, |data| vfs.write(path, data)))
+            .unwrap_or_else(|| Err(io::ErrorKind::InvalidInput.into()));
+
+        trace!("vfs write {}: {:?}", path, res);
+
+        res.map(|_| ffi::UNDEFINED)
+    })
+}
+
+fn readdir(ctxt: &ContextRef, _this: Option<&Value>, args: &[Value]) -> ffi::JSValue {
+    with_vfs(ctxt, args, |vfs, path| {
+        let names = vfs.read_dir(path)?;
+        let values = names
+            .into_iter()
+            .map(|name| ctxt.bind(name))
+            .collect::<Vec<_>>();
+
+        ctxt.array_from_locals(&values)
+            .map(|arr| arr.into_inner().raw())
+            .map_err(|err| io::Error::other(err.to_string()))
+    })
+}
+
+/// Encode the string to UTF-8 bytes, or decode the bytes to string.
+fn utf8(ctxt: &ContextRef, _this: Option<&Value>, args: &[Value]) -> ffi::JSValue {
+    let arg = args.first().unwrap_or_default();
+
+    if arg.is_string() {
+        let s = ctxt.to_cstring(arg).unwrap_or_default();
+
+        ctxt.new_uint8_array(s.as_bytes())
+            .map(|arr| arr.into_inner().raw())
+            .unwrap_or_else(|err| ctxt.throw(err.to_string()).into_inner().raw())
+    } else {
+        match ctxt.with_buffer_source(arg, |buf| String::from_utf8_lossy(buf).into_owned()) {
+            Some(s) => ctxt.bind(s).into_inner().raw(),
+            None => ctxt
+                .throw_type_error("expected string or ArrayBufferView")
+                .into_inner()
+                .raw(),
+        }
+    }
+}
+
+impl ContextRef {
+    /// Install the virtual filesystem seen by `std.open`, `std.loadFile` and `os.readdir`.
+    ///
+    /// The handler should be installed before `ContextRef::init_module_std` and `ContextRef::init_module_os`,
+    /// the functions are replaced when the modules are imported at the first time.
+    ///
+    /// It is not a sandbox, the other functions still reach the real filesystem,
+    /// e.g. `std.loadScript`, `std.popen`, `std.fdopen`, `std.urlGet`, `os.open`, `os.stat`, `os.realpath` and `os.readlink`,
+    /// use `ContextRef::init_module_std_with` and `ContextRef::init_module_os_with` to deny them.
+    pub fn set_vfs_handler<H: VfsHandler>(&self, handler: H) {
+        self.with_state(|state| state.vfs = Some(Arc::new(handler)));
+    }
+
+    fn vfs_glue(&self) -> Result<Local<Value>, Error> {
+        let glue = self.eval_script(GLUE, "<vfs>", Eval::GLOBAL)?;
+
+        self.call(
+            &glue,
+            None,
+            (
+                self.new_c_function(read, Some("read"), 1)?,
+                self.new_c_function(write, Some("write"), 2)?,
+                self.new_c_function(readdir, Some("readdir"), 1)?,
+                self.new_c_function(utf8, Some("utf8"), 1)?,
+            ),
+        )
+    }
+
+    /// Replace the exported functions with the VFS ones, the exports missing in QuickJS are added.
+    fn add_vfs_hook(
+        &self,
+        m: NonNull<ModuleDef>,
+        names: &'static [&'static str],
+        added: &[&str],
+    ) -> Result<(), Error> {
+        for name in added {
+            // the new exports must be added before the module is instantiated
+            let cname = CString::new(*name)?;
+
+            self.check_error(unsafe {
+                ffi::JS_AddModuleExport(self.as_ptr(), m.as_ptr(), cname.as_ptr())
+            })?;
+        }
+
+        self.add_module_hook(
+            m,
+            Box::new(move |ctxt, m| {
+                let glue = ctxt.vfs_glue()?;
+
+                for name in names {
+                    trace!("patch `{}` with VFS", name);
+
+                    let func = glue
+                        .get_property(*name)
+                        .ok_or_else(|| format!("missing VFS function `{}`", name))?;
+
+                    ctxt.set_module_export(m, name, func)?;
+                }
+
+                Ok(())
+            }),
+        );
+
+        Ok(())
+    }
+
+    pub(crate) fn add_std_vfs_hook(&self, m: NonNull<ModuleDef>) -> Result<(), Error> {
+        self.add_vfs_hook(m, &["open", "loadFile"], &["loadFile"])
+    }
+
+    pub(crate) fn add_os_vfs_hook(&self, m: NonNull<ModuleDef>) -> Result<(), Error> {
+        self.add_vfs_hook(m, &["readdir"], &[])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, Runtime};
+
+    use super::*;
+
+    #[test]
+    fn vfs() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+        let fs = Arc::new(MemoryFs::new());
+
+        fs.insert("/data/hello.txt", "hello\nworld")
+            .insert("/data/sub/empty.txt", "");
+
+        ctxt.set_vfs_handler(fs.clone());
+        ctxt.init_module_std().unwrap();
+        ctxt.init_module_os().unwrap();
+
+        ctxt.eval_module(
+            r#"
+import * as std from 'std';
+import * as os from 'os';
+
+const f = std.open('/data/hello.txt', 'r');
+globalThis.first = f.getline();
+globalThis.rest = f.readAsString();
+f.close();
+
+const out = std.open('/data/out.txt', 'w');
+out.puts('written');
+out.close();
+
+globalThis.loaded = std.loadFile('/data/out.txt');
+globalThis.missing = std.loadFile('/missing');
+globalThis.entries = os.readdir('/data')[0].join();
+globalThis.err = os.readdir('/missing')[1];
+
+try {
+    std.open('/missing', 'r');
+} catch (err) {
+    globalThis.errno = err.errno;
+}
+"#,
+            "<evalModule>",
+        )
+        .unwrap();
+
+        let get = |name| ctxt.global_object().get_property(name).unwrap().to_string();
+
+        assert_eq!(get("first"), "hello");
+        assert_eq!(get("rest"), "world");
+        assert_eq!(get("loaded"), "written");
+        assert_eq!(get("missing"), "null");
+        assert_eq!(get("entries"), "hello.txt,out.txt,sub");
+        assert_eq!(get("err"), "-2");
+        assert_eq!(get("errno"), "2");
+        assert_eq!(fs.get("/data/out.txt").unwrap(), b"written");
+
+        rt.std_free_handlers();
+    }
+}
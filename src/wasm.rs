@@ -0,0 +1,791 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::os::raw::{c_int, c_void};
+use std::panic;
+use std::ptr::NonNull;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use foreign_types::ForeignTypeRef;
+use wasmtime::{Config, Engine, Extern, Func, Memory, Module, Store, Val, ValType};
+
+use crate::{
+    cfunc::c_args, ffi, runtime::InterruptCallback, ContextRef, Error, ErrorKind, Eval, Local,
+    NewValue, Prop, RuntimeRef, Value, UNDEFINED,
+};
+
+/// The interval to poll the interrupt closure of the runtime when a WebAssembly function is running.
+const INTERRUPT_INTERVAL: Duration = Duration::from_millis(5);
+
+const GLUE: &str = r#"
+(function (instantiate) {
+    globalThis.WebAssembly = {
+        instantiate(source) {
+            return new Promise(resolve => resolve(instantiate(source)));
+        },
+    };
+})
+"#;
+
+fn wasm_error<E: ToString>(err: E) -> Error {
+    Error::Msg(err.to_string())
+}
+
+/// The WebAssembly modules supplied by the host.
+pub struct WasmModules {
+    engine: Engine,
+    modules: HashMap<String, Module>,
+}
+
+impl Default for WasmModules {
+    fn default() -> Self {
+        WasmModules::new()
+    }
+}
+
+impl WasmModules {
+    /// Construct a new `WasmModules` with an `Engine` which enables the epoch interruption.
+    pub fn new() -> Self {
+        let engine = Engine::new(Config::new().epoch_interruption(true))
+            .expect("WebAssembly engine with epoch interruption");
+
+        WasmModules::with_engine(engine)
+    }
+
+    /// Construct a new `WasmModules` with the `Engine`.
+    ///
+    /// The running functions could be interrupted only if the engine enables `Config::epoch_interruption`.
+    pub fn with_engine(engine: Engine) -> Self {
+        WasmModules {
+            engine,
+            modules: HashMap::new(),
+        }
+    }
+
+    /// The engine to compile and run the modules.
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    /// Compile a module in the binary or text format, which could be instantiated by name.
+    pub fn add_module<T: AsRef<[u8]>>(&mut self, name: &str, bytes: T) -> Result<&mut Self, Error> {
+        trace!("compile WebAssembly module `{}`", name);
+
+        let module = Module::new(&self.engine, bytes).map_err(wasm_error)?;
+
+        self.modules.insert(name.to_owned(), module);
+
+        Ok(self)
+    }
+
+    fn instantiate<'a>(
+        &self,
+        ctxt: &'a ContextRef,
+        source: &Value,
+    ) -> Result<Local<'a, Value>, Error> {
+        let (name, module) = if source.is_string() {
+            let name = ctxt
+                .to_cstring(source)
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let module = self
+                .modules
+                .get(&name)
+                .cloned()
+                .ok_or_else(|| format!("WebAssembly module `{}` not found", name))?;
+
+            (Some(name), module)
+        } else {
+            let module = ctxt
+                .with_buffer_source(source, |bytes| Module::new(&self.engine, bytes))
+                .ok_or("expected module name, ArrayBuffer or TypedArray")?
+                .map_err(wasm_error)?;
+
+            (None, module)
+        };
+
+        trace!("instantiate WebAssembly module {:?}", name);
+
+        let mut store = Store::new(&self.engine, ());
+        let instance = wasmtime::Instance::new(&mut store, &module, &[]).map_err(wasm_error)?;
+        let externs = instance
+            .exports(&mut store)
+            .map(|export| (export.name().to_owned(), export.into_extern()))
+            .collect::<Vec<_>>();
+        let memories = externs
+            .iter()
+            .filter_map(|(_, ext)| ext.clone().into_memory())
+            .collect::<Vec<_>>();
+        let shared = Rc::new(Shared {
+            store: RefCell::new(store),
+            views: RefCell::new(vec![None; memories.len()]),
+            memories,
+        });
+
+        let exports = ctxt.bind(ctxt.new_object());
+        let mut memory_idx = 0;
+
+        for (name, ext) in externs {
+            match ext {
+                Extern::Func(func) => {
+                    let length = func.ty(&*shared.store.borrow()).params().len();
+                    let export = Export {
+                        shared: shared.clone(),
+                        func,
+                    };
+                    let func = ctxt.new_c_function_data(
+                        call_export,
+                        length,
+                        0,
+                        ctxt.new_userdata(export),
+                    )?;
+
+                    func.define_property_value("name", name.as_str(), Prop::CONFIGURABLE)?;
+                    exports.set_property(name.as_str(), func)?;
+                }
+                Extern::Memory(_) => {
+                    let export = MemoryExport {
+                        shared: shared.clone(),
+                        idx: memory_idx,
+                    };
+
+                    memory_idx += 1;
+
+                    let getter = ctxt.new_c_function_data(
+                        memory_buffer,
+                        0,
+                        0,
+                        ctxt.new_userdata(export.clone()),
+                    )?;
+                    let grow =
+                        ctxt.new_c_function_data(memory_grow, 1, 0, ctxt.new_userdata(export))?;
+                    let obj = ctxt.bind(ctxt.new_object());
+
+                    obj.define_property("buffer", None, Some(&*getter), None, Prop::CONFIGURABLE)?;
+                    obj.define_property_value("grow", grow, Prop::CONFIGURABLE | Prop::WRITABLE)?;
+                    exports.set_property(name.as_str(), obj)?;
+                }
+                _ => trace!("skip WebAssembly export `{}`", name),
+            }
+        }
+
+        let instance = ctxt.bind(ctxt.new_object());
+
+        instance.set_property("exports", exports)?;
+
+        let res = ctxt.bind(ctxt.new_object());
+
+        if let Some(name) = name {
+            res.set_property("module", name)?;
+        }
+        res.set_property("instance", instance)?;
+
+        Ok(res)
+    }
+}
+
+/// The store of a WebAssembly instance, shared by its exports and the buffers of its memories.
+struct Shared {
+    store: RefCell<Store<()>>,
+    memories: Vec<Memory>,
+    /// The live `ArrayBuffer` over each memory, which is not owned and cleared when the buffer is freed.
+    views: RefCell<Vec<Option<View>>>,
+}
+
+/// The `ArrayBuffer` over the linear memory.
+#[derive(Clone, Copy)]
+struct View {
+    buffer: ffi::JSValue,
+    data: *mut u8,
+    len: usize,
+}
+
+/// The owner of an `ArrayBuffer` over the linear memory, which keeps the store alive.
+struct ViewOwner {
+    shared: Rc<Shared>,
+    idx: usize,
+}
+
+impl Shared {
+    /// Returns the bytes of the linear memory, which may be moved or resized when it grows.
+    fn memory_data(&self, store: &mut Store<()>, idx: usize) -> (*mut u8, usize) {
+        let data = self.memories[idx].data_mut(store);
+
+        if data.is_empty() {
+            (NonNull::dangling().as_ptr(), 0)
+        } else {
+            (data.as_mut_ptr(), data.len())
+        }
+    }
+
+    /// Returns the `ArrayBuffer` over the linear memory without copying it.
+    fn buffer<'a>(
+        self: &Rc<Self>,
+        ctxt: &'a ContextRef,
+        idx: usize,
+    ) -> Result<Local<'a, Value>, Error> {
+        self.detach_stale(ctxt);
+
+        if let Some(view) = self.views.borrow()[idx] {
+            return Ok(ctxt.clone_value(unsafe { &*(&view.buffer as *const _ as *const Value) }));
+        }
+
+        let (data, len) = {
+            let mut store = self
+                .store
+                .try_borrow_mut()
+                .map_err(|_| "WebAssembly instance is busy")?;
+
+            self.memory_data(&mut store, idx)
+        };
+        let owner = Box::new(ViewOwner {
+            shared: self.clone(),
+            idx,
+        });
+        let buffer = ctxt
+            .bind(unsafe {
+                ffi::JS_NewArrayBuffer(
+                    ctxt.as_ptr(),
+                    data,
+                    len,
+                    Some(free_view),
+                    Box::into_raw(owner) as *mut _,
+                    0,
+                )
+            })
+            .ok()?;
+
+        self.views.borrow_mut()[idx] = Some(View {
+            buffer: buffer.raw(),
+            data,
+            len,
+        });
+
+        Ok(buffer)
+    }
+
+    /// Detach the `ArrayBuffer`s over the memories which have been moved or resized.
+    fn detach_stale(&self, ctxt: &ContextRef) {
+        let stale = match self.store.try_borrow_mut() {
+            Ok(mut store) => {
+                let mut views = self.views.borrow_mut();
+
+                views
+                    .iter_mut()
+                    .enumerate()
+                    .filter(|(idx, view)| {
+                        matches!(view, Some(view)
+                            if (view.data, view.len) != self.memory_data(&mut store, *idx))
+                    })
+                    .flat_map(|(_, view)| view.take())
+                    .collect::<Vec<_>>()
+            }
+            Err(_) => return,
+        };
+
+        // the views were taken, so the free function will not touch them
+        for view in stale {
+            trace!("detach the ArrayBuffer over the grown memory");
+
+            unsafe { ffi::JS_DetachArrayBuffer(ctxt.as_ptr(), view.buffer) }
+        }
+    }
+}
+
+/// Release the store when the `ArrayBuffer` over the memory is detached or freed.
+unsafe extern "C" fn free_view(_rt: *mut ffi::JSRuntime, opaque: *mut c_void, data: *mut c_void) {
+    // the function is called again with null data when a detached buffer is finalized
+    if data.is_null() {
+        return;
+    }
+
+    let owner = Box::from_raw(opaque as *mut ViewOwner);
+
+    if let Ok(mut views) = owner.shared.views.try_borrow_mut() {
+        if matches!(views[owner.idx], Some(view) if view.data == data as *mut u8) {
+            views[owner.idx] = None;
+        }
+    };
+}
+
+/// The exported function of a WebAssembly instance.
+struct Export {
+    shared: Rc<Shared>,
+    func: Func,
+}
+
+impl Export {
+    fn call<'a>(&self, ctxt: &'a ContextRef, args: &[Value]) -> Result<Local<'a, Value>, Error> {
+        let mut store = self
+            .shared
+            .store
+            .try_borrow_mut()
+            .map_err(|_| "WebAssembly instance is busy")?;
+        let ty = self.func.ty(&*store);
+        let params = ty
+            .params()
+            .enumerate()
+            .map(|(idx, ty)| to_val(ctxt, args.get(idx).unwrap_or(&UNDEFINED), ty))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut results = vec![Val::I32(0); ty.results().len()];
+
+        store.set_epoch_deadline(1);
+
+        let watchdog = Watchdog::start(ctxt.runtime(), store.engine());
+        let res = self.func.call(&mut *store, &params, &mut results);
+        let interrupted = watchdog.stop(ctxt.runtime());
+
+        drop(store);
+
+        // the memories may grow in the function
+        self.shared.detach_stale(ctxt);
+
+        if interrupted {
+            return Err(ErrorKind::InternalError("interrupted".to_owned(), None).into());
+        }
+
+        res.map_err(wasm_error)?;
+
+        match results.as_slice() {
+            [] => Ok(ctxt.undefined()),
+            [val] => from_val(ctxt, val),
+            vals => {
+                let arr = ctxt.bind(ctxt.new_array());
+
+                for (idx, val) in vals.iter().enumerate() {
+                    arr.set_property(idx as u32, from_val(ctxt, val)?)?;
+                }
+
+                Ok(arr)
+            }
+        }
+    }
+}
+
+/// Poll the interrupt closure of the runtime in a thread when a WebAssembly function is running,
+/// and interrupt the function by incrementing the epoch of the engine.
+///
+/// The closure is moved to the thread and back, because the runtime is blocked by the function.
+struct Watchdog {
+    stopped: Arc<AtomicBool>,
+    handle: Option<JoinHandle<(InterruptCallback, bool)>>,
+}
+
+impl Watchdog {
+    fn start(rt: &RuntimeRef, engine: &Engine) -> Self {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let handle = rt
+            .with_interrupts(|interrupts| interrupts.callback.take())
+            .map(|mut callback| {
+                let engine = engine.clone();
+                let stopped = stopped.clone();
+
+                thread::spawn(move || {
+                    while !stopped.load(Ordering::SeqCst) {
+                        if callback() {
+                            engine.increment_epoch();
+
+                            return (callback, true);
+                        }
+
+                        thread::park_timeout(INTERRUPT_INTERVAL);
+                    }
+
+                    (callback, false)
+                })
+            });
+
+        Watchdog { stopped, handle }
+    }
+
+    /// Stop the thread and restore the closure, returns `true` if the function was interrupted.
+    fn stop(mut self, rt: &RuntimeRef) -> bool {
+        self.stopped.store(true, Ordering::SeqCst);
+
+        match self.handle.take() {
+            Some(handle) => {
+                handle.thread().unpark();
+
+                match handle.join() {
+                    Ok((callback, interrupted)) => {
+                        rt.with_interrupts(|interrupts| {
+                            if interrupts.callback.is_none() {
+                                interrupts.callback = Some(callback)
+                            }
+                        });
+
+                        interrupted
+                    }
+                    Err(_) => true,
+                }
+            }
+            None => false,
+        }
+    }
+}
+
+fn to_val(ctxt: &ContextRef, arg: &Value, ty: ValType) -> Result<Val, Error> {
+    match ty {
+        ValType::I32 => ctxt.to_int32(arg).map(Val::I32),
+        ValType::I64 => ctxt.to_int64(arg).map(Val::I64),
+        ValType::F32 => ctxt.to_float64(arg).map(|n| Val::F32((n as f32).to_bits())),
+        ValType::F64 => ctxt.to_float64(arg).map(|n| Val::F64(n.to_bits())),
+        _ => None,
+    }
+    .ok_or_else(|| format!("unsupported {} argument", ty).into())
+}
+
+fn from_val<'a>(ctxt: &'a ContextRef, val: &Val) -> Result<Local<'a, Value>, Error> {
+    match *val {
+        Val::I32(n) => Ok(ctxt.bind(n)),
+        Val::I64(n) => Ok(ctxt.bind(n)),
+        Val::F32(n) => Ok(ctxt.bind(f32::from_bits(n))),
+        Val::F64(n) => Ok(ctxt.bind(f64::from_bits(n))),
+        _ => Err(format!("unsupported {} result", val.ty()).into()),
+    }
+}
+
+unsafe extern "C" fn call_export(
+    ctx: *mut ffi::JSContext,
+    _this_val: ffi::JSValue,
+    argc: c_int,
+    argv: *mut ffi::JSValue,
+    _magic: c_int,
+    data: *mut ffi::JSValue,
+) -> ffi::JSValue {
+    panic::catch_unwind(|| {
+        let ctxt = ContextRef::from_ptr(ctx);
+        let export = ctxt.get_userdata_unchecked::<Export>(&*(data as *const Value));
+        let args = c_args(argc, argv);
+
+        export.as_ref().call(ctxt, args).new_value(ctxt)
+    })
+    .unwrap_or_default()
+}
+
+/// The exported memory of a WebAssembly instance.
+#[derive(Clone)]
+struct MemoryExport {
+    shared: Rc<Shared>,
+    idx: usize,
+}
+
+impl MemoryExport {
+    fn grow<'a>(&self, ctxt: &'a ContextRef, delta: &Value) -> Result<Local<'a, Value>, Error> {
+        let delta = ctxt
+            .to_index(delta)
+            .ok_or_else(|| Error::from("expected the number of pages"))?;
+        let pages = {
+            let mut store = self
+                .shared
+                .store
+                .try_borrow_mut()
+                .map_err(|_| "WebAssembly instance is busy")?;
+
+            self.shared.memories[self.idx]
+                .grow(&mut *store, delta)
+                .map_err(wasm_error)?
+        };
+
+        self.shared.detach_stale(ctxt);
+
+        Ok(ctxt.bind(pages as f64))
+    }
+}
+
+unsafe extern "C" fn memory_buffer(
+    ctx: *mut ffi::JSContext,
+    _this_val: ffi::JSValue,
+    _argc: c_int,
+    _argv: *mut ffi::JSValue,
+    _magic: c_int,
+    data: *mut ffi::JSValue,
+) -> ffi::JSValue {
+    panic::catch_unwind(|| {
+        let ctxt = ContextRef::from_ptr(ctx);
+        let export = ctxt.get_userdata_unchecked::<MemoryExport>(&*(data as *const Value));
+        let export = export.as_ref();
+
+        export.shared.buffer(ctxt, export.idx).new_value(ctxt)
+    })
+    .unwrap_or_default()
+}
+
+unsafe extern "C" fn memory_grow(
+    ctx: *mut ffi::JSContext,
+    _this_val: ffi::JSValue,
+    argc: c_int,
+    argv: *mut ffi::JSValue,
+    _magic: c_int,
+    data: *mut ffi::JSValue,
+) -> ffi::JSValue {
+    panic::catch_unwind(|| {
+        let ctxt = ContextRef::from_ptr(ctx);
+        let export = ctxt.get_userdata_unchecked::<MemoryExport>(&*(data as *const Value));
+        let args = c_args(argc, argv);
+
+        export
+            .as_ref()
+            .grow(ctxt, args.first().unwrap_or(&UNDEFINED))
+            .new_value(ctxt)
+    })
+    .unwrap_or_default()
+}
+
+impl ContextRef {
+    /// Install the `WebAssembly` global object backed by `wasmtime`.
+    ///
+    /// The script could instantiate a module supplied by the host with its name,
+    /// or compile a module from an `ArrayBuffer` or `TypedArray`, and the imports are not supported.
+    ///
+    /// The `buffer` of the exported memory is a view of the linear memory without copying,
+    /// which is detached when the memory grows, by the instance or its `grow` method.
+    ///
+    /// A running function is interrupted if the interrupt closure of the runtime returns `true`,
+    /// the closure is polled by a watchdog thread until the function returns.
+    ///
+    /// ```js
+    /// WebAssembly.instantiate('math').then(({ instance }) => instance.exports.add(1, 2))
+    /// ```
+    pub fn init_wasm(&self, modules: WasmModules) -> Result<(), Error> {
+        unsafe extern "C" fn instantiate(
+            ctx: *mut ffi::JSContext,
+            _this_val: ffi::JSValue,
+            argc: c_int,
+            argv: *mut ffi::JSValue,
+            _magic: c_int,
+            data: *mut ffi::JSValue,
+        ) -> ffi::JSValue {
+            panic::catch_unwind(|| {
+                let ctxt = ContextRef::from_ptr(ctx);
+                let modules = ctxt.get_userdata_unchecked::<WasmModules>(&*(data as *const Value));
+                let args = c_args(argc, argv);
+
+                modules
+                    .as_ref()
+                    .instantiate(ctxt, args.first().unwrap_or(&UNDEFINED))
+                    .new_value(ctxt)
+            })
+            .unwrap_or_default()
+        }
+
+        let instantiate =
+            self.new_c_function_data(instantiate, 1, 0, self.new_userdata(modules))?;
+
+        self.eval_script(GLUE, "<wasm>", Eval::GLOBAL)?
+            .call(None, instantiate)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, Eval, Runtime};
+
+    use super::*;
+
+    const MATH: &str = r#"
+(module
+    (memory (export "memory") 1)
+    (func (export "add") (param i32 i32) (result i32)
+        local.get 0
+        local.get 1
+        i32.add)
+    (func (export "store") (param i32 i32)
+        local.get 0
+        local.get 1
+        i32.store8)
+    (func (export "load") (param i32) (result i32)
+        local.get 0
+        i32.load8_u)
+    (func (export "spin")
+        (loop (br 0)))
+    (func (export "div") (param f64 f64) (result f64)
+        local.get 0
+        local.get 1
+        f64.div))
+"#;
+
+    #[test]
+    fn wasm() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+        let mut modules = WasmModules::new();
+
+        modules.add_module("math", MATH).unwrap();
+        ctxt.init_wasm(modules).unwrap();
+
+        ctxt.eval_script(
+            r#"
+var result;
+
+WebAssembly.instantiate('math').then(({ module, instance: { exports } }) => {
+    exports.store(8, 42);
+
+    result = [
+        module,
+        exports.add(1, 2),
+        exports.div(1, 4),
+        new Uint8Array(exports.memory.buffer)[8],
+        exports.memory.buffer.byteLength,
+    ].join();
+});
+"#,
+            "<evalScript>",
+            Eval::GLOBAL,
+        )
+        .unwrap();
+
+        while rt.is_job_pending() {
+            rt.execute_pending_job().unwrap();
+        }
+
+        assert_eq!(
+            ctxt.eval::<_, String>("result", Eval::GLOBAL).unwrap(),
+            Some("math,3,0.25,42,65536".to_owned())
+        );
+
+        ctxt.eval_script(
+            "WebAssembly.instantiate('foo').catch(err => result = String(err))",
+            "<evalScript>",
+            Eval::GLOBAL,
+        )
+        .unwrap();
+
+        while rt.is_job_pending() {
+            rt.execute_pending_job().unwrap();
+        }
+
+        assert_eq!(
+            ctxt.eval::<_, String>("result", Eval::GLOBAL).unwrap(),
+            Some("WebAssembly module `foo` not found".to_owned())
+        );
+
+        ctxt.eval_script(
+            r#"
+WebAssembly.instantiate(new Uint8Array([0, 97, 115, 109, 1, 0, 0, 0]))
+    .then(({ instance }) => result = Object.keys(instance.exports).length);
+"#,
+            "<evalScript>",
+            Eval::GLOBAL,
+        )
+        .unwrap();
+
+        while rt.is_job_pending() {
+            rt.execute_pending_job().unwrap();
+        }
+
+        assert_eq!(
+            ctxt.eval::<_, i32>("result", Eval::GLOBAL).unwrap(),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn wasm_memory() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+        let mut modules = WasmModules::new();
+
+        modules.add_module("math", MATH).unwrap();
+        ctxt.init_wasm(modules).unwrap();
+
+        ctxt.eval_script(
+            r#"
+var result;
+
+WebAssembly.instantiate('math').then(({ instance: { exports } }) => {
+    const { memory } = exports;
+    const buffer = memory.buffer;
+
+    new Uint8Array(buffer)[8] = 42;
+    exports.store(9, 7);
+
+    const loaded = exports.load(8);
+    const stored = new Uint8Array(buffer)[9];
+    const same = buffer === memory.buffer;
+    const pages = memory.grow(1);
+    let detached = false;
+
+    try {
+        buffer.byteLength;
+    } catch (err) {
+        detached = err instanceof TypeError;
+    }
+
+    result = [
+        loaded,
+        stored,
+        same,
+        pages,
+        detached,
+        memory.buffer.byteLength,
+        new Uint8Array(memory.buffer)[8],
+    ].join();
+});
+"#,
+            "<evalScript>",
+            Eval::GLOBAL,
+        )
+        .unwrap();
+
+        while rt.is_job_pending() {
+            rt.execute_pending_job().unwrap();
+        }
+
+        assert_eq!(
+            ctxt.eval::<_, String>("result", Eval::GLOBAL).unwrap(),
+            Some("42,7,true,1,true,131072,42".to_owned())
+        );
+    }
+
+    #[test]
+    fn wasm_interrupt() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+        let mut modules = WasmModules::new();
+
+        modules.add_module("math", MATH).unwrap();
+        ctxt.init_wasm(modules).unwrap();
+
+        let mut polls = 0;
+
+        rt.set_interrupt_callback(move || {
+            polls += 1;
+            polls > 3
+        });
+
+        ctxt.eval_script(
+            r#"
+var result;
+
+WebAssembly.instantiate('math').then(({ instance: { exports } }) => exports.spin())
+    .catch(err => result = String(err));
+"#,
+            "<evalScript>",
+            Eval::GLOBAL,
+        )
+        .unwrap();
+
+        while rt.is_job_pending() {
+            let _ = rt.execute_pending_job();
+        }
+
+        rt.clear_interrupt_callback();
+
+        assert_eq!(
+            ctxt.eval::<_, String>("result", Eval::GLOBAL).unwrap(),
+            Some("InternalError: interrupted".to_owned())
+        );
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::fs;
+use std::os::raw::{c_char, c_void};
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::ptr::null_mut;
+use std::time::SystemTime;
+
+use crate::{ffi, module::load_module, ContextRef, Error, Eval, RuntimeRef};
+
+/// Strip the generation suffix (`#<n>`) which the `Watcher` appended to the module name.
+pub(crate) fn module_filename(name: &str) -> &str {
+    match name.rfind('#') {
+        Some(pos) if name[pos + 1..].chars().all(|c| c.is_ascii_digit()) => &name[..pos],
+        _ => name,
+    }
+}
+
+/// Record the script file loaded by the module loader when the `Watcher` is tracking.
+pub(crate) fn touch_module(rt: &RuntimeRef, filename: &str) {
+    rt.with_state(|state| {
+        if let Some(files) = state.watched_modules.as_mut() {
+            files.push(PathBuf::from(filename))
+        }
+    })
+}
+
+/// Normalize the module name like the default normalizer, and keep the generation of the base module,
+/// so the dependencies are loaded again instead of using the modules of the previous generation.
+fn normalize_name(base: &str, name: &str) -> String {
+    let (base, generation) = match module_filename(base) {
+        filename if filename.len() < base.len() => (filename, &base[filename.len()..]),
+        filename => (filename, ""),
+    };
+
+    if !name.starts_with('.') {
+        return name.to_owned();
+    }
+
+    let mut dir = base.rfind('/').map_or("", |pos| &base[..pos]).to_owned();
+    let mut name = name;
+
+    loop {
+        if name.starts_with("./") {
+            name = &name[2..];
+        } else if name.starts_with("../") {
+            if dir.is_empty() {
+                break;
+            }
+
+            let last = dir.rfind('/').map_or(0, |pos| pos + 1);
+
+            if &dir[last..] == "." || &dir[last..] == ".." {
+                break;
+            }
+
+            dir.truncate(last.saturating_sub(1));
+            name = &name[3..];
+        } else {
+            break;
+        }
+    }
+
+    if dir.is_empty() {
+        format!("{}{}", name, generation)
+    } else {
+        format!("{}/{}{}", dir, name, generation)
+    }
+}
+
+unsafe extern "C" fn normalize_module(
+    ctx: *mut ffi::JSContext,
+    module_base_name: *const c_char,
+    module_name: *const c_char,
+    _opaque: *mut c_void,
+) -> *mut c_char {
+    panic::catch_unwind(|| {
+        let base = CStr::from_ptr(module_base_name).to_string_lossy();
+        let name = CStr::from_ptr(module_name).to_string_lossy();
+        let normalized = normalize_name(&base, &name);
+
+        trace!(
+            "normalize module `{}` @ `{}` to `{}`",
+            name,
+            base,
+            normalized
+        );
+
+        CString::new(normalized).map_or(null_mut(), |s| ffi::js_strdup(ctx, s.as_ptr()))
+    })
+    .unwrap_or(null_mut())
+}
+
+/// Watch the script files of a module and its dependencies, and evaluate it again on change.
+///
+/// The files are tracked when they were loaded by the module loader,
+/// and each evaluation loads the modules as a new generation,
+/// the modules of the previous generations are kept until the context is freed.
+pub struct Watcher<'a, F> {
+    ctxt: &'a ContextRef,
+    entry: PathBuf,
+    generation: usize,
+    files: HashMap<PathBuf, Option<SystemTime>>,
+    on_change: F,
+}
+
+impl<'a, F> Watcher<'a, F>
+where
+    F: FnMut(&ContextRef, Result<(), Error>),
+{
+    /// Evaluate the entry module and watch the files it touched.
+    ///
+    /// The module loader of the runtime will be replaced to track the loaded files.
+    pub fn watch<P: AsRef<Path>>(
+        entry: P,
+        ctxt: &'a ContextRef,
+        on_change: F,
+    ) -> Result<Self, Error> {
+        let rt = ctxt.runtime();
+
+        rt.with_state(|state| {
+            if state.module_loader.is_none() {
+                state.module_loader = Some(Default::default())
+            }
+        });
+        rt.set_module_loader::<()>(Some(normalize_module), Some(load_module), None);
+
+        let mut watcher = Watcher {
+            ctxt,
+            entry: entry.as_ref().to_owned(),
+            generation: 0,
+            files: HashMap::new(),
+            on_change,
+        };
+
+        watcher.eval()?;
+
+        Ok(watcher)
+    }
+
+    /// The files touched by the last evaluation.
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.files.keys().map(|path| path.as_path())
+    }
+
+    /// Check the watched files, and evaluate the entry module again if some of them were changed.
+    ///
+    /// Returns `true` if the module was evaluated again, and the `on_change` callback was called with the result.
+    pub fn poll(&mut self) -> bool {
+        let changed = self
+            .files
+            .iter()
+            .any(|(path, mtime)| &modified(path) != mtime);
+
+        if changed {
+            trace!("watched files changed, reload `{}`", self.entry.display());
+
+            let res = self.eval();
+
+            (self.on_change)(self.ctxt, res);
+        }
+
+        changed
+    }
+
+    fn eval(&mut self) -> Result<(), Error> {
+        let rt = self.ctxt.runtime();
+        let name = format!("{}#{}", self.entry.display(), self.generation);
+
+        self.generation += 1;
+
+        rt.with_state(|state| state.watched_modules = Some(vec![self.entry.clone()]));
+
+        let res = fs::read(&self.entry)
+            .map_err(Error::from)
+            .and_then(|source| self.ctxt.eval_script(source, &name, Eval::MODULE))
+            .map(|_| ());
+
+        let files = rt
+            .with_state(|state| state.watched_modules.take())
+            .unwrap_or_default();
+
+        self.files = files
+            .into_iter()
+            .map(|path| {
+                let mtime = modified(&path);
+
+                (path, mtime)
+            })
+            .collect();
+
+        trace!("watching {} files", self.files.len());
+
+        res
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::time::Duration;
+
+    use crate::{Context, Runtime};
+
+    use super::*;
+
+    #[test]
+    fn normalize() {
+        assert_eq!(normalize_name("main.js", "./lib.js"), "lib.js");
+        assert_eq!(normalize_name("a/b/main.js#2", "../lib.js"), "a/lib.js#2");
+        assert_eq!(normalize_name("a/main.js#2", "std"), "std");
+        assert_eq!(module_filename("a/lib.js#12"), "a/lib.js");
+        assert_eq!(module_filename("a/#lib.js"), "a/#lib.js");
+    }
+
+    #[test]
+    fn watcher() {
+        let _ = pretty_env_logger::try_init();
+
+        let dir = tempfile::tempdir().unwrap();
+        let main = dir.path().join("main.js");
+        let lib = dir.path().join("lib.js");
+
+        fs::write(&main, "import { v } from './lib.js'; globalThis.v = v;").unwrap();
+        fs::write(&lib, "export const v = 1;").unwrap();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+        let results = RefCell::new(vec![]);
+
+        let mut watcher = Watcher::watch(&main, &ctxt, |ctxt, res| {
+            results.borrow_mut().push(
+                res.and_then(|_| ctxt.eval::<_, i32>("v", Eval::GLOBAL))
+                    .unwrap(),
+            )
+        })
+        .unwrap();
+
+        assert_eq!(ctxt.eval::<_, i32>("v", Eval::GLOBAL).unwrap(), Some(1));
+        assert_eq!(watcher.files().count(), 2);
+        assert!(!watcher.poll());
+
+        // make sure the modified time was changed
+        std::thread::sleep(Duration::from_millis(20));
+        fs::write(&lib, "export const v = 2;").unwrap();
+
+        assert!(watcher.poll());
+        assert!(!watcher.poll());
+        assert_eq!(*results.borrow(), vec![Some(2)]);
+    }
+}
@@ -0,0 +1,233 @@
+use std::convert::TryFrom;
+
+use crate::{ffi, ContextRef, Error, Eval, Prop, Value};
+
+const GLUE: &str = r#"
+(function (encode, decode) {
+    class TextEncoder {
+        get encoding() { return 'utf-8'; }
+
+        encode(input = '') { return encode(String(input)); }
+    }
+
+    class TextDecoder {
+        constructor(label = 'utf-8') {
+            label = String(label).trim().toLowerCase();
+
+            if (label !== 'utf-8' && label !== 'utf8' && label !== 'unicode-1-1-utf-8') {
+                throw new RangeError(`The "${label}" encoding is not supported`);
+            }
+        }
+
+        get encoding() { return 'utf-8'; }
+
+        decode(input) { return input === undefined ? '' : decode(input); }
+    }
+
+    globalThis.TextEncoder = TextEncoder;
+    globalThis.TextDecoder = TextDecoder;
+})
+"#;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn encode_base64(data: &[u8]) -> String {
+    let mut s = String::with_capacity(data.len().div_ceil(3) * 4);
+
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - i * 8));
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                s.push(BASE64[(n >> (18 - i * 6)) as usize & 0x3f] as char);
+            } else {
+                s.push('=');
+            }
+        }
+    }
+
+    s
+}
+
+/// Decode the base64 string with the forgiving rules of the HTML standard.
+fn decode_base64(s: &str) -> Option<Vec<u8>> {
+    let mut s = s
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '\t' | '\n' | '\x0C' | '\r'))
+        .collect::<String>();
+
+    if s.len() % 4 == 0 {
+        if s.ends_with("==") {
+            s.truncate(s.len() - 2);
+        } else if s.ends_with('=') {
+            s.truncate(s.len() - 1);
+        }
+    }
+
+    if s.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut data = Vec::with_capacity(s.len() * 3 / 4);
+    let (mut n, mut bits) = (0u32, 0);
+
+    for c in s.bytes() {
+        n = n << 6 | BASE64.iter().position(|&b| b == c)? as u32;
+        bits += 6;
+
+        if bits >= 8 {
+            bits -= 8;
+            data.push((n >> bits) as u8);
+        }
+    }
+
+    Some(data)
+}
+
+fn encode(ctxt: &ContextRef, _this: Option<&Value>, args: &[Value]) -> ffi::JSValue {
+    let s = args
+        .first()
+        .and_then(|s| ctxt.to_cstring(s))
+        .unwrap_or_default();
+
+    ctxt.new_uint8_array(s.as_bytes())
+        .map(|arr| arr.into_inner().raw())
+        .unwrap_or_else(|err| ctxt.throw(err.to_string()).into_inner().raw())
+}
+
+fn decode(ctxt: &ContextRef, _this: Option<&Value>, args: &[Value]) -> ffi::JSValue {
+    match args.first().and_then(|buf| {
+        ctxt.with_buffer_source(buf, |buf| String::from_utf8_lossy(buf).into_owned())
+    }) {
+        Some(s) => ctxt.bind(s).into_inner().raw(),
+        None => ctxt
+            .throw_type_error("expected ArrayBuffer or ArrayBufferView")
+            .into_inner()
+            .raw(),
+    }
+}
+
+/// Throw the `InvalidCharacterError` like the `DOMException` of the Web platform.
+fn invalid_character(ctxt: &ContextRef, msg: &str) -> ffi::JSValue {
+    let err = ctxt.new_error();
+
+    for &(name, value) in &[("name", "InvalidCharacterError"), ("message", msg)] {
+        if let Err(err) =
+            err.define_property_value(name, value, Prop::WRITABLE | Prop::CONFIGURABLE)
+        {
+            return ctxt.throw(err.to_string()).into_inner().raw();
+        }
+    }
+
+    ctxt.throw(err).into_inner().raw()
+}
+
+fn btoa(ctxt: &ContextRef, _this: Option<&Value>, args: &[Value]) -> ffi::JSValue {
+    let s = args
+        .first()
+        .and_then(|s| ctxt.to_cstring(s))
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let latin1 = s
+        .chars()
+        .map(|c| u8::try_from(u32::from(c)).ok())
+        .collect::<Option<Vec<_>>>();
+
+    match latin1 {
+        Some(data) => ctxt.bind(encode_base64(&data)).into_inner().raw(),
+        None => invalid_character(
+            ctxt,
+            "The string contains characters outside of the Latin1 range",
+        ),
+    }
+}
+
+fn atob(ctxt: &ContextRef, _this: Option<&Value>, args: &[Value]) -> ffi::JSValue {
+    let s = args
+        .first()
+        .and_then(|s| ctxt.to_cstring(s))
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    match decode_base64(&s) {
+        Some(data) => ctxt
+            .bind(data.into_iter().map(char::from).collect::<String>())
+            .into_inner()
+            .raw(),
+        None => invalid_character(ctxt, "The string to be decoded is not correctly encoded"),
+    }
+}
+
+impl ContextRef {
+    /// Install the `TextEncoder`, `TextDecoder` (UTF-8 only), `atob` and `btoa` globals of the Web platform.
+    pub fn init_web_compat(&self) -> Result<(), Error> {
+        let global = self.global_object();
+
+        global.set_property("btoa", self.new_c_function(btoa, Some("btoa"), 1)?)?;
+        global.set_property("atob", self.new_c_function(atob, Some("atob"), 1)?)?;
+
+        self.eval_script(GLUE, "<web>", Eval::GLOBAL)?.call(
+            None,
+            (
+                self.new_c_function(encode, Some("encode"), 1)?,
+                self.new_c_function(decode, Some("decode"), 1)?,
+            ),
+        )?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, Runtime};
+
+    use super::*;
+
+    #[test]
+    fn base64() {
+        assert_eq!(encode_base64(b""), "");
+        assert_eq!(encode_base64(b"f"), "Zg==");
+        assert_eq!(encode_base64(b"fo"), "Zm8=");
+        assert_eq!(encode_base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(decode_base64("Zg==").unwrap(), b"f");
+        assert_eq!(decode_base64(" Zm8 ").unwrap(), b"fo");
+        assert_eq!(decode_base64("Zm9vYmFy").unwrap(), b"foobar");
+        assert!(decode_base64("Z").is_none());
+        assert!(decode_base64("Zm9v!").is_none());
+    }
+
+    #[test]
+    fn web_compat() {
+        let _ = pretty_env_logger::try_init();
+
+        let rt = Runtime::new();
+        let ctxt = Context::new(&rt);
+
+        ctxt.init_web_compat().unwrap();
+
+        assert_eq!(
+            ctxt.eval::<_, String>(
+                r#"
+var bytes = new TextEncoder().encode('héllo');
+var text = new TextDecoder().decode(bytes.subarray(1));
+[bytes.length, text, btoa('hello'), atob('aGVsbG8='), btoa(atob('/w=='))].join()
+"#,
+                Eval::GLOBAL
+            )
+            .unwrap(),
+            Some("6,éllo,aGVsbG8=,hello,/w==".to_owned())
+        );
+        assert_eq!(
+            ctxt.eval::<_, bool>(
+                "try { btoa('中') } catch (err) { err.name == 'InvalidCharacterError' }",
+                Eval::GLOBAL
+            )
+            .unwrap(),
+            Some(true)
+        );
+    }
+}